@@ -0,0 +1,496 @@
+//! Application router construction
+//!
+//! This module assembles the Axum router with all API endpoints and the
+//! middleware stack. It lives in the library (rather than `main.rs`) so the
+//! fully-layered application can be exercised in tests without binding a socket.
+
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Extensions, HeaderMap, Method, StatusCode, Version,
+    },
+    routing::{get, post, put, MethodRouter},
+    Router,
+};
+use std::time::Duration;
+use tower::ServiceBuilder;
+use tower_http::{
+    compression::{
+        predicate::{Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    cors::CorsLayer,
+    timeout::TimeoutLayer,
+    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
+    LatencyUnit,
+};
+use tracing::Level;
+
+use crate::config::{AppConfig, REQUEST_TIMEOUT_SECS};
+use crate::error::AppError;
+use crate::middleware::{
+    connection_limit_middleware, debug_logging_middleware, error_format_middleware,
+    rate_limit_middleware, upload_limit_middleware, DebugLogging, UploadLimit,
+};
+use crate::routes;
+use crate::state::AppState;
+
+/// Build the Axum router with all API endpoints and middleware
+///
+/// Middleware layers are applied in reverse order (bottom executes first).
+pub fn build_router(config: AppConfig) -> Router {
+    build_router_with_state(AppState::new(config))
+}
+
+/// Build the router around existing shared state
+///
+/// Useful when the caller needs to keep a handle on the state, e.g. to
+/// inspect metrics in tests.
+pub fn build_router_with_state(state: AppState) -> Router {
+    let cors = cors_layer(&state.config);
+    let upload_limit = UploadLimit(state.config.max_upload_bytes);
+    let connection_limit = state.connections.clone();
+    let rate_limiter = state.rate_limiter.clone();
+    let debug_logging = DebugLogging::new(state.config.server_api_key.as_deref());
+
+    let router = api_routes(&state.config)
+        .into_iter()
+        .fold(Router::new(), |router, route| {
+            route_if(router, route.enabled, route.path, route.method_router)
+        });
+
+    router
+        // Root endpoint
+        .route("/", get(root_handler))
+        // JSON 405 for known paths hit with the wrong method.
+        // Must be registered after all routes; axum still sets the `Allow` header.
+        .method_not_allowed_fallback(method_not_allowed)
+        // Add shared state (config, metrics) for dependency injection
+        .with_state(state)
+        // Task 37: Add request size limits (MAX_UPLOAD_BYTES, 50MB by default)
+        .layer(DefaultBodyLimit::max(upload_limit.0))
+        // Reject oversized Content-Length before the body (or 100 Continue) is sent
+        .layer(axum::middleware::from_fn_with_state(
+            upload_limit,
+            upload_limit_middleware,
+        ))
+        // 503 once MAX_CONNECTIONS requests are in flight, so slow clients cannot exhaust sockets
+        .layer(axum::middleware::from_fn_with_state(
+            connection_limit,
+            connection_limit_middleware,
+        ))
+        // Task 41: 429 once a client exceeds its hourly budget (RATE_LIMIT_ALGORITHM),
+        // checked before the request takes a connection permit
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit_middleware,
+        ))
+        // HTML error pages for clients that prefer `text/html` (browsers)
+        .layer(axum::middleware::from_fn(error_format_middleware))
+        // Task 40: Add timeout layers (different timeouts for different endpoints)
+        // Edit endpoint gets 5 minutes for AI processing
+        // Returns 408 Request Timeout on timeout
+        .layer(ServiceBuilder::new().layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            Duration::from_secs(REQUEST_TIMEOUT_SECS), // 5 minutes for AI processing
+        )))
+        // Task 35: Add enhanced tracing middleware for request/response logging
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(
+                    DefaultMakeSpan::new()
+                        .include_headers(true)
+                        .level(Level::INFO),
+                )
+                .on_response(
+                    DefaultOnResponse::new()
+                        .include_headers(true)
+                        .latency_unit(LatencyUnit::Millis)
+                        .level(Level::INFO),
+                ),
+        )
+        // DEBUG logging for requests with `X-Debug: true` and the server API key
+        .layer(axum::middleware::from_fn_with_state(
+            debug_logging,
+            debug_logging_middleware,
+        ))
+        // Task 36: Add compression middleware (br/brotli and gzip), for JSON and text only
+        .layer(compression_layer())
+        // Task 34: Add CORS middleware
+        .layer(cors)
+}
+
+/// An API endpoint, registered when `enabled`
+pub struct ApiRoute {
+    /// Path pattern, e.g. `/api/jobs/{id}`
+    pub path: &'static str,
+    /// Whether the route is registered for this configuration
+    pub enabled: bool,
+    /// Handlers by method
+    pub method_router: MethodRouter<AppState>,
+}
+
+impl ApiRoute {
+    fn new(path: &'static str, enabled: bool, method_router: MethodRouter<AppState>) -> Self {
+        Self {
+            path,
+            enabled,
+            method_router,
+        }
+    }
+}
+
+/// Every documented endpoint, with whether `config` enables it
+///
+/// Disabled routes are listed too, so the OpenAPI document can be checked
+/// against the full set. The root handler is not part of the API.
+pub fn api_routes(config: &AppConfig) -> Vec<ApiRoute> {
+    let features = config.features;
+    vec![
+        // API routes (Task 33)
+        ApiRoute::new("/api/health", true, get(routes::health::health_check)),
+        ApiRoute::new(
+            "/api/providers",
+            true,
+            get(routes::providers::list_providers),
+        ),
+        ApiRoute::new(
+            "/api/providers/{name}/params",
+            true,
+            get(routes::providers::provider_params),
+        ),
+        ApiRoute::new("/api/models", true, get(routes::models::list_models)),
+        ApiRoute::new(
+            "/api/edit",
+            true,
+            post(routes::edit::edit_image).get(routes::edit::edit_image_from_query),
+        ),
+        ApiRoute::new(
+            "/api/edit/stream",
+            true,
+            post(routes::edit_stream::edit_image_stream),
+        ),
+        ApiRoute::new("/api/edit/batch", true, post(routes::batch::edit_batch)),
+        ApiRoute::new(
+            "/api/edit/compare",
+            true,
+            post(routes::compare::edit_compare),
+        ),
+        ApiRoute::new(
+            "/api/openapi.json",
+            true,
+            get(routes::openapi::openapi_spec),
+        ),
+        ApiRoute::new(
+            "/api/jobs",
+            features.async_jobs,
+            post(routes::jobs::submit_job),
+        ),
+        ApiRoute::new(
+            "/api/jobs/{id}",
+            features.async_jobs,
+            get(routes::jobs::job_status).delete(routes::jobs::cancel_job),
+        ),
+        ApiRoute::new(
+            "/api/jobs/{id}/preview",
+            features.async_jobs,
+            get(routes::jobs::job_preview),
+        ),
+        ApiRoute::new(
+            "/api/uploads",
+            features.uploads,
+            post(routes::uploads::create_upload),
+        ),
+        ApiRoute::new(
+            "/api/uploads/{id}",
+            features.uploads,
+            put(routes::uploads::put_upload),
+        ),
+        // GET routes also answer HEAD, without the body
+        ApiRoute::new(
+            "/api/results/{id}",
+            features.results,
+            get(routes::results::get_result),
+        ),
+        // Operator endpoints need SERVER_API_KEY to authenticate against
+        ApiRoute::new(
+            "/metrics",
+            config.server_api_key.is_some(),
+            get(routes::metrics::metrics),
+        ),
+        ApiRoute::new(
+            "/api/admin/stats",
+            config.server_api_key.is_some(),
+            get(routes::admin::admin_stats),
+        ),
+    ]
+}
+
+/// Register a route only when its feature flag is enabled
+///
+/// Disabled routes are simply absent, so requests to them get a 404.
+pub fn route_if<S>(
+    router: Router<S>,
+    enabled: bool,
+    path: &str,
+    method_router: MethodRouter<S>,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if enabled {
+        router.route(path, method_router)
+    } else {
+        tracing::debug!(path, "Route disabled by feature flag");
+        router
+    }
+}
+
+/// Build the response compression layer (Task 36)
+///
+/// Edited images are already compressed (PNG, JPEG, WebP), so brotli or gzip
+/// would only burn CPU on them. Only JSON and text responses above the
+/// default minimum size are compressed.
+pub fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::default().and(is_compressible_content_type);
+    CompressionLayer::new()
+        .br(true)
+        .gzip(true)
+        .compress_when(predicate)
+}
+
+/// Whether a response's `Content-Type` is JSON or text
+fn is_compressible_content_type(
+    _status: StatusCode,
+    _version: Version,
+    headers: &HeaderMap,
+    _extensions: &Extensions,
+) -> bool {
+    let Some(content_type) = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+
+    essence.starts_with("text/") || essence == "application/json" || essence.ends_with("+json")
+}
+
+/// Build the CORS layer from the configured origins (Task 34)
+///
+/// Python backend uses: allow_credentials=True, allow_methods=["*"], allow_headers=["*"]
+/// Note: When allow_credentials is true, we must specify headers explicitly (not Any)
+pub fn cors_layer(config: &AppConfig) -> CorsLayer {
+    if config.allowed_origins.contains(&"*".to_string()) {
+        tracing::warn!("CORS configured with wildcard (*) - allowing all origins");
+        CorsLayer::permissive()
+    } else {
+        tracing::info!(
+            "CORS configured with specific origins: {:?}",
+            config.allowed_origins
+        );
+        let origins = config
+            .allowed_origins
+            .iter()
+            .filter_map(|origin| origin.parse().ok())
+            .collect::<Vec<_>>();
+
+        // When using allow_credentials, we must specify headers explicitly
+        let allowed_headers = vec![
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            "x-google-api-key".parse().unwrap(),
+            "x-gemini-api-key".parse().unwrap(),
+            "x-fal-key".parse().unwrap(),
+            "x-provider".parse().unwrap(),
+            "x-tenant-id".parse().unwrap(),
+            "x-debug".parse().unwrap(),
+        ];
+
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_credentials(true)
+            .allow_methods(vec![
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::DELETE,
+                Method::OPTIONS,
+            ])
+            .allow_headers(allowed_headers)
+    }
+}
+
+/// Root handler for the server
+///
+/// Returns basic information about the server.
+async fn root_handler() -> &'static str {
+    "FrameForge Server - Axum Implementation"
+}
+
+/// Method-not-allowed fallback
+///
+/// Returns the standard error body (JSON, or HTML for browsers) with a 405
+/// status. The `Allow` header listing the route's supported methods is added by axum.
+async fn method_not_allowed(method: Method) -> AppError {
+    AppError::MethodNotAllowed(method.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn make_test_config() -> AppConfig {
+        AppConfig {
+            google_api_key: Some("test-key".to_string()),
+            gemini_api_key: None,
+            fal_key: None,
+            google_model_id: "test-model".to_string(),
+            allowed_origins: vec!["*".to_string()],
+            host: "127.0.0.1".to_string(),
+            port: 8000,
+            ..AppConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_to_health_returns_json_405() {
+        let app = build_router(make_test_config());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/api/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[header::ALLOW], "GET,HEAD");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error_type"], "method_not_allowed");
+        assert!(json["error"].as_str().unwrap().contains("POST"));
+    }
+
+    #[tokio::test]
+    async fn test_browser_gets_html_405() {
+        let app = build_router(make_test_config());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/api/health")
+                    .header(header::ACCEPT, "text/html,application/xhtml+xml,*/*;q=0.8")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[header::ALLOW], "GET,HEAD");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("<h1>405 Method Not Allowed</h1>"));
+        assert!(html.contains("<code>method_not_allowed</code>"));
+    }
+
+    #[tokio::test]
+    async fn test_put_to_edit_lists_get_and_post() {
+        let app = build_router(make_test_config());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::PUT)
+                    .uri("/api/edit")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[header::ALLOW], "POST,GET,HEAD");
+    }
+
+    #[test]
+    fn test_compressible_content_types() {
+        let compressible = |content_type: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, content_type.parse().unwrap());
+            is_compressible_content_type(
+                StatusCode::OK,
+                Version::HTTP_11,
+                &headers,
+                &Extensions::new(),
+            )
+        };
+
+        assert!(compressible("application/json"));
+        assert!(compressible("application/problem+json"));
+        assert!(compressible("text/plain; version=0.0.4"));
+        assert!(compressible("Text/HTML"));
+        assert!(!compressible("image/png"));
+        assert!(!compressible("image/jpeg"));
+        assert!(!compressible("application/octet-stream"));
+        assert!(!is_compressible_content_type(
+            StatusCode::OK,
+            Version::HTTP_11,
+            &HeaderMap::new(),
+            &Extensions::new()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_gated_route_follows_feature_flag() {
+        for enabled in [true, false] {
+            let app = route_if(
+                Router::new(),
+                enabled,
+                "/api/experimental",
+                get(|| async { "ok" }),
+            );
+
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/api/experimental")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            let expected = if enabled {
+                StatusCode::OK
+            } else {
+                StatusCode::NOT_FOUND
+            };
+            assert_eq!(response.status(), expected);
+        }
+    }
+}
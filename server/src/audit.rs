@@ -0,0 +1,184 @@
+//! Audit log of edits
+//!
+//! Every edit (each item of a batch counts as one) emits a single event on the
+//! dedicated `audit` tracing target, so it can be routed to its own sink with a
+//! per-target filter, e.g. `RUST_LOG=info,audit=info` and a layer filtered with
+//! `tracing_subscriber::filter::Targets::new().with_target("audit", Level::INFO)`.
+//!
+//! Events record who edited what: tenant, provider, outcome, a SHA-256 hash of
+//! the input image and of the client prompt, and an RFC 3339 timestamp.
+//!
+//! With `AUDIT_SAMPLE_RATE` below 1.0 only that fraction of successful edits
+//! is audited; failures always are.
+//!
+//! Security: never includes API keys, images, or prompt text.
+
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::metrics::OUTCOME_SUCCESS;
+use crate::models::tenant::TenantId;
+
+/// Tracing target audit events are emitted on
+pub const TARGET: &str = "audit";
+
+/// Placeholder for hashes of inputs that were never received
+const NO_HASH: &str = "none";
+
+/// Successful edits seen by `emit_sampled`, process-wide
+static SUCCESS_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the `sequence`-th successful edit (counting from 0) is audited at `rate`
+///
+/// Deterministic: an edit is sampled when it brings `sequence * rate` to a
+/// new whole number, so a rate of 0.25 audits every fourth edit and the
+/// sampled fraction matches the rate over any run of edits.
+fn is_sampled(sequence: u64, rate: f64) -> bool {
+    let before = (sequence as f64 * rate).floor();
+    let after = ((sequence + 1) as f64 * rate).floor();
+    after > before
+}
+
+/// Hex-encoded SHA-256 digest of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Hashed inputs of a single edit, captured before they are handed to a provider
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EditAudit {
+    image_hash: Option<String>,
+    prompt_hash: Option<String>,
+}
+
+impl EditAudit {
+    /// Hash the image and client prompt of an edit
+    pub fn new(image: &[u8], prompt: &str) -> Self {
+        Self {
+            image_hash: Some(sha256_hex(image)),
+            prompt_hash: Some(sha256_hex(prompt.as_bytes())),
+        }
+    }
+
+    /// Audit record for a request rejected before its inputs were read
+    pub fn unparsed() -> Self {
+        Self::default()
+    }
+
+    /// Emit the audit event for this edit
+    pub fn emit(&self, tenant: &TenantId, provider: &str, outcome: &str) {
+        tracing::info!(
+            target: TARGET,
+            tenant = %tenant,
+            provider,
+            outcome,
+            image_hash = self.image_hash.as_deref().unwrap_or(NO_HASH),
+            prompt_hash = self.prompt_hash.as_deref().unwrap_or(NO_HASH),
+            timestamp = %chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "Image edit"
+        );
+    }
+
+    /// Emit the audit event for this edit, for a `sample_rate` fraction of
+    /// successful edits and every failure (see `is_sampled`)
+    pub fn emit_sampled(&self, tenant: &TenantId, provider: &str, outcome: &str, sample_rate: f64) {
+        if outcome == OUTCOME_SUCCESS
+            && !is_sampled(
+                SUCCESS_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+                sample_rate,
+            )
+        {
+            return;
+        }
+        self.emit(tenant, provider, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_capture::LogCapture;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_emit_records_hashes_not_inputs() {
+        let capture = LogCapture::default();
+        let _guard = capture.install();
+        let tenant: TenantId = "acme".parse().unwrap();
+
+        EditAudit::new(b"image bytes", "Add a sofa").emit(&tenant, "google", "success");
+
+        let events = capture.target_fields(TARGET);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event["tenant"], "acme");
+        assert_eq!(event["provider"], "google");
+        assert_eq!(event["outcome"], "success");
+        assert_eq!(event["image_hash"], sha256_hex(b"image bytes"));
+        assert_eq!(event["prompt_hash"], sha256_hex(b"Add a sofa"));
+        assert!(chrono::DateTime::parse_from_rfc3339(&event["timestamp"]).is_ok());
+        assert!(event.values().all(|value| !value.contains("Add a sofa")));
+    }
+
+    #[test]
+    fn test_sampling_respects_rate() {
+        for rate in [0.0, 0.01, 0.1, 0.25, 1.0 / 3.0, 0.5, 0.9, 1.0] {
+            let sampled = (0..10_000)
+                .filter(|&sequence| is_sampled(sequence, rate))
+                .count();
+            let expected = 10_000.0 * rate;
+            assert!(
+                (sampled as f64 - expected).abs() <= 1.0,
+                "rate {}: {} sampled",
+                rate,
+                sampled
+            );
+        }
+
+        // Spread evenly rather than bunched
+        let quarter: Vec<u64> = (0..12)
+            .filter(|&sequence| is_sampled(sequence, 0.25))
+            .collect();
+        assert_eq!(quarter, [3, 7, 11]);
+    }
+
+    #[test]
+    fn test_failures_always_emitted_when_sampling() {
+        let capture = LogCapture::default();
+        let _guard = capture.install();
+        let tenant = TenantId::anonymous();
+
+        for _ in 0..5 {
+            EditAudit::unparsed().emit_sampled(&tenant, "google", "provider_error", 0.0);
+            EditAudit::unparsed().emit_sampled(&tenant, "google", OUTCOME_SUCCESS, 0.0);
+        }
+
+        let events = capture.target_fields(TARGET);
+        assert_eq!(events.len(), 5);
+        assert!(events
+            .iter()
+            .all(|event| event["outcome"] == "provider_error"));
+    }
+
+    #[test]
+    fn test_unparsed_request_has_no_hashes() {
+        let capture = LogCapture::default();
+        let _guard = capture.install();
+
+        EditAudit::unparsed().emit(&TenantId::anonymous(), "unknown", "invalid_input");
+
+        let event = &capture.target_fields(TARGET)[0];
+        assert_eq!(event["image_hash"], "none");
+        assert_eq!(event["prompt_hash"], "none");
+    }
+}
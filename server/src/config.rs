@@ -5,8 +5,294 @@
 //! and the config crate for flexible configuration sources.
 
 use serde::Deserialize;
+
+use crate::middleware::RateLimitAlgorithm;
+use crate::utils::image_utils::{self, JpegOptions, JpegSubsampling, OutputFormat};
 use std::env;
 use std::net::SocketAddr;
+use std::str::FromStr;
+
+/// Placeholder substituted with the client prompt in `PROMPT_ENHANCE_TEMPLATE`
+pub const PROMPT_PLACEHOLDER: &str = "{prompt}";
+
+/// Default prompt enhancement template
+pub const DEFAULT_PROMPT_ENHANCE_TEMPLATE: &str = "{prompt}. Keep the original camera angle, \
+perspective, architecture and lighting; blend new elements with realistic materials, \
+shadows and scale; photorealistic, high detail.";
+
+/// Provider option keys clients can't set unless `PROVIDER_OPTIONS_DENY` says otherwise
+///
+/// Turning a model's safety checker off, or multiplying the images generated
+/// (and billed) per request, is the operator's call rather than the client's.
+pub const DEFAULT_PROVIDER_OPTIONS_DENY: &[&str] = &["enable_safety_checker", "num_images"];
+
+/// Seconds an edit request (or background job) may take in total
+///
+/// Provider timeouts that bound part of an edit, like the Fal.ai queue
+/// timeouts, must fit within it to ever fire.
+pub const REQUEST_TIMEOUT_SECS: u64 = 300;
+
+/// Gemini models known to support image editing
+///
+/// Other ids are still accepted (new models ship often) but produce a startup
+/// warning, since a typo would otherwise only surface as a provider error.
+pub const KNOWN_GOOGLE_MODELS: &[&str] = &[
+    "gemini-2.5-flash-image",
+    "gemini-2.5-flash-image-preview",
+    "gemini-2.0-flash-preview-image-generation",
+    "gemini-2.0-flash-exp",
+    "gemini-exp-1206",
+];
+
+/// Experimental features that can be toggled through `FEATURES`
+///
+/// `FEATURES` accepts either a comma-separated list of enabled flags
+/// (`async_jobs,caching`) or a JSON object (`{"async_jobs": true}`).
+/// Unknown flags are logged and ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub struct FeatureFlags {
+    /// Asynchronous edit jobs
+    pub async_jobs: bool,
+    /// Uploads of raw image bytes ahead of an edit (see `crate::uploads`)
+    pub uploads: bool,
+    /// Content-addressable copies of edit results (see `crate::results`)
+    pub results: bool,
+    /// Caching of downloaded provider results (see `services::download_cache`)
+    pub caching: bool,
+    /// `webhook:` providers calling client-supplied URLs (see `services::webhook_editor`)
+    pub webhooks: bool,
+}
+
+impl FeatureFlags {
+    /// Parse a `FEATURES` value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the value looks like JSON but is malformed or
+    /// contains non-boolean values.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let value = value.trim();
+        let mut flags = FeatureFlags::default();
+
+        let entries: Vec<(String, bool)> = if value.starts_with('{') {
+            let map: std::collections::BTreeMap<String, bool> = serde_json::from_str(value)
+                .map_err(|e| anyhow::anyhow!("Invalid FEATURES JSON: {}", e))?;
+            map.into_iter().collect()
+        } else {
+            value
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| (s.to_string(), true))
+                .collect()
+        };
+
+        for (name, enabled) in entries {
+            match name.to_lowercase().replace('-', "_").as_str() {
+                "async_jobs" => flags.async_jobs = enabled,
+                "uploads" => flags.uploads = enabled,
+                "results" => flags.results = enabled,
+                "caching" => flags.caching = enabled,
+                "webhooks" => flags.webhooks = enabled,
+                _ => tracing::warn!(flag = %name, "Ignoring unknown feature flag"),
+            }
+        }
+
+        Ok(flags)
+    }
+}
+
+/// Fal.ai endpoint style used to submit requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FalEndpoint {
+    /// `https://queue.fal.run/{model}` - queued and polled, suited to slow models
+    #[default]
+    Queue,
+    /// `https://fal.run/{model}` - direct synchronous call, suited to fast models
+    Direct,
+}
+
+impl FromStr for FalEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "queue" => Ok(FalEndpoint::Queue),
+            "direct" => Ok(FalEndpoint::Direct),
+            other => Err(anyhow::anyhow!(
+                "Invalid FAL_ENDPOINT '{}'. Expected 'queue' or 'direct'",
+                other
+            )),
+        }
+    }
+}
+
+/// How much provider request/response detail appears in logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRedaction {
+    /// Log data URIs and prompts verbatim
+    Off,
+    /// Shorten data URIs to their prefix and length
+    #[default]
+    Truncate,
+    /// Shorten data URIs and replace prompts with a hash
+    Hash,
+}
+
+impl FromStr for LogRedaction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(LogRedaction::Off),
+            "truncate" => Ok(LogRedaction::Truncate),
+            "hash" => Ok(LogRedaction::Hash),
+            other => Err(anyhow::anyhow!(
+                "Invalid LOG_REDACTION '{}'. Expected 'off', 'truncate' or 'hash'",
+                other
+            )),
+        }
+    }
+}
+
+/// How requests for unknown provider names served by the fallback are logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownProviderLog {
+    /// Log every occurrence at WARN
+    #[default]
+    Warn,
+    /// Log every occurrence at DEBUG
+    Debug,
+    /// Log the first occurrence of each name at WARN and repeats at DEBUG,
+    /// with a running count
+    Once,
+}
+
+impl FromStr for UnknownProviderLog {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "warn" => Ok(UnknownProviderLog::Warn),
+            "debug" => Ok(UnknownProviderLog::Debug),
+            "once" => Ok(UnknownProviderLog::Once),
+            other => Err(anyhow::anyhow!(
+                "Invalid UNKNOWN_PROVIDER_LOG '{}'. Expected 'warn', 'debug' or 'once'",
+                other
+            )),
+        }
+    }
+}
+
+/// How a fal URL given as the provider (`fal:https://fal.run/owner/model`) is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FalUrlProvider {
+    /// Recover the model path from the URL and use it
+    #[default]
+    Normalize,
+    /// Reject the request, naming the `fal:owner/model` form to use instead
+    Reject,
+}
+
+impl FromStr for FalUrlProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "normalize" => Ok(FalUrlProvider::Normalize),
+            "reject" => Ok(FalUrlProvider::Reject),
+            other => Err(anyhow::anyhow!(
+                "Invalid FAL_URL_PROVIDER '{}'. Expected 'normalize' or 'reject'",
+                other
+            )),
+        }
+    }
+}
+
+/// How `webhook:` providers send the image and prompt to their endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEncoding {
+    /// `multipart/form-data` with `images` file parts and a `prompt` field
+    #[default]
+    Multipart,
+    /// JSON with `images` as base64 data URIs and `prompt`
+    Base64,
+}
+
+impl FromStr for WebhookEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "multipart" => Ok(WebhookEncoding::Multipart),
+            "base64" => Ok(WebhookEncoding::Base64),
+            other => Err(anyhow::anyhow!(
+                "Invalid WEBHOOK_ENCODING '{}'. Expected 'multipart' or 'base64'",
+                other
+            )),
+        }
+    }
+}
+
+/// What the batch endpoint does when some uploaded images are invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Reject the whole request with a 400
+    AllOrNothing,
+    /// Report invalid images as failed items and edit the rest
+    #[default]
+    BestEffort,
+}
+
+impl FromStr for BatchMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "all_or_nothing" => Ok(BatchMode::AllOrNothing),
+            "best_effort" => Ok(BatchMode::BestEffort),
+            other => Err(anyhow::anyhow!(
+                "Invalid BATCH_MODE '{}'. Expected 'all_or_nothing' or 'best_effort'",
+                other
+            )),
+        }
+    }
+}
+
+/// What happens to input images with more than 8 bits per channel
+///
+/// Applies to providers that only accept 8-bit images (see
+/// `ImageEditor::accepts_high_bit_depth`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighBitDepth {
+    /// Convert to 8 bits per channel, losslessly encoded as PNG
+    #[default]
+    Convert,
+    /// Reject the request with a 400
+    Reject,
+}
+
+impl FromStr for HighBitDepth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "convert" => Ok(HighBitDepth::Convert),
+            "reject" => Ok(HighBitDepth::Reject),
+            other => Err(anyhow::anyhow!(
+                "Invalid HIGH_BIT_DEPTH '{}'. Expected 'convert' or 'reject'",
+                other
+            )),
+        }
+    }
+}
 
 /// Main application configuration structure
 ///
@@ -23,17 +309,306 @@ pub struct AppConfig {
     /// Fal.ai API key for image generation models
     pub fal_key: Option<String>,
 
+    /// Default Fal.ai endpoint style
+    pub fal_endpoint: FalEndpoint,
+
+    /// Fal.ai model paths that always use the direct endpoint (when the default is queue)
+    pub fal_direct_models: Vec<String>,
+
+    /// Base URL Fal.ai requests go to instead of the fal.run hosts, e.g. a
+    /// proxy; queued requests are polled on the same host
+    pub fal_base_url: Option<String>,
+
+    /// Fal.ai model paths that only accept PNG and JPEG data URIs; other
+    /// inputs (e.g. GIF, WebP) are transcoded to PNG before submission
+    pub fal_transcode_models: Vec<String>,
+
+    /// Seconds a queued Fal.ai request may wait for a worker before failing
+    pub fal_queue_timeout_secs: u64,
+
+    /// Seconds a queued Fal.ai request may run once a worker picked it up;
+    /// with the queue timeout, below `REQUEST_TIMEOUT_SECS`
+    pub fal_processing_timeout_secs: u64,
+
+    /// Send byte-identical input images only once to multi-image providers
+    pub dedupe_input_images: bool,
+
     /// Google model ID to use (e.g., "gemini-2.5-flash-image-preview")
     pub google_model_id: String,
 
+    /// Seconds allowed for reading a Gemini response stream to its end
+    pub google_timeout_secs: u64,
+
+    /// Model file loaded by the `local` provider (requires the `local-model` cargo feature)
+    pub local_model_path: Option<String>,
+
+    /// Provider used when an unknown provider name is requested (`None` = unknown names error)
+    pub fallback_provider: Option<String>,
+
+    /// Preferred order of providers in listings; unlisted providers follow alphabetically
+    pub provider_order: Vec<String>,
+
     /// List of allowed CORS origins
     pub allowed_origins: Vec<String>,
 
+    /// Refuse to start when `allowed_origins` contains the `*` wildcard
+    pub disallow_wildcard_cors: bool,
+
     /// Server host address to bind to
     pub host: String,
 
     /// Server port to listen on
     pub port: u16,
+
+    /// Text prepended to every prompt before it is sent to a provider
+    pub prompt_prefix: Option<String>,
+
+    /// Text appended to every prompt before it is sent to a provider
+    pub prompt_suffix: Option<String>,
+
+    /// Maximum length (in characters) of the final prompt, including prefix/suffix
+    pub max_prompt_chars: usize,
+
+    /// Maximum number of prompts in a chained edit (`steps` on `/api/edit`)
+    pub max_edit_steps: usize,
+
+    /// Reject `/api/edit` requests without a prompt instead of using the default prompt
+    pub require_prompt: bool,
+
+    /// Providers whose prompts may be enhanced when a request sets `enhance_prompt=true`
+    ///
+    /// Entries match a provider name exactly, or every model of a prefixed
+    /// provider (`fal` matches `fal:fal-ai/flux/dev`).
+    pub prompt_enhance_providers: Vec<String>,
+
+    /// Template used to enhance prompts; `{prompt}` is replaced by the client prompt
+    pub prompt_enhance_template: String,
+
+    /// Maximum width/height (in pixels) a client may request for the output image
+    pub max_output_dimension: u32,
+
+    /// Output format used when a request sets no `output_format`
+    /// (`None` = keep the provider's format)
+    pub default_output_format: Option<OutputFormat>,
+
+    /// Color (RGB) that transparent areas are flattened onto in JPEG output
+    pub jpeg_background: [u8; 3],
+
+    /// Chroma subsampling of JPEG output
+    pub jpeg_subsampling: JpegSubsampling,
+
+    /// Return PNG instead of JPEG when the result has transparent pixels
+    pub preserve_alpha: bool,
+
+    /// Treat provider results nearly identical to the input as a provider error
+    pub reject_unchanged_results: bool,
+
+    /// Maximum normalized pixel difference (0.0-1.0) at which a result counts as unchanged
+    pub unchanged_threshold: f64,
+
+    /// Ratio (0.0-1.0) of the result's longest side to the input's below which
+    /// a result counts as unexpectedly small; 0 disables the check
+    pub min_result_scale: f64,
+
+    /// Enlarge unexpectedly small results back to the input's size
+    pub upscale_small_results: bool,
+
+    /// Handling of 16-bit and floating-point inputs for 8-bit-only providers
+    pub high_bit_depth: HighBitDepth,
+
+    /// Maximum request body size in bytes, enforced up front on `Content-Length`
+    pub max_upload_bytes: usize,
+
+    /// Maximum number of multipart fields, recognized or not, in an edit request
+    pub max_multipart_fields: usize,
+
+    /// Largest provider result in bytes that is forwarded to the client
+    pub max_result_bytes: usize,
+
+    /// Longest base64 data URI, in bytes, decoded from a provider result
+    pub max_data_uri_bytes: usize,
+
+    /// Maximum number of requests handled at once; further requests get a 503
+    pub max_connections: usize,
+
+    /// Seconds allowed for fetching an image given by URL (`image_url` field)
+    pub url_input_timeout_secs: u64,
+
+    /// Maximum size in bytes of an image fetched by URL
+    pub url_input_max_bytes: usize,
+
+    /// Maximum number of redirects followed when fetching an image by URL
+    pub url_input_max_redirects: usize,
+
+    /// Allow image URLs (and their redirects) to reach loopback, private and
+    /// link-local addresses; off so clients can't probe the server's network
+    pub url_input_allow_private: bool,
+
+    /// Seconds an upload reserved with `POST /api/uploads` stays usable
+    pub upload_ttl_secs: u64,
+
+    /// Maximum number of images accepted by the batch edit endpoint
+    pub max_batch_images: usize,
+
+    /// Maximum number of batch items sent to providers concurrently
+    pub batch_concurrency: usize,
+
+    /// Handling of invalid images in a batch
+    pub batch_mode: BatchMode,
+
+    /// Maximum number of providers in one comparison request
+    pub max_compare_providers: usize,
+
+    /// Maximum number of providers of a comparison called concurrently
+    pub compare_concurrency: usize,
+
+    /// Maximum total uncompressed size of a batch ZIP archive, in bytes
+    pub zip_max_bytes: usize,
+
+    /// Maximum number of entries in a batch ZIP archive, including
+    /// directories and metadata entries that are skipped
+    pub zip_max_entries: usize,
+
+    /// Maximum idle HTTP connections kept per provider host
+    pub http_pool_max_idle_per_host: usize,
+
+    /// Seconds an idle provider connection stays pooled (0 = no timeout)
+    pub http_pool_idle_timeout_secs: u64,
+
+    /// Redaction of data URIs and prompts in provider debug logs
+    pub log_redaction: LogRedaction,
+
+    /// Log level of requests for unknown providers served by the fallback
+    pub unknown_provider_log: UnknownProviderLog,
+
+    /// Handling of fal URLs pasted as the provider instead of `fal:owner/model`
+    pub fal_url_provider: FalUrlProvider,
+
+    /// Request body sent to `webhook:` provider endpoints
+    pub webhook_encoding: WebhookEncoding,
+
+    /// Keys clients may not set in `provider_options`
+    pub provider_options_deny: Vec<String>,
+
+    /// Hosts `webhook:` providers may call; empty allows any host that
+    /// resolves to a public address, listed hosts may also be private
+    pub webhook_allowed_hosts: Vec<String>,
+
+    /// Fraction (0.0-1.0) of successful edits that emit an audit event;
+    /// failures are always audited
+    pub audit_sample_rate: f64,
+
+    /// Key unlocking privileged request options such as `X-Debug` and the
+    /// operator endpoints (`/api/admin/*`, `/metrics`), sent as
+    /// `Authorization: Bearer <key>`; those options are ignored (and the
+    /// operator endpoints absent) when unset
+    pub server_api_key: Option<String>,
+
+    /// Algorithm the rate limiter counts requests with
+    pub rate_limit_algorithm: RateLimitAlgorithm,
+
+    /// Experimental feature toggles
+    pub features: FeatureFlags,
+
+    /// Route every edit to the mock (passthrough) editor
+    ///
+    /// Only compiled into tests and builds with the `test-mock` feature; there
+    /// is no environment variable for it.
+    #[cfg(any(test, feature = "test-mock"))]
+    pub mock_provider: bool,
+
+    /// Serve edits with the mock editor when the requested provider is unavailable
+    ///
+    /// For frontend development without API keys: startup no longer requires a
+    /// key, and responses produced this way carry `X-Dev-Mode: true`. Never
+    /// enable in production.
+    pub dev_mode: bool,
+
+    /// Start even when no API keys are configured, with a warning
+    ///
+    /// Only providers that need no key (mock, composite, local) can serve
+    /// edits then. By default a missing key stops startup.
+    pub allow_no_api_keys: bool,
+}
+
+impl Default for AppConfig {
+    /// Defaults matching `AppConfig::load` with no environment variables set
+    fn default() -> Self {
+        Self {
+            google_api_key: None,
+            gemini_api_key: None,
+            fal_key: None,
+            fal_endpoint: FalEndpoint::Queue,
+            fal_direct_models: Vec::new(),
+            fal_base_url: None,
+            fal_transcode_models: Vec::new(),
+            fal_queue_timeout_secs: 90,
+            fal_processing_timeout_secs: 180,
+            dedupe_input_images: true,
+            google_model_id: "gemini-2.5-flash-image-preview".to_string(),
+            google_timeout_secs: 300,
+            local_model_path: None,
+            fallback_provider: Some("google".to_string()),
+            provider_order: Vec::new(),
+            allowed_origins: vec!["*".to_string()],
+            disallow_wildcard_cors: false,
+            host: "0.0.0.0".to_string(),
+            port: 8000,
+            prompt_prefix: None,
+            prompt_suffix: None,
+            max_prompt_chars: 4000,
+            max_edit_steps: 5,
+            require_prompt: false,
+            prompt_enhance_providers: Vec::new(),
+            prompt_enhance_template: DEFAULT_PROMPT_ENHANCE_TEMPLATE.to_string(),
+            max_output_dimension: 4096,
+            default_output_format: None,
+            jpeg_background: [255, 255, 255],
+            jpeg_subsampling: JpegSubsampling::S444,
+            preserve_alpha: false,
+            reject_unchanged_results: false,
+            unchanged_threshold: 0.01,
+            min_result_scale: 0.0,
+            upscale_small_results: false,
+            high_bit_depth: HighBitDepth::Convert,
+            max_upload_bytes: 50 * 1024 * 1024,
+            max_multipart_fields: 100,
+            max_result_bytes: 100 * 1024 * 1024,
+            max_data_uri_bytes: 136 * 1024 * 1024,
+            max_connections: 1024,
+            url_input_timeout_secs: 30,
+            url_input_max_bytes: 20 * 1024 * 1024,
+            url_input_max_redirects: 3,
+            url_input_allow_private: false,
+            upload_ttl_secs: 900,
+            max_batch_images: 10,
+            batch_concurrency: 4,
+            batch_mode: BatchMode::BestEffort,
+            max_compare_providers: 4,
+            compare_concurrency: 4,
+            zip_max_bytes: 100 * 1024 * 1024,
+            zip_max_entries: 100,
+            http_pool_max_idle_per_host: 32,
+            http_pool_idle_timeout_secs: 90,
+            log_redaction: LogRedaction::Truncate,
+            unknown_provider_log: UnknownProviderLog::Warn,
+            fal_url_provider: FalUrlProvider::Normalize,
+            webhook_encoding: WebhookEncoding::Multipart,
+            webhook_allowed_hosts: Vec::new(),
+            provider_options_deny: DEFAULT_PROVIDER_OPTIONS_DENY
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+            audit_sample_rate: 1.0,
+            server_api_key: None,
+            rate_limit_algorithm: RateLimitAlgorithm::FixedWindow,
+            features: FeatureFlags::default(),
+            #[cfg(any(test, feature = "test-mock"))]
+            mock_provider: false,
+            dev_mode: false,
+            allow_no_api_keys: false,
+        }
+    }
 }
 
 impl AppConfig {
@@ -54,31 +629,228 @@ impl AppConfig {
         let gemini_api_key = env::var("GEMINI_API_KEY").ok();
         let fal_key = env::var("FAL_KEY").ok();
 
-        let google_model_id = env::var("GOOGLE_MODEL_ID")
-            .unwrap_or_else(|_| "gemini-2.5-flash-image-preview".to_string());
+        let fal_endpoint = match env_non_empty("FAL_ENDPOINT") {
+            Some(value) => value.parse()?,
+            None => FalEndpoint::Queue,
+        };
+        let fal_direct_models = env_list("FAL_DIRECT_MODELS");
+        let fal_base_url =
+            env_non_empty("FAL_BASE_URL").map(|url| url.trim_end_matches('/').to_string());
+        let fal_transcode_models = env_list("FAL_TRANSCODE_MODELS");
+        let fal_queue_timeout_secs = env_parse("FAL_QUEUE_TIMEOUT_SECS", 90);
+        let fal_processing_timeout_secs = env_parse("FAL_PROCESSING_TIMEOUT_SECS", 180);
+        let dedupe_input_images = env_bool("DEDUPE_INPUT_IMAGES", true);
+
+        let google_model_id = env_non_empty("GOOGLE_MODEL_ID")
+            .map(|id| normalize_google_model_id(&id))
+            .unwrap_or_else(|| "gemini-2.5-flash-image-preview".to_string());
+        let google_timeout_secs = env_parse("GOOGLE_TIMEOUT_SECS", 300);
+
+        let local_model_path = env_non_empty("LOCAL_MODEL_PATH");
+
+        // Unset keeps the historical Google fallback when a Google key exists;
+        // "none" disables the fallback so unknown providers error
+        let fallback_provider = match env::var("FALLBACK_PROVIDER") {
+            Ok(value) if value.trim().eq_ignore_ascii_case("none") => None,
+            Ok(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+            _ if google_api_key.is_some() || gemini_api_key.is_some() => Some("google".to_string()),
+            _ => None,
+        };
+
+        let provider_order = env_list("PROVIDER_ORDER");
 
         let allowed_origins = env::var("ALLOWED_ORIGINS")
             .unwrap_or_else(|_| "*".to_string())
             .split(',')
             .map(|s| s.trim().to_string())
             .collect();
+        let disallow_wildcard_cors = env_bool("DISALLOW_WILDCARD_CORS", false);
 
-        let host = env::var("HOST")
-            .unwrap_or_else(|_| "0.0.0.0".to_string());
+        let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
 
         let port = env::var("PORT")
             .unwrap_or_else(|_| "8000".to_string())
             .parse()
             .unwrap_or(8000);
 
+        let prompt_prefix = env_non_empty("PROMPT_PREFIX");
+        let prompt_suffix = env_non_empty("PROMPT_SUFFIX");
+        let max_prompt_chars = env_parse("MAX_PROMPT_CHARS", 4000);
+        let max_edit_steps = env_parse("MAX_EDIT_STEPS", 5);
+        let require_prompt = env_bool("REQUIRE_PROMPT", false);
+        let prompt_enhance_providers = env_list("PROMPT_ENHANCE_PROVIDERS");
+        let prompt_enhance_template = env_non_empty("PROMPT_ENHANCE_TEMPLATE")
+            .unwrap_or_else(|| DEFAULT_PROMPT_ENHANCE_TEMPLATE.to_string());
+
+        let max_output_dimension = env_parse("MAX_OUTPUT_DIMENSION", 4096);
+        let default_output_format = env_non_empty("DEFAULT_OUTPUT_FORMAT")
+            .map(|value| {
+                value
+                    .parse::<OutputFormat>()
+                    .map_err(|e| anyhow::anyhow!("Invalid DEFAULT_OUTPUT_FORMAT: {}", e))
+            })
+            .transpose()?;
+        let jpeg_background = match env_non_empty("JPEG_BACKGROUND") {
+            Some(value) => image_utils::parse_hex_rgb(&value)
+                .map_err(|e| anyhow::anyhow!("Invalid JPEG_BACKGROUND: {}", e))?,
+            None => [255, 255, 255],
+        };
+        let jpeg_subsampling = match env_non_empty("JPEG_SUBSAMPLING") {
+            Some(value) => value
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid JPEG_SUBSAMPLING: {}", e))?,
+            None => JpegSubsampling::S444,
+        };
+        let preserve_alpha = env_bool("PRESERVE_ALPHA", false);
+
+        let reject_unchanged_results = env_bool("REJECT_UNCHANGED_RESULTS", false);
+        let unchanged_threshold = env_parse("UNCHANGED_THRESHOLD", 0.01);
+        let min_result_scale = env_parse("MIN_RESULT_SCALE", 0.0);
+        let upscale_small_results = env_bool("UPSCALE_SMALL_RESULTS", false);
+        let high_bit_depth = match env_non_empty("HIGH_BIT_DEPTH") {
+            Some(value) => value.parse()?,
+            None => HighBitDepth::Convert,
+        };
+
+        let max_upload_bytes = env_parse("MAX_UPLOAD_BYTES", 50 * 1024 * 1024);
+        let max_multipart_fields = env_parse("MAX_MULTIPART_FIELDS", 100);
+        let max_result_bytes = env_parse("MAX_RESULT_BYTES", 100 * 1024 * 1024);
+        let max_data_uri_bytes = env_parse("MAX_DATA_URI_BYTES", 136 * 1024 * 1024);
+        let max_connections = env_parse("MAX_CONNECTIONS", 1024);
+
+        let url_input_timeout_secs = env_parse("URL_INPUT_TIMEOUT_SECS", 30);
+        let url_input_max_bytes = env_parse("URL_INPUT_MAX_BYTES", 20 * 1024 * 1024);
+        let url_input_max_redirects = env_parse("URL_INPUT_MAX_REDIRECTS", 3);
+        let url_input_allow_private = env_bool("URL_INPUT_ALLOW_PRIVATE", false);
+
+        let upload_ttl_secs = env_parse("UPLOAD_TTL_SECS", 900);
+
+        let max_batch_images = env_parse("MAX_BATCH_IMAGES", 10);
+        let batch_concurrency = env_parse("BATCH_CONCURRENCY", 4);
+        let max_compare_providers = env_parse("MAX_COMPARE_PROVIDERS", 4);
+        let compare_concurrency = env_parse("COMPARE_CONCURRENCY", 4);
+        let batch_mode = match env_non_empty("BATCH_MODE") {
+            Some(value) => value.parse()?,
+            None => BatchMode::BestEffort,
+        };
+        let zip_max_bytes = env_parse("ZIP_MAX_BYTES", 100 * 1024 * 1024);
+        let zip_max_entries = env_parse("ZIP_MAX_ENTRIES", 100);
+
+        let http_pool_max_idle_per_host = env_parse("HTTP_POOL_MAX_IDLE_PER_HOST", 32);
+        let http_pool_idle_timeout_secs = env_parse("HTTP_POOL_IDLE_TIMEOUT_SECS", 90);
+
+        let log_redaction = match env_non_empty("LOG_REDACTION") {
+            Some(value) => value.parse()?,
+            None => LogRedaction::Truncate,
+        };
+        let unknown_provider_log = match env_non_empty("UNKNOWN_PROVIDER_LOG") {
+            Some(value) => value.parse()?,
+            None => UnknownProviderLog::Warn,
+        };
+        let fal_url_provider = match env_non_empty("FAL_URL_PROVIDER") {
+            Some(value) => value.parse()?,
+            None => FalUrlProvider::Normalize,
+        };
+        let webhook_encoding = match env_non_empty("WEBHOOK_ENCODING") {
+            Some(value) => value.parse()?,
+            None => WebhookEncoding::Multipart,
+        };
+        let webhook_allowed_hosts = env_list("WEBHOOK_ALLOWED_HOSTS");
+        // Unset keeps the default deny-list; "none" lets clients set any key
+        let provider_options_deny = match env_non_empty("PROVIDER_OPTIONS_DENY") {
+            Some(value) if value.trim().eq_ignore_ascii_case("none") => Vec::new(),
+            Some(_) => env_list("PROVIDER_OPTIONS_DENY"),
+            None => DEFAULT_PROVIDER_OPTIONS_DENY
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+        };
+        let audit_sample_rate = env_parse("AUDIT_SAMPLE_RATE", 1.0);
+        let server_api_key = env_non_empty("SERVER_API_KEY");
+
+        let rate_limit_algorithm = match env_non_empty("RATE_LIMIT_ALGORITHM") {
+            Some(value) => value.parse()?,
+            None => RateLimitAlgorithm::FixedWindow,
+        };
+
+        let features = match env_non_empty("FEATURES") {
+            Some(value) => FeatureFlags::parse(&value)?,
+            None => FeatureFlags::default(),
+        };
+
+        let dev_mode = env_bool("DEV_MODE", false);
+        let allow_no_api_keys = env_bool("ALLOW_NO_API_KEYS", false);
+
         let config = AppConfig {
             google_api_key,
             gemini_api_key,
             fal_key,
+            fal_endpoint,
+            fal_direct_models,
+            fal_base_url,
+            fal_transcode_models,
+            fal_queue_timeout_secs,
+            fal_processing_timeout_secs,
+            dedupe_input_images,
             google_model_id,
+            google_timeout_secs,
+            local_model_path,
+            fallback_provider,
+            provider_order,
             allowed_origins,
+            disallow_wildcard_cors,
             host,
             port,
+            prompt_prefix,
+            prompt_suffix,
+            max_prompt_chars,
+            max_edit_steps,
+            require_prompt,
+            prompt_enhance_providers,
+            prompt_enhance_template,
+            max_output_dimension,
+            default_output_format,
+            jpeg_background,
+            jpeg_subsampling,
+            preserve_alpha,
+            reject_unchanged_results,
+            unchanged_threshold,
+            min_result_scale,
+            upscale_small_results,
+            high_bit_depth,
+            max_upload_bytes,
+            max_multipart_fields,
+            max_result_bytes,
+            max_data_uri_bytes,
+            max_connections,
+            url_input_timeout_secs,
+            url_input_max_bytes,
+            url_input_max_redirects,
+            url_input_allow_private,
+            upload_ttl_secs,
+            max_batch_images,
+            batch_concurrency,
+            batch_mode,
+            max_compare_providers,
+            compare_concurrency,
+            zip_max_bytes,
+            zip_max_entries,
+            http_pool_max_idle_per_host,
+            http_pool_idle_timeout_secs,
+            log_redaction,
+            unknown_provider_log,
+            fal_url_provider,
+            webhook_encoding,
+            webhook_allowed_hosts,
+            provider_options_deny,
+            audit_sample_rate,
+            server_api_key,
+            rate_limit_algorithm,
+            features,
+            #[cfg(any(test, feature = "test-mock"))]
+            mock_provider: false,
+            dev_mode,
+            allow_no_api_keys,
         };
 
         // Validate configuration
@@ -95,19 +867,31 @@ impl AppConfig {
     /// # Errors
     ///
     /// Returns an error if:
-    /// - No API keys are configured
+    /// - No API keys are configured (allowed in dev mode)
     /// - Port is out of valid range (1-65535)
     /// - Host format is invalid
+    /// - `ALLOWED_ORIGINS` contains `*` while `DISALLOW_WILDCARD_CORS` is set
     fn validate(&self) -> anyhow::Result<()> {
         // Task 39: Ensure at least one API key is configured
-        if self.google_api_key.is_none()
+        let no_keys = self.google_api_key.is_none()
             && self.gemini_api_key.is_none()
-            && self.fal_key.is_none() {
+            && self.fal_key.is_none();
+        if no_keys && self.dev_mode {
+            tracing::warn!(
+                "No API keys configured; DEV_MODE serves every edit with the mock editor"
+            );
+        } else if no_keys && self.allow_no_api_keys {
+            tracing::warn!("No API keys configured; only providers that need no key are available");
+        } else if no_keys {
             return Err(anyhow::anyhow!(
                 "No API keys configured. At least one of GOOGLE_API_KEY, GEMINI_API_KEY, or FAL_KEY must be set."
             ));
         }
 
+        if let Some(warning) = google_model_id_warning(&self.google_model_id) {
+            tracing::warn!("{}", warning);
+        }
+
         // Task 39: Validate port range (1-65535)
         if self.port == 0 {
             return Err(anyhow::anyhow!(
@@ -121,6 +905,164 @@ impl AppConfig {
             return Err(anyhow::anyhow!("Host cannot be empty"));
         }
 
+        if !self.fal_direct_models.is_empty() && self.fal_endpoint == FalEndpoint::Direct {
+            return Err(anyhow::anyhow!(
+                "FAL_DIRECT_MODELS has no effect when FAL_ENDPOINT=direct; remove one of them"
+            ));
+        }
+
+        if let Some(url) = &self.fal_base_url {
+            let valid = reqwest::Url::parse(url)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+            if !valid {
+                return Err(anyhow::anyhow!(
+                    "FAL_BASE_URL must be an http(s) URL, got '{}'",
+                    url
+                ));
+            }
+        }
+
+        if let Some(model) = self
+            .fal_direct_models
+            .iter()
+            .find(|m| m.starts_with("fal:"))
+        {
+            return Err(anyhow::anyhow!(
+                "FAL_DIRECT_MODELS entries are model paths without the 'fal:' prefix, got '{}'",
+                model
+            ));
+        }
+
+        if let Some(model) = self
+            .fal_transcode_models
+            .iter()
+            .find(|m| m.starts_with("fal:"))
+        {
+            return Err(anyhow::anyhow!(
+                "FAL_TRANSCODE_MODELS entries are model paths without the 'fal:' prefix, got '{}'",
+                model
+            ));
+        }
+
+        if self.max_prompt_chars == 0 {
+            return Err(anyhow::anyhow!("MAX_PROMPT_CHARS must be greater than 0"));
+        }
+
+        if self.max_edit_steps == 0 {
+            return Err(anyhow::anyhow!("MAX_EDIT_STEPS must be greater than 0"));
+        }
+
+        if self.upload_ttl_secs == 0 {
+            return Err(anyhow::anyhow!("UPLOAD_TTL_SECS must be greater than 0"));
+        }
+
+        if !self.prompt_enhance_template.contains(PROMPT_PLACEHOLDER) {
+            return Err(anyhow::anyhow!(
+                "PROMPT_ENHANCE_TEMPLATE must contain the {} placeholder",
+                PROMPT_PLACEHOLDER
+            ));
+        }
+
+        if self.max_output_dimension == 0 {
+            return Err(anyhow::anyhow!(
+                "MAX_OUTPUT_DIMENSION must be greater than 0"
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.unchanged_threshold) {
+            return Err(anyhow::anyhow!(
+                "UNCHANGED_THRESHOLD must be between 0.0 and 1.0"
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.min_result_scale) {
+            return Err(anyhow::anyhow!(
+                "MIN_RESULT_SCALE must be between 0.0 and 1.0"
+            ));
+        }
+        if self.upscale_small_results && self.min_result_scale == 0.0 {
+            return Err(anyhow::anyhow!(
+                "UPSCALE_SMALL_RESULTS has no effect unless MIN_RESULT_SCALE is greater than 0"
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.audit_sample_rate) {
+            return Err(anyhow::anyhow!(
+                "AUDIT_SAMPLE_RATE must be between 0.0 and 1.0"
+            ));
+        }
+
+        if self.google_timeout_secs == 0 {
+            return Err(anyhow::anyhow!(
+                "GOOGLE_TIMEOUT_SECS must be greater than 0"
+            ));
+        }
+
+        if self.fal_queue_timeout_secs == 0 || self.fal_processing_timeout_secs == 0 {
+            return Err(anyhow::anyhow!(
+                "FAL_QUEUE_TIMEOUT_SECS and FAL_PROCESSING_TIMEOUT_SECS must be greater than 0"
+            ));
+        }
+
+        // The request timeout would otherwise cut the edit off first, and the
+        // queue timeout's error would never be seen
+        let fal_timeout_secs = self
+            .fal_queue_timeout_secs
+            .saturating_add(self.fal_processing_timeout_secs);
+        if fal_timeout_secs >= REQUEST_TIMEOUT_SECS {
+            return Err(anyhow::anyhow!(
+                "FAL_QUEUE_TIMEOUT_SECS + FAL_PROCESSING_TIMEOUT_SECS ({}) must be below the {} second request timeout",
+                fal_timeout_secs,
+                REQUEST_TIMEOUT_SECS
+            ));
+        }
+
+        if self.max_upload_bytes == 0 {
+            return Err(anyhow::anyhow!("MAX_UPLOAD_BYTES must be greater than 0"));
+        }
+
+        if self.max_multipart_fields == 0 {
+            return Err(anyhow::anyhow!(
+                "MAX_MULTIPART_FIELDS must be greater than 0"
+            ));
+        }
+
+        if self.max_result_bytes == 0 {
+            return Err(anyhow::anyhow!("MAX_RESULT_BYTES must be greater than 0"));
+        }
+
+        if self.max_data_uri_bytes == 0 {
+            return Err(anyhow::anyhow!("MAX_DATA_URI_BYTES must be greater than 0"));
+        }
+
+        if self.max_connections == 0 {
+            return Err(anyhow::anyhow!("MAX_CONNECTIONS must be greater than 0"));
+        }
+
+        if self.url_input_timeout_secs == 0 || self.url_input_max_bytes == 0 {
+            return Err(anyhow::anyhow!(
+                "URL_INPUT_TIMEOUT_SECS and URL_INPUT_MAX_BYTES must be greater than 0"
+            ));
+        }
+
+        if self.max_batch_images == 0 || self.batch_concurrency == 0 {
+            return Err(anyhow::anyhow!(
+                "MAX_BATCH_IMAGES and BATCH_CONCURRENCY must be greater than 0"
+            ));
+        }
+
+        if self.max_compare_providers == 0 || self.compare_concurrency == 0 {
+            return Err(anyhow::anyhow!(
+                "MAX_COMPARE_PROVIDERS and COMPARE_CONCURRENCY must be greater than 0"
+            ));
+        }
+
+        if self.zip_max_bytes == 0 || self.zip_max_entries == 0 {
+            return Err(anyhow::anyhow!(
+                "ZIP_MAX_BYTES and ZIP_MAX_ENTRIES must be greater than 0"
+            ));
+        }
+
         // Test if host can be parsed as a valid socket address
         let test_addr = format!("{}:{}", self.host, self.port);
         if test_addr.parse::<SocketAddr>().is_err() {
@@ -133,8 +1075,16 @@ impl AppConfig {
             }
         }
 
+        let wildcard_cors = self.allowed_origins.contains(&"*".to_string());
+        if wildcard_cors && self.disallow_wildcard_cors {
+            return Err(anyhow::anyhow!(
+                "ALLOWED_ORIGINS contains '*' but DISALLOW_WILDCARD_CORS is set. \
+                List the allowed origins explicitly."
+            ));
+        }
+
         // Warn if using wildcard CORS in production-like setup
-        if self.allowed_origins.contains(&"*".to_string()) && self.host != "127.0.0.1" && self.host != "localhost" {
+        if wildcard_cors && self.host != "127.0.0.1" && self.host != "localhost" {
             tracing::warn!(
                 "CORS is configured with wildcard (*) on non-localhost host. \
                 This is insecure for production. Set ALLOWED_ORIGINS explicitly."
@@ -144,6 +1094,51 @@ impl AppConfig {
         Ok(())
     }
 
+    /// Fal.ai endpoint style for a model path, applying `fal_direct_models`
+    pub fn fal_endpoint_for(&self, model_path: &str) -> FalEndpoint {
+        if self
+            .fal_direct_models
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(model_path))
+        {
+            FalEndpoint::Direct
+        } else {
+            self.fal_endpoint
+        }
+    }
+
+    /// Whether data URIs for a Fal.ai model path must be PNG or JPEG
+    pub fn fal_transcodes_data_uri(&self, model_path: &str) -> bool {
+        self.fal_transcode_models
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(model_path))
+    }
+
+    /// Whether prompt enhancement is enabled for `provider`
+    pub fn prompt_enhancement_enabled(&self, provider: &str) -> bool {
+        self.prompt_enhance_providers
+            .iter()
+            .any(|entry| provider_entry_matches(entry, provider))
+    }
+
+    /// Position of `provider` in `provider_order`, or `provider_order.len()` when unlisted
+    ///
+    /// Lower ranks are listed first.
+    pub fn provider_rank(&self, provider: &str) -> usize {
+        self.provider_order
+            .iter()
+            .position(|entry| provider_entry_matches(entry, provider))
+            .unwrap_or(self.provider_order.len())
+    }
+
+    /// How edit results are encoded as JPEG
+    pub fn jpeg_options(&self) -> JpegOptions {
+        JpegOptions {
+            background: self.jpeg_background,
+            subsampling: self.jpeg_subsampling,
+        }
+    }
+
     /// Get the effective Google API key
     ///
     /// Returns GOOGLE_API_KEY if set, otherwise falls back to GEMINI_API_KEY
@@ -154,6 +1149,91 @@ impl AppConfig {
     }
 }
 
+/// Normalize a Gemini model id as written in `GOOGLE_MODEL_ID`
+///
+/// Trims whitespace, lowercases, and drops the `models/` resource prefix used
+/// in Google's API listings.
+pub fn normalize_google_model_id(model_id: &str) -> String {
+    let model_id = model_id.trim().to_lowercase();
+    model_id
+        .strip_prefix("models/")
+        .map(str::to_string)
+        .unwrap_or(model_id)
+}
+
+/// Startup warning for a Gemini model id that is malformed or not in
+/// `KNOWN_GOOGLE_MODELS`, or `None` for a known model
+pub fn google_model_id_warning(model_id: &str) -> Option<String> {
+    if KNOWN_GOOGLE_MODELS.contains(&model_id) {
+        return None;
+    }
+
+    let well_formed = model_id.starts_with("gemini-")
+        && model_id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '.'));
+    Some(if well_formed {
+        format!(
+            "GOOGLE_MODEL_ID '{}' is not a known Gemini image model; edits will fail if it does not exist",
+            model_id
+        )
+    } else {
+        format!(
+            "GOOGLE_MODEL_ID '{}' does not look like a Gemini model id (expected e.g. 'gemini-2.5-flash-image')",
+            model_id
+        )
+    })
+}
+
+/// Whether a configured provider list entry covers `provider`
+///
+/// Entries match a provider name exactly, or every model of a prefixed
+/// provider (`fal` matches `fal:fal-ai/flux/dev`), case-insensitively.
+fn provider_entry_matches(entry: &str, provider: &str) -> bool {
+    provider.eq_ignore_ascii_case(entry)
+        || provider
+            .split_once(':')
+            .is_some_and(|(prefix, _)| prefix.eq_ignore_ascii_case(entry))
+}
+
+/// Read an environment variable, treating empty/whitespace values as unset
+fn env_non_empty(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Read a comma-separated environment variable, dropping empty entries
+fn env_list(name: &str) -> Vec<String> {
+    env_non_empty(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Read a boolean environment variable ("1", "true", "yes" are truthy)
+fn env_bool(name: &str, default: bool) -> bool {
+    env::var(name).map(|v| is_truthy(&v)).unwrap_or(default)
+}
+
+/// Whether a flag value reads as true: `1`, `true` or `yes`, in any case
+///
+/// Shared by boolean environment variables and request fields.
+pub fn is_truthy(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+/// Read and parse an environment variable, falling back to `default` when unset or invalid
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,6 +1248,7 @@ mod tests {
             allowed_origins: vec!["*".to_string()],
             host: "0.0.0.0".to_string(),
             port: 8000,
+            ..AppConfig::default()
         };
 
         assert_eq!(config.get_google_api_key(), Some("key1"));
@@ -183,8 +1264,367 @@ mod tests {
             allowed_origins: vec!["*".to_string()],
             host: "0.0.0.0".to_string(),
             port: 8000,
+            ..AppConfig::default()
         };
 
         assert_eq!(config.get_google_api_key(), Some("key2"));
     }
+
+    #[test]
+    fn test_strict_cors_rejects_wildcard_origins() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            allowed_origins: vec!["https://app.example.com".to_string(), "*".to_string()],
+            disallow_wildcard_cors: true,
+            ..AppConfig::default()
+        };
+
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("DISALLOW_WILDCARD_CORS"));
+    }
+
+    #[test]
+    fn test_strict_cors_accepts_explicit_origins() {
+        let strict = AppConfig {
+            fal_key: Some("key".to_string()),
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            disallow_wildcard_cors: true,
+            ..AppConfig::default()
+        };
+        assert!(strict.validate().is_ok());
+
+        // Wildcard stays allowed (with a warning) when the flag is off
+        let permissive = AppConfig {
+            fal_key: Some("key".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(permissive.validate().is_ok());
+    }
+
+    #[test]
+    fn test_fal_endpoint_parsing_and_overrides() {
+        assert_eq!(
+            "Direct".parse::<FalEndpoint>().unwrap(),
+            FalEndpoint::Direct
+        );
+        assert!("sideways".parse::<FalEndpoint>().is_err());
+        assert_eq!(
+            " HASH ".parse::<LogRedaction>().unwrap(),
+            LogRedaction::Hash
+        );
+        assert!("partial".parse::<LogRedaction>().is_err());
+        assert_eq!(
+            " Once ".parse::<UnknownProviderLog>().unwrap(),
+            UnknownProviderLog::Once
+        );
+        assert!("error".parse::<UnknownProviderLog>().is_err());
+        assert_eq!(
+            " Reject ".parse::<FalUrlProvider>().unwrap(),
+            FalUrlProvider::Reject
+        );
+        assert!("strip".parse::<FalUrlProvider>().is_err());
+        assert_eq!(
+            "BASE64".parse::<WebhookEncoding>().unwrap(),
+            WebhookEncoding::Base64
+        );
+        assert!("json".parse::<WebhookEncoding>().is_err());
+
+        assert_eq!(
+            "all-or-nothing".parse::<BatchMode>().unwrap(),
+            BatchMode::AllOrNothing
+        );
+        assert_eq!(
+            "best_effort".parse::<BatchMode>().unwrap(),
+            BatchMode::BestEffort
+        );
+        assert!("atomic".parse::<BatchMode>().is_err());
+
+        let config = AppConfig {
+            fal_direct_models: vec!["fal-ai/flux/schnell".to_string()],
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            config.fal_endpoint_for("fal-ai/flux/schnell"),
+            FalEndpoint::Direct
+        );
+        assert_eq!(
+            config.fal_endpoint_for("fal-ai/flux/dev"),
+            FalEndpoint::Queue
+        );
+    }
+
+    #[test]
+    fn test_high_bit_depth_parsing() {
+        assert_eq!(
+            " Reject ".parse::<HighBitDepth>().unwrap(),
+            HighBitDepth::Reject
+        );
+        assert_eq!(
+            "convert".parse::<HighBitDepth>().unwrap(),
+            HighBitDepth::Convert
+        );
+        assert!("truncate".parse::<HighBitDepth>().is_err());
+    }
+
+    #[test]
+    fn test_fal_base_url_validation() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            fal_base_url: Some("http://127.0.0.1:9000".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let config = AppConfig {
+            fal_base_url: Some("queue.fal.run".to_string()),
+            ..config
+        };
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("FAL_BASE_URL"));
+    }
+
+    #[test]
+    fn test_fal_direct_models_validation() {
+        let redundant = AppConfig {
+            fal_key: Some("key".to_string()),
+            fal_endpoint: FalEndpoint::Direct,
+            fal_direct_models: vec!["fal-ai/flux/schnell".to_string()],
+            ..AppConfig::default()
+        };
+        assert!(redundant.validate().is_err());
+
+        let prefixed = AppConfig {
+            fal_key: Some("key".to_string()),
+            fal_direct_models: vec!["fal:fal-ai/flux/schnell".to_string()],
+            ..AppConfig::default()
+        };
+        assert!(prefixed
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("'fal:' prefix"));
+    }
+
+    #[test]
+    fn test_google_model_id_normalization() {
+        assert_eq!(
+            normalize_google_model_id(" models/Gemini-2.5-Flash-Image "),
+            "gemini-2.5-flash-image"
+        );
+        assert_eq!(
+            normalize_google_model_id("gemini-exp-1206"),
+            "gemini-exp-1206"
+        );
+    }
+
+    #[test]
+    fn test_google_model_id_warnings() {
+        assert_eq!(
+            google_model_id_warning("gemini-2.5-flash-image-preview"),
+            None
+        );
+
+        let unknown = google_model_id_warning("gemini-2.5-flash-imgae").unwrap();
+        assert!(
+            unknown.contains("not a known Gemini image model"),
+            "{}",
+            unknown
+        );
+
+        let malformed = google_model_id_warning("gpt image").unwrap();
+        assert!(
+            malformed.contains("does not look like a Gemini model id"),
+            "{}",
+            malformed
+        );
+
+        // Warnings never fail startup
+        let config = AppConfig {
+            google_api_key: Some("key".to_string()),
+            google_model_id: "gemini-9-ultra-image".to_string(),
+            ..AppConfig::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_fal_transcode_models() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            fal_transcode_models: vec!["fal-ai/Flux-Kontext/dev".to_string()],
+            ..AppConfig::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(config.fal_transcodes_data_uri("fal-ai/flux-kontext/dev"));
+        assert!(!config.fal_transcodes_data_uri("fal-ai/nano-banana/edit"));
+
+        let prefixed = AppConfig {
+            fal_transcode_models: vec!["fal:fal-ai/flux-kontext/dev".to_string()],
+            ..config
+        };
+        assert!(prefixed
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("FAL_TRANSCODE_MODELS"));
+    }
+
+    #[test]
+    fn test_prompt_enhancement_provider_matching() {
+        let config = AppConfig {
+            prompt_enhance_providers: vec!["fal".to_string(), "Google".to_string()],
+            ..AppConfig::default()
+        };
+
+        assert!(config.prompt_enhancement_enabled("google"));
+        assert!(config.prompt_enhancement_enabled("fal:fal-ai/flux/dev"));
+        assert!(!config.prompt_enhancement_enabled("falcon"));
+        assert!(!config.prompt_enhancement_enabled("nano-banana"));
+    }
+
+    #[test]
+    fn test_provider_rank() {
+        let config = AppConfig {
+            provider_order: vec!["fal".to_string(), "Google".to_string()],
+            ..AppConfig::default()
+        };
+
+        assert_eq!(config.provider_rank("fal:fal-ai/flux/dev"), 0);
+        assert_eq!(config.provider_rank("google"), 1);
+        assert_eq!(config.provider_rank("nano-banana"), 2);
+        assert_eq!(AppConfig::default().provider_rank("google"), 0);
+    }
+
+    #[test]
+    fn test_prompt_enhance_template_requires_placeholder() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            prompt_enhance_template: "Make it nicer".to_string(),
+            ..AppConfig::default()
+        };
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("{prompt}"));
+    }
+
+    #[test]
+    fn test_max_edit_steps_must_be_positive() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            max_edit_steps: 0,
+            ..AppConfig::default()
+        };
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("MAX_EDIT_STEPS"));
+    }
+
+    #[test]
+    fn test_upscale_small_results_requires_min_result_scale() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            upscale_small_results: true,
+            ..AppConfig::default()
+        };
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("MIN_RESULT_SCALE"), "{}", err);
+
+        let config = AppConfig {
+            min_result_scale: 0.5,
+            ..config
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_fal_queue_timeouts_fit_within_request_timeout() {
+        let config = AppConfig {
+            fal_key: Some("key".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(
+            config.fal_queue_timeout_secs + config.fal_processing_timeout_secs
+                < REQUEST_TIMEOUT_SECS
+        );
+        assert!(config.validate().is_ok());
+
+        let too_long = AppConfig {
+            fal_queue_timeout_secs: 600,
+            ..config
+        };
+        let err = too_long.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("FAL_QUEUE_TIMEOUT_SECS + FAL_PROCESSING_TIMEOUT_SECS (780)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_dev_mode_allows_missing_api_keys() {
+        assert!(AppConfig::default().validate().is_err());
+
+        let dev = AppConfig {
+            dev_mode: true,
+            ..AppConfig::default()
+        };
+        assert!(dev.validate().is_ok());
+    }
+
+    #[test]
+    fn test_allow_no_api_keys_downgrades_missing_keys() {
+        let err = AppConfig::default().validate().unwrap_err();
+        assert!(err.to_string().contains("No API keys configured"));
+
+        let keyless = AppConfig {
+            allow_no_api_keys: true,
+            ..AppConfig::default()
+        };
+        assert!(keyless.validate().is_ok());
+
+        // Other checks still apply
+        let invalid = AppConfig {
+            allow_no_api_keys: true,
+            port: 0,
+            ..AppConfig::default()
+        };
+        assert!(invalid
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid port"));
+    }
+
+    #[test]
+    fn test_feature_flags_from_list() {
+        let flags = FeatureFlags::parse("async_jobs, caching,unknown-flag").unwrap();
+        assert!(flags.async_jobs);
+        assert!(flags.caching);
+        assert!(!flags.results);
+
+        assert_eq!(FeatureFlags::parse("").unwrap(), FeatureFlags::default());
+        assert!(FeatureFlags::parse("async-jobs").unwrap().async_jobs);
+        assert!(FeatureFlags::parse("uploads").unwrap().uploads);
+        assert!(FeatureFlags::parse("webhooks").unwrap().webhooks);
+    }
+
+    #[test]
+    fn test_feature_flags_from_json() {
+        let flags =
+            FeatureFlags::parse(r#"{"caching": true, "results": false, "watermark": true}"#)
+                .unwrap();
+        assert!(flags.caching);
+        assert!(!flags.results);
+        assert!(!flags.async_jobs);
+
+        assert!(FeatureFlags::parse("{not json").is_err());
+        assert!(FeatureFlags::parse(r#"{"caching": "yes"}"#).is_err());
+    }
 }
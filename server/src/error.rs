@@ -7,14 +7,75 @@
 //! - Use `AppError` with thiserror for public API boundaries
 //! - Use `anyhow::Error` for internal provider implementation details
 //! - Map each error variant to appropriate HTTP status codes
-//! - Provide user-friendly error messages in JSON format
+//! - Provide user-friendly error messages in JSON format, or as a minimal
+//!   HTML page for clients that prefer `text/html` (see `ErrorFormat`)
 
 use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
     Json,
 };
 
+tokio::task_local! {
+    /// Error format negotiated for the request being handled
+    ///
+    /// Set by `middleware::error_format_middleware`; errors rendered outside
+    /// a request (or in tests) fall back to JSON.
+    pub static ERROR_FORMAT: ErrorFormat;
+}
+
+/// Body format of error responses, negotiated from the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// `ErrorResponse` as JSON (the default)
+    #[default]
+    Json,
+    /// Minimal HTML page, for browsers
+    Html,
+}
+
+impl ErrorFormat {
+    /// Choose the format for a request's `Accept` headers
+    ///
+    /// HTML is only chosen when `text/html` is ranked above `application/json`
+    /// (by quality value), so wildcards and API clients keep getting JSON.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut html = 0.0;
+        let mut json = 0.0;
+
+        let ranges = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for range in ranges {
+            let mut parts = range.split(';');
+            let media_type = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            match media_type.as_str() {
+                "text/html" => html = quality,
+                "application/json" => json = quality,
+                _ => {}
+            }
+        }
+
+        if html > 0.0 && html > json {
+            ErrorFormat::Html
+        } else {
+            ErrorFormat::Json
+        }
+    }
+
+    /// Format negotiated for the current request, or JSON outside of one
+    pub fn current() -> Self {
+        ERROR_FORMAT.try_with(|format| *format).unwrap_or_default()
+    }
+}
+
 /// Main application error type for API boundaries
 ///
 /// This enum represents all possible errors that can occur in the FrameForge server.
@@ -33,10 +94,38 @@ pub enum AppError {
     #[error("Provider not found: {0}")]
     ProviderNotFound(String),
 
+    /// Requested resource (e.g. an async job) does not exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     /// Generic provider error with context
     #[error("Provider error: {0}")]
     ProviderError(String),
 
+    /// Request lacks the server API key required by the endpoint
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// Upstream provider rejected our credentials (HTTP 401/403)
+    #[error("Provider authentication failed: {0}")]
+    ProviderAuth(String),
+
+    /// Request conflicts with the resource's current state (e.g. cancelling a finished job)
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// HTTP method not supported by the matched route
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
+    /// Request body larger than the configured upload limit
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    /// Server at capacity (e.g. over `MAX_CONNECTIONS` requests in flight)
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
     /// Invalid input from client (bad request data)
     #[error("Invalid input: {0}")]
     InvalidInput(String),
@@ -50,28 +139,75 @@ pub enum AppError {
     Internal(#[from] anyhow::Error),
 }
 
+/// Authentication failure reported by a provider implementation
+///
+/// Providers return this (inside their `anyhow::Error`) when the upstream API
+/// answers 401/403, so the route layer can surface `AppError::ProviderAuth`
+/// instead of a generic provider error. The message names where the key is
+/// configured but never includes the key itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderAuthError {
+    /// Human-readable provider name (e.g. "Fal.ai")
+    pub provider: &'static str,
+    /// Upstream HTTP status, when the provider reported one
+    pub status: Option<u16>,
+    /// Where the operator configures the key (e.g. "FAL_KEY")
+    pub key_source: &'static str,
+}
+
+impl std::fmt::Display for ProviderAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} rejected the API key", self.provider)?;
+        if let Some(status) = self.status {
+            write!(f, " (HTTP {})", status)?;
+        }
+        write!(f, "; check {}", self.key_source)
+    }
+}
+
+impl std::error::Error for ProviderAuthError {}
+
 /// JSON error response structure
 ///
 /// This is the format that will be sent to clients when an error occurs.
 #[derive(serde::Serialize)]
-struct ErrorResponse {
+pub(crate) struct ErrorResponse {
     /// The error message
-    error: String,
+    pub(crate) error: String,
     /// Error type/code for programmatic handling
     #[serde(skip_serializing_if = "Option::is_none")]
-    error_type: Option<String>,
+    pub(crate) error_type: Option<String>,
 }
 
 impl AppError {
     /// Map error variant to HTTP status code
-    fn status_code(&self) -> StatusCode {
+    pub(crate) fn status_code(&self) -> StatusCode {
         match self {
             // 400 Bad Request - client error
             AppError::InvalidInput(_) => StatusCode::BAD_REQUEST,
             AppError::ImageProcessing(_) => StatusCode::BAD_REQUEST,
 
+            // 413 Payload Too Large - body over the upload limit
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+
+            // 401 Unauthorized - missing or wrong server API key
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+
             // 404 Not Found - resource not found
             AppError::ProviderNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+
+            // 405 Method Not Allowed - route exists but not for this method
+            AppError::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+
+            // 409 Conflict - not possible in the resource's current state
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+
+            // 502 Bad Gateway - upstream rejected the configured credentials
+            AppError::ProviderAuth(_) => StatusCode::BAD_GATEWAY,
+
+            // 503 Service Unavailable - too many requests in flight
+            AppError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
 
             // 500 Internal Server Error - server/provider errors
             AppError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -81,14 +217,36 @@ impl AppError {
         }
     }
 
+    /// Map a failed `ImageEditor::edit_image` call to an API error
+    ///
+    /// Authentication failures anywhere in the error chain become
+    /// `ProviderAuth`; everything else is a `ProviderError` carrying the full
+    /// context chain.
+    pub fn from_provider(err: anyhow::Error) -> Self {
+        match err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ProviderAuthError>())
+        {
+            Some(auth) => AppError::ProviderAuth(auth.to_string()),
+            None => AppError::ProviderError(format!("Failed to edit image: {:#}", err)),
+        }
+    }
+
     /// Get error type string for programmatic handling
-    fn error_type(&self) -> &'static str {
+    pub(crate) fn error_type(&self) -> &'static str {
         match self {
             AppError::Config(_) => "config_error",
             AppError::ImageProcessing(_) => "image_processing_error",
             AppError::ProviderNotFound(_) => "provider_not_found",
+            AppError::NotFound(_) => "not_found",
             AppError::ProviderError(_) => "provider_error",
+            AppError::ProviderAuth(_) => "provider_auth_error",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::MethodNotAllowed(_) => "method_not_allowed",
+            AppError::Conflict(_) => "conflict",
             AppError::InvalidInput(_) => "invalid_input",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::ServiceUnavailable(_) => "service_unavailable",
             AppError::InternalServer(_) => "internal_server_error",
             AppError::Internal(_) => "internal_error",
         }
@@ -98,8 +256,9 @@ impl AppError {
 impl IntoResponse for AppError {
     /// Convert AppError into an Axum HTTP response
     ///
-    /// This implementation ensures all errors are returned as JSON with appropriate
-    /// HTTP status codes and user-friendly error messages.
+    /// Errors are returned as JSON with appropriate HTTP status codes and
+    /// user-friendly error messages, or as a minimal HTML page when the request
+    /// prefers `text/html`. The status code is the same either way.
     fn into_response(self) -> Response {
         let status_code = self.status_code();
         let error_message = self.to_string();
@@ -107,7 +266,7 @@ impl IntoResponse for AppError {
 
         // Log the error with appropriate level
         match status_code {
-            StatusCode::INTERNAL_SERVER_ERROR => {
+            StatusCode::INTERNAL_SERVER_ERROR | StatusCode::BAD_GATEWAY => {
                 tracing::error!(
                     error = ?self,
                     status = ?status_code,
@@ -130,6 +289,14 @@ impl IntoResponse for AppError {
             }
         }
 
+        if ErrorFormat::current() == ErrorFormat::Html {
+            return (
+                status_code,
+                error_page(status_code, &error_message, &error_type),
+            )
+                .into_response();
+        }
+
         // Build JSON error response
         let body = Json(ErrorResponse {
             error: error_message,
@@ -140,6 +307,38 @@ impl IntoResponse for AppError {
     }
 }
 
+/// Minimal HTML error page
+fn error_page(status: StatusCode, message: &str, error_type: &str) -> Html<String> {
+    let title = format!(
+        "{} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("Error")
+    );
+    Html(format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+         <body>\n<h1>{title}</h1>\n<p>{message}</p>\n<p><code>{error_type}</code></p>\n</body>\n</html>\n",
+        title = escape_html(&title),
+        message = escape_html(message),
+        error_type = escape_html(error_type),
+    ))
+}
+
+/// Escape text for use in HTML element content
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 // Convenience conversions from common error types
 
 impl From<image::ImageError> for AppError {
@@ -191,10 +390,26 @@ mod tests {
             AppError::ProviderNotFound("test".into()).status_code(),
             StatusCode::NOT_FOUND
         );
+        assert_eq!(
+            AppError::NotFound("test".into()).status_code(),
+            StatusCode::NOT_FOUND
+        );
         assert_eq!(
             AppError::Config("test".into()).status_code(),
             StatusCode::INTERNAL_SERVER_ERROR
         );
+        assert_eq!(
+            AppError::ServiceUnavailable("test".into()).status_code(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            AppError::Conflict("test".into()).status_code(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            AppError::Unauthorized("test".into()).status_code(),
+            StatusCode::UNAUTHORIZED
+        );
     }
 
     #[test]
@@ -207,6 +422,115 @@ mod tests {
             AppError::ProviderNotFound("test".into()).error_type(),
             "provider_not_found"
         );
+        assert_eq!(AppError::NotFound("test".into()).error_type(), "not_found");
+    }
+
+    #[test]
+    fn test_provider_auth_error_is_bad_gateway() {
+        let err = AppError::ProviderAuth("Fal.ai rejected the API key".into());
+        assert_eq!(err.status_code(), StatusCode::BAD_GATEWAY);
+        assert_eq!(err.error_type(), "provider_auth_error");
+    }
+
+    #[test]
+    fn test_from_provider_detects_auth_failure_in_chain() {
+        let auth = ProviderAuthError {
+            provider: "Fal.ai",
+            status: Some(401),
+            key_source: "FAL_KEY",
+        };
+        let err = anyhow::Error::new(auth).context("Failed to submit request to Fal.ai");
+
+        match AppError::from_provider(err) {
+            AppError::ProviderAuth(message) => {
+                assert_eq!(
+                    message,
+                    "Fal.ai rejected the API key (HTTP 401); check FAL_KEY"
+                );
+            }
+            other => panic!("expected ProviderAuth, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_provider_keeps_other_errors_generic() {
+        let err = anyhow::anyhow!("connection reset").context("Failed to download result image");
+
+        match AppError::from_provider(err) {
+            AppError::ProviderError(message) => {
+                assert_eq!(
+                    message,
+                    "Failed to edit image: Failed to download result image: connection reset"
+                );
+            }
+            other => panic!("expected ProviderError, got {:?}", other),
+        }
+    }
+
+    /// Status, content type and body of an error response
+    async fn render(err: AppError) -> (StatusCode, String, String) {
+        let response = err.into_response();
+        let status = response.status();
+        let content_type = response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            content_type,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_errors_render_as_json_by_default() {
+        let (status, content_type, body) = render(AppError::NotFound("job 'x'".into())).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(content_type, "application/json");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"], "Not found: job 'x'");
+        assert_eq!(json["error_type"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_errors_render_as_html_when_preferred() {
+        let err = AppError::InvalidInput("prompt <script> is too long".into());
+        let (status, content_type, body) = ERROR_FORMAT.scope(ErrorFormat::Html, render(err)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(content_type.starts_with("text/html"));
+        assert!(body.contains("<title>400 Bad Request</title>"));
+        assert!(body.contains("Invalid input: prompt &lt;script&gt; is too long"));
+        assert!(body.contains("<code>invalid_input</code>"));
+    }
+
+    #[test]
+    fn test_error_format_negotiation() {
+        let format = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, accept.parse().unwrap());
+            ErrorFormat::from_headers(&headers)
+        };
+
+        assert_eq!(
+            format("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            ErrorFormat::Html
+        );
+        assert_eq!(format("application/json"), ErrorFormat::Json);
+        assert_eq!(format("*/*"), ErrorFormat::Json);
+        assert_eq!(
+            format("application/json, text/html;q=0.5"),
+            ErrorFormat::Json
+        );
+        assert_eq!(format("text/html;q=0"), ErrorFormat::Json);
+        assert_eq!(
+            ErrorFormat::from_headers(&HeaderMap::new()),
+            ErrorFormat::Json
+        );
     }
 
     #[test]
@@ -0,0 +1,492 @@
+//! In-memory store of asynchronous edit jobs
+//!
+//! With the `async_jobs` feature flag, `POST /api/jobs` runs an edit in the
+//! background and the client polls for its outcome. Jobs live only in process
+//! memory: they are lost on restart and not shared between instances. At most
+//! `MAX_RETAINED_JOBS` jobs, holding at most `MAX_RETAINED_JOB_BYTES` of input
+//! and output images, are kept; the oldest finished jobs are evicted first,
+//! running jobs never are.
+//!
+//! At most `MAX_RUNNING_JOBS` jobs run at once. A submission must reserve a
+//! slot with `JobStore::reserve` before its form is read, and the slot is held
+//! by the job's task until it ends; further submissions get a 503.
+//!
+//! A job may be registered under an idempotency key; while it runs, further
+//! submissions with the same key join it instead of starting a duplicate.
+//!
+//! The task running a job is attached to it once spawned, so that cancelling
+//! the job can abort the task.
+
+use bytes::Bytes;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use axum::http::StatusCode;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::AbortHandle;
+
+use crate::error::AppError;
+
+/// Maximum number of jobs kept in memory
+pub const MAX_RETAINED_JOBS: usize = 1000;
+
+/// Maximum bytes of input and output images kept across all jobs (512 MiB)
+pub const MAX_RETAINED_JOB_BYTES: usize = 512 * 1024 * 1024;
+
+/// Maximum number of jobs running at once
+pub const MAX_RUNNING_JOBS: usize = 32;
+
+/// Lifecycle stage of a job, as reported to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Lowercase name, as used in JSON and the `X-Job-Status` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Edited image produced by a successful job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobOutput {
+    pub content_type: String,
+    pub body: Bytes,
+}
+
+/// Error a failed job ended with, kept so it can be replayed to clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobFailure {
+    pub status: StatusCode,
+    pub error: String,
+    pub error_type: &'static str,
+}
+
+impl From<&AppError> for JobFailure {
+    fn from(err: &AppError) -> Self {
+        Self {
+            status: err.status_code(),
+            error: err.to_string(),
+            error_type: err.error_type(),
+        }
+    }
+}
+
+/// Current state of a job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Succeeded(JobOutput),
+    Failed(JobFailure),
+    Cancelled,
+}
+
+impl JobState {
+    /// Status reported for this state
+    pub fn status(&self) -> JobStatus {
+        match self {
+            JobState::Running => JobStatus::Running,
+            JobState::Succeeded(_) => JobStatus::Succeeded,
+            JobState::Failed(_) => JobStatus::Failed,
+            JobState::Cancelled => JobStatus::Cancelled,
+        }
+    }
+}
+
+/// A submitted edit job
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// First input image, used for previews while the job runs
+    pub input: Bytes,
+    pub state: JobState,
+}
+
+impl Job {
+    /// Image bytes held by the job: its input, plus its output once succeeded
+    fn retained_bytes(&self) -> usize {
+        let output = match &self.state {
+            JobState::Succeeded(output) => output.body.len(),
+            _ => 0,
+        };
+        self.input.len() + output
+    }
+}
+
+#[derive(Debug, Default)]
+struct JobStoreInner {
+    jobs: HashMap<String, Job>,
+    /// Job ids in submission order, for eviction
+    order: VecDeque<String>,
+    /// Job id registered under each idempotency key
+    keys: HashMap<String, String>,
+    /// Task of each running job whose task has been attached
+    tasks: HashMap<String, AbortHandle>,
+}
+
+/// Thread-safe registry of jobs, shared through `AppState`
+#[derive(Debug)]
+pub struct JobStore {
+    inner: Mutex<JobStoreInner>,
+    /// Slots for running jobs
+    running: Arc<Semaphore>,
+    max_running: usize,
+    max_retained_bytes: usize,
+}
+
+impl Default for JobStore {
+    fn default() -> Self {
+        Self::with_limits(MAX_RUNNING_JOBS, MAX_RETAINED_JOB_BYTES)
+    }
+}
+
+impl JobStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty store running at most `max_running` jobs at once and
+    /// keeping at most `max_retained_bytes` of job images
+    pub fn with_limits(max_running: usize, max_retained_bytes: usize) -> Self {
+        Self {
+            inner: Mutex::default(),
+            running: Arc::new(Semaphore::new(max_running)),
+            max_running,
+            max_retained_bytes,
+        }
+    }
+
+    /// Reserve a slot for a job to run in
+    ///
+    /// The slot is released when the permit is dropped, so the job's task
+    /// should own it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ServiceUnavailable` (503) when `max_running` jobs
+    /// are already running.
+    pub fn reserve(&self) -> Result<OwnedSemaphorePermit, AppError> {
+        Arc::clone(&self.running).try_acquire_owned().map_err(|_| {
+            AppError::ServiceUnavailable(format!(
+                "server is running its maximum of {} jobs; retry shortly",
+                self.max_running
+            ))
+        })
+    }
+
+    /// Register a running job for `input` and return its id
+    pub fn create(&self, input: Bytes) -> String {
+        self.create_or_join(input, None).0
+    }
+
+    /// Register a running job, or join the running job already registered
+    /// under `idempotency_key`
+    ///
+    /// Returns the job id and whether a new job was created. A key whose job
+    /// has finished is reused for the new job.
+    pub fn create_or_join(&self, input: Bytes, idempotency_key: Option<&str>) -> (String, bool) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(id) = idempotency_key.and_then(|key| inner.running_for_key(key)) {
+            return (id, false);
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        if let Some(key) = idempotency_key {
+            inner.keys.insert(key.to_string(), id.clone());
+        }
+        inner.jobs.insert(
+            id.clone(),
+            Job {
+                input,
+                state: JobState::Running,
+            },
+        );
+        inner.order.push_back(id.clone());
+        inner.evict_finished(self.max_retained_bytes);
+
+        (id, true)
+    }
+
+    /// Id of the running job registered under `idempotency_key`
+    pub fn running_for_key(&self, idempotency_key: &str) -> Option<String> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .running_for_key(idempotency_key)
+    }
+
+    /// Attach the task running a job, so that cancelling the job aborts it
+    ///
+    /// The task is aborted right away if the job was cancelled before this call.
+    pub fn attach(&self, id: &str, task: AbortHandle) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner
+            .jobs
+            .get(id)
+            .is_some_and(|job| job.state == JobState::Running)
+        {
+            inner.tasks.insert(id.to_string(), task);
+        } else {
+            task.abort();
+        }
+    }
+
+    /// Record the final state of a running job; unknown ids and jobs that
+    /// have already ended (e.g. been cancelled) are ignored
+    pub fn finish(&self, id: &str, state: JobState) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.tasks.remove(id);
+        if let Some(job) = inner
+            .jobs
+            .get_mut(id)
+            .filter(|job| job.state == JobState::Running)
+        {
+            job.state = state;
+        }
+        inner.evict_finished(self.max_retained_bytes);
+    }
+
+    /// Cancel a running job
+    ///
+    /// A job whose task is attached has it aborted and is kept as `cancelled`.
+    /// A job without a task yet has not started, and is removed instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NotFound` for unknown ids and `AppError::Conflict`
+    /// for jobs that have already ended.
+    pub fn cancel(&self, id: &str) -> Result<(), AppError> {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let inner = &mut *guard;
+        let job = inner
+            .jobs
+            .get_mut(id)
+            .ok_or_else(|| AppError::NotFound(format!("job '{}'", id)))?;
+        if job.state != JobState::Running {
+            return Err(AppError::Conflict(format!(
+                "job '{}' has already ended ({})",
+                id,
+                job.state.status().as_str()
+            )));
+        }
+
+        match inner.tasks.remove(id) {
+            Some(task) => {
+                task.abort();
+                job.state = JobState::Cancelled;
+            }
+            None => {
+                inner.jobs.remove(id);
+                inner.order.retain(|queued| queued != id);
+                let jobs = &inner.jobs;
+                inner.keys.retain(|_, job_id| jobs.contains_key(job_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Snapshot of a job
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .jobs
+            .get(id)
+            .cloned()
+    }
+}
+
+impl JobStoreInner {
+    fn running_for_key(&self, key: &str) -> Option<String> {
+        let id = self.keys.get(key)?;
+        let running = self.jobs.get(id)?.state == JobState::Running;
+        running.then(|| id.clone())
+    }
+
+    /// Drop the oldest finished jobs while over `MAX_RETAINED_JOBS` jobs or
+    /// `max_bytes` of retained images
+    fn evict_finished(&mut self, max_bytes: usize) {
+        let mut bytes: usize = self.jobs.values().map(Job::retained_bytes).sum();
+        let mut index = 0;
+        while (self.jobs.len() > MAX_RETAINED_JOBS || bytes > max_bytes) && index < self.order.len()
+        {
+            let finished = self
+                .jobs
+                .get(&self.order[index])
+                .is_none_or(|job| job.state != JobState::Running);
+            if finished {
+                if let Some(job) = self
+                    .order
+                    .remove(index)
+                    .and_then(|id| self.jobs.remove(&id))
+                {
+                    bytes -= job.retained_bytes();
+                }
+            } else {
+                index += 1;
+            }
+        }
+
+        let jobs = &self.jobs;
+        self.keys.retain(|_, id| jobs.contains_key(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn succeeded() -> JobState {
+        JobState::Succeeded(JobOutput {
+            content_type: "image/png".to_string(),
+            body: Bytes::from_static(b"png"),
+        })
+    }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let store = JobStore::new();
+        let id = store.create(Bytes::from_static(b"input"));
+
+        let job = store.get(&id).unwrap();
+        assert_eq!(job.state.status(), JobStatus::Running);
+        assert_eq!(job.input, Bytes::from_static(b"input"));
+
+        store.finish(&id, succeeded());
+        assert_eq!(store.get(&id).unwrap().state, succeeded());
+        assert!(store.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_same_key_joins_running_job() {
+        let store = JobStore::new();
+        let (first, created) = store.create_or_join(Bytes::new(), Some("retry-1"));
+        assert!(created);
+
+        let (second, created) = store.create_or_join(Bytes::new(), Some("retry-1"));
+        assert!(!created);
+        assert_eq!(second, first);
+        assert_eq!(store.running_for_key("retry-1"), Some(first.clone()));
+
+        let (other, created) = store.create_or_join(Bytes::new(), Some("retry-2"));
+        assert!(created);
+        assert_ne!(other, first);
+
+        // Once finished, the key starts a fresh job
+        store.finish(&first, succeeded());
+        assert!(store.running_for_key("retry-1").is_none());
+        let (third, created) = store.create_or_join(Bytes::new(), Some("retry-1"));
+        assert!(created);
+        assert_ne!(third, first);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_attached_task() {
+        let store = JobStore::new();
+        let (id, _) = store.create_or_join(Bytes::new(), Some("retry-1"));
+        let task = tokio::spawn(std::future::pending::<()>());
+        store.attach(&id, task.abort_handle());
+
+        store.cancel(&id).unwrap();
+
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert_eq!(store.get(&id).unwrap().state, JobState::Cancelled);
+        // A late result does not overwrite the cancellation, and the key is free again
+        store.finish(&id, succeeded());
+        assert_eq!(store.get(&id).unwrap().state, JobState::Cancelled);
+        assert!(store.running_for_key("retry-1").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_before_attach_removes_job_and_aborts_late_task() {
+        let store = JobStore::new();
+        let id = store.create(Bytes::new());
+
+        store.cancel(&id).unwrap();
+        assert!(store.get(&id).is_none());
+
+        let task = tokio::spawn(std::future::pending::<()>());
+        store.attach(&id, task.abort_handle());
+        assert!(task.await.unwrap_err().is_cancelled());
+    }
+
+    #[test]
+    fn test_cancel_unknown_or_ended_job() {
+        let store = JobStore::new();
+        assert!(matches!(
+            store.cancel("missing"),
+            Err(AppError::NotFound(_))
+        ));
+
+        let id = store.create(Bytes::new());
+        store.finish(&id, succeeded());
+        assert!(matches!(store.cancel(&id), Err(AppError::Conflict(_))));
+        assert_eq!(store.get(&id).unwrap().state, succeeded());
+    }
+
+    #[test]
+    fn test_failure_keeps_error_details() {
+        let failure = JobFailure::from(&AppError::InvalidInput("bad prompt".into()));
+
+        assert_eq!(failure.status, StatusCode::BAD_REQUEST);
+        assert_eq!(failure.error_type, "invalid_input");
+        assert!(failure.error.contains("bad prompt"));
+    }
+
+    #[test]
+    fn test_eviction_skips_running_jobs() {
+        let store = JobStore::new();
+        let running = store.create(Bytes::new());
+        let finished = store.create(Bytes::new());
+        store.finish(&finished, succeeded());
+
+        for _ in 0..MAX_RETAINED_JOBS - 1 {
+            store.create(Bytes::new());
+        }
+
+        assert!(store.get(&running).is_some());
+        assert!(store.get(&finished).is_none());
+        assert_eq!(store.inner.lock().unwrap().jobs.len(), MAX_RETAINED_JOBS);
+    }
+
+    #[test]
+    fn test_eviction_caps_retained_bytes() {
+        let store = JobStore::with_limits(MAX_RUNNING_JOBS, 10);
+        let running = store.create(Bytes::from_static(b"input"));
+        let first = store.create(Bytes::new());
+        store.finish(&first, succeeded());
+        let second = store.create(Bytes::new());
+
+        // 5 input bytes + 3 + 3 output bytes is over the 10 byte budget
+        store.finish(&second, succeeded());
+
+        assert!(store.get(&running).is_some());
+        assert!(store.get(&first).is_none());
+        assert_eq!(store.get(&second).unwrap().state, succeeded());
+    }
+
+    #[test]
+    fn test_reserve_limits_running_jobs() {
+        let store = JobStore::with_limits(1, MAX_RETAINED_JOB_BYTES);
+        let permit = store.reserve().unwrap();
+
+        let err = store.reserve().unwrap_err();
+        assert!(matches!(err, AppError::ServiceUnavailable(_)));
+        assert!(err.to_string().contains("maximum of 1 jobs"));
+
+        drop(permit);
+        assert!(store.reserve().is_ok());
+    }
+}
@@ -7,6 +7,15 @@
 //! # Architecture
 //!
 //! The application is structured into several key modules:
+//! - `app`: Router construction and middleware stack
+//! - `state`: Shared application state
+//! - `metrics`: In-process request metrics
+//! - `audit`: Audit log events for compliance
+//! - `jobs`: In-memory store of asynchronous edit jobs
+//! - `uploads`: In-memory store of images uploaded ahead of an edit
+//! - `results`: In-memory, content-addressable store of edit results
+//! - `shutdown`: Flushing of buffered metrics and logs on shutdown
+//! - `log_capture`: Recording of log events for tests (`test-mock` feature)
 //! - `routes`: HTTP endpoint handlers
 //! - `services`: AI provider service implementations
 //! - `models`: Request/response data structures
@@ -22,6 +31,34 @@
 
 // Module declarations - these modules will be implemented in subsequent tasks
 
+/// Router construction and middleware stack
+pub mod app;
+
+/// Shared application state
+pub mod state;
+
+/// In-process request metrics
+pub mod metrics;
+
+/// Audit log events emitted on the `audit` tracing target
+pub mod audit;
+
+/// In-memory store of asynchronous edit jobs
+pub mod jobs;
+
+/// In-memory store of images uploaded ahead of an edit
+pub mod uploads;
+
+/// In-memory, content-addressable store of edit results
+pub mod results;
+
+/// Flushing of buffered metrics and logs on shutdown
+pub mod shutdown;
+
+/// Recording of log events for tests
+#[cfg(any(test, feature = "test-mock"))]
+pub mod log_capture;
+
 /// Configuration management
 pub mod config;
 
@@ -0,0 +1,89 @@
+//! Capture of `tracing` events for tests
+//!
+//! `LogCapture` is a `tracing_subscriber` layer that records every event with
+//! its target, level and fields, so tests can assert what was (or wasn't)
+//! logged. Installed as the thread's default subscriber, it sees the events of
+//! a current-thread Tokio test, including those of requests driven through
+//! the router in-process.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::Level;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+/// Fields of one event, formatted, keyed by name (the text is under `message`)
+pub type Fields = BTreeMap<String, String>;
+
+/// One recorded event
+#[derive(Debug, Clone)]
+pub struct CapturedEvent {
+    /// Target the event was logged on, usually its module path
+    pub target: String,
+    /// Level the event was logged at
+    pub level: Level,
+    /// Fields of the event
+    pub fields: Fields,
+}
+
+/// Layer recording every event it sees
+#[derive(Clone, Default)]
+pub struct LogCapture(Arc<Mutex<Vec<CapturedEvent>>>);
+
+impl LogCapture {
+    /// Install the capture as the thread's default subscriber
+    pub fn install(&self) -> tracing::subscriber::DefaultGuard {
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(self.clone()))
+    }
+
+    /// Events captured so far
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Fields of the events captured on `target`
+    pub fn target_fields(&self, target: &str) -> Vec<Fields> {
+        self.events()
+            .into_iter()
+            .filter(|event| event.target == target)
+            .map(|event| event.fields)
+            .collect()
+    }
+
+    /// Number of events captured at `level`
+    pub fn count_level(&self, level: Level) -> usize {
+        self.events()
+            .iter()
+            .filter(|event| event.level == level)
+            .count()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for LogCapture {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(CapturedEvent {
+                target: event.metadata().target().to_string(),
+                level: *event.metadata().level(),
+                fields,
+            });
+    }
+}
@@ -4,49 +4,35 @@
 //! It initializes logging, loads configuration, sets up the router with middleware,
 //! and starts the HTTP server.
 
-use axum::{
-    extract::DefaultBodyLimit,
-    http::StatusCode,
-    routing::{get, post},
-    Router,
-};
 use std::net::SocketAddr;
-use std::time::Duration;
-use tower::ServiceBuilder;
-use tower_http::{
-    compression::CompressionLayer,
-    cors::CorsLayer,
-    timeout::TimeoutLayer,
-    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
-    LatencyUnit,
-};
-use tracing::Level;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{filter::FilterExt, layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 // Import modules from the library
+use frameforge_server::app;
 use frameforge_server::config::AppConfig;
-use frameforge_server::middleware::RateLimiter;
-use frameforge_server::routes;
+use frameforge_server::middleware::DebugRequestFilter;
+use frameforge_server::services::factory;
+use frameforge_server::shutdown::{self, LogFlusher, SHUTDOWN_FLUSH_TIMEOUT};
+use frameforge_server::state::AppState;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Task 8: Initialize tracing/logging
     // Set up tracing with environment filter support
     // This allows control via RUST_LOG environment variable (e.g., RUST_LOG=debug)
+    // Requests with an authorized `X-Debug` header are additionally logged at DEBUG
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        // Default to INFO level logging
+        "info,frameforge_server=debug,tower_http=debug".into()
+    });
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| {
-                    // Default to INFO level logging
-                    "info,frameforge_server=debug,tower_http=debug".into()
-                }),
-        )
         .with(
             tracing_subscriber::fmt::layer()
                 .with_target(true)
                 .with_thread_ids(true)
                 .with_file(true)
-                .with_line_number(true),
+                .with_line_number(true)
+                .with_filter(env_filter.or(DebugRequestFilter)),
         )
         .init();
 
@@ -55,126 +41,55 @@ async fn main() -> anyhow::Result<()> {
     // Load configuration from environment variables
     let config = AppConfig::load()?;
 
+    if config.dev_mode {
+        tracing::warn!("DEV_MODE enabled: unavailable providers are served by the mock editor");
+    }
+
+    // Fail fast if unknown providers would fall back to an unusable provider
+    factory::validate_fallback_provider(&config)?;
+
     // Log configuration (without sensitive data)
     tracing::info!(
         host = %config.host,
         port = config.port,
         model_id = %config.google_model_id,
+        fallback_provider = ?config.fallback_provider,
         allowed_origins = ?config.allowed_origins,
         "Configuration loaded"
     );
 
-    // Task 34: Set up CORS middleware to match Python backend
-    // Python backend uses: allow_credentials=True, allow_methods=["*"], allow_headers=["*"]
-    // Note: When allow_credentials is true, we must specify headers explicitly (not Any)
-    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
-    use axum::http::Method;
-
-    let cors = if config.allowed_origins.contains(&"*".to_string()) {
-        tracing::warn!("CORS configured with wildcard (*) - allowing all origins");
-        CorsLayer::permissive()
-    } else {
-        tracing::info!("CORS configured with specific origins: {:?}", config.allowed_origins);
-        let origins = config
-            .allowed_origins
-            .iter()
-            .filter_map(|origin| origin.parse().ok())
-            .collect::<Vec<_>>();
-
-        // When using allow_credentials, we must specify headers explicitly
-        let allowed_headers = vec![
-            AUTHORIZATION,
-            CONTENT_TYPE,
-            "x-google-api-key".parse().unwrap(),
-            "x-gemini-api-key".parse().unwrap(),
-            "x-fal-key".parse().unwrap(),
-        ];
-
-        CorsLayer::new()
-            .allow_origin(origins)
-            .allow_credentials(true)
-            .allow_methods(vec![
-                Method::GET,
-                Method::POST,
-                Method::OPTIONS,
-            ])
-            .allow_headers(allowed_headers)
-    };
-
-    // Task 41: Create rate limiter (implementation available in middleware::rate_limit)
-    // Note: Rate limiting middleware is implemented but not yet integrated into the router
-    // It can be added later by using axum::middleware::from_fn with rate_limit_middleware
-    let _rate_limiter = RateLimiter::new();
-
-    // Build the Axum router with all API endpoints
-    // Middleware layers are applied in reverse order (bottom executes first)
-    let app = Router::new()
-        // API routes (Task 33)
-        .route("/api/health", get(routes::health::health_check))
-        .route("/api/providers", get(routes::providers::list_providers))
-        .route("/api/edit", post(routes::edit::edit_image))
-        // Root endpoint
-        .route("/", get(root_handler))
-        // Add AppConfig to shared state for dependency injection
-        .with_state(config.clone())
-        // Task 37: Add request size limits (50MB for image uploads)
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024)) // 50MB
-        // Task 40: Add timeout layers (different timeouts for different endpoints)
-        // Edit endpoint gets 5 minutes for AI processing
-        // Returns 408 Request Timeout on timeout
-        .layer(
-            ServiceBuilder::new()
-                .layer(TimeoutLayer::with_status_code(
-                    StatusCode::REQUEST_TIMEOUT,
-                    Duration::from_secs(300) // 5 minutes for AI processing
-                ))
-        )
-        // Task 35: Add enhanced tracing middleware for request/response logging
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(
-                    DefaultMakeSpan::new()
-                        .include_headers(true)
-                        .level(Level::INFO),
-                )
-                .on_response(
-                    DefaultOnResponse::new()
-                        .include_headers(true)
-                        .latency_unit(LatencyUnit::Millis)
-                        .level(Level::INFO),
-                ),
-        )
-        // Task 36: Add compression middleware (br/brotli and gzip)
-        .layer(CompressionLayer::new().br(true).gzip(true))
-        // Task 34: Add CORS middleware
-        .layer(cors);
+    // Build the Axum router with all API endpoints and middleware, keeping a
+    // handle on the state for the shutdown flush
+    let state = AppState::new(config.clone());
+    let app = app::build_router_with_state(state.clone());
 
     // Bind to the configured host and port
-    let addr = SocketAddr::new(
-        config.host.parse()?,
-        config.port,
-    );
+    let addr = SocketAddr::new(config.host.parse()?, config.port);
 
     tracing::info!("Server listening on {}", addr);
 
     // Start the server with graceful shutdown
     let listener = tokio::net::TcpListener::bind(addr).await?;
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // Peer addresses identify clients for rate limiting (Task 41)
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    // Write out buffered metrics and logs before exiting
+    shutdown::flush_all(
+        &[state.metrics.as_ref(), &LogFlusher],
+        SHUTDOWN_FLUSH_TIMEOUT,
+    )
+    .await;
 
     tracing::info!("Server shutdown complete");
     Ok(())
 }
 
-/// Root handler for the server
-///
-/// Returns basic information about the server.
-async fn root_handler() -> &'static str {
-    "FrameForge Server - Axum Implementation"
-}
-
 /// Graceful shutdown signal handler
 ///
 /// This function listens for SIGTERM and SIGINT signals (Ctrl+C)
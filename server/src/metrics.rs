@@ -0,0 +1,460 @@
+//! In-process request metrics
+//!
+//! This module keeps simple counters for edit requests, labelled by tenant,
+//! provider family, and outcome, plus histograms of input image sizes, and
+//! renders them in the Prometheus text exposition format for the `/metrics`
+//! endpoint.
+//!
+//! Every label has bounded cardinality: providers are collapsed to their
+//! family (`google`, `fal`, `webhook`, ...), and tenants beyond the first
+//! `MAX_TENANT_LABELS` share the `other` label.
+//!
+//! Security: labels never include API keys, prompts, or provider URLs.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::sync::Mutex;
+
+use crate::error::AppError;
+use crate::models::tenant::TenantId;
+use crate::services::factory::provider_family;
+use crate::utils::image_utils;
+
+/// Outcome label used for successful edits
+pub const OUTCOME_SUCCESS: &str = "success";
+
+/// Most distinct tenants labelled individually
+pub const MAX_TENANT_LABELS: usize = 100;
+
+/// Tenant label shared by tenants seen after the first `MAX_TENANT_LABELS`
+pub const OTHER_TENANT: &str = "other";
+
+/// Label set for the edit request counter
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct EditLabels {
+    tenant: String,
+    provider: String,
+    outcome: String,
+}
+
+/// Upper bounds of the input byte size buckets (64 KiB to 50 MiB)
+const INPUT_BYTES_BUCKETS: &[f64] = &[
+    65_536.0,
+    262_144.0,
+    1_048_576.0,
+    4_194_304.0,
+    16_777_216.0,
+    52_428_800.0,
+];
+
+/// Upper bounds of the input pixel count buckets (0.25 to 64 megapixels)
+const INPUT_PIXELS_BUCKETS: &[f64] = &[
+    262_144.0,
+    1_048_576.0,
+    4_194_304.0,
+    16_777_216.0,
+    67_108_864.0,
+];
+
+/// Fixed-bucket histogram
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts; the last entry is the `+Inf` bucket
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Record one observation
+    pub fn observe(&mut self, value: f64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Cumulative `(upper_bound, count)` pairs, ending with `+Inf`
+    pub fn buckets(&self) -> Vec<(f64, u64)> {
+        let mut cumulative = 0;
+        self.bounds
+            .iter()
+            .copied()
+            .chain(std::iter::once(f64::INFINITY))
+            .zip(&self.counts)
+            .map(|(bound, count)| {
+                cumulative += count;
+                (bound, cumulative)
+            })
+            .collect()
+    }
+
+    /// Sum of all observations
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bound, count) in self.buckets() {
+            let le = if bound.is_infinite() {
+                "+Inf".to_string()
+            } else {
+                bound.to_string()
+            };
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, count);
+        }
+        let _ = writeln!(out, "{}_sum {}", name, self.sum);
+        let _ = writeln!(out, "{}_count {}", name, self.count);
+    }
+}
+
+/// Histograms describing uploaded images
+#[derive(Debug, Clone)]
+struct InputHistograms {
+    bytes: Histogram,
+    pixels: Histogram,
+}
+
+impl Default for InputHistograms {
+    fn default() -> Self {
+        Self {
+            bytes: Histogram::new(INPUT_BYTES_BUCKETS),
+            pixels: Histogram::new(INPUT_PIXELS_BUCKETS),
+        }
+    }
+}
+
+/// Edit counters and the tenants labelled so far
+#[derive(Debug, Default)]
+struct EditCounters {
+    counts: BTreeMap<EditLabels, u64>,
+    tenants: BTreeSet<String>,
+}
+
+impl EditCounters {
+    /// Label for `tenant`, or `OTHER_TENANT` once the label budget is spent
+    fn tenant_label(&mut self, tenant: &str) -> String {
+        if self.tenants.contains(tenant) {
+            return tenant.to_string();
+        }
+        if self.tenants.len() >= MAX_TENANT_LABELS {
+            return OTHER_TENANT.to_string();
+        }
+        self.tenants.insert(tenant.to_string());
+        tenant.to_string()
+    }
+}
+
+/// Shared metrics registry
+#[derive(Debug, Default)]
+pub struct Metrics {
+    edits: Mutex<EditCounters>,
+    inputs: Mutex<InputHistograms>,
+}
+
+impl Metrics {
+    /// Create an empty metrics registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one edit for the given tenant, provider, and outcome
+    ///
+    /// `provider` is the requested provider name, labelled by its family (see
+    /// [`provider_family`]). `outcome` is `OUTCOME_SUCCESS` or an `AppError`
+    /// error type (see [`outcome_label`]).
+    pub fn record_edit(&self, tenant: &TenantId, provider: &str, outcome: &str) {
+        let mut edits = self.edits.lock().unwrap_or_else(|e| e.into_inner());
+        let labels = EditLabels {
+            tenant: edits.tenant_label(tenant.as_str()),
+            provider: provider_family(provider).to_string(),
+            outcome: outcome.to_string(),
+        };
+
+        *edits.counts.entry(labels).or_insert(0) += 1;
+    }
+
+    /// Number of edits recorded for the given label set
+    ///
+    /// `provider` is a provider family label, e.g. `fal`.
+    pub fn edit_count(&self, tenant: &str, provider: &str, outcome: &str) -> u64 {
+        let labels = EditLabels {
+            tenant: tenant.to_string(),
+            provider: provider.to_string(),
+            outcome: outcome.to_string(),
+        };
+
+        let edits = self.edits.lock().unwrap_or_else(|e| e.into_inner());
+        edits.counts.get(&labels).copied().unwrap_or(0)
+    }
+
+    /// Edits recorded per provider family, across tenants and outcomes
+    pub fn edits_by_provider(&self) -> BTreeMap<String, u64> {
+        let edits = self.edits.lock().unwrap_or_else(|e| e.into_inner());
+        let mut totals = BTreeMap::new();
+        for (labels, count) in edits.counts.iter() {
+            *totals.entry(labels.provider.clone()).or_insert(0) += count;
+        }
+        totals
+    }
+
+    /// Record the size of every uploaded image
+    ///
+    /// Each image is one observation, so multi-image and batch requests add one
+    /// observation per image and the histogram sums cover all uploaded data.
+    /// Images whose dimensions can't be read are only counted in the byte histogram.
+    pub fn record_input_images<T: AsRef<[u8]>>(&self, images: &[T]) {
+        // Headers are read before locking, so concurrent requests don't wait on them
+        let sizes: Vec<_> = images
+            .iter()
+            .map(|image| {
+                let data = image.as_ref();
+                let pixels = image_utils::image_dimensions(data)
+                    .ok()
+                    .map(|(width, height)| f64::from(width) * f64::from(height));
+                (data.len(), pixels)
+            })
+            .collect();
+
+        let mut inputs = self.inputs.lock().unwrap_or_else(|e| e.into_inner());
+        for (bytes, pixels) in sizes {
+            inputs.bytes.observe(bytes as f64);
+            if let Some(pixels) = pixels {
+                inputs.pixels.observe(pixels);
+            }
+        }
+    }
+
+    /// Snapshot of the input byte size histogram
+    pub fn input_bytes(&self) -> Histogram {
+        self.inputs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .bytes
+            .clone()
+    }
+
+    /// Snapshot of the input pixel count histogram
+    pub fn input_pixels(&self) -> Histogram {
+        self.inputs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pixels
+            .clone()
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let edits = self.edits.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+
+        out.push_str("# HELP frameforge_edit_requests_total Image edit requests by tenant, provider and outcome\n");
+        out.push_str("# TYPE frameforge_edit_requests_total counter\n");
+        for (labels, count) in edits.counts.iter() {
+            let _ = writeln!(
+                out,
+                "frameforge_edit_requests_total{{tenant=\"{}\",provider=\"{}\",outcome=\"{}\"}} {}",
+                escape_label(&labels.tenant),
+                escape_label(&labels.provider),
+                escape_label(&labels.outcome),
+                count
+            );
+        }
+        drop(edits);
+
+        let inputs = self.inputs.lock().unwrap_or_else(|e| e.into_inner());
+        inputs.bytes.render(
+            &mut out,
+            "frameforge_input_image_bytes",
+            "Size of uploaded images in bytes",
+        );
+        inputs.pixels.render(
+            &mut out,
+            "frameforge_input_image_pixels",
+            "Pixel count of uploaded images",
+        );
+
+        out
+    }
+}
+
+/// Outcome label for a handler result: `success` or the error type
+pub fn outcome_label<T>(result: &Result<T, AppError>) -> &'static str {
+    match result {
+        Ok(_) => OUTCOME_SUCCESS,
+        Err(e) => e.error_type(),
+    }
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_edit_counts_per_label_set() {
+        let metrics = Metrics::new();
+        let acme: TenantId = "acme".parse().unwrap();
+
+        metrics.record_edit(&acme, "google", OUTCOME_SUCCESS);
+        metrics.record_edit(&acme, "google", OUTCOME_SUCCESS);
+        metrics.record_edit(&TenantId::anonymous(), "google", "provider_error");
+
+        assert_eq!(metrics.edit_count("acme", "google", OUTCOME_SUCCESS), 2);
+        assert_eq!(
+            metrics.edit_count("anonymous", "google", "provider_error"),
+            1
+        );
+        assert_eq!(metrics.edit_count("acme", "fal", OUTCOME_SUCCESS), 0);
+    }
+
+    #[test]
+    fn test_edits_by_provider_sums_tenants_and_outcomes() {
+        let metrics = Metrics::new();
+        metrics.record_edit(&"acme".parse().unwrap(), "google", OUTCOME_SUCCESS);
+        metrics.record_edit(&TenantId::anonymous(), "google", "provider_error");
+        metrics.record_edit(&TenantId::anonymous(), "nano-banana", OUTCOME_SUCCESS);
+        metrics.record_edit(
+            &TenantId::anonymous(),
+            "fal:fal-ai/flux/dev",
+            OUTCOME_SUCCESS,
+        );
+
+        let totals = metrics.edits_by_provider();
+        assert_eq!(totals.get("google"), Some(&3));
+        assert_eq!(totals.get("fal"), Some(&1));
+        assert_eq!(totals.len(), 2);
+    }
+
+    #[test]
+    fn test_render_includes_tenant_label() {
+        let metrics = Metrics::new();
+        metrics.record_edit(
+            &"team-7".parse().unwrap(),
+            "fal:fal-ai/flux/dev",
+            OUTCOME_SUCCESS,
+        );
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE frameforge_edit_requests_total counter"));
+        assert!(text.contains(
+            "frameforge_edit_requests_total{tenant=\"team-7\",provider=\"fal\",outcome=\"success\"} 1"
+        ));
+    }
+
+    #[test]
+    fn test_provider_urls_are_not_labels() {
+        let metrics = Metrics::new();
+        metrics.record_edit(
+            &TenantId::anonymous(),
+            "webhook:https://10.0.0.5/edit?token=abc",
+            OUTCOME_SUCCESS,
+        );
+
+        let text = metrics.render();
+        assert!(text.contains("provider=\"webhook\""));
+        assert!(!text.contains("10.0.0.5"));
+    }
+
+    #[test]
+    fn test_tenant_labels_are_capped() {
+        let metrics = Metrics::new();
+        for i in 0..MAX_TENANT_LABELS + 5 {
+            let tenant: TenantId = format!("tenant-{}", i).parse().unwrap();
+            metrics.record_edit(&tenant, "google", OUTCOME_SUCCESS);
+        }
+        metrics.record_edit(&"tenant-0".parse().unwrap(), "google", OUTCOME_SUCCESS);
+
+        assert_eq!(metrics.edit_count("tenant-0", "google", OUTCOME_SUCCESS), 2);
+        assert_eq!(
+            metrics.edit_count(OTHER_TENANT, "google", OUTCOME_SUCCESS),
+            5
+        );
+        let series = metrics
+            .render()
+            .matches("frameforge_edit_requests_total{")
+            .count();
+        assert_eq!(series, MAX_TENANT_LABELS + 1);
+    }
+
+    #[test]
+    fn test_outcome_label() {
+        let ok: Result<(), AppError> = Ok(());
+        let err: Result<(), AppError> = Err(AppError::InvalidInput("bad".to_string()));
+
+        assert_eq!(outcome_label(&ok), "success");
+        assert_eq!(outcome_label(&err), "invalid_input");
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::new(&[10.0, 100.0]);
+        histogram.observe(5.0);
+        histogram.observe(50.0);
+        histogram.observe(500.0);
+
+        assert_eq!(
+            histogram.buckets(),
+            vec![(10.0, 1), (100.0, 2), (f64::INFINITY, 3)]
+        );
+        assert_eq!(histogram.sum(), 555.0);
+        assert_eq!(histogram.count(), 3);
+    }
+
+    #[test]
+    fn test_record_input_images() {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(100, 50));
+        let png = image_utils::image_to_bytes(&img, image::ImageFormat::Png).unwrap();
+        let metrics = Metrics::new();
+
+        metrics.record_input_images(&[png.to_vec(), b"not an image".to_vec()]);
+
+        let bytes = metrics.input_bytes();
+        assert_eq!(bytes.count(), 2);
+        assert_eq!(bytes.sum(), (png.len() + 12) as f64);
+        // Both uploads are far below the smallest bucket
+        assert_eq!(bytes.buckets()[0], (65_536.0, 2));
+
+        let pixels = metrics.input_pixels();
+        assert_eq!(pixels.count(), 1);
+        assert_eq!(pixels.sum(), 5000.0);
+
+        let text = metrics.render();
+        assert!(text.contains("frameforge_input_image_pixels_bucket{le=\"262144\"} 1"));
+        assert!(text.contains("frameforge_input_image_bytes_bucket{le=\"+Inf\"} 2"));
+        assert!(text.contains("frameforge_input_image_pixels_sum 5000"));
+    }
+}
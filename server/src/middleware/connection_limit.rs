@@ -0,0 +1,131 @@
+//! Global limit on requests in flight
+//!
+//! Caps how many requests the server handles at once (`MAX_CONNECTIONS`), so
+//! a flood of slow clients - e.g. ones trickling a request body in - cannot
+//! tie up unbounded sockets and memory. Requests over the limit are rejected
+//! at once with a 503 instead of queueing.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use crate::error::AppError;
+
+/// Permits for requests in flight, shared by every clone
+#[derive(Debug, Clone)]
+pub struct ConnectionLimit {
+    permits: Arc<Semaphore>,
+    max: usize,
+}
+
+impl ConnectionLimit {
+    /// Allow at most `max` requests in flight
+    pub fn new(max: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max)),
+            max,
+        }
+    }
+
+    /// Number of requests currently holding a permit
+    pub fn in_flight(&self) -> usize {
+        self.max - self.permits.available_permits()
+    }
+}
+
+/// Connection limit middleware
+///
+/// Add to the router with `axum::middleware::from_fn_with_state`. The permit
+/// is held until the response head is returned.
+///
+/// # Errors
+///
+/// Returns `AppError::ServiceUnavailable` (503) when the limit is reached.
+pub async fn connection_limit_middleware(
+    State(limit): State<ConnectionLimit>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Ok(_permit) = limit.permits.try_acquire() else {
+        tracing::warn!(
+            max_connections = limit.max,
+            path = %request.uri().path(),
+            "Rejecting request over the connection limit"
+        );
+        return Err(AppError::ServiceUnavailable(format!(
+            "server is handling its maximum of {} requests; retry shortly",
+            limit.max
+        )));
+    };
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    /// Router whose `/slow` requests notify `entered`, then wait until
+    /// `release` is notified
+    fn app(max: usize, entered: Arc<Notify>, release: Arc<Notify>) -> Router {
+        Router::new()
+            .route(
+                "/slow",
+                get(move || async move {
+                    entered.notify_one();
+                    release.notified().await;
+                    "done"
+                }),
+            )
+            .route("/fast", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                ConnectionLimit::new(max),
+                connection_limit_middleware,
+            ))
+    }
+
+    fn get_request(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_over_limit_rejected() {
+        let entered = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+        let app = app(1, entered.clone(), release.clone());
+
+        // The slow request holds the only permit while it waits
+        let slow = tokio::spawn(app.clone().oneshot(get_request("/slow")));
+        entered.notified().await;
+
+        let rejected = app.clone().oneshot(get_request("/fast")).await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        release.notify_one();
+        assert_eq!(slow.await.unwrap().unwrap().status(), StatusCode::OK);
+
+        // The permit is returned once the slow request finishes
+        let allowed = app.oneshot(get_request("/fast")).await.unwrap();
+        assert_eq!(allowed.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_requests_within_limit_allowed() {
+        let app = app(2, Arc::new(Notify::new()), Arc::new(Notify::new()));
+
+        for _ in 0..3 {
+            let response = app.clone().oneshot(get_request("/fast")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Per-request debug logging
+//!
+//! A request sent with `X-Debug: true` and the server API key
+//! (`Authorization: Bearer <SERVER_API_KEY>`) is logged at DEBUG level
+//! whatever the global filter, while concurrent requests keep the configured
+//! level. The middleware marks the request in a task-local, and
+//! [`DebugRequestFilter`] — combined with the global filter through
+//! `FilterExt::or` — lets DEBUG spans and events through while it is set.
+//! Work the request hands to other tasks is not covered.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use tracing::level_filters::LevelFilter;
+use tracing::subscriber::Interest;
+use tracing::{Level, Metadata};
+use tracing_subscriber::layer::{Context, Filter};
+
+/// Request header asking for DEBUG logging of that request
+pub const DEBUG_HEADER: &str = "X-Debug";
+
+tokio::task_local! {
+    /// Set while a request with authorized `X-Debug` is being handled
+    static DEBUG_REQUEST: bool;
+}
+
+/// Key that authorizes `X-Debug`, if the server has one
+#[derive(Debug, Clone, Default)]
+pub struct DebugLogging {
+    api_key: Option<Arc<str>>,
+}
+
+impl DebugLogging {
+    /// Debug logging authorized by `api_key`; without a key `X-Debug` is ignored
+    pub fn new(api_key: Option<&str>) -> Self {
+        Self {
+            api_key: api_key.map(Arc::from),
+        }
+    }
+
+    /// Whether the headers ask for debug logging and carry the server API key
+    fn authorizes(&self, headers: &HeaderMap) -> bool {
+        let Some(key) = &self.api_key else {
+            return false;
+        };
+        let wants_debug = headers
+            .get(DEBUG_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"));
+
+        wants_debug && bearer_token(headers).is_some_and(|token| keys_match(token, key))
+    }
+}
+
+/// Token of an `Authorization: Bearer` header
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?.trim();
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// Compare keys without stopping at the first differing byte
+pub(crate) fn keys_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether the current task is handling a request with authorized `X-Debug`
+pub fn in_debug_request() -> bool {
+    DEBUG_REQUEST.try_with(|debug| *debug).unwrap_or(false)
+}
+
+/// Debug logging middleware
+///
+/// Add to the router with `axum::middleware::from_fn_with_state`, outside the
+/// `TraceLayer` so its request and response events are covered too.
+/// `X-Debug` without a matching key is ignored rather than rejected.
+pub async fn debug_logging_middleware(
+    State(debug): State<DebugLogging>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !debug.authorizes(request.headers()) {
+        if request.headers().contains_key(DEBUG_HEADER) {
+            tracing::warn!(path = %request.uri().path(), "Ignoring X-Debug without the server API key");
+        }
+        return next.run(request).await;
+    }
+
+    tracing::info!(path = %request.uri().path(), "Debug logging enabled for request");
+    DEBUG_REQUEST.scope(true, next.run(request)).await
+}
+
+/// Per-layer filter enabling DEBUG (and less verbose) spans and events
+/// while the current task handles an `X-Debug` request
+///
+/// Combine it with the global filter, e.g.
+/// `fmt::layer().with_filter(env_filter.or(DebugRequestFilter))`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DebugRequestFilter;
+
+impl<S> Filter<S> for DebugRequestFilter {
+    fn enabled(&self, metadata: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        *metadata.level() <= Level::DEBUG && in_debug_request()
+    }
+
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        // Decided per request, so never cache "disabled" for a callsite
+        if *metadata.level() <= Level::DEBUG {
+            Interest::sometimes()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::DEBUG)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use std::io::Write;
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+    use tracing_subscriber::filter::{EnvFilter, FilterExt};
+    use tracing_subscriber::layer::{Layer, SubscriberExt};
+
+    /// Log output shared with the test
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Captured {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    async fn handler() -> &'static str {
+        tracing::info!("handling request");
+        tracing::debug!("handler detail");
+        "ok"
+    }
+
+    /// Send a request with the given headers and return what was logged for it
+    async fn logs_for(headers: &[(&str, &str)]) -> String {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .with_filter(EnvFilter::new("info").or(DebugRequestFilter));
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+        let app =
+            Router::new()
+                .route("/", get(handler))
+                .layer(axum::middleware::from_fn_with_state(
+                    DebugLogging::new(Some("secret")),
+                    debug_logging_middleware,
+                ));
+        let mut request = Request::builder().uri("/");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        // Logs emitted after the request are back at the global level
+        tracing::debug!("after request");
+        let logs = captured.take();
+        assert!(!logs.contains("after request"));
+        logs
+    }
+
+    #[tokio::test]
+    async fn test_x_debug_with_key_logs_debug_for_that_request() {
+        let logs = logs_for(&[(DEBUG_HEADER, "true"), ("Authorization", "Bearer secret")]).await;
+
+        assert!(logs.contains("handling request"));
+        assert!(logs.contains("handler detail"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_authorized_x_debug_keep_global_level() {
+        for headers in [
+            &[][..],
+            &[(DEBUG_HEADER, "true")][..],
+            &[(DEBUG_HEADER, "true"), ("Authorization", "Bearer wrong!")][..],
+            &[(DEBUG_HEADER, "false"), ("Authorization", "Bearer secret")][..],
+        ] {
+            let logs = logs_for(headers).await;
+
+            assert!(logs.contains("handling request"), "headers {:?}", headers);
+            assert!(!logs.contains("handler detail"), "headers {:?}", headers);
+        }
+    }
+
+    #[test]
+    fn test_without_server_key_x_debug_is_never_authorized() {
+        let mut headers = HeaderMap::new();
+        headers.insert(DEBUG_HEADER, "true".parse().unwrap());
+        headers.insert(AUTHORIZATION, "Bearer ".parse().unwrap());
+
+        assert!(!DebugLogging::new(None).authorizes(&headers));
+        assert!(!DebugLogging::new(Some("secret")).authorizes(&headers));
+    }
+
+    #[test]
+    fn test_keys_match() {
+        assert!(keys_match("secret", "secret"));
+        assert!(!keys_match("secreT", "secret"));
+        assert!(!keys_match("secret2", "secret"));
+    }
+}
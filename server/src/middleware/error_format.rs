@@ -0,0 +1,18 @@
+//! Error format negotiation
+//!
+//! Records whether the client prefers HTML or JSON errors (from `Accept`) for
+//! the duration of the request, so `AppError::into_response` can render the
+//! matching body without access to the request.
+
+use axum::{body::Body, extract::Request, middleware::Next, response::Response};
+
+use crate::error::{ErrorFormat, ERROR_FORMAT};
+
+/// Error format middleware
+///
+/// Add to the router with `axum::middleware::from_fn`, outside every layer
+/// and handler whose errors should be negotiated.
+pub async fn error_format_middleware(request: Request<Body>, next: Next) -> Response {
+    let format = ErrorFormat::from_headers(request.headers());
+    ERROR_FORMAT.scope(format, next.run(request)).await
+}
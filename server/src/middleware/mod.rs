@@ -2,6 +2,17 @@
 //!
 //! This module contains custom middleware for the FrameForge server.
 
+pub mod connection_limit;
+pub mod debug_logging;
+pub mod error_format;
 pub mod rate_limit;
+pub mod upload_limit;
 
-pub use rate_limit::{rate_limit_middleware, RateLimiter};
+pub use connection_limit::{connection_limit_middleware, ConnectionLimit};
+pub use debug_logging::{debug_logging_middleware, DebugLogging, DebugRequestFilter};
+pub use error_format::error_format_middleware;
+pub use rate_limit::{
+    rate_limit_middleware, Clock, FixedWindow, RateLimitAlgorithm, RateLimitStrategy, RateLimiter,
+    SlidingWindowLog, SystemClock,
+};
+pub use upload_limit::{upload_limit_middleware, UploadLimit};
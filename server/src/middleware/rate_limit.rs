@@ -6,73 +6,185 @@
 //! - Other endpoints: 1000 requests/hour per IP
 //!
 //! Security: Never logs IP addresses alongside API keys
+//!
+//! Requests are counted by a `RateLimitStrategy`: a fixed window (default)
+//! or a sliding-window log, chosen with `RATE_LIMIT_ALGORITHM`. The router
+//! mounts the middleware with the `RateLimiter` held in `AppState`, so
+//! `/api/admin/stats` reports the clients it tracks.
+//!
+//! Clients are identified by the peer address from `ConnectInfo`, which the
+//! server provides by serving with `into_make_service_with_connect_info`.
+//! Requests without it (e.g. routers driven directly in tests) share one
+//! client key.
+//!
+//! Time is read through a `Clock`, so tests can move it forward
+//! deterministically instead of sleeping.
 
 use axum::{
     body::Body,
-    extract::ConnectInfo,
+    extract::{ConnectInfo, State},
     http::{Request, Response, StatusCode},
     middleware::Next,
     response::IntoResponse,
 };
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::Mutex;
 
 /// Rate limit configuration
 const EDIT_LIMIT: usize = 100; // requests per hour for /api/edit
 const GENERAL_LIMIT: usize = 1000; // requests per hour for other endpoints
 const WINDOW_DURATION: Duration = Duration::from_secs(3600); // 1 hour
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60); // forget idle clients this often
 
-/// Rate limit entry for an IP address
-#[derive(Debug, Clone)]
-struct RateLimitEntry {
-    count: usize,
-    window_start: Instant,
+/// Source of the current time for rate limit windows
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// Current instant
+    fn now(&self) -> Instant;
 }
 
-/// Rate limiter state
-#[derive(Debug, Clone)]
-pub struct RateLimiter {
-    state: Arc<Mutex<HashMap<String, RateLimitEntry>>>,
+/// Clock reading the monotonic system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
 }
 
-impl RateLimiter {
-    /// Create a new rate limiter
+/// Clock that only moves when advanced, for tests
+#[cfg(any(test, feature = "test-mock"))]
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    elapsed: std::sync::Mutex<Duration>,
+}
+
+#[cfg(any(test, feature = "test-mock"))]
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "test-mock"))]
+impl MockClock {
+    /// Clock starting at the current instant
     pub fn new() -> Self {
         Self {
-            state: Arc::new(Mutex::new(HashMap::new())),
+            start: Instant::now(),
+            elapsed: std::sync::Mutex::new(Duration::ZERO),
         }
     }
 
-    /// Check if a request should be allowed
-    async fn check_rate_limit(&self, ip: &str, path: &str) -> Result<(), Duration> {
-        let mut state = self.state.lock().await;
-        let now = Instant::now();
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
 
-        // Determine limit based on endpoint
-        let limit = if path.starts_with("/api/edit") {
-            EDIT_LIMIT
-        } else {
-            GENERAL_LIMIT
-        };
+#[cfg(any(test, feature = "test-mock"))]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+}
+
+/// Algorithm used to count requests against the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// Counter reset when a window started by the first request ends (`FixedWindow`)
+    #[default]
+    FixedWindow,
+    /// Timestamps of every request in the trailing window (`SlidingWindowLog`)
+    SlidingWindow,
+}
+
+impl FromStr for RateLimitAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "fixed_window" => Ok(RateLimitAlgorithm::FixedWindow),
+            "sliding_window" => Ok(RateLimitAlgorithm::SlidingWindow),
+            other => Err(anyhow::anyhow!(
+                "Invalid RATE_LIMIT_ALGORITHM '{}'. Expected 'fixed_window' or 'sliding_window'",
+                other
+            )),
+        }
+    }
+}
+
+/// Counting of requests per client
+///
+/// Implementations keep their own state behind a lock and may be shared
+/// between tasks. A request at exactly `window` after an earlier one still
+/// counts that earlier request.
+pub trait RateLimitStrategy: std::fmt::Debug + Send + Sync {
+    /// Record a request from `client` at `now` if fewer than `limit` requests
+    /// were counted in its window; otherwise return the time until one is
+    /// allowed again
+    fn check(
+        &self,
+        client: &str,
+        limit: usize,
+        window: Duration,
+        now: Instant,
+    ) -> Result<(), Duration>;
+
+    /// Forget clients with no requests left in their window
+    fn cleanup(&self, window: Duration, now: Instant);
+
+    /// Number of clients currently tracked
+    fn tracked_clients(&self) -> usize;
+}
+
+/// Rate limit entry for an IP address
+#[derive(Debug, Clone)]
+struct RateLimitEntry {
+    count: usize,
+    window_start: Instant,
+}
+
+/// Fixed-window counter
+///
+/// Uses constant memory per client, but a client can send up to twice the
+/// limit across the end of one window and the start of the next.
+#[derive(Debug, Default)]
+pub struct FixedWindow {
+    entries: std::sync::Mutex<HashMap<String, RateLimitEntry>>,
+}
+
+impl RateLimitStrategy for FixedWindow {
+    fn check(
+        &self,
+        client: &str,
+        limit: usize,
+        window: Duration,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut entries = self.entries.lock().unwrap();
 
         // Get or create entry for this IP
-        let entry = state.entry(ip.to_string()).or_insert(RateLimitEntry {
+        let entry = entries.entry(client.to_string()).or_insert(RateLimitEntry {
             count: 0,
             window_start: now,
         });
 
         // Reset window if expired
-        if now.duration_since(entry.window_start) > WINDOW_DURATION {
+        if now.duration_since(entry.window_start) > window {
             entry.count = 0;
             entry.window_start = now;
         }
 
         // Check limit
         if entry.count >= limit {
-            let retry_after = WINDOW_DURATION
+            let retry_after = window
                 .checked_sub(now.duration_since(entry.window_start))
                 .unwrap_or(Duration::from_secs(0));
             return Err(retry_after);
@@ -84,30 +196,171 @@ impl RateLimiter {
         Ok(())
     }
 
-    /// Clean up expired entries (optional optimization)
-    #[allow(dead_code)]
+    fn cleanup(&self, window: Duration, now: Instant) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| now.duration_since(entry.window_start) <= window);
+    }
+
+    fn tracked_clients(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+/// Sliding-window log
+///
+/// Never allows more than the limit in any window-long span, at the cost of
+/// keeping one timestamp per counted request.
+#[derive(Debug, Default)]
+pub struct SlidingWindowLog {
+    logs: std::sync::Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimitStrategy for SlidingWindowLog {
+    fn check(
+        &self,
+        client: &str,
+        limit: usize,
+        window: Duration,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut logs = self.logs.lock().unwrap();
+        let log = logs.entry(client.to_string()).or_default();
+
+        while log
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > window)
+        {
+            log.pop_front();
+        }
+
+        if log.len() >= limit {
+            // Allowed again once the oldest counted request leaves the window
+            let oldest = log.front().copied().unwrap_or(now);
+            return Err(window
+                .checked_sub(now.duration_since(oldest))
+                .unwrap_or(Duration::ZERO));
+        }
+
+        log.push_back(now);
+        Ok(())
+    }
+
+    fn cleanup(&self, window: Duration, now: Instant) {
+        self.logs.lock().unwrap().retain(|_, log| {
+            log.back()
+                .is_some_and(|&at| now.duration_since(at) <= window)
+        });
+    }
+
+    fn tracked_clients(&self) -> usize {
+        self.logs.lock().unwrap().len()
+    }
+}
+
+/// Rate limiter state
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    strategy: Arc<dyn RateLimitStrategy>,
+    clock: Arc<dyn Clock>,
+    /// When idle clients were last forgotten
+    last_cleanup: Arc<std::sync::Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    /// Create a new fixed-window rate limiter using the system clock
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Create a new fixed-window rate limiter reading time from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self::with_strategy(Arc::new(FixedWindow::default()), clock)
+    }
+
+    /// Create a rate limiter using `algorithm` and the system clock
+    pub fn with_algorithm(algorithm: RateLimitAlgorithm) -> Self {
+        let strategy: Arc<dyn RateLimitStrategy> = match algorithm {
+            RateLimitAlgorithm::FixedWindow => Arc::new(FixedWindow::default()),
+            RateLimitAlgorithm::SlidingWindow => Arc::new(SlidingWindowLog::default()),
+        };
+        Self::with_strategy(strategy, Arc::new(SystemClock))
+    }
+
+    /// Create a rate limiter counting requests with `strategy`
+    pub fn with_strategy(strategy: Arc<dyn RateLimitStrategy>, clock: Arc<dyn Clock>) -> Self {
+        let last_cleanup = Arc::new(std::sync::Mutex::new(clock.now()));
+        Self {
+            strategy,
+            clock,
+            last_cleanup,
+        }
+    }
+
+    /// Check if a request should be allowed
+    async fn check_rate_limit(&self, ip: &str, path: &str) -> Result<(), Duration> {
+        // Determine limit based on endpoint
+        let limit = if path.starts_with("/api/edit") {
+            EDIT_LIMIT
+        } else {
+            GENERAL_LIMIT
+        };
+
+        let now = self.clock.now();
+        self.cleanup_if_due(now);
+        self.strategy.check(ip, limit, WINDOW_DURATION, now)
+    }
+
+    /// Forget idle clients at most once per `CLEANUP_INTERVAL`, so the
+    /// number of tracked clients stays bounded by recent traffic
+    fn cleanup_if_due(&self, now: Instant) {
+        let mut last_cleanup = self.last_cleanup.lock().unwrap();
+        if now.duration_since(*last_cleanup) >= CLEANUP_INTERVAL {
+            *last_cleanup = now;
+            drop(last_cleanup);
+            self.strategy.cleanup(WINDOW_DURATION, now);
+        }
+    }
+
+    /// Number of clients (IP addresses) currently tracked
+    pub fn tracked_clients(&self) -> usize {
+        self.strategy.tracked_clients()
+    }
+
+    /// Clean up expired entries now
+    #[cfg(test)]
     async fn cleanup(&self) {
-        let mut state = self.state.lock().await;
-        let now = Instant::now();
-        state.retain(|_, entry| now.duration_since(entry.window_start) <= WINDOW_DURATION);
+        self.strategy.cleanup(WINDOW_DURATION, self.clock.now());
     }
 }
 
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Client key of requests without a peer address
+const UNKNOWN_CLIENT: &str = "unknown";
+
 /// Rate limiting middleware
+///
+/// Add to the router with `axum::middleware::from_fn_with_state` and the
+/// shared `RateLimiter`. Requests over the limit get a 429 with `Retry-After`.
 pub async fn rate_limit_middleware(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(limiter): State<RateLimiter>,
     request: Request<Body>,
     next: Next,
 ) -> Result<Response<Body>, impl IntoResponse> {
-    let ip = addr.ip().to_string();
-    let path = request.uri().path().to_string();
-
-    // Get rate limiter from request extensions (must be added in main.rs)
-    let limiter = request
+    let ip = request
         .extensions()
-        .get::<RateLimiter>()
-        .cloned()
-        .unwrap_or_else(RateLimiter::new);
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or_else(
+            || UNKNOWN_CLIENT.to_string(),
+            |ConnectInfo(addr)| addr.ip().to_string(),
+        );
+    let path = request.uri().path().to_string();
 
     match limiter.check_rate_limit(&ip, &path).await {
         Ok(()) => {
@@ -119,11 +372,7 @@ pub async fn rate_limit_middleware(
             let retry_after_secs = retry_after.as_secs();
 
             // Log rate limit hit (without any sensitive data like API keys)
-            tracing::warn!(
-                "Rate limit exceeded for IP: {} on path: {}",
-                ip,
-                path
-            );
+            tracing::warn!("Rate limit exceeded for IP: {} on path: {}", ip, path);
 
             // Return 429 Too Many Requests with Retry-After header
             let response = Response::builder()
@@ -140,3 +389,256 @@ pub async fn rate_limit_middleware(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn limiter() -> (RateLimiter, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new());
+        (RateLimiter::with_clock(clock.clone()), clock)
+    }
+
+    #[tokio::test]
+    async fn test_limit_applies_within_window() {
+        let (limiter, clock) = limiter();
+
+        for _ in 0..EDIT_LIMIT {
+            assert!(limiter
+                .check_rate_limit("10.0.0.1", "/api/edit")
+                .await
+                .is_ok());
+        }
+        clock.advance(Duration::from_secs(600));
+
+        let retry_after = limiter
+            .check_rate_limit("10.0.0.1", "/api/edit")
+            .await
+            .unwrap_err();
+        assert_eq!(retry_after, WINDOW_DURATION - Duration::from_secs(600));
+
+        // Other clients and the general limit are counted separately
+        assert!(limiter
+            .check_rate_limit("10.0.0.2", "/api/edit")
+            .await
+            .is_ok());
+        assert!(limiter
+            .check_rate_limit("10.0.0.1", "/api/health")
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_window_resets_after_boundary() {
+        let (limiter, clock) = limiter();
+        for _ in 0..EDIT_LIMIT {
+            limiter
+                .check_rate_limit("10.0.0.1", "/api/edit")
+                .await
+                .unwrap();
+        }
+
+        // The window is inclusive of its last instant
+        clock.advance(WINDOW_DURATION);
+        assert_eq!(
+            limiter.check_rate_limit("10.0.0.1", "/api/edit").await,
+            Err(Duration::ZERO)
+        );
+
+        clock.advance(Duration::from_millis(1));
+        assert!(limiter
+            .check_rate_limit("10.0.0.1", "/api/edit")
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_drops_expired_entries() {
+        let clock = Arc::new(MockClock::new());
+        let fixed = Arc::new(FixedWindow::default());
+        let limiter = RateLimiter::with_strategy(fixed.clone(), clock.clone());
+        limiter
+            .check_rate_limit("10.0.0.1", "/api/edit")
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(1800));
+        limiter
+            .check_rate_limit("10.0.0.2", "/api/edit")
+            .await
+            .unwrap();
+
+        clock.advance(Duration::from_secs(1801));
+        limiter.cleanup().await;
+
+        assert_eq!(limiter.tracked_clients(), 1);
+        let state = fixed.entries.lock().unwrap();
+        assert!(!state.contains_key("10.0.0.1"));
+        assert!(state.contains_key("10.0.0.2"));
+    }
+
+    /// Limiters for both algorithms sharing one mock clock
+    fn both_algorithms() -> (RateLimiter, RateLimiter, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::new());
+        let fixed = RateLimiter::with_strategy(Arc::new(FixedWindow::default()), clock.clone());
+        let sliding =
+            RateLimiter::with_strategy(Arc::new(SlidingWindowLog::default()), clock.clone());
+        (fixed, sliding, clock)
+    }
+
+    #[tokio::test]
+    async fn test_algorithms_agree_at_exact_window_edge() {
+        let (fixed, sliding, clock) = both_algorithms();
+        for limiter in [&fixed, &sliding] {
+            for _ in 0..EDIT_LIMIT {
+                limiter
+                    .check_rate_limit("10.0.0.1", "/api/edit")
+                    .await
+                    .unwrap();
+            }
+        }
+
+        clock.advance(WINDOW_DURATION);
+        for limiter in [&fixed, &sliding] {
+            assert_eq!(
+                limiter.check_rate_limit("10.0.0.1", "/api/edit").await,
+                Err(Duration::ZERO)
+            );
+        }
+
+        clock.advance(Duration::from_millis(1));
+        for limiter in [&fixed, &sliding] {
+            assert!(limiter
+                .check_rate_limit("10.0.0.1", "/api/edit")
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_denies_burst_across_edge() {
+        let (fixed, sliding, clock) = both_algorithms();
+        let half = WINDOW_DURATION / 2;
+
+        // One request opens the window, the rest arrive halfway through it
+        for limiter in [&fixed, &sliding] {
+            limiter
+                .check_rate_limit("10.0.0.1", "/api/edit")
+                .await
+                .unwrap();
+        }
+        clock.advance(half);
+        for limiter in [&fixed, &sliding] {
+            for _ in 1..EDIT_LIMIT {
+                limiter
+                    .check_rate_limit("10.0.0.1", "/api/edit")
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // Just past the edge the fixed window starts over with a full budget...
+        clock.advance(half + Duration::from_millis(1));
+        for _ in 0..EDIT_LIMIT {
+            assert!(fixed
+                .check_rate_limit("10.0.0.1", "/api/edit")
+                .await
+                .is_ok());
+        }
+
+        // ...while the sliding window still counts the requests from halfway
+        assert!(sliding
+            .check_rate_limit("10.0.0.1", "/api/edit")
+            .await
+            .is_ok());
+        assert_eq!(
+            sliding.check_rate_limit("10.0.0.1", "/api/edit").await,
+            Err(half - Duration::from_millis(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_cleanup_keeps_recent_clients() {
+        let clock = Arc::new(MockClock::new());
+        let sliding = Arc::new(SlidingWindowLog::default());
+        let limiter = RateLimiter::with_strategy(sliding.clone(), clock.clone());
+        limiter
+            .check_rate_limit("10.0.0.1", "/api/edit")
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(1800));
+        limiter
+            .check_rate_limit("10.0.0.2", "/api/edit")
+            .await
+            .unwrap();
+
+        clock.advance(Duration::from_secs(1801));
+        limiter.cleanup().await;
+
+        let logs = sliding.logs.lock().unwrap();
+        assert!(!logs.contains_key("10.0.0.1"));
+        assert!(logs.contains_key("10.0.0.2"));
+    }
+
+    #[test]
+    fn test_algorithm_parsing() {
+        assert_eq!(
+            "sliding-window".parse::<RateLimitAlgorithm>().unwrap(),
+            RateLimitAlgorithm::SlidingWindow
+        );
+        assert_eq!(
+            " FIXED_WINDOW ".parse::<RateLimitAlgorithm>().unwrap(),
+            RateLimitAlgorithm::FixedWindow
+        );
+        assert!("token_bucket".parse::<RateLimitAlgorithm>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_idle_clients_forgotten_as_requests_arrive() {
+        let (limiter, clock) = limiter();
+        limiter
+            .check_rate_limit("10.0.0.1", "/api/edit")
+            .await
+            .unwrap();
+
+        clock.advance(WINDOW_DURATION + CLEANUP_INTERVAL);
+        limiter
+            .check_rate_limit("10.0.0.2", "/api/edit")
+            .await
+            .unwrap();
+
+        assert_eq!(limiter.tracked_clients(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_counts_clients_by_peer_address() {
+        let (limiter, _clock) = limiter();
+        let app = Router::new()
+            .route("/api/edit", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                limiter.clone(),
+                rate_limit_middleware,
+            ));
+        let request = |ip: [u8; 4]| {
+            let mut request = Request::get("/api/edit").body(Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+            request
+        };
+
+        for _ in 0..EDIT_LIMIT {
+            let response = app.clone().oneshot(request([10, 0, 0, 1])).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app.clone().oneshot(request([10, 0, 0, 1])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["Retry-After"], "3600");
+        let response = app.oneshot(request([10, 0, 0, 2])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(limiter.tracked_clients(), 2);
+    }
+}
@@ -0,0 +1,101 @@
+//! Early upload size check
+//!
+//! Rejects requests whose declared `Content-Length` exceeds
+//! `AppConfig::max_upload_bytes` before any of the body is read. Because hyper
+//! only answers `Expect: 100-continue` once a handler starts reading the body,
+//! clients using that flow receive the 413 instead of `100 Continue` and never
+//! transfer the oversized body. Bodies without a `Content-Length` (chunked
+//! uploads) are still bounded by the router's `DefaultBodyLimit`.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::error::AppError;
+
+/// Maximum accepted request body size in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadLimit(pub usize);
+
+/// Declared body length of a request, if it has a valid `Content-Length`
+fn declared_length(request: &Request<Body>) -> Option<u64> {
+    request
+        .headers()
+        .get(CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Upload limit middleware
+///
+/// Add to the router with `axum::middleware::from_fn_with_state`.
+///
+/// # Errors
+///
+/// Returns `AppError::PayloadTooLarge` (413) when `Content-Length` exceeds the limit.
+pub async fn upload_limit_middleware(
+    State(UploadLimit(limit)): State<UploadLimit>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(length) = declared_length(&request) {
+        if length > limit as u64 {
+            tracing::warn!(
+                content_length = length,
+                limit,
+                path = %request.uri().path(),
+                "Rejecting upload before reading the body"
+            );
+            return Err(AppError::PayloadTooLarge(format!(
+                "request body of {} bytes exceeds the {} byte limit",
+                length, limit
+            )));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn app(limit: usize) -> Router {
+        Router::new()
+            .route("/upload", post(|body: String| async move { body }))
+            .layer(axum::middleware::from_fn_with_state(
+                UploadLimit(limit),
+                upload_limit_middleware,
+            ))
+    }
+
+    fn upload(body: &'static str, content_length: &str) -> Request<Body> {
+        Request::post("/upload")
+            .header(CONTENT_LENGTH, content_length)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rejects_declared_length_over_limit() {
+        let response = app(4).oneshot(upload("too long", "8")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_allows_body_within_limit() {
+        let response = app(4).oneshot(upload("fits", "4")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -12,3 +12,6 @@ pub mod request;
 
 /// Response payload models
 pub mod response;
+
+/// Tenant identification for metrics attribution
+pub mod tenant;
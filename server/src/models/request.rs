@@ -3,8 +3,33 @@
 //! This module defines the data transfer objects (DTOs) used for incoming API requests.
 //! The models are designed to match the Python FastAPI backend's request structure.
 
+use crate::services::base::{EditRegion, ProviderOptions, SamplingOptions};
+use crate::utils::image_utils::{OutputFormat, ResizeFit, Rotation};
 use serde::{Deserialize, Serialize};
 
+/// Maximum number of formats in one `formats` request
+pub const MAX_FORMATS: usize = 3;
+
+/// Provider request fields the server sets, from the request itself or its own
+/// settings, which `provider_options` can't name
+pub const PROTECTED_PROVIDER_OPTIONS: &[&str] = &[
+    "prompt",
+    "image_url",
+    "image_urls",
+    "mask_url",
+    "output_format",
+    "sync_mode",
+];
+
+/// Highest sampling `temperature` accepted (Gemini's upper bound)
+pub const MAX_TEMPERATURE: f64 = 2.0;
+
+/// Accepted `num_inference_steps` values
+pub const INFERENCE_STEPS_RANGE: std::ops::RangeInclusive<u32> = 1..=50;
+
+/// Accepted `guidance_scale` values
+pub const GUIDANCE_SCALE_RANGE: std::ops::RangeInclusive<f64> = 1.0..=20.0;
+
 /// Request structure for the `/api/edit` endpoint
 ///
 /// This struct represents the multipart form data sent to the image editing endpoint.
@@ -17,6 +42,24 @@ use serde::{Deserialize, Serialize};
 ///   If not provided, a default prompt will be used.
 /// - `provider`: Optional provider selection (e.g., "google", "fal:fal-ai/flux/dev").
 ///   Defaults to "google" if not specified.
+/// - `out_width` / `out_height`: Optional output dimensions. When only one is set,
+///   the other follows the result's aspect ratio.
+/// - `fit`: How the result is fitted into the output dimensions (`contain`, `cover`,
+///   `fill`, `smart`). Defaults to `contain`.
+/// - `output_format`: Encoding of the returned image (`png`, `jpeg`, `webp`).
+///   Defaults to `DEFAULT_OUTPUT_FORMAT`, or the provider's format when unset.
+/// - `rotate`: Optional clockwise rotation (0, 90, 180 or 270 degrees) applied
+///   to the input images before the edit, for images without (or with wrong)
+///   orientation metadata.
+/// - `region`: Optional `EditRegion` limiting the edit to part of the first image.
+/// - `temperature` / `top_p`: Optional sampling parameters, ignored by providers
+///   that don't take them.
+/// - `seed` / `num_inference_steps` / `guidance_scale`: Optional generation
+///   parameters for Fal.ai models, ignored by other providers.
+/// - `provider_options`: Optional extra provider request fields, as a JSON
+///   object of scalars, ignored by providers that don't take them.
+/// - `steps`: Optional chained prompts, used instead of `prompt`. Each step edits
+///   the previous step's result.
 ///
 /// # Example Default Prompt
 ///
@@ -38,16 +81,78 @@ pub struct EditImageRequest {
     /// Examples: "google", "nano-banana", "fal:fal-ai/flux/dev"
     /// Defaults to "google" if not specified
     pub provider: Option<String>,
+
+    /// Requested output width in pixels (optional)
+    pub out_width: Option<u32>,
+
+    /// Requested output height in pixels (optional)
+    pub out_height: Option<u32>,
+
+    /// Fit mode for the requested output dimensions (optional)
+    pub fit: Option<ResizeFit>,
+
+    /// Encoding of the returned image (optional)
+    pub output_format: Option<OutputFormat>,
+
+    /// Encodings to return the image in all at once, as JSON data URLs (optional)
+    /// Replaces `output_format` when non-empty
+    #[serde(default)]
+    pub formats: Vec<OutputFormat>,
+
+    /// Losslessly optimize PNG output before returning it (optional)
+    pub optimize: bool,
+
+    /// Enhance the prompt before the edit, if enabled for the provider (optional)
+    pub enhance_prompt: bool,
+
+    /// Report provider details in an `X-Provider-Metadata` header (optional)
+    pub include_metadata: bool,
+
+    /// Report a perceptual hash of the output in an `X-Image-Phash` header (optional)
+    pub phash: bool,
+
+    /// Report a sharpness score of the output in an `X-Quality-Score` header (optional)
+    pub quality_score: bool,
+
+    /// Write the prompt, provider, seed and model into PNG output text chunks (optional)
+    pub embed_metadata: bool,
+
+    /// Clockwise rotation applied to the input images before the edit (optional)
+    #[serde(default)]
+    pub rotate: Rotation,
+
+    /// Limit the edit to this part of the first image (optional)
+    pub region: Option<EditRegion>,
+
+    /// Sampling temperature, for providers that accept it (optional)
+    pub temperature: Option<f64>,
+
+    /// Nucleus sampling (top-p), for providers that accept it (optional)
+    pub top_p: Option<f64>,
+
+    /// Seed for reproducible results, for providers that accept it (optional)
+    pub seed: Option<u32>,
+
+    /// Number of denoising steps, for providers that accept it (optional)
+    pub num_inference_steps: Option<u32>,
+
+    /// Classifier-free guidance scale, for providers that accept it (optional)
+    pub guidance_scale: Option<f64>,
+
+    /// Extra provider request fields, passed through as given (optional)
+    #[serde(default)]
+    pub provider_options: ProviderOptions,
+
+    /// Prompts of a chained edit, applied in order to the previous step's result (optional)
+    /// Replaces `prompt` when non-empty
+    #[serde(default)]
+    pub steps: Vec<String>,
 }
 
 impl EditImageRequest {
     /// Creates a new EditImageRequest with images and default values
     pub fn new(images: Vec<Vec<u8>>) -> Self {
-        Self {
-            images,
-            prompt: None,
-            provider: None,
-        }
+        Self::with_options(images, None, None)
     }
 
     /// Creates a new EditImageRequest with all fields specified
@@ -60,6 +165,26 @@ impl EditImageRequest {
             images,
             prompt,
             provider,
+            out_width: None,
+            out_height: None,
+            fit: None,
+            output_format: None,
+            formats: Vec::new(),
+            optimize: false,
+            enhance_prompt: false,
+            include_metadata: false,
+            phash: false,
+            quality_score: false,
+            embed_metadata: false,
+            rotate: Rotation::None,
+            region: None,
+            temperature: None,
+            top_p: None,
+            seed: None,
+            num_inference_steps: None,
+            guidance_scale: None,
+            provider_options: ProviderOptions::new(),
+            steps: Vec::new(),
         }
     }
 
@@ -80,6 +205,23 @@ impl EditImageRequest {
             .unwrap_or_else(|| Self::default_prompt().to_string())
     }
 
+    /// Whether the client supplied a non-blank prompt or any steps
+    pub fn has_prompt(&self) -> bool {
+        !self.steps.is_empty() || self.prompt.as_ref().is_some_and(|s| !s.trim().is_empty())
+    }
+
+    /// Gets the prompt of every edit step: the chained `steps` if any, otherwise the prompt
+    pub fn get_steps(&self) -> Vec<String> {
+        if self.steps.is_empty() {
+            vec![self.get_prompt()]
+        } else {
+            self.steps
+                .iter()
+                .map(|step| step.trim().to_string())
+                .collect()
+        }
+    }
+
     /// Gets the provider name, using the default if none is specified
     pub fn get_provider(&self) -> String {
         self.provider
@@ -109,6 +251,307 @@ impl EditImageRequest {
 
         Ok(())
     }
+
+    /// Validates a chained edit
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if there are more than `max_steps` steps, or if
+    /// both `prompt` and `steps` are given.
+    pub fn validate_steps(&self, max_steps: usize) -> Result<(), String> {
+        if self.steps.len() > max_steps {
+            return Err(format!(
+                "Too many edit steps: {} (maximum {})",
+                self.steps.len(),
+                max_steps
+            ));
+        }
+        if !self.steps.is_empty() && self.prompt.is_some() {
+            return Err("Provide either prompt or steps, not both".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Sampling parameters to pass to the editor
+    pub fn sampling(&self) -> SamplingOptions {
+        SamplingOptions {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            seed: self.seed,
+            num_inference_steps: self.num_inference_steps,
+            guidance_scale: self.guidance_scale,
+        }
+    }
+
+    /// Validates the sampling parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if `temperature` is outside 0-2, `top_p`
+    /// outside 0-1, `num_inference_steps` outside 1-50 or `guidance_scale`
+    /// outside 1-20.
+    pub fn validate_sampling(&self) -> Result<(), String> {
+        if self
+            .temperature
+            .is_some_and(|value| !(0.0..=MAX_TEMPERATURE).contains(&value))
+        {
+            return Err(format!(
+                "temperature must be between 0 and {}",
+                MAX_TEMPERATURE
+            ));
+        }
+        if self
+            .top_p
+            .is_some_and(|value| !(0.0..=1.0).contains(&value))
+        {
+            return Err("top_p must be between 0 and 1".to_string());
+        }
+        if self
+            .num_inference_steps
+            .is_some_and(|value| !INFERENCE_STEPS_RANGE.contains(&value))
+        {
+            return Err(format!(
+                "num_inference_steps must be between {} and {}",
+                INFERENCE_STEPS_RANGE.start(),
+                INFERENCE_STEPS_RANGE.end()
+            ));
+        }
+        if self
+            .guidance_scale
+            .is_some_and(|value| !GUIDANCE_SCALE_RANGE.contains(&value))
+        {
+            return Err(format!(
+                "guidance_scale must be between {} and {}",
+                GUIDANCE_SCALE_RANGE.start(),
+                GUIDANCE_SCALE_RANGE.end()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validates the provider options
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if an option is not a string, number or
+    /// boolean, names a field that carries the request's own content
+    /// (see `PROTECTED_PROVIDER_OPTIONS`) or is one of the `denied` keys
+    /// (`PROVIDER_OPTIONS_DENY`).
+    pub fn validate_provider_options(&self, denied: &[String]) -> Result<(), String> {
+        for (name, value) in &self.provider_options {
+            if PROTECTED_PROVIDER_OPTIONS.contains(&name.as_str()) {
+                return Err(format!(
+                    "provider_options cannot set `{}`; the server sets it",
+                    name
+                ));
+            }
+            if denied.iter().any(|key| key == name) {
+                return Err(format!(
+                    "provider_options cannot set `{}` on this server",
+                    name
+                ));
+            }
+            if !(value.is_string() || value.is_number() || value.is_boolean()) {
+                return Err(format!(
+                    "provider_options `{}` must be a string, number or boolean",
+                    name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates a multi-format request
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if more than `MAX_FORMATS` formats are
+    /// requested, a format is repeated, or both `output_format` and `formats`
+    /// are given.
+    pub fn validate_formats(&self) -> Result<(), String> {
+        if self.formats.len() > MAX_FORMATS {
+            return Err(format!(
+                "Too many formats: {} (maximum {})",
+                self.formats.len(),
+                MAX_FORMATS
+            ));
+        }
+        for (index, format) in self.formats.iter().enumerate() {
+            if self.formats[..index].contains(format) {
+                return Err(format!(
+                    "Format '{}' is requested more than once",
+                    format.as_str()
+                ));
+            }
+        }
+        if !self.formats.is_empty() && self.output_format.is_some() {
+            return Err("Provide either output_format or formats, not both".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Validates the requested output dimensions
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if a requested dimension is zero or exceeds `max_dimension`.
+    pub fn validate_output_size(&self, max_dimension: u32) -> Result<(), String> {
+        for (label, value) in [
+            ("out_width", self.out_width),
+            ("out_height", self.out_height),
+        ] {
+            if let Some(value) = value {
+                if value == 0 || value > max_dimension {
+                    return Err(format!(
+                        "{} must be between 1 and {} (got {})",
+                        label, max_dimension, value
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Request structure for the `/api/edit/batch` endpoint
+///
+/// Each image is edited independently. Prompts can be supplied either as a
+/// single `prompt` applied to every image, or as a parallel `prompts` list
+/// mapping one prompt to each image by position.
+///
+/// # Fields
+///
+/// - `images`: Uploaded image files (as bytes). Required field.
+/// - `prompt`: Optional prompt shared by all images.
+/// - `prompts`: Optional per-image prompts. Must have one entry per image,
+///   or exactly one entry which then applies to all images.
+/// - `provider`: Optional provider selection, as for `EditImageRequest`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchEditRequest {
+    /// Uploaded image files (required)
+    #[serde(skip)]
+    pub images: Vec<Vec<u8>>,
+
+    /// Prompt applied to every image (optional)
+    pub prompt: Option<String>,
+
+    /// Per-image prompts, matched to `images` by position (optional)
+    #[serde(default)]
+    pub prompts: Vec<String>,
+
+    /// Provider selection (optional)
+    pub provider: Option<String>,
+}
+
+impl BatchEditRequest {
+    /// Gets the prompt for the image at `index`, using the default if none is specified
+    pub fn prompt_for(&self, index: usize) -> String {
+        let prompt = match self.prompts.len() {
+            0 => self.prompt.clone(),
+            1 => self.prompts.first().cloned(),
+            _ => self.prompts.get(index).cloned(),
+        };
+
+        EditImageRequest::with_options(Vec::new(), prompt, None).get_prompt()
+    }
+
+    /// Gets the provider name, using the default if none is specified
+    pub fn get_provider(&self) -> String {
+        EditImageRequest::with_options(Vec::new(), None, self.provider.clone()).get_provider()
+    }
+
+    /// Validates the request
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if:
+    /// - No images are provided, or more than `max_images`
+    /// - Both `prompt` and `prompts` are provided
+    /// - The number of `prompts` doesn't match the number of images (and isn't 1)
+    pub fn validate(&self, max_images: usize) -> Result<(), String> {
+        if self.images.is_empty() {
+            return Err("At least one image is required".to_string());
+        }
+
+        if self.images.len() > max_images {
+            return Err(format!(
+                "Too many images in batch: {} (maximum {})",
+                self.images.len(),
+                max_images
+            ));
+        }
+
+        if self.prompt.is_some() && !self.prompts.is_empty() {
+            return Err("Provide either `prompt` or `prompts`, not both".to_string());
+        }
+
+        if self.prompts.len() > 1 && self.prompts.len() != self.images.len() {
+            return Err(format!(
+                "Number of prompts ({}) must match number of images ({})",
+                self.prompts.len(),
+                self.images.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Provider comparison request
+///
+/// Sent to `/api/edit/compare` to edit one image with one prompt on several
+/// providers.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompareEditRequest {
+    /// Uploaded image file (required)
+    #[serde(skip)]
+    pub image: Vec<u8>,
+
+    /// Prompt sent to every provider (optional)
+    pub prompt: Option<String>,
+
+    /// Providers to compare, in response order (required)
+    #[serde(default)]
+    pub providers: Vec<String>,
+}
+
+impl CompareEditRequest {
+    /// Gets the prompt, using the default if none is specified
+    pub fn get_prompt(&self) -> String {
+        EditImageRequest::with_options(Vec::new(), self.prompt.clone(), None).get_prompt()
+    }
+
+    /// Validates the request
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if:
+    /// - No image is provided
+    /// - No providers are named, or more than `max_providers`
+    pub fn validate(&self, max_providers: usize) -> Result<(), String> {
+        if self.image.is_empty() {
+            return Err("An image is required".to_string());
+        }
+
+        if self.providers.is_empty() {
+            return Err("At least one provider is required".to_string());
+        }
+
+        if self.providers.len() > max_providers {
+            return Err(format!(
+                "Too many providers to compare: {} (maximum {})",
+                self.providers.len(),
+                max_providers
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -145,6 +588,21 @@ mod tests {
         assert_eq!(request.get_prompt(), EditImageRequest::default_prompt());
     }
 
+    #[test]
+    fn test_has_prompt() {
+        let mut request =
+            EditImageRequest::with_options(vec![vec![1]], Some("  ".to_string()), None);
+        assert!(!request.has_prompt());
+        assert!(!EditImageRequest::new(vec![vec![1]]).has_prompt());
+
+        request.steps.push("Add a rug".to_string());
+        assert!(request.has_prompt());
+        assert!(
+            EditImageRequest::with_options(vec![vec![1]], Some("Add a rug".to_string()), None)
+                .has_prompt()
+        );
+    }
+
     #[test]
     fn test_default_provider() {
         let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
@@ -178,4 +636,251 @@ mod tests {
         let request = EditImageRequest::new(vec![vec![], vec![1, 2, 3]]);
         assert!(request.validate().is_err());
     }
+
+    #[test]
+    fn test_output_size_validation() {
+        let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        assert!(request.validate_output_size(4096).is_ok());
+
+        request.out_width = Some(1024);
+        request.out_height = Some(1024);
+        assert!(request.validate_output_size(4096).is_ok());
+
+        request.out_height = Some(0);
+        assert!(request.validate_output_size(4096).is_err());
+
+        request.out_height = Some(5000);
+        assert!(request
+            .validate_output_size(4096)
+            .unwrap_err()
+            .contains("out_height"));
+    }
+
+    #[test]
+    fn test_steps_default_to_prompt() {
+        let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        assert_eq!(
+            request.get_steps(),
+            vec![EditImageRequest::default_prompt()]
+        );
+
+        request.steps = vec![" Add a sofa ".to_string(), "Make it blue".to_string()];
+        assert_eq!(request.get_steps(), vec!["Add a sofa", "Make it blue"]);
+    }
+
+    #[test]
+    fn test_steps_validation_boundary() {
+        let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        request.steps = vec!["step".to_string(); 3];
+        assert!(request.validate_steps(3).is_ok());
+
+        request.steps.push("one too many".to_string());
+        assert!(request.validate_steps(3).unwrap_err().contains("maximum 3"));
+
+        request.steps.truncate(1);
+        request.prompt = Some("Add a sofa".to_string());
+        assert!(request.validate_steps(3).is_err());
+    }
+
+    #[test]
+    fn test_formats_validation() {
+        let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        request.formats = vec![OutputFormat::Webp, OutputFormat::Png, OutputFormat::Jpeg];
+        assert!(request.validate_formats().is_ok());
+
+        request.formats.push(OutputFormat::Png);
+        assert!(request
+            .validate_formats()
+            .unwrap_err()
+            .contains("maximum 3"));
+
+        request.formats = vec![OutputFormat::Png, OutputFormat::Png];
+        assert!(request
+            .validate_formats()
+            .unwrap_err()
+            .contains("more than once"));
+
+        request.formats = vec![OutputFormat::Png];
+        request.output_format = Some(OutputFormat::Webp);
+        assert!(request.validate_formats().is_err());
+    }
+
+    #[test]
+    fn test_sampling_validation() {
+        let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        assert!(request.validate_sampling().is_ok());
+
+        request.temperature = Some(2.0);
+        request.top_p = Some(0.0);
+        assert!(request.validate_sampling().is_ok());
+        assert_eq!(request.sampling().temperature, Some(2.0));
+
+        request.temperature = Some(2.5);
+        assert!(request
+            .validate_sampling()
+            .unwrap_err()
+            .contains("temperature"));
+
+        request.temperature = Some(f64::NAN);
+        assert!(request.validate_sampling().is_err());
+
+        request.temperature = None;
+        request.top_p = Some(1.1);
+        assert!(request.validate_sampling().unwrap_err().contains("top_p"));
+
+        request.top_p = None;
+        request.seed = Some(42);
+        request.num_inference_steps = Some(50);
+        request.guidance_scale = Some(1.0);
+        assert!(request.validate_sampling().is_ok());
+        assert_eq!(request.sampling().seed, Some(42));
+
+        request.num_inference_steps = Some(0);
+        assert!(request
+            .validate_sampling()
+            .unwrap_err()
+            .contains("num_inference_steps"));
+
+        request.num_inference_steps = None;
+        request.guidance_scale = Some(20.5);
+        assert!(request
+            .validate_sampling()
+            .unwrap_err()
+            .contains("guidance_scale"));
+    }
+
+    #[test]
+    fn test_provider_options_validation() {
+        let options = |json: serde_json::Value| {
+            let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+            request.provider_options = json.as_object().unwrap().clone();
+            request.validate_provider_options(
+                &crate::config::AppConfig::default().provider_options_deny,
+            )
+        };
+
+        assert!(options(serde_json::json!({})).is_ok());
+        assert!(options(
+            serde_json::json!({ "guidance_scale": 3.5, "acceleration": "high", "safe": true })
+        )
+        .is_ok());
+
+        assert!(
+            options(serde_json::json!({ "image_url": "https://example.com/a.png" }))
+                .unwrap_err()
+                .contains("cannot set `image_url`")
+        );
+        assert!(options(serde_json::json!({ "prompt": "other" })).is_err());
+        assert!(options(serde_json::json!({ "sync_mode": false }))
+            .unwrap_err()
+            .contains("cannot set `sync_mode`"));
+        assert!(options(serde_json::json!({ "output_format": "jpeg" })).is_err());
+        assert!(options(serde_json::json!({ "loras": [{ "path": "x" }] }))
+            .unwrap_err()
+            .contains("string, number or boolean"));
+        assert!(options(serde_json::json!({ "seed": null })).is_err());
+        // Denied by default
+        assert!(
+            options(serde_json::json!({ "enable_safety_checker": false }))
+                .unwrap_err()
+                .contains("cannot set `enable_safety_checker`")
+        );
+        assert!(options(serde_json::json!({ "num_images": 50 })).is_err());
+    }
+
+    #[test]
+    fn test_provider_options_deny_list_configurable() {
+        let mut request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        request.provider_options = serde_json::json!({ "num_images": 2, "acceleration": "high" })
+            .as_object()
+            .unwrap()
+            .clone();
+
+        assert!(request.validate_provider_options(&[]).is_ok());
+        assert!(request
+            .validate_provider_options(&["acceleration".to_string()])
+            .unwrap_err()
+            .contains("`acceleration`"));
+    }
+
+    fn make_batch(image_count: usize, prompts: &[&str]) -> BatchEditRequest {
+        BatchEditRequest {
+            images: vec![vec![1, 2, 3]; image_count],
+            prompt: None,
+            prompts: prompts.iter().map(|p| p.to_string()).collect(),
+            provider: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_matched_prompts() {
+        let request = make_batch(2, &["first", "second"]);
+        assert!(request.validate(10).is_ok());
+        assert_eq!(request.prompt_for(0), "first");
+        assert_eq!(request.prompt_for(1), "second");
+    }
+
+    #[test]
+    fn test_batch_single_prompt_applies_to_all() {
+        let request = make_batch(3, &["shared"]);
+        assert!(request.validate(10).is_ok());
+        assert_eq!(request.prompt_for(2), "shared");
+
+        let mut request = make_batch(2, &[]);
+        request.prompt = Some("also shared".to_string());
+        assert!(request.validate(10).is_ok());
+        assert_eq!(request.prompt_for(1), "also shared");
+    }
+
+    #[test]
+    fn test_batch_default_prompt() {
+        let request = make_batch(2, &[]);
+        assert_eq!(request.prompt_for(0), EditImageRequest::default_prompt());
+        assert_eq!(request.get_provider(), "google");
+    }
+
+    #[test]
+    fn test_batch_prompt_count_mismatch() {
+        let request = make_batch(3, &["one", "two"]);
+        let err = request.validate(10).unwrap_err();
+        assert!(err.contains("must match"));
+    }
+
+    #[test]
+    fn test_batch_prompt_and_prompts_conflict() {
+        let mut request = make_batch(2, &["one", "two"]);
+        request.prompt = Some("shared".to_string());
+        assert!(request.validate(10).is_err());
+    }
+
+    #[test]
+    fn test_batch_size_limits() {
+        assert!(make_batch(0, &[]).validate(10).is_err());
+        assert!(make_batch(11, &[]).validate(10).is_err());
+    }
+
+    #[test]
+    fn test_compare_validation() {
+        let request = CompareEditRequest {
+            image: vec![1, 2, 3],
+            prompt: None,
+            providers: vec!["google".to_string(), "composite".to_string()],
+        };
+        assert!(request.validate(2).is_ok());
+
+        let err = request.validate(1).unwrap_err();
+        assert!(err.contains("Too many providers"), "{}", err);
+
+        let no_providers = CompareEditRequest {
+            providers: Vec::new(),
+            ..request.clone()
+        };
+        assert!(no_providers.validate(2).is_err());
+
+        let no_image = CompareEditRequest {
+            image: Vec::new(),
+            ..request
+        };
+        assert!(no_image.validate(2).is_err());
+    }
 }
@@ -50,6 +50,244 @@ impl Default for HealthResponse {
 /// Note: This is just a Vec<String>, no wrapper object needed to match Python backend.
 pub type ProvidersResponse = Vec<String>;
 
+/// Models list response
+///
+/// Returned by the `/api/models` endpoint: catalog entries for every provider
+/// with a configured API key.
+pub type ModelsResponse = Vec<crate::services::catalog::ModelInfo>;
+
+/// Provider parameter schema response
+///
+/// Returned by the `/api/providers/{name}/params` endpoint.
+///
+/// # Example JSON Response
+///
+/// ```json
+/// {
+///   "provider": "fal:fal-ai/flux-kontext/dev",
+///   "params": [
+///     { "name": "prompt", "type": "string", "description": "Editing instructions", "required": false },
+///     { "name": "seed", "type": "integer", "description": "...", "required": false, "minimum": 0.0 }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderParamsResponse {
+    /// Canonical provider name
+    pub provider: String,
+    /// Accepted parameters
+    pub params: Vec<crate::services::catalog::ParamSpec>,
+}
+
+/// Batch edit response
+///
+/// Returned by the `/api/edit/batch` endpoint. Results are in the same order
+/// as the uploaded images; each item carries either the edited image or an error.
+///
+/// # Example JSON Response
+///
+/// ```json
+/// {
+///   "results": [
+///     { "index": 0, "image": "data:image/png;base64,..." },
+///     { "index": 1, "error": "Provider error: ...", "error_type": "provider_error" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchEditResponse {
+    /// Per-image results, ordered by input index
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Result for a single image in a batch edit
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatchItemResult {
+    /// Position of the image in the request
+    pub index: usize,
+
+    /// Edited image as a base64 data URL (present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+
+    /// Error message (present on failure)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Error type/code for programmatic handling (present on failure)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+}
+
+impl BatchItemResult {
+    /// Create a successful item result
+    pub fn success(index: usize, image: String) -> Self {
+        Self {
+            index,
+            image: Some(image),
+            error: None,
+            error_type: None,
+        }
+    }
+
+    /// Create a failed item result
+    pub fn failure(index: usize, error: String, error_type: &str) -> Self {
+        Self {
+            index,
+            image: None,
+            error: Some(error),
+            error_type: Some(error_type.to_string()),
+        }
+    }
+}
+
+/// Provider comparison response
+///
+/// Returned by the `/api/edit/compare` endpoint. Results are in the order the
+/// providers were requested; each item carries either the edited image or an
+/// error, and how long the provider took.
+///
+/// # Example JSON Response
+///
+/// ```json
+/// {
+///   "results": [
+///     { "provider": "google", "latency_ms": 5120, "image": "data:image/png;base64,..." },
+///     { "provider": "fal:fal-ai/flux/dev", "latency_ms": 0, "error": "...", "error_type": "provider_not_found" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompareEditResponse {
+    /// Per-provider results, in request order
+    pub results: Vec<CompareItemResult>,
+}
+
+/// Result of one provider in a comparison
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompareItemResult {
+    /// Provider name as requested
+    pub provider: String,
+
+    /// Milliseconds from the provider call to its result or error
+    pub latency_ms: u64,
+
+    /// Edited image as a base64 data URL (present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+
+    /// Error message (present on failure)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Error type/code for programmatic handling (present on failure)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+}
+
+impl CompareItemResult {
+    /// Create a successful provider result
+    pub fn success(provider: String, latency_ms: u64, image: String) -> Self {
+        Self {
+            provider,
+            latency_ms,
+            image: Some(image),
+            error: None,
+            error_type: None,
+        }
+    }
+
+    /// Create a failed provider result
+    pub fn failure(provider: String, latency_ms: u64, error: String, error_type: &str) -> Self {
+        Self {
+            provider,
+            latency_ms,
+            image: None,
+            error: Some(error),
+            error_type: Some(error_type.to_string()),
+        }
+    }
+}
+
+/// Async job status response
+///
+/// Returned by `POST /api/jobs`, `GET /api/jobs/{id}` and `DELETE /api/jobs/{id}`.
+///
+/// # Example JSON Response
+///
+/// ```json
+/// { "job_id": "4f0c...", "status": "failed", "error": "Provider error: ...", "error_type": "provider_error" }
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct JobResponse {
+    /// Job identifier used in `/api/jobs/{id}` paths
+    pub job_id: String,
+    /// `running`, `succeeded`, `failed` or `cancelled`
+    pub status: crate::jobs::JobStatus,
+
+    /// Error message (present when the job failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Error type/code for programmatic handling (present when the job failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+}
+
+/// Reserved upload response
+///
+/// Returned by `POST /api/uploads`.
+///
+/// # Example JSON Response
+///
+/// ```json
+/// { "upload_id": "9b1d...", "upload_url": "/api/uploads/9b1d...", "expires_in_secs": 900 }
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadResponse {
+    /// Upload identifier, sent as the `upload_id` field of `/api/edit`
+    pub upload_id: String,
+    /// Path to `PUT` the raw image bytes to
+    pub upload_url: String,
+    /// Seconds until the upload expires
+    pub expires_in_secs: u64,
+}
+
+/// Operational summary
+///
+/// Returned by `GET /api/admin/stats`.
+///
+/// # Example JSON Response
+///
+/// ```json
+/// {
+///   "uptime_secs": 3600,
+///   "total_edits": 42,
+///   "edits_by_provider": { "google": 40, "fal": 2 },
+///   "in_flight": 1,
+///   "max_connections": 1024,
+///   "cache_hit_rate": 0.25,
+///   "rate_limit_clients": 17
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminStatsResponse {
+    /// Seconds since the server started
+    pub uptime_secs: u64,
+    /// Edits recorded since startup, whatever their outcome
+    pub total_edits: u64,
+    /// `total_edits` split by provider family (see `factory::provider_family`)
+    pub edits_by_provider: std::collections::BTreeMap<String, u64>,
+    /// Requests being handled right now, this one included
+    pub in_flight: usize,
+    /// Limit on `in_flight` (`MAX_CONNECTIONS`)
+    pub max_connections: usize,
+    /// Share of result download cache lookups that hit; `null` before the first lookup
+    pub cache_hit_rate: Option<f64>,
+    /// Clients (IP addresses) tracked by the rate limiter
+    pub rate_limit_clients: usize,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +317,19 @@ mod tests {
         let json = serde_json::to_string(&providers).unwrap();
         assert_eq!(json, r#"["google","nano-banana"]"#);
     }
+
+    #[test]
+    fn test_batch_item_serialization_skips_empty_fields() {
+        let ok = BatchItemResult::success(0, "data:image/png;base64,AA==".to_string());
+        assert_eq!(
+            serde_json::to_string(&ok).unwrap(),
+            r#"{"index":0,"image":"data:image/png;base64,AA=="}"#
+        );
+
+        let failed = BatchItemResult::failure(1, "boom".to_string(), "provider_error");
+        assert_eq!(
+            serde_json::to_string(&failed).unwrap(),
+            r#"{"index":1,"error":"boom","error_type":"provider_error"}"#
+        );
+    }
 }
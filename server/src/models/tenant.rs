@@ -0,0 +1,174 @@
+//! Tenant identification for metrics attribution
+//!
+//! Clients may tag requests with an `X-Tenant-Id` header so edits can be
+//! attributed to a user or project. Requests without the header are
+//! attributed to `anonymous`.
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::AppError;
+
+/// Header carrying the tenant id
+pub const TENANT_HEADER: &str = "X-Tenant-Id";
+
+/// Tenant used when no `X-Tenant-Id` header is sent
+pub const ANONYMOUS_TENANT: &str = "anonymous";
+
+/// Maximum tenant id length in characters
+pub const MAX_TENANT_ID_LEN: usize = 64;
+
+/// Validated tenant id
+///
+/// Tenant ids are 1-64 ASCII alphanumeric characters, `-` or `_`.
+///
+/// # Example
+///
+/// ```
+/// use frameforge_server::models::tenant::TenantId;
+///
+/// let tenant: TenantId = "acme-prod".parse()?;
+/// assert_eq!(tenant.as_str(), "acme-prod");
+/// assert!("bad tenant!".parse::<TenantId>().is_err());
+/// # Ok::<(), frameforge_server::error::AppError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TenantId(String);
+
+impl TenantId {
+    /// The default tenant for untagged requests
+    pub fn anonymous() -> Self {
+        Self(ANONYMOUS_TENANT.to_string())
+    }
+
+    /// The tenant id as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for TenantId {
+    fn default() -> Self {
+        Self::anonymous()
+    }
+}
+
+impl fmt::Display for TenantId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for TenantId {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s.trim();
+
+        if id.is_empty() || id.len() > MAX_TENANT_ID_LEN {
+            return Err(AppError::InvalidInput(format!(
+                "Tenant id must be between 1 and {} characters",
+                MAX_TENANT_ID_LEN
+            )));
+        }
+
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(AppError::InvalidInput(
+                "Tenant id may only contain ASCII letters, digits, '-' and '_'".to_string(),
+            ));
+        }
+
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl<S> FromRequestParts<S> for TenantId
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    /// Read the tenant from `X-Tenant-Id`, defaulting to `anonymous`
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.headers.get(TENANT_HEADER) {
+            None => Ok(Self::anonymous()),
+            Some(value) => value
+                .to_str()
+                .map_err(|_| AppError::InvalidInput("Tenant id must be valid ASCII".to_string()))?
+                .parse(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    #[test]
+    fn test_valid_tenant_ids() {
+        for id in [
+            "acme",
+            "team_42",
+            "Project-X",
+            &"a".repeat(MAX_TENANT_ID_LEN),
+        ] {
+            assert_eq!(id.parse::<TenantId>().unwrap().as_str(), id);
+        }
+    }
+
+    #[test]
+    fn test_invalid_tenant_ids() {
+        let too_long = "a".repeat(MAX_TENANT_ID_LEN + 1);
+        for id in [
+            "",
+            "   ",
+            "acme corp",
+            "team/42",
+            "tenant;drop",
+            "ténant",
+            too_long.as_str(),
+        ] {
+            assert!(
+                id.parse::<TenantId>().is_err(),
+                "expected {:?} to be rejected",
+                id
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extractor_defaults_to_anonymous() {
+        let (mut parts, _) = Request::new(()).into_parts();
+        let tenant = TenantId::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(tenant, TenantId::anonymous());
+    }
+
+    #[tokio::test]
+    async fn test_extractor_reads_header() {
+        let (mut parts, _) = Request::builder()
+            .header(TENANT_HEADER, "acme")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let tenant = TenantId::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(tenant.as_str(), "acme");
+    }
+
+    #[tokio::test]
+    async fn test_extractor_rejects_invalid_header() {
+        let (mut parts, _) = Request::builder()
+            .header(TENANT_HEADER, "not valid!")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let err = TenantId::from_request_parts(&mut parts, &())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,153 @@
+//! In-memory, content-addressable store of edit results
+//!
+//! With the `results` feature flag, every successful `/api/edit` response is
+//! kept under the hex SHA-256 of its body and can be fetched again from
+//! `/api/results/{id}`. Since an id names exactly one body, results never
+//! change and can be cached forever. Results live only in process memory; at
+//! most `MAX_STORED_RESULTS` results holding at most `MAX_STORED_RESULT_BYTES`
+//! are kept and the oldest are evicted first.
+
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::audit::sha256_hex;
+
+/// Maximum number of results kept in memory
+pub const MAX_STORED_RESULTS: usize = 100;
+
+/// Maximum bytes of results kept in memory (512 MiB)
+pub const MAX_STORED_RESULT_BYTES: usize = 512 * 1024 * 1024;
+
+/// A stored edit result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResult {
+    pub content_type: String,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Default)]
+struct ResultStoreInner {
+    results: HashMap<String, StoredResult>,
+    /// Result ids in insertion order, for eviction
+    order: VecDeque<String>,
+}
+
+/// Thread-safe registry of results, shared through `AppState`
+#[derive(Debug)]
+pub struct ResultStore {
+    max_bytes: usize,
+    inner: Mutex<ResultStoreInner>,
+}
+
+impl Default for ResultStore {
+    fn default() -> Self {
+        Self::with_max_bytes(MAX_STORED_RESULT_BYTES)
+    }
+}
+
+impl ResultStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty store holding at most `max_bytes` of results
+    pub fn with_max_bytes(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            inner: Mutex::new(ResultStoreInner::default()),
+        }
+    }
+
+    /// Store a result and return its id, the hex SHA-256 of `body`
+    ///
+    /// Storing the same body again keeps a single copy. Older results are
+    /// evicted to keep the store within its count and byte budgets.
+    pub fn insert(&self, content_type: &str, body: Bytes) -> String {
+        let id = sha256_hex(&body);
+        let mut inner = self.inner.lock().unwrap();
+
+        if !inner.results.contains_key(&id) {
+            let mut bytes: usize = inner.results.values().map(|result| result.body.len()).sum();
+            while inner.results.len() >= MAX_STORED_RESULTS || bytes + body.len() > self.max_bytes {
+                match inner.order.pop_front() {
+                    Some(oldest) => {
+                        if let Some(result) = inner.results.remove(&oldest) {
+                            bytes -= result.body.len();
+                        }
+                    }
+                    None => break,
+                }
+            }
+            inner.order.push_back(id.clone());
+        }
+        inner.results.insert(
+            id.clone(),
+            StoredResult {
+                content_type: content_type.to_string(),
+                body,
+            },
+        );
+
+        id
+    }
+
+    /// Snapshot of a stored result
+    pub fn get(&self, id: &str) -> Option<StoredResult> {
+        self.inner.lock().unwrap().results.get(id).cloned()
+    }
+
+    /// Number of stored results
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().results.len()
+    }
+
+    /// Whether no results are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_are_keyed_by_content_hash() {
+        let store = ResultStore::new();
+        let id = store.insert("image/png", Bytes::from_static(b"image"));
+
+        assert_eq!(id, sha256_hex(b"image"));
+        assert_eq!(store.insert("image/png", Bytes::from_static(b"image")), id);
+        assert_eq!(store.get(&id).unwrap().body, Bytes::from_static(b"image"));
+        assert_eq!(store.inner.lock().unwrap().order.len(), 1);
+        assert!(store.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_oldest_results_evicted_at_capacity() {
+        let store = ResultStore::new();
+        let first = store.insert("image/png", Bytes::from_static(b"first"));
+
+        for i in 0..MAX_STORED_RESULTS {
+            store.insert("image/png", Bytes::from(i.to_string()));
+        }
+
+        assert!(store.get(&first).is_none());
+        assert_eq!(store.len(), MAX_STORED_RESULTS);
+    }
+
+    #[test]
+    fn test_oldest_results_evicted_over_byte_budget() {
+        let store = ResultStore::with_max_bytes(10);
+        let first = store.insert("image/png", Bytes::from_static(b"aaaa"));
+        let second = store.insert("image/png", Bytes::from_static(b"bbbb"));
+        let third = store.insert("image/png", Bytes::from_static(b"cccc"));
+
+        assert!(store.get(&first).is_none());
+        assert!(store.get(&second).is_some());
+        assert!(store.get(&third).is_some());
+        assert_eq!(store.len(), 2);
+    }
+}
@@ -0,0 +1,79 @@
+//! Admin statistics endpoint
+//!
+//! `GET /api/admin/stats` summarizes the server's state for operators in one
+//! JSON object: a read-only convenience over the metrics registry and shared
+//! state. The route only exists when `SERVER_API_KEY` is set, and requests
+//! must send that key as `Authorization: Bearer <SERVER_API_KEY>`.
+
+use axum::{extract::State, http::HeaderMap, Json};
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::middleware::debug_logging::{bearer_token, keys_match};
+use crate::models::response::AdminStatsResponse;
+use crate::services::download_cache;
+use crate::state::AppState;
+
+/// Admin statistics handler
+///
+/// # Endpoint
+///
+/// `GET /api/admin/stats`
+///
+/// # Response
+///
+/// An `AdminStatsResponse`: uptime, edit counts in total and per provider,
+/// requests in flight, the result download cache hit rate and the number of
+/// clients tracked by the rate limiter.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer $SERVER_API_KEY" http://localhost:8000/api/admin/stats
+/// ```
+///
+/// # Errors
+///
+/// Returns `AppError::Unauthorized` (401) when the bearer token is missing or
+/// doesn't match `SERVER_API_KEY`.
+pub async fn admin_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminStatsResponse>, AppError> {
+    require_server_key(&state.config, &headers)?;
+
+    let edits_by_provider = state.metrics.edits_by_provider();
+    let (hits, misses) = download_cache::lookup_counts();
+    let lookups = hits + misses;
+
+    Ok(Json(AdminStatsResponse {
+        uptime_secs: state.started_at.elapsed().as_secs(),
+        total_edits: edits_by_provider.values().sum(),
+        edits_by_provider,
+        in_flight: state.connections.in_flight(),
+        max_connections: state.config.max_connections,
+        cache_hit_rate: (lookups > 0).then(|| hits as f64 / lookups as f64),
+        rate_limit_clients: state.rate_limiter.tracked_clients(),
+    }))
+}
+
+/// Check the request carries `Authorization: Bearer <SERVER_API_KEY>`
+///
+/// Shared by the operator endpoints (`/api/admin/*` and `/metrics`).
+///
+/// # Errors
+///
+/// Returns `AppError::Unauthorized` when no server key is configured, or the
+/// bearer token is missing or doesn't match it.
+pub(crate) fn require_server_key(config: &AppConfig, headers: &HeaderMap) -> Result<(), AppError> {
+    let authorized = match (&config.server_api_key, bearer_token(headers)) {
+        (Some(key), Some(token)) => keys_match(token, key),
+        _ => false,
+    };
+    if !authorized {
+        return Err(AppError::Unauthorized(
+            "admin endpoints require Authorization: Bearer <SERVER_API_KEY>".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,412 @@
+//! Batch image editing endpoint
+//!
+//! This module implements the `/api/edit/batch` endpoint, which edits several
+//! images in one request. Each image is processed independently and
+//! concurrently (bounded by `AppConfig.batch_concurrency`), and the response
+//! reports a result or an error for every input, in input order. Whether an
+//! invalid upload fails the whole request or only its item is set by
+//! `AppConfig.batch_mode`.
+
+use axum::{
+    extract::{Multipart, State},
+    http::{HeaderMap, HeaderValue},
+    Json,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+
+use crate::audit::EditAudit;
+use crate::config::{AppConfig, BatchMode};
+use crate::error::AppError;
+use crate::metrics::{self, Metrics};
+use crate::models::request::BatchEditRequest;
+use crate::models::response::{BatchEditResponse, BatchItemResult};
+use crate::models::tenant::TenantId;
+use crate::routes::edit::{
+    check_provider_prompt, check_result_changed, check_result_is_image, check_result_scale,
+    check_result_size, compose_prompt, fit_bit_depth, multipart_read_error, read_image_field,
+    read_text_field, run_blocking, runtime_config_from_headers, validate_image_header,
+    DEV_MODE_HEADER,
+};
+use crate::services::base::ImageEditor;
+use crate::services::factory;
+use crate::utils::archive::{self, ArchiveLimits};
+use crate::utils::image_utils;
+
+/// Batch image editing handler
+///
+/// # Endpoint
+///
+/// `POST /api/edit/batch`
+///
+/// # Request Format
+///
+/// Multipart form data with the following fields:
+/// - `images`: One or more image files (required unless `archive` is sent,
+///   up to `MAX_BATCH_IMAGES` in total)
+/// - `archive`: ZIP file whose entries are added as images, in archive order,
+///   where the part appears among the `images` (optional). Directories and
+///   macOS metadata entries are skipped; the uncompressed size is capped by
+///   `ZIP_MAX_BYTES`.
+/// - `prompt`: Prompt applied to every image (optional)
+/// - `prompts`: Repeated field, one prompt per image in upload order (optional).
+///   A single `prompts` entry applies to all images.
+/// - `provider`: AI provider to use (optional, defaults to "google"); send it
+///   before the images to have an unusable provider rejected early
+///
+/// The same API key override and `X-Tenant-Id` headers as `/api/edit` are
+/// supported. Metrics count one edit per image.
+///
+/// # Response
+///
+/// Returns a JSON `BatchEditResponse`. Provider failures for individual images
+/// are reported per item and do not fail the whole request. So are empty
+/// `images` parts and images in an unrecognized format with
+/// `BATCH_MODE=best_effort` (the default); with
+/// `all_or_nothing` they fail the request instead. As with
+/// `/api/edit`, `X-Dev-Mode: true` marks results from the dev-mode mock editor.
+///
+/// # Errors
+///
+/// - `400 Bad Request`: Missing images, too many images, mismatched prompt count,
+///   malformed `X-Provider-Keys`, an unreadable or oversized archive, or
+///   (`all_or_nothing` mode) an invalid image
+/// - `404 Not Found`: Provider not found or not configured
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST http://localhost:8000/api/edit/batch \
+///   -F "images=@kitchen.jpg" -F "prompts=Add an island" \
+///   -F "images=@bedroom.jpg" -F "prompts=Add a king bed"
+/// ```
+pub async fn edit_batch(
+    State(config): State<Arc<AppConfig>>,
+    State(metrics): State<Arc<Metrics>>,
+    tenant: TenantId,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<(HeaderMap, Json<BatchEditResponse>), AppError> {
+    tracing::info!("Received batch edit request");
+    let started = Instant::now();
+
+    let (provider_name, dev_fallback, response) =
+        match process_batch(&config, &headers, &metrics, &tenant, multipart).await {
+            Ok(processed) => processed,
+            Err(e) => {
+                metrics.record_edit(&tenant, "unknown", e.error_type());
+                EditAudit::unparsed().emit_sampled(
+                    &tenant,
+                    "unknown",
+                    e.error_type(),
+                    config.audit_sample_rate,
+                );
+                return Err(e);
+            }
+        };
+
+    for item in &response.results {
+        let outcome = item
+            .error_type
+            .as_deref()
+            .unwrap_or(metrics::OUTCOME_SUCCESS);
+        metrics.record_edit(&tenant, &provider_name, outcome);
+    }
+
+    // Request summary; never includes API keys
+    tracing::info!(
+        tenant = %tenant,
+        provider = %provider_name,
+        image_count = response.results.len(),
+        failed = response.results.iter().filter(|item| item.error.is_some()).count(),
+        duration_ms = started.elapsed().as_millis() as u64,
+        "Batch edit request summary"
+    );
+
+    let mut response_headers = HeaderMap::new();
+    if dev_fallback {
+        response_headers.insert(DEV_MODE_HEADER, HeaderValue::from_static("true"));
+    }
+
+    Ok((response_headers, Json(response)))
+}
+
+/// Add an uploaded image to the batch
+///
+/// With `BatchMode::BestEffort` an empty image or one in an unrecognized
+/// format keeps its position as an empty slot, so later images and prompts
+/// stay aligned, and is reported as a failed item.
+fn push_image(
+    config: &AppConfig,
+    request: &mut BatchEditRequest,
+    rejected: &mut HashMap<usize, AppError>,
+    image: Result<Vec<u8>, AppError>,
+) -> Result<(), AppError> {
+    match image {
+        Ok(data) => request.images.push(data),
+        Err(e @ AppError::ImageProcessing(_)) if config.batch_mode == BatchMode::BestEffort => {
+            tracing::warn!(index = request.images.len(), error = %e, "Invalid batch image");
+            rejected.insert(request.images.len(), e);
+            request.images.push(Vec::new());
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// Parse a batch request and edit every image, returning the provider used
+/// and whether the dev-mode mock editor stood in for it
+///
+/// Emits one audit event per item as it completes.
+async fn process_batch(
+    config: &AppConfig,
+    headers: &HeaderMap,
+    metrics: &Metrics,
+    tenant: &TenantId,
+    mut multipart: Multipart,
+) -> Result<(String, bool, BatchEditResponse), AppError> {
+    // Parsed before the body so unusable providers are rejected before the upload is read
+    let runtime_config = runtime_config_from_headers(config, headers)?;
+
+    let mut request = BatchEditRequest {
+        images: Vec::new(),
+        prompt: None,
+        prompts: Vec::new(),
+        provider: None,
+    };
+    // Invalid images by position (best-effort mode); their slot in `images` is empty
+    let mut rejected: HashMap<usize, AppError> = HashMap::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| multipart_read_error(&e, "multipart field"))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+
+        match name.as_str() {
+            "images" | "image" => {
+                // An empty part still takes its slot, so `prompts` stay aligned
+                let image = read_image_field(field).await.and_then(|image| {
+                    image
+                        .ok_or_else(|| AppError::ImageProcessing("image part is empty".to_string()))
+                });
+                push_image(config, &mut request, &mut rejected, image)?;
+            }
+            "archive" => {
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| multipart_read_error(&e, "archive"))?;
+                if data.is_empty() {
+                    continue;
+                }
+                let limits = ArchiveLimits {
+                    max_entries: config.zip_max_entries,
+                    max_files: config.max_batch_images,
+                    max_bytes: config.zip_max_bytes,
+                };
+                let files = run_blocking(move || archive::extract_files(&data, limits)).await?;
+                for (entry, contents) in files {
+                    tracing::debug!(entry = %entry, size = contents.len(), "Extracted archive image");
+                    let image = validate_image_header(&contents).map(|()| contents);
+                    push_image(config, &mut request, &mut rejected, image)?;
+                }
+            }
+            "prompt" => {
+                request.prompt = read_text_field(field, "prompt").await?;
+            }
+            "prompts" => {
+                // Keep blank entries so positions stay aligned with images
+                let text = read_text_field(field, "prompts").await?.unwrap_or_default();
+                request.prompts.push(text);
+            }
+            "provider" => {
+                request.provider = read_text_field(field, "provider").await?;
+                if let Some(provider) = &request.provider {
+                    factory::check_provider_available(provider, &runtime_config)?;
+                }
+            }
+            _ => {
+                tracing::debug!(field_name = %name, "Ignoring unknown field");
+            }
+        }
+    }
+
+    request
+        .validate(config.max_batch_images)
+        .map_err(AppError::InvalidInput)?;
+    let valid_images: Vec<&Vec<u8>> = request
+        .images
+        .iter()
+        .filter(|image| !image.is_empty())
+        .collect();
+    metrics.record_input_images(&valid_images);
+
+    // Resolve every prompt up front so length violations fail before any provider call
+    let provider_name = request.get_provider();
+    let prompts = (0..request.images.len())
+        .map(|index| {
+            let prompt = compose_prompt(config, &request.prompt_for(index))?;
+            check_provider_prompt(&provider_name, &prompt)?;
+            Ok(prompt)
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    let audits: Vec<EditAudit> = request
+        .images
+        .iter()
+        .enumerate()
+        .map(|(index, image)| EditAudit::new(image, &request.prompt_for(index)))
+        .collect();
+
+    let (editor, dev_fallback) =
+        factory::get_editor_or_dev_fallback(&provider_name, &runtime_config)?;
+    let editor: Arc<dyn ImageEditor> = Arc::from(editor);
+
+    tracing::info!(
+        provider = %provider_name,
+        image_count = request.images.len(),
+        concurrency = config.batch_concurrency,
+        "Processing batch edit"
+    );
+
+    let semaphore = Arc::new(Semaphore::new(config.batch_concurrency));
+    let items = request
+        .images
+        .into_iter()
+        .zip(prompts)
+        .zip(audits)
+        .enumerate()
+        .map(|(index, ((image, prompt), audit))| {
+            let editor = Arc::clone(&editor);
+            let semaphore = Arc::clone(&semaphore);
+            let provider_name = &provider_name;
+            let rejected = rejected.remove(&index);
+            async move {
+                // The permit is held for the duration of the provider call
+                let item = if let Some(e) = rejected {
+                    BatchItemResult::failure(index, e.to_string(), e.error_type())
+                } else {
+                    match semaphore.acquire().await {
+                        Ok(_permit) => {
+                            edit_item(
+                                config,
+                                editor.as_ref(),
+                                provider_name,
+                                index,
+                                image,
+                                &prompt,
+                            )
+                            .await
+                        }
+                        Err(e) => {
+                            let err =
+                                AppError::InternalServer(format!("Batch semaphore closed: {}", e));
+                            BatchItemResult::failure(index, err.to_string(), err.error_type())
+                        }
+                    }
+                };
+                let outcome = item
+                    .error_type
+                    .as_deref()
+                    .unwrap_or(metrics::OUTCOME_SUCCESS);
+                audit.emit_sampled(tenant, provider_name, outcome, config.audit_sample_rate);
+                item
+            }
+        });
+
+    // join_all preserves input order regardless of completion order
+    let results = futures::future::join_all(items).await;
+
+    Ok((provider_name, dev_fallback, BatchEditResponse { results }))
+}
+
+/// Edit a single batch item, converting any failure into a per-item error
+async fn edit_item(
+    config: &AppConfig,
+    editor: &dyn ImageEditor,
+    provider_name: &str,
+    index: usize,
+    image: Vec<u8>,
+    prompt: &str,
+) -> BatchItemResult {
+    match edit_and_encode(config, editor, provider_name, Bytes::from(image), prompt).await {
+        Ok(data_url) => BatchItemResult::success(index, data_url),
+        Err(e) => {
+            tracing::warn!(index, error = %e, "Batch item failed");
+            BatchItemResult::failure(index, e.to_string(), e.error_type())
+        }
+    }
+}
+
+/// Normalize, edit and encode one image as a data URL
+pub(crate) async fn edit_and_encode(
+    config: &AppConfig,
+    editor: &dyn ImageEditor,
+    provider_name: &str,
+    image: Bytes,
+    prompt: &str,
+) -> Result<String, AppError> {
+    let image = run_blocking(move || image_utils::normalize_color_space(image)).await?;
+    let image = fit_bit_depth(config, editor, provider_name, image).await?;
+    let bytes = editor
+        .edit_image(image.clone(), prompt)
+        .await
+        .map_err(AppError::from_provider)?;
+    check_result_size(config, &bytes)?;
+    check_result_is_image(&bytes)?;
+    check_result_changed(config, &image, &bytes).await?;
+    let bytes = check_result_scale(config, &image, bytes).await?;
+    image_utils::bytes_to_base64(&bytes, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock_editor::MockEditor;
+
+    #[tokio::test]
+    async fn test_edit_item_success_returns_data_url() {
+        let img = image::DynamicImage::new_rgb8(1, 1);
+        let png = image_utils::image_to_bytes(&img, image::ImageFormat::Png)
+            .unwrap()
+            .to_vec();
+        let result = edit_item(
+            &AppConfig::default(),
+            &MockEditor::new(),
+            "mock",
+            3,
+            png,
+            "prompt",
+        )
+        .await;
+
+        assert_eq!(result.index, 3);
+        assert!(result.image.unwrap().starts_with("data:image/png;base64,"));
+        assert!(result.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_edit_item_failure_is_reported() {
+        // The mock echoes unrecognizable bytes, which is a provider failure
+        let result = edit_item(
+            &AppConfig::default(),
+            &MockEditor::new(),
+            "mock",
+            0,
+            vec![0, 1, 2],
+            "prompt",
+        )
+        .await;
+
+        assert!(result.image.is_none());
+        assert_eq!(result.error_type.as_deref(), Some("provider_error"));
+        assert!(result
+            .error
+            .unwrap()
+            .contains("provider returned non-image data"));
+    }
+}
@@ -0,0 +1,279 @@
+//! Provider comparison endpoint
+//!
+//! This module implements the `/api/edit/compare` endpoint, which sends the
+//! same image and prompt to several providers concurrently (bounded by
+//! `AppConfig.compare_concurrency`) and returns every result, tagged with the
+//! provider name and its latency, in request order. A provider that is
+//! unavailable or fails is reported in its own item; the others still run.
+
+use axum::{
+    extract::{Multipart, State},
+    http::{HeaderMap, HeaderValue},
+    Json,
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+
+use crate::audit::EditAudit;
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::metrics::{self, Metrics};
+use crate::models::request::CompareEditRequest;
+use crate::models::response::{CompareEditResponse, CompareItemResult};
+use crate::models::tenant::TenantId;
+use crate::routes::batch::edit_and_encode;
+use crate::routes::edit::{
+    check_provider_prompt, compose_prompt, multipart_read_error, read_image_field, read_text_field,
+    runtime_config_from_headers, DEV_MODE_HEADER,
+};
+use crate::services::factory;
+
+/// Provider comparison handler
+///
+/// # Endpoint
+///
+/// `POST /api/edit/compare`
+///
+/// # Request Format
+///
+/// Multipart form data with the following fields:
+/// - `images`: The image file to edit (required, exactly one)
+/// - `prompt`: Prompt sent to every provider (optional)
+/// - `providers`: Repeated field, one provider name per entry, up to
+///   `MAX_COMPARE_PROVIDERS` (required). Results follow this order.
+///
+/// The same API key override and `X-Tenant-Id` headers as `/api/edit` are
+/// supported. Metrics and audit events count one edit per provider.
+///
+/// # Response
+///
+/// Returns a JSON `CompareEditResponse` with one item per provider, carrying
+/// the edited image as a data URL or the error, and `latency_ms`. Unknown,
+/// unconfigured and failing providers are reported per item and do not fail
+/// the whole request. `X-Dev-Mode: true` marks a response in which the dev-mode
+/// mock editor stood in for at least one provider.
+///
+/// # Errors
+///
+/// - `400 Bad Request`: Missing or extra image, no providers or more than
+///   `MAX_COMPARE_PROVIDERS`, an over-long prompt, or malformed `X-Provider-Keys`
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST http://localhost:8000/api/edit/compare \
+///   -F "images=@kitchen.jpg" -F "prompt=Add an island" \
+///   -F "providers=google" -F "providers=fal:fal-ai/flux/dev"
+/// ```
+pub async fn edit_compare(
+    State(config): State<Arc<AppConfig>>,
+    State(metrics): State<Arc<Metrics>>,
+    tenant: TenantId,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<(HeaderMap, Json<CompareEditResponse>), AppError> {
+    tracing::info!("Received provider comparison request");
+    let started = Instant::now();
+
+    let (dev_fallback, response) =
+        match process_compare(&config, &headers, &metrics, &tenant, multipart).await {
+            Ok(processed) => processed,
+            Err(e) => {
+                metrics.record_edit(&tenant, "unknown", e.error_type());
+                EditAudit::unparsed().emit_sampled(
+                    &tenant,
+                    "unknown",
+                    e.error_type(),
+                    config.audit_sample_rate,
+                );
+                return Err(e);
+            }
+        };
+
+    for item in &response.results {
+        let outcome = item
+            .error_type
+            .as_deref()
+            .unwrap_or(metrics::OUTCOME_SUCCESS);
+        metrics.record_edit(&tenant, &item.provider, outcome);
+    }
+
+    // Request summary; never includes API keys
+    tracing::info!(
+        tenant = %tenant,
+        provider_count = response.results.len(),
+        failed = response.results.iter().filter(|item| item.error.is_some()).count(),
+        duration_ms = started.elapsed().as_millis() as u64,
+        "Provider comparison request summary"
+    );
+
+    let mut response_headers = HeaderMap::new();
+    if dev_fallback {
+        response_headers.insert(DEV_MODE_HEADER, HeaderValue::from_static("true"));
+    }
+
+    Ok((response_headers, Json(response)))
+}
+
+/// Parse a comparison request and run it on every provider, returning whether
+/// the dev-mode mock editor stood in for any of them
+///
+/// Emits one audit event per provider as it completes.
+async fn process_compare(
+    config: &AppConfig,
+    headers: &HeaderMap,
+    metrics: &Metrics,
+    tenant: &TenantId,
+    mut multipart: Multipart,
+) -> Result<(bool, CompareEditResponse), AppError> {
+    let runtime_config = runtime_config_from_headers(config, headers)?;
+
+    let mut request = CompareEditRequest {
+        image: Vec::new(),
+        prompt: None,
+        providers: Vec::new(),
+    };
+    let mut field_count = 0usize;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| multipart_read_error(&e, "multipart field"))?
+    {
+        // Counted before the name is looked at, so unknown fields count too
+        field_count += 1;
+        if field_count > config.max_multipart_fields {
+            return Err(AppError::InvalidInput(format!(
+                "too many multipart fields (maximum {})",
+                config.max_multipart_fields
+            )));
+        }
+
+        let name = field.name().unwrap_or("").to_string();
+
+        match name.as_str() {
+            "images" | "image" => {
+                if let Some(image) = read_image_field(field).await? {
+                    if !request.image.is_empty() {
+                        return Err(AppError::InvalidInput(
+                            "Comparisons take exactly one image".to_string(),
+                        ));
+                    }
+                    request.image = image;
+                }
+            }
+            "prompt" => {
+                request.prompt = read_text_field(field, "prompt").await?;
+            }
+            "providers" => {
+                if let Some(provider) = read_text_field(field, "providers").await? {
+                    request.providers.push(provider.trim().to_string());
+                    // Reject runaway provider lists without reading the rest of the body
+                    if request.providers.len() > config.max_compare_providers {
+                        return Err(AppError::InvalidInput(format!(
+                            "Too many providers to compare (maximum {})",
+                            config.max_compare_providers
+                        )));
+                    }
+                }
+            }
+            _ => {
+                tracing::debug!(field_name = %name, "Ignoring unknown field");
+            }
+        }
+    }
+
+    request
+        .validate(config.max_compare_providers)
+        .map_err(AppError::InvalidInput)?;
+    metrics.record_input_images(&[&request.image]);
+
+    let requested_prompt = request.get_prompt();
+    let prompt = compose_prompt(config, &requested_prompt)?;
+    let image = Bytes::from(request.image);
+
+    tracing::info!(
+        providers = ?request.providers,
+        concurrency = config.compare_concurrency,
+        "Processing provider comparison"
+    );
+
+    let semaphore = Arc::new(Semaphore::new(config.compare_concurrency));
+    let items = request.providers.into_iter().map(|provider_name| {
+        let semaphore = Arc::clone(&semaphore);
+        let image = image.clone();
+        let runtime_config = &runtime_config;
+        let prompt = &prompt;
+        let audit = EditAudit::new(&image, &requested_prompt);
+        async move {
+            // The permit is held for the duration of the provider call
+            let (item, dev_fallback) = match semaphore.acquire().await {
+                Ok(_permit) => {
+                    compare_item(config, runtime_config, provider_name.clone(), image, prompt).await
+                }
+                Err(e) => {
+                    let err = AppError::InternalServer(format!("Compare semaphore closed: {}", e));
+                    (
+                        CompareItemResult::failure(
+                            provider_name.clone(),
+                            0,
+                            err.to_string(),
+                            err.error_type(),
+                        ),
+                        false,
+                    )
+                }
+            };
+            let outcome = item
+                .error_type
+                .as_deref()
+                .unwrap_or(metrics::OUTCOME_SUCCESS);
+            audit.emit_sampled(tenant, &provider_name, outcome, config.audit_sample_rate);
+            (item, dev_fallback)
+        }
+    });
+
+    // join_all preserves request order regardless of completion order
+    let (results, dev_fallbacks): (Vec<_>, Vec<_>) =
+        futures::future::join_all(items).await.into_iter().unzip();
+
+    Ok((
+        dev_fallbacks.contains(&true),
+        CompareEditResponse { results },
+    ))
+}
+
+/// Run the comparison on one provider, converting any failure into an item error
+///
+/// Returns the item and whether the dev-mode mock editor stood in for the provider.
+async fn compare_item(
+    config: &AppConfig,
+    runtime_config: &AppConfig,
+    provider_name: String,
+    image: Bytes,
+    prompt: &str,
+) -> (CompareItemResult, bool) {
+    let started = Instant::now();
+    let mut dev_fallback = false;
+
+    let result = async {
+        check_provider_prompt(&provider_name, prompt)?;
+        let (editor, fallback) =
+            factory::get_editor_or_dev_fallback(&provider_name, runtime_config)?;
+        dev_fallback = fallback;
+        edit_and_encode(config, editor.as_ref(), &provider_name, image, prompt).await
+    }
+    .await;
+
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let item = match result {
+        Ok(data_url) => CompareItemResult::success(provider_name, latency_ms, data_url),
+        Err(e) => {
+            tracing::warn!(provider = %provider_name, error = %e, "Compared provider failed");
+            CompareItemResult::failure(provider_name, latency_ms, e.to_string(), e.error_type())
+        }
+    };
+    (item, dev_fallback)
+}
@@ -4,17 +4,48 @@
 //! The endpoint accepts multipart form data with images and optional parameters,
 //! processes them through the selected AI provider, and streams the result back.
 
+use crate::audit::EditAudit;
+use crate::config::{AppConfig, HighBitDepth};
+use crate::error::AppError;
+use crate::metrics::{self, Metrics};
+use crate::models::request::EditImageRequest;
+use crate::models::tenant::TenantId;
+use crate::routes::{jobs, results, uploads};
+use crate::services::base::{EditRegion, ImageEditor, ProviderMetadata};
+use crate::services::{catalog, factory, prompt_enhancer};
+use crate::state::AppState;
+use crate::uploads::UploadStore;
+use crate::utils::image_utils::{JpegOptions, OutputFormat};
+use crate::utils::{image_utils, log_redaction, remote_image};
 use axum::{
     body::Body,
-    extract::{Multipart, State},
-    http::{header, HeaderMap, StatusCode},
-    response::{IntoResponse, Response},
+    extract::{multipart::Field, Multipart, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
 };
-use bytes::Bytes;
-use crate::config::AppConfig;
-use crate::error::AppError;
-use crate::models::request::EditImageRequest;
-use crate::services::factory;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use image::{GenericImageView, ImageFormat};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Response header marking results produced by the dev-mode mock editor
+pub(crate) const DEV_MODE_HEADER: &str = "X-Dev-Mode";
+
+/// Response header carrying provider details as compact JSON, on request
+pub(crate) const PROVIDER_METADATA_HEADER: &str = "X-Provider-Metadata";
+
+/// Header carrying the perceptual hash of the output (`phash=true`)
+pub(crate) const IMAGE_PHASH_HEADER: &str = "X-Image-Phash";
+
+/// Header carrying the sharpness score of the output (`quality_score=true`)
+pub(crate) const QUALITY_SCORE_HEADER: &str = "X-Quality-Score";
+
+/// Longest fetch of a `GET /api/edit` image, in seconds (caps `URL_INPUT_TIMEOUT_SECS`)
+const QUERY_EDIT_FETCH_TIMEOUT_SECS: u64 = 10;
+
+/// Largest `GET /api/edit` image, in bytes (caps `URL_INPUT_MAX_BYTES`)
+const QUERY_EDIT_MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
 
 /// Image editing handler
 ///
@@ -23,14 +54,80 @@ use crate::services::factory;
 ///
 /// # Endpoint
 ///
-/// `POST /api/edit`
+/// `POST /api/edit` (see `edit_image_from_query` for the `GET` variant)
 ///
 /// # Request Format
 ///
 /// Multipart form data with the following fields:
-/// - `images`: One or more image files (required)
-/// - `prompt`: Text description for image editing (optional)
-/// - `provider`: AI provider to use (optional, defaults to "google")
+/// - `images`: One or more image files (required unless `image_url` is given)
+/// - `base_image` / `reference_image`: Images with a fixed role for multi-image
+///   providers (optional, at most one each). Providers receive the base image
+///   first, then the reference image, then `images`, `image_url` and `upload_id`
+///   inputs in the order they were sent, whatever the field order in the form
+/// - `image_url`: http(s) URL of an input image, fetched by the server within the
+///   `URL_INPUT_*` limits (optional, repeatable)
+/// - `upload_id`: Id of an image sent beforehand with `PUT /api/uploads/{id}`
+///   (`uploads` feature; optional, repeatable)
+/// - `prompt`: Text description for image editing (optional; required with
+///   `REQUIRE_PROMPT`, otherwise the default prompt applies)
+/// - `provider`: AI provider to use (optional, defaults to the `X-Provider`
+///   header, then "google"); `composite`
+///   lays the images out in a grid without AI (see `services::composite_editor`). Send it
+///   before the images so a provider without a usable API key is rejected
+///   before the upload is read
+/// - `out_width` / `out_height`: Resize the result to these dimensions (optional)
+/// - `fit`: `contain` (default), `cover`, `fill`, or `smart` (cover
+///   cropped around the most detailed region) when resizing (optional)
+/// - `output_format`: `png`, `jpeg` or `webp`; defaults to `DEFAULT_OUTPUT_FORMAT`,
+///   or the provider's format when that is unset. With `PRESERVE_ALPHA`, results
+///   with transparent pixels are returned as PNG instead of JPEG (optional)
+/// - `formats`: Comma-separated formats (e.g. `webp,png`, up to `MAX_FORMATS`)
+///   to return the result in all at once, instead of `output_format`; the
+///   response is then a JSON object mapping each format to a base64 data URL
+///   (optional, repeatable)
+/// - `optimize`: `true` to losslessly optimize PNG output (optional)
+/// - `enhance_prompt`: `true` to enrich the prompt first, for providers listed in
+///   `PROMPT_ENHANCE_PROVIDERS` (optional)
+/// - `include_metadata`: `true` to return what the provider reported about the
+///   edit (seed, inference time, model version) as JSON in an
+///   `X-Provider-Metadata` header; fields a provider doesn't report are
+///   omitted (optional)
+/// - `phash`: `true` to return a 64-bit perceptual hash of the output image
+///   (16 hex digits) in an `X-Image-Phash` header, for deduplicating visually
+///   identical results (optional)
+/// - `quality_score`: `true` to return a no-reference sharpness score of the
+///   output (variance of the Laplacian; higher is sharper) in an
+///   `X-Quality-Score` header, for flagging blurry results (optional)
+/// - `embed_metadata`: `true` to write the prompt (the last step's, for chained
+///   edits), provider, seed and model into PNG output as `tEXt` chunks (`iTXt`
+///   for text outside Latin-1), for reproducibility. JPEG and WebP output is
+///   returned without them (optional)
+/// - `rotate`: Clockwise rotation in degrees (`0`, `90`, `180` or `270`) applied
+///   to every input image before it is sent to the provider, for images whose
+///   orientation metadata is missing or wrong. `region` refers to the rotated
+///   image (optional)
+/// - `region`: `x,y,width,height` as fractions of the first image's size
+///   (e.g. `0.25,0.5,0.5,0.25`) to limit the edit to that rectangle. Each
+///   provider translates it to its own mask format; providers without region
+///   support (currently all but Fal.ai inpainting models) reject the request
+///   (optional)
+/// - `temperature` / `top_p`: Sampling parameters (0-2 and 0-1) for providers
+///   that accept them, currently Google Gemini; other providers ignore them
+///   (optional)
+/// - `seed` / `num_inference_steps` / `guidance_scale`: Generation parameters
+///   (a `u32`, 1-50 and 1-20) for Fal.ai models, as listed by
+///   `GET /api/providers/{name}/params`; they replace the same keys in
+///   `provider_options`, and other providers ignore them (optional)
+/// - `provider_options`: JSON object of extra fields for the provider request,
+///   for model parameters the server doesn't model, e.g.
+///   `{"guidance_scale": 3.5}`. Values must be strings, numbers or booleans,
+///   `prompt`, `image_url`, `image_urls` and `mask_url` can't be set, nor
+///   can the keys in `PROVIDER_OPTIONS_DENY` (by default
+///   `enable_safety_checker` and `num_images`).
+///   Merged into Fal.ai request bodies without replacing the fields the server
+///   sets; other providers ignore them (optional)
+/// - `steps`: Chained prompts, used instead of `prompt`; each step edits the
+///   previous step's result. Up to `MAX_EDIT_STEPS` (optional, repeatable)
 ///
 /// # Headers
 ///
@@ -38,17 +135,49 @@ use crate::services::factory;
 /// - `X-Google-Api-Key`: Override GOOGLE_API_KEY from config
 /// - `X-Gemini-Api-Key`: Override GEMINI_API_KEY from config
 /// - `X-Fal-Key`: Override FAL_KEY from config
+/// - `X-Provider-Keys`: JSON object with any of `google`, `gemini` and `fal`,
+///   e.g. `{"google": "...", "fal": "..."}`; the individual headers above win
+///
+/// Optional `X-Provider` selects the provider like the `provider` field, which
+/// takes precedence when both are sent. Unlike an early `provider` field, it is
+/// only checked for a usable API key once the form has been read.
+///
+/// Optional `X-Tenant-Id` (ASCII letters, digits, `-`, `_`; up to 64 characters)
+/// attributes the edit to a tenant in metrics and the request summary log.
+/// Defaults to `anonymous`.
+///
+/// With the `async_jobs` feature enabled, `Prefer: respond-async` runs the edit
+/// as a job instead: the response is the `202 Accepted` of `POST /api/jobs` plus
+/// `Preference-Applied: respond-async`. Without the feature the preference is
+/// ignored and the edit runs synchronously.
 ///
 /// # Response
 ///
-/// Returns the edited image with appropriate Content-Type header.
+/// Returns the edited image with appropriate Content-Type header, or with
+/// `formats` a JSON object such as `{"png": "data:image/png;base64,...",
+/// "webp": "data:image/webp;base64,..."}`.
+/// With the `results` feature enabled, `X-Result-Id` names a copy of the
+/// response that stays available from `GET /api/results/{id}`.
+/// When PNG optimization runs, `X-Original-Content-Length` carries the size
+/// before optimization. With `DEV_MODE` enabled, results produced by the mock
+/// editor because the provider is unavailable carry `X-Dev-Mode: true`.
+/// When a provider rejects an input as too large, the edit is retried once with
+/// the inputs shrunk to the model's `max_input_dimension` from `GET /api/models`.
 /// The image is streamed efficiently without loading entirely into memory.
 ///
 /// # Errors
 ///
-/// - `400 Bad Request`: Invalid image format, missing images, or validation failure
-/// - `404 Not Found`: Provider not found or not configured
-/// - `500 Internal Server Error`: AI service error or internal failure
+/// - `400 Bad Request`: Invalid image format, missing images, invalid tenant id,
+///   malformed `X-Provider-Keys`, more than `MAX_MULTIPART_FIELDS` fields, an incomplete
+///   (truncated) upload, or validation failure
+/// - `404 Not Found`: Provider not found or not configured, or an unknown or expired `upload_id`
+/// - `413 Payload Too Large`: `Content-Length` over `MAX_UPLOAD_BYTES` (checked before the body is read)
+/// - `500 Internal Server Error`: AI service error or internal failure, including a
+///   provider result that is not an image or was truncated in transit; results are
+///   buffered and checked before the status is sent, so a `200` always carries a
+///   complete image
+/// - `502 Bad Gateway`: The provider rejected the configured API key
+/// - `503 Service Unavailable`: `MAX_CONNECTIONS` requests are already in flight
 ///
 /// # Example
 ///
@@ -69,62 +198,436 @@ use crate::services::factory;
 /// - Task 31: Call edit_image
 /// - Task 32: Stream response
 pub async fn edit_image(
-    State(config): State<AppConfig>,
+    State(state): State<AppState>,
+    tenant: TenantId,
     headers: HeaderMap,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> Result<Response, AppError> {
+    let AppState {
+        config,
+        metrics,
+        jobs,
+        uploads,
+        results,
+        ..
+    } = state;
     tracing::info!("Received image edit request");
 
+    // RFC 7240: the preference is only honored when async jobs are enabled
+    if config.features.async_jobs && jobs::prefers_respond_async(&headers) {
+        let mut response =
+            jobs::start_job(config, metrics, jobs, &uploads, tenant, &headers, multipart).await?;
+        response.headers_mut().insert(
+            jobs::PREFERENCE_APPLIED_HEADER,
+            HeaderValue::from_static("respond-async"),
+        );
+        return Ok(response);
+    }
+
+    let started = Instant::now();
+
+    match prepare_edit(&config, &metrics, &uploads, &headers, multipart).await {
+        Ok(prepared) => {
+            let response = run_edit(&config, &metrics, &tenant, prepared, started).await?;
+            if config.features.results {
+                results::store_result(&results, response, config.max_result_bytes).await
+            } else {
+                Ok(response)
+            }
+        }
+        Err(e) => {
+            let result = Err(e);
+            record_edit_outcome(
+                &config,
+                &metrics,
+                &tenant,
+                "unknown",
+                &EditAudit::unparsed(),
+                &result,
+                started,
+            );
+            result
+        }
+    }
+}
+
+/// A parsed edit request, ready to run
+pub(crate) struct PreparedEdit {
+    /// `AppConfig` with the request's API key overrides applied
+    pub(crate) runtime_config: AppConfig,
+    pub(crate) request: EditImageRequest,
+    pub(crate) provider_name: String,
+    pub(crate) audit: EditAudit,
+}
+
+/// Parse an edit request and record its input images
+pub(crate) async fn prepare_edit(
+    config: &AppConfig,
+    metrics: &Metrics,
+    uploads: &UploadStore,
+    headers: &HeaderMap,
+    multipart: Multipart,
+) -> Result<PreparedEdit, AppError> {
+    let (runtime_config, request) = parse_edit_request(config, uploads, headers, multipart).await?;
+    metrics.record_input_images(&request.images);
+
+    Ok(PreparedEdit::new(runtime_config, request))
+}
+
+impl PreparedEdit {
+    fn new(runtime_config: AppConfig, request: EditImageRequest) -> Self {
+        Self {
+            provider_name: request.get_provider(),
+            audit: EditAudit::new(&request.images[0], &request.get_steps().join("\n")),
+            runtime_config,
+            request,
+        }
+    }
+}
+
+/// Query parameters of `GET /api/edit`
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct EditQuery {
+    image_url: Option<String>,
+    prompt: Option<String>,
+    provider: Option<String>,
+}
+
+/// Image editing handler for link-style clients
+///
+/// # Endpoint
+///
+/// `GET /api/edit?image_url=...&prompt=...`
+///
+/// # Query Parameters
+///
+/// - `image_url`: http(s) URL of the input image (required). Fetched like the
+///   `image_url` form field of `POST /api/edit`, but within at most 10 seconds
+///   and 5 MiB regardless of `URL_INPUT_*`
+/// - `prompt`: Text description for image editing (optional; required with
+///   `REQUIRE_PROMPT`, otherwise the default prompt applies)
+/// - `provider`: AI provider to use (optional, defaults to the `X-Provider`
+///   header, then "google")
+///
+/// API key override and `X-Tenant-Id` headers, the response and the errors are
+/// those of `POST /api/edit`; the other form fields have no query equivalent.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://localhost:8000/api/edit?image_url=https%3A%2F%2Fexample.com%2Froom.jpg&prompt=Add%20a%20rug"
+/// ```
+pub async fn edit_image_from_query(
+    State(state): State<AppState>,
+    tenant: TenantId,
+    headers: HeaderMap,
+    Query(query): Query<EditQuery>,
+) -> Result<Response, AppError> {
+    let AppState {
+        config,
+        metrics,
+        results,
+        ..
+    } = state;
+    tracing::info!("Received query image edit request");
+
+    let started = Instant::now();
+
+    match prepare_query_edit(&config, &metrics, &headers, query).await {
+        Ok(prepared) => {
+            let response = run_edit(&config, &metrics, &tenant, prepared, started).await?;
+            if config.features.results {
+                results::store_result(&results, response, config.max_result_bytes).await
+            } else {
+                Ok(response)
+            }
+        }
+        Err(e) => {
+            let result = Err(e);
+            record_edit_outcome(
+                &config,
+                &metrics,
+                &tenant,
+                "unknown",
+                &EditAudit::unparsed(),
+                &result,
+                started,
+            );
+            result
+        }
+    }
+}
+
+/// Fetch the image of a `GET /api/edit` request and record it
+async fn prepare_query_edit(
+    config: &AppConfig,
+    metrics: &Metrics,
+    headers: &HeaderMap,
+    query: EditQuery,
+) -> Result<PreparedEdit, AppError> {
+    let runtime_config = runtime_config_from_headers(config, headers)?;
+
+    let image_url = query
+        .image_url
+        .filter(|url| !url.trim().is_empty())
+        .ok_or_else(|| {
+            AppError::InvalidInput("image_url query parameter is required".to_string())
+        })?;
+
+    // Checked before the fetch, like a `provider` field sent ahead of the images
+    let provider = query.provider.or_else(|| provider_header(headers));
+    if let Some(provider) = &provider {
+        factory::check_provider_available(provider, &runtime_config)?;
+    }
+
+    let fetch_config = AppConfig {
+        url_input_timeout_secs: config
+            .url_input_timeout_secs
+            .min(QUERY_EDIT_FETCH_TIMEOUT_SECS),
+        url_input_max_bytes: config.url_input_max_bytes.min(QUERY_EDIT_MAX_IMAGE_BYTES),
+        ..config.clone()
+    };
+    let data = remote_image::fetch_image(&fetch_config, &image_url).await?;
+    image_utils::validate_image_bytes(&data)?;
+
+    let request = EditImageRequest::with_options(vec![data], query.prompt, provider);
+    if config.require_prompt && !request.has_prompt() {
+        return Err(AppError::InvalidInput("prompt is required".to_string()));
+    }
+    metrics.record_input_images(&request.images);
+
+    Ok(PreparedEdit::new(runtime_config, request))
+}
+
+/// Run a prepared edit and record its outcome
+pub(crate) async fn run_edit(
+    config: &AppConfig,
+    metrics: &Metrics,
+    tenant: &TenantId,
+    prepared: PreparedEdit,
+    started: Instant,
+) -> Result<Response, AppError> {
+    let PreparedEdit {
+        runtime_config,
+        request,
+        provider_name,
+        audit,
+    } = prepared;
+
+    let result = process_edit(config, &runtime_config, request).await;
+    record_edit_outcome(
+        config,
+        metrics,
+        tenant,
+        &provider_name,
+        &audit,
+        &result,
+        started,
+    );
+    result
+}
+
+/// Record metrics, the audit event and the request summary log of an edit
+pub(crate) fn record_edit_outcome<T>(
+    config: &AppConfig,
+    metrics: &Metrics,
+    tenant: &TenantId,
+    provider_name: &str,
+    audit: &EditAudit,
+    result: &Result<T, AppError>,
+    started: Instant,
+) {
+    let outcome = metrics::outcome_label(result);
+    metrics.record_edit(tenant, provider_name, outcome);
+    audit.emit_sampled(tenant, provider_name, outcome, config.audit_sample_rate);
+
+    // Request summary; never includes API keys
+    tracing::info!(
+        tenant = %tenant,
+        provider = %provider_name,
+        outcome,
+        duration_ms = started.elapsed().as_millis() as u64,
+        "Edit request summary"
+    );
+}
+
+/// Images sent in the fields that fix their position for the provider
+#[derive(Debug, Default)]
+struct NamedImages {
+    /// `base_image`: the image being edited, always sent first
+    base: Option<Vec<u8>>,
+    /// `reference_image`: guidance for the edit, sent after the base image
+    reference: Option<Vec<u8>>,
+}
+
+impl NamedImages {
+    /// Store a named image, rejecting a second one for the same field
+    fn fill(slot: &mut Option<Vec<u8>>, label: &str, data: Vec<u8>) -> Result<(), AppError> {
+        if slot.is_some() {
+            return Err(AppError::InvalidInput(format!(
+                "{} may only be sent once",
+                label
+            )));
+        }
+        *slot = Some(data);
+        Ok(())
+    }
+
+    /// The provider's input order: base, reference, then the generic `images`
+    fn ahead_of(self, images: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        self.base
+            .into_iter()
+            .chain(self.reference)
+            .chain(images)
+            .collect()
+    }
+}
+
+/// Parse the key override headers and the multipart form of an edit request (Tasks 26-28)
+///
+/// Returns the per-request configuration along with the request. The headers
+/// are parsed first so that a `provider` field naming a provider without a
+/// usable API key is rejected before any image part that follows it is read.
+async fn parse_edit_request(
+    config: &AppConfig,
+    uploads: &UploadStore,
+    headers: &HeaderMap,
+    mut multipart: Multipart,
+) -> Result<(AppConfig, EditImageRequest), AppError> {
+    // Tasks 27-28: Extract API key overrides from headers
+    let runtime_config = runtime_config_from_headers(config, headers)?;
+
     // Task 26: Extract multipart form data
-    let mut images: Vec<Vec<u8>> = Vec::new();
-    let mut prompt: Option<String> = None;
-    let mut provider: Option<String> = None;
+    let mut request = EditImageRequest::new(Vec::new());
+    let mut named = NamedImages::default();
+    let mut field_count = 0usize;
 
     // Parse multipart fields
     while let Some(field) = multipart
         .next_field()
         .await
-        .map_err(|e| AppError::InvalidInput(format!("Failed to read multipart field: {}", e)))?
+        .map_err(|e| multipart_read_error(&e, "multipart field"))?
     {
+        // Counted before the name is looked at, so unknown fields count too
+        field_count += 1;
+        if field_count > config.max_multipart_fields {
+            return Err(AppError::InvalidInput(format!(
+                "too many multipart fields (maximum {})",
+                config.max_multipart_fields
+            )));
+        }
+
         let name = field.name().unwrap_or("").to_string();
 
         match name.as_str() {
             "images" | "image" => {
-                // Read image bytes
-                let data = field
-                    .bytes()
-                    .await
-                    .map_err(|e| AppError::InvalidInput(format!("Failed to read image data: {}", e)))?;
-
-                if !data.is_empty() {
-                    // Validate that it's a valid image
-                    image::guess_format(&data)
-                        .map_err(|e| AppError::ImageProcessing(format!("Invalid image format: {}", e)))?;
-
-                    images.push(data.to_vec());
-                    tracing::debug!(size = data.len(), "Received image");
+                if let Some(data) = read_image_field(field).await? {
+                    request.images.push(data);
+                }
+            }
+            "base_image" => {
+                if let Some(data) = read_image_field(field).await? {
+                    NamedImages::fill(&mut named.base, "base_image", data)?;
+                }
+            }
+            "reference_image" => {
+                if let Some(data) = read_image_field(field).await? {
+                    NamedImages::fill(&mut named.reference, "reference_image", data)?;
+                }
+            }
+            "upload_id" => {
+                if let Some(id) = read_text_field(field, "upload_id").await? {
+                    let data = uploads::uploaded_image(uploads, &id)?;
+                    request.images.push(data.to_vec());
+                }
+            }
+            "image_url" => {
+                if let Some(url) = read_text_field(field, "image_url").await? {
+                    let data = remote_image::fetch_image(config, &url).await?;
+                    image_utils::validate_image_bytes(&data)?;
+                    request.images.push(data);
                 }
             }
             "prompt" => {
-                let text = field
-                    .text()
-                    .await
-                    .map_err(|e| AppError::InvalidInput(format!("Failed to read prompt: {}", e)))?;
-
-                if !text.trim().is_empty() {
-                    tracing::debug!(prompt = %text, "Received prompt");
-                    prompt = Some(text);
+                if let Some(text) = read_text_field(field, "prompt").await? {
+                    tracing::debug!(prompt = %log_redaction::prompt(&text, config.log_redaction), "Received prompt");
+                    request.prompt = Some(text);
                 }
             }
             "provider" => {
-                let text = field
-                    .text()
-                    .await
-                    .map_err(|e| AppError::InvalidInput(format!("Failed to read provider: {}", e)))?;
-
-                if !text.trim().is_empty() {
+                if let Some(text) = read_text_field(field, "provider").await? {
                     tracing::debug!(provider = %text, "Received provider");
-                    provider = Some(text);
+                    // Fail fast, before buffering any image parts that follow
+                    factory::check_provider_available(&text, &runtime_config)?;
+                    request.provider = Some(text);
+                }
+            }
+            "out_width" => request.out_width = read_parsed_field(field, "out_width").await?,
+            "out_height" => request.out_height = read_parsed_field(field, "out_height").await?,
+            "fit" => request.fit = read_parsed_field(field, "fit").await?,
+            "output_format" => {
+                request.output_format = read_parsed_field(field, "output_format").await?;
+            }
+            "optimize" => {
+                request.optimize = read_flag_field(field, "optimize").await?;
+            }
+            "enhance_prompt" => {
+                request.enhance_prompt = read_flag_field(field, "enhance_prompt").await?;
+            }
+            "formats" => {
+                if let Some(text) = read_text_field(field, "formats").await? {
+                    for format in text.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+                        let format: OutputFormat = format.parse()?;
+                        request.formats.push(format);
+                    }
+                    request.validate_formats().map_err(AppError::InvalidInput)?;
+                }
+            }
+            "include_metadata" => {
+                request.include_metadata = read_flag_field(field, "include_metadata").await?;
+            }
+            "phash" => {
+                request.phash = read_flag_field(field, "phash").await?;
+            }
+            "quality_score" => {
+                request.quality_score = read_flag_field(field, "quality_score").await?;
+            }
+            "embed_metadata" => {
+                request.embed_metadata = read_flag_field(field, "embed_metadata").await?;
+            }
+            "rotate" => {
+                request.rotate = read_parsed_field(field, "rotate")
+                    .await?
+                    .unwrap_or_default();
+            }
+            "region" => request.region = read_parsed_field(field, "region").await?,
+            "temperature" => request.temperature = read_parsed_field(field, "temperature").await?,
+            "top_p" => request.top_p = read_parsed_field(field, "top_p").await?,
+            "seed" => request.seed = read_parsed_field(field, "seed").await?,
+            "num_inference_steps" => {
+                request.num_inference_steps =
+                    read_parsed_field(field, "num_inference_steps").await?;
+            }
+            "guidance_scale" => {
+                request.guidance_scale = read_parsed_field(field, "guidance_scale").await?;
+            }
+            "provider_options" => {
+                if let Some(text) = read_text_field(field, "provider_options").await? {
+                    request.provider_options = serde_json::from_str(&text).map_err(|e| {
+                        AppError::InvalidInput(format!(
+                            "provider_options must be a JSON object: {}",
+                            e
+                        ))
+                    })?;
+                }
+            }
+            "steps" | "step" => {
+                if let Some(text) = read_text_field(field, "steps").await? {
+                    request.steps.push(text);
+                    // Reject runaway chains without reading the rest of the body
+                    request
+                        .validate_steps(config.max_edit_steps)
+                        .map_err(AppError::InvalidInput)?;
                 }
             }
             _ => {
@@ -134,85 +637,397 @@ pub async fn edit_image(
         }
     }
 
+    request.images = named.ahead_of(request.images);
+
+    // The header only applies without a `provider` field. It can't be checked
+    // ahead of the images, since a later field may still override it.
+    if request.provider.is_none() {
+        if let Some(provider) = provider_header(headers) {
+            factory::check_provider_available(&provider, &runtime_config)?;
+            request.provider = Some(provider);
+        }
+    }
+
     // Validate that we have at least one image
-    if images.is_empty() {
+    if request.images.is_empty() {
         return Err(AppError::InvalidInput(
             "At least one image is required".to_string(),
         ));
     }
 
-    tracing::info!(image_count = images.len(), "Parsed multipart form");
-
-    // Tasks 27-28: Extract API key overrides from headers
-    let mut runtime_config = config.clone();
-
-    if let Some(google_key) = headers.get("X-Google-Api-Key") {
-        if let Ok(key_str) = google_key.to_str() {
-            runtime_config.google_api_key = Some(key_str.to_string());
-            tracing::debug!("Using Google API key from header");
-        }
-    }
-
-    if let Some(gemini_key) = headers.get("X-Gemini-Api-Key") {
-        if let Ok(key_str) = gemini_key.to_str() {
-            runtime_config.gemini_api_key = Some(key_str.to_string());
-            tracing::debug!("Using Gemini API key from header");
-        }
+    // Without a prompt the default staging prompt applies, unless one is required
+    if config.require_prompt && !request.has_prompt() {
+        return Err(AppError::InvalidInput("prompt is required".to_string()));
     }
 
-    if let Some(fal_key) = headers.get("X-Fal-Key") {
-        if let Ok(key_str) = fal_key.to_str() {
-            runtime_config.fal_key = Some(key_str.to_string());
-            tracing::debug!("Using Fal API key from header");
-        }
-    }
+    tracing::info!(image_count = request.images.len(), "Parsed multipart form");
 
-    // Build request object for convenience
-    let request = EditImageRequest::with_options(images, prompt, provider);
+    Ok((runtime_config, request))
+}
 
-    // Task 29: Get prompt with default fallback
-    let final_prompt = request.get_prompt();
-    tracing::info!(prompt = %final_prompt, "Using prompt");
+/// Run a parsed edit request through the selected provider (Tasks 28-32)
+///
+/// `runtime_config` is `config` with the request's API key overrides applied.
+async fn process_edit(
+    config: &AppConfig,
+    runtime_config: &AppConfig,
+    mut request: EditImageRequest,
+) -> Result<Response, AppError> {
+    request
+        .validate_output_size(config.max_output_dimension)
+        .map_err(AppError::InvalidInput)?;
+    request
+        .validate_steps(config.max_edit_steps)
+        .map_err(AppError::InvalidInput)?;
+    request.validate_formats().map_err(AppError::InvalidInput)?;
+    request
+        .validate_sampling()
+        .map_err(AppError::InvalidInput)?;
+    request
+        .validate_provider_options(&config.provider_options_deny)
+        .map_err(AppError::InvalidInput)?;
 
     // Task 28: Get provider with default fallback
     let provider_name = request.get_provider();
     tracing::info!(provider = %provider_name, "Using provider");
 
-    // Task 30: Get editor from factory
-    let editor = factory::get_editor(&provider_name, &runtime_config)
-        .map_err(|e| {
+    // Task 29: Get the prompt of every step (default prompt when none), optionally
+    // enhanced, then wrapped with configured prefix/suffix. All steps are resolved
+    // up front so length violations fail before any provider call.
+    let mut final_prompts = Vec::new();
+    for prompt in request.get_steps() {
+        let prompt = if request.enhance_prompt {
+            prompt_enhancer::enhance_prompt(config, &provider_name, prompt).await
+        } else {
+            prompt
+        };
+        let final_prompt = compose_prompt(config, &prompt)?;
+        check_provider_prompt(&provider_name, &final_prompt)?;
+        tracing::info!(
+            step = final_prompts.len(),
+            prompt = %log_redaction::prompt(&final_prompt, config.log_redaction),
+            "Using prompt"
+        );
+        final_prompts.push(final_prompt);
+    }
+
+    // Task 30: Get editor from factory (mock editor in dev mode when unavailable)
+    let (mut editor, dev_fallback) =
+        factory::get_editor_or_dev_fallback(&provider_name, runtime_config).map_err(|e| {
             tracing::error!(error = ?e, provider = %provider_name, "Failed to get editor");
             e
         })?;
 
+    editor.set_sampling(request.sampling());
+    editor.set_provider_options(request.provider_options.clone());
     tracing::info!(provider = %provider_name, "Created editor instance");
 
-    // Task 31: Call edit_image
-    // Note: The ImageEditor trait currently accepts a single Bytes image
-    // For now, we'll use the first image. Multi-image support may be added in future.
-    let first_image = Bytes::from(request.images.into_iter().next().unwrap());
+    if request.region.is_some() && !editor.supports_regions() {
+        return Err(AppError::InvalidInput(format!(
+            "provider '{}' does not support region editing",
+            provider_name
+        )));
+    }
+
+    // Task 31: Call edit_images
+    // Single-image providers edit the first image; the composite editor uses all of them.
+    // Providers mishandle CMYK JPEGs from print workflows and 16-bit images, so
+    // those are converted (or, for 16-bit, rejected per HIGH_BIT_DEPTH) first,
+    // along with any requested rotation.
+    let mut images = Vec::with_capacity(request.images.len());
+    for image in std::mem::take(&mut request.images) {
+        let rotation = request.rotate;
+        let image = run_blocking(move || {
+            let image = image_utils::normalize_color_space(Bytes::from(image))?;
+            image_utils::rotate(image, rotation)
+        })
+        .await?;
+        images.push(fit_bit_depth(config, editor.as_ref(), &provider_name, image).await?);
+    }
+    let first_image = images[0].clone();
+
+    // A side derived from the aspect ratio can be checked against the input's
+    // before paying for the edit; the result is checked again once known
+    if let Ok(dimensions) = input_dimensions(&first_image, image_utils::image_dimensions).await {
+        output_size(&request, dimensions, config.max_output_dimension)?;
+    }
 
     tracing::info!(
+        image_count = images.len(),
         image_size = first_image.len(),
         "Calling AI provider to edit image"
     );
 
-    let result_bytes = editor
-        .edit_image(first_image, &final_prompt)
+    // Chained steps each edit the previous step's result; the metadata reported
+    // is that of the last step
+    let mut result_bytes = Bytes::new();
+    let mut metadata = ProviderMetadata::default();
+    for (step, prompt) in final_prompts.iter().enumerate() {
+        let inputs = if step == 0 {
+            std::mem::take(&mut images)
+        } else {
+            vec![result_bytes]
+        };
+        (result_bytes, metadata) = edit_with_downscale_retry(
+            editor.as_ref(),
+            &provider_name,
+            inputs,
+            prompt,
+            request.region.as_ref(),
+        )
         .await
         .map_err(|e| {
-            tracing::error!(error = ?e, "Failed to edit image");
-            AppError::ProviderError(format!("Failed to edit image: {}", e))
+            tracing::error!(error = ?e, step, "Failed to edit image");
+            AppError::from_provider(e)
         })?;
 
-    tracing::info!(
-        result_size = result_bytes.len(),
-        "Successfully edited image"
-    );
+        tracing::info!(
+            step,
+            result_size = result_bytes.len(),
+            "Successfully edited image"
+        );
+
+        check_result_size(config, &result_bytes)?;
+        check_result_is_image(&result_bytes)?;
+    }
+    check_result_changed(config, &first_image, &result_bytes).await?;
+    let result_bytes = check_result_scale(config, &first_image, result_bytes).await?;
+
+    let output_format = request.output_format.or(config.default_output_format);
+    let (content_type, result_bytes, original_size, phash, quality_score) =
+        if !output_transforms_requested(&request, output_format) {
+            // Fast path: the provider's bytes are returned verbatim, never decoded
+            tracing::debug!("No output transforms requested; returning the provider result as is");
+            (
+                result_content_type(&result_bytes).to_string(),
+                result_bytes,
+                None,
+                None,
+                None,
+            )
+        } else {
+            // Resize to the requested output dimensions and encode in the requested format, if any
+            let jpeg = config.jpeg_options();
+            // With PRESERVE_ALPHA, JPEG is only chosen once the resized result is known to be opaque
+            let resize_format =
+                if config.preserve_alpha && output_format == Some(OutputFormat::Jpeg) {
+                    None
+                } else {
+                    output_format
+                };
+            // Decoding, resizing, hashing and encoding run on the blocking pool
+            let single_format = request.formats.is_empty();
+            let preserve_alpha = config.preserve_alpha;
+            let max_output_dimension = config.max_output_dimension;
+            let transform_request = request.clone();
+            let (result_bytes, phash, quality_score) = run_blocking(move || {
+                let result_bytes = resize_output(
+                    result_bytes,
+                    &transform_request,
+                    max_output_dimension,
+                    resize_format,
+                    output_format,
+                    &jpeg,
+                )?;
+                // Hashed before encoding; the hash is meant to survive re-encoding anyway
+                let phash = transform_request
+                    .phash
+                    .then(|| image_utils::perceptual_hash(&result_bytes))
+                    .transpose()?;
+                let quality_score = transform_request
+                    .quality_score
+                    .then(|| image_utils::sharpness_score(&result_bytes))
+                    .transpose()?;
+                let result_bytes = if single_format {
+                    let output_format =
+                        alpha_preserving_format(preserve_alpha, output_format, &result_bytes)?;
+                    encode_output(result_bytes, output_format, &jpeg)?
+                } else {
+                    result_bytes
+                };
+                Ok((result_bytes, phash, quality_score))
+            })
+            .await?;
+
+            let png_text = if request.embed_metadata {
+                let prompt = final_prompts.last().map(String::as_str).unwrap_or_default();
+                generation_text(&provider_name, prompt, &metadata)
+            } else {
+                Vec::new()
+            };
+
+            // Several requested formats are returned together, as JSON data URLs
+            if single_format {
+                let (content_type, result_bytes, original_size) =
+                    finish_image(result_bytes, request.optimize, &png_text).await?;
+                (
+                    content_type.to_string(),
+                    result_bytes,
+                    original_size,
+                    phash,
+                    quality_score,
+                )
+            } else {
+                let body = encode_formats(
+                    result_bytes,
+                    &request.formats,
+                    request.optimize,
+                    &jpeg,
+                    &png_text,
+                )
+                .await?;
+                (
+                    "application/json".to_string(),
+                    body,
+                    None,
+                    phash,
+                    quality_score,
+                )
+            }
+        };
 
     // Task 32: Stream response with proper headers
-    // Determine content type from image bytes
-    let content_type = image::guess_format(&result_bytes)
+    // Every header is set here from scratch: editors return only bytes, so
+    // nothing a provider or image_url origin sent (Set-Cookie, Server, ...)
+    // reaches the client, and Content-Type describes our own encoding.
+    // The body is fully buffered, so its Content-Length is exact; streamed
+    // variants (`POST /api/edit/stream`) drop it and use chunked encoding.
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, result_bytes.len());
+    if let Some(size) = original_size {
+        builder = builder.header("X-Original-Content-Length", size);
+    }
+    if dev_fallback {
+        builder = builder.header(DEV_MODE_HEADER, "true");
+    }
+    if request.include_metadata {
+        builder = builder.header(PROVIDER_METADATA_HEADER, metadata_header(&metadata)?);
+    }
+    if let Some(phash) = phash {
+        builder = builder.header(IMAGE_PHASH_HEADER, phash);
+    }
+    if let Some(score) = quality_score {
+        builder = builder.header(QUALITY_SCORE_HEADER, format!("{:.2}", score));
+    }
+
+    let response = builder
+        .body(Body::from(result_bytes))
+        .map_err(|e| AppError::InternalServer(format!("Failed to build response: {}", e)))?;
+
+    Ok(response)
+}
+
+/// Words of a provider error that refer to the input's dimensions
+const SIZE_ERROR_SUBJECTS: &[&str] = &[
+    "dimension",
+    "resolution",
+    "pixels",
+    "width",
+    "height",
+    "image size",
+];
+
+/// Words of a provider error that say a limit was exceeded
+const SIZE_ERROR_LIMITS: &[&str] = &["too large", "too big", "exceed", "maximum", "max "];
+
+/// Whether a provider error says the input image is too large
+///
+/// Providers only report this in free text, so the whole error chain is
+/// matched against a subject (dimensions, resolution, ...) and a limit
+/// (too large, exceeds, ...); both must appear.
+fn is_size_error(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error).to_lowercase();
+    SIZE_ERROR_SUBJECTS
+        .iter()
+        .any(|word| message.contains(word))
+        && SIZE_ERROR_LIMITS.iter().any(|word| message.contains(word))
+}
+
+/// Edit `inputs`, limited to `region` when one is set
+async fn call_editor(
+    editor: &dyn ImageEditor,
+    inputs: Vec<Bytes>,
+    prompt: &str,
+    region: Option<&EditRegion>,
+) -> anyhow::Result<(Bytes, ProviderMetadata)> {
+    match region {
+        Some(region) => editor.edit_region(inputs, prompt, region).await,
+        None => editor.edit_images_with_metadata(inputs, prompt).await,
+    }
+}
+
+/// Edit `inputs`, retrying once with downscaled inputs when the provider
+/// rejects them as too large
+///
+/// The retry only happens for size errors (see `is_size_error`) from
+/// providers with a catalog entry, when an input is larger than the entry's
+/// `max_input_dimension`; inputs are then shrunk to that maximum. Any other
+/// failure, and a failed retry, is returned as is. Regions are fractions of
+/// the image size, so they apply to downscaled inputs unchanged.
+async fn edit_with_downscale_retry(
+    editor: &dyn ImageEditor,
+    provider_name: &str,
+    inputs: Vec<Bytes>,
+    prompt: &str,
+    region: Option<&EditRegion>,
+) -> anyhow::Result<(Bytes, ProviderMetadata)> {
+    let error = match call_editor(editor, inputs.clone(), prompt, region).await {
+        Ok(result) => return Ok(result),
+        Err(error) => error,
+    };
+    let Some(model) = catalog::find_model(provider_name) else {
+        return Err(error);
+    };
+    let max_dimension = model.max_input_dimension;
+    let oversized = inputs.iter().any(|image| {
+        image_utils::image_dimensions(image)
+            .is_ok_and(|(width, height)| width.max(height) > max_dimension)
+    });
+    if !oversized || !is_size_error(&error) {
+        return Err(error);
+    }
+
+    tracing::warn!(
+        provider = %provider_name,
+        max_input_dimension = max_dimension,
+        error = %error,
+        "Provider rejected the input size; retrying with downscaled input"
+    );
+    let inputs = run_blocking(move || {
+        inputs
+            .into_iter()
+            .map(|image| image_utils::fit_within(image, max_dimension))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await?;
+    call_editor(editor, inputs, prompt, region).await
+}
+
+/// Whether the request asks for any processing of the provider result
+///
+/// `output_format` is the requested or default output format. Without any of
+/// these transforms the result is returned exactly as the provider sent it.
+fn output_transforms_requested(
+    request: &EditImageRequest,
+    output_format: Option<OutputFormat>,
+) -> bool {
+    output_format.is_some()
+        || !request.formats.is_empty()
+        || request.out_width.is_some()
+        || request.out_height.is_some()
+        || request.optimize
+        || request.phash
+        || request.quality_score
+        || request.embed_metadata
+}
+
+/// Content type of an encoded result, from its format signature
+///
+/// Formats the server doesn't write are reported as PNG.
+fn result_content_type(result: &[u8]) -> &'static str {
+    image::guess_format(result)
         .ok()
         .and_then(|fmt| match fmt {
             image::ImageFormat::Png => Some("image/png"),
@@ -221,42 +1036,1315 @@ pub async fn edit_image(
             _ => None,
         })
         .unwrap_or("image/png")
-        .to_string();
+}
 
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, content_type)
-        .header(header::CONTENT_LENGTH, result_bytes.len())
-        .body(Body::from(result_bytes))
-        .map_err(|e| AppError::InternalServer(format!("Failed to build response: {}", e)))?;
+/// Content type of an encoded result, optionally tagging and shrinking PNG output
+///
+/// `png_text` is written into PNG output as text chunks (see
+/// `image_utils::embed_png_text`); other formats are left without it.
+/// Returns the (possibly optimized) bytes along with their size before
+/// optimization, when it ran.
+async fn finish_image(
+    result: Bytes,
+    optimize: bool,
+    png_text: &[(&str, String)],
+) -> Result<(&'static str, Bytes, Option<usize>), AppError> {
+    let content_type = result_content_type(&result);
 
-    Ok(response)
+    // Embedded first: optimization keeps text chunks
+    let result = if content_type == "image/png" && !png_text.is_empty() {
+        image_utils::embed_png_text(&result, png_text)?
+    } else {
+        result
+    };
+
+    if !(optimize && content_type == "image/png") {
+        return Ok((content_type, result, None));
+    }
+
+    let before = result.len();
+    // oxipng takes hundreds of milliseconds of CPU on large images
+    let optimized = run_blocking(move || image_utils::optimize_png(result)).await?;
+    tracing::debug!(
+        original_size = before,
+        optimized_size = optimized.len(),
+        saved_bytes = before - optimized.len(),
+        "Optimized PNG output"
+    );
+    Ok((content_type, optimized, Some(before)))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Dimensions of an input image, from `read_header` when possible
+///
+/// Some unusual but valid images have headers the decoders can't size on
+/// their own; those are decoded in full on the blocking pool instead, so they
+/// aren't rejected.
+///
+/// # Errors
+///
+/// Returns the header error if the image can't be decoded either.
+async fn input_dimensions(
+    image: &Bytes,
+    read_header: impl Fn(&[u8]) -> Result<(u32, u32), AppError>,
+) -> Result<(u32, u32), AppError> {
+    let header_error = match read_header(image) {
+        Ok(dimensions) => return Ok(dimensions),
+        Err(e) => e,
+    };
+    let image = image.clone();
+    match run_blocking(move || image_utils::decoded_dimensions(&image)).await {
+        Ok(dimensions) => {
+            tracing::debug!(error = %header_error, "Image header unreadable; dimensions taken from a full decode");
+            Ok(dimensions)
+        }
+        Err(_) => Err(header_error),
+    }
+}
 
-    #[test]
-    fn test_edit_image_request_validation() {
-        let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
-        assert!(request.validate().is_ok());
+/// Run CPU-heavy image work on the blocking thread pool, off the async workers
+///
+/// # Errors
+///
+/// Returns the task's own error, or `AppError::InternalServer` if it panicked.
+pub(crate) async fn run_blocking<T, F>(task: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| AppError::InternalServer(format!("Image processing task failed: {}", e)))?
+}
 
-        let empty_request = EditImageRequest::new(vec![]);
-        assert!(empty_request.validate().is_err());
+/// Encode a result in each of `formats`, as a JSON object of format name to data URL
+async fn encode_formats(
+    result: Bytes,
+    formats: &[OutputFormat],
+    optimize: bool,
+    jpeg: &JpegOptions,
+    png_text: &[(&str, String)],
+) -> Result<Bytes, AppError> {
+    let mut images = BTreeMap::new();
+    for format in formats {
+        let (result, format_to_encode, jpeg) = (result.clone(), *format, *jpeg);
+        let encoded =
+            run_blocking(move || encode_output(result, Some(format_to_encode), &jpeg)).await?;
+        let (content_type, encoded, _) = finish_image(encoded, optimize, png_text).await?;
+        images.insert(
+            format.as_str(),
+            image_utils::bytes_to_base64(&encoded, Some(content_type))?,
+        );
     }
 
-    #[test]
-    fn test_default_prompt() {
-        let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
-        let prompt = request.get_prompt();
-        assert!(!prompt.is_empty());
-        assert!(prompt.contains("minimalist modern furniture"));
+    tracing::debug!(formats = ?formats, "Encoded result in several formats");
+    serde_json::to_vec(&images)
+        .map(Bytes::from)
+        .map_err(|e| AppError::InternalServer(format!("Failed to encode formats response: {}", e)))
+}
+
+/// PNG text chunks describing how a result was generated (`embed_metadata`)
+///
+/// Seed and model are only included when the provider reported them.
+fn generation_text(
+    provider: &str,
+    prompt: &str,
+    metadata: &ProviderMetadata,
+) -> Vec<(&'static str, String)> {
+    let mut text = vec![
+        ("prompt", prompt.to_string()),
+        ("provider", provider.to_string()),
+    ];
+    if let Some(seed) = metadata.seed {
+        text.push(("seed", seed.to_string()));
+    }
+    if let Some(model) = &metadata.model_version {
+        text.push(("model", model.clone()));
     }
+    text
+}
 
-    #[test]
-    fn test_default_provider() {
-        let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
-        assert_eq!(request.get_provider(), "google");
+/// Encode provider metadata as a header value
+fn metadata_header(metadata: &ProviderMetadata) -> Result<HeaderValue, AppError> {
+    let json = serde_json::to_string(metadata).map_err(|e| {
+        AppError::InternalServer(format!("Failed to encode provider metadata: {}", e))
+    })?;
+    HeaderValue::from_str(&json)
+        .map_err(|e| AppError::InternalServer(format!("Invalid provider metadata header: {}", e)))
+}
+
+/// Reject provider results larger than `max_result_bytes`
+///
+/// Runs before the result is decoded or a response body is built.
+pub(crate) fn check_result_size(config: &AppConfig, output: &[u8]) -> Result<(), AppError> {
+    if output.len() <= config.max_result_bytes {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        size = output.len(),
+        max_result_bytes = config.max_result_bytes,
+        "Provider result over MAX_RESULT_BYTES"
+    );
+    Err(AppError::ProviderError(format!(
+        "result too large: {} bytes exceeds the limit of {} bytes",
+        output.len(),
+        config.max_result_bytes
+    )))
+}
+
+/// Reject provider results that are not in a recognized image format or were
+/// cut short
+///
+/// Guards against forwarding e.g. an HTML error page, or an image whose
+/// download or stream dropped mid-transfer, as a broken image. Results are
+/// fully buffered and checked here before any status is sent, so a failure is
+/// an error response rather than a truncated `200`. Only the format signature
+/// and end-of-file structure are checked; the pixels are not decoded.
+pub(crate) fn check_result_is_image(output: &[u8]) -> Result<(), AppError> {
+    match image::guess_format(output) {
+        Ok(format) if !image_utils::is_complete_image(output) => {
+            tracing::warn!(size = output.len(), format = ?format, "Provider returned a truncated image");
+            Err(AppError::ProviderError(
+                "provider returned a truncated image".to_string(),
+            ))
+        }
+        Ok(format) => {
+            tracing::debug!(format = ?format, "Provider result is an image");
+            Ok(())
+        }
+        Err(_) => {
+            let head = &output[..output.len().min(16)];
+            tracing::warn!(
+                size = output.len(),
+                head = %String::from_utf8_lossy(head),
+                "Provider returned non-image data"
+            );
+            Err(AppError::ProviderError(
+                "provider returned non-image data".to_string(),
+            ))
+        }
+    }
+}
+
+/// Reject provider results that are nearly identical to the input
+///
+/// Providers sometimes echo the input back instead of editing it (e.g. on a
+/// refusal). With `reject_unchanged_results` enabled, a result whose pixel
+/// difference from the input is at most `unchanged_threshold` is reported as a
+/// provider error rather than a misleading success. Results that can't be
+/// decoded for comparison are let through.
+///
+/// The comparison decodes both images, so it runs on the blocking pool.
+pub(crate) async fn check_result_changed(
+    config: &AppConfig,
+    input: &Bytes,
+    output: &Bytes,
+) -> Result<(), AppError> {
+    if !config.reject_unchanged_results {
+        return Ok(());
+    }
+
+    let (input, output) = (input.clone(), output.clone());
+    let difference =
+        run_blocking(move || Ok(image_utils::image_difference(&input, &output))).await?;
+    match difference {
+        Ok(difference) if difference <= config.unchanged_threshold => {
+            tracing::warn!(
+                difference,
+                threshold = config.unchanged_threshold,
+                "Provider returned an unchanged image"
+            );
+            Err(AppError::ProviderError(
+                "provider returned unchanged image".to_string(),
+            ))
+        }
+        Ok(difference) => {
+            tracing::debug!(difference, "Provider result differs from input");
+            Ok(())
+        }
+        Err(e) => {
+            tracing::debug!(error = %e, "Skipping unchanged-result check");
+            Ok(())
+        }
+    }
+}
+
+/// Detect provider results much smaller than the input, optionally enlarging them
+///
+/// Some models silently downscale their output. With `min_result_scale` set, a
+/// result whose longest side is less than that fraction of the input's is
+/// logged; with `upscale_small_results` it is also enlarged back to the
+/// input's longest side, on the blocking pool. Results that can't be measured
+/// are let through.
+pub(crate) async fn check_result_scale(
+    config: &AppConfig,
+    input: &[u8],
+    output: Bytes,
+) -> Result<Bytes, AppError> {
+    if config.min_result_scale <= 0.0 {
+        return Ok(output);
+    }
+    let (Ok(input_size), Ok(output_size)) = (
+        image_utils::image_dimensions(input),
+        image_utils::image_dimensions(&output),
+    ) else {
+        tracing::debug!("Skipping result scale check");
+        return Ok(output);
+    };
+
+    let input_longest = input_size.0.max(input_size.1);
+    let scale = f64::from(output_size.0.max(output_size.1)) / f64::from(input_longest.max(1));
+    if scale >= config.min_result_scale {
+        return Ok(output);
+    }
+
+    tracing::warn!(
+        input_width = input_size.0,
+        input_height = input_size.1,
+        output_width = output_size.0,
+        output_height = output_size.1,
+        scale,
+        min_scale = config.min_result_scale,
+        upscale = config.upscale_small_results,
+        "Provider returned an unexpectedly small image"
+    );
+    if config.upscale_small_results {
+        run_blocking(move || image_utils::enlarge_to(output, input_longest)).await
+    } else {
+        Ok(output)
+    }
+}
+
+/// Number of leading bytes inspected to recognize an image format
+///
+/// Covers the magic numbers of every format `image::guess_format` detects.
+const IMAGE_SNIFF_BYTES: usize = 32;
+
+/// Read an image part, validating that it looks like a supported image
+///
+/// Returns `None` for empty parts so clients can send blank file inputs.
+pub(crate) async fn read_image_field(field: Field<'_>) -> Result<Option<Vec<u8>>, AppError> {
+    read_image_stream(field).await
+}
+
+/// Collect an image from a stream of chunks, validating the format early
+///
+/// The format is sniffed as soon as the first `IMAGE_SNIFF_BYTES` have
+/// arrived, and the stream is abandoned on an unrecognized header, so invalid
+/// uploads are rejected without buffering the rest of the part.
+async fn read_image_stream<S, E>(chunks: S) -> Result<Option<Vec<u8>>, AppError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::error::Error + 'static,
+{
+    let mut chunks = std::pin::pin!(chunks);
+    let mut data = BytesMut::new();
+    let mut validated = false;
+
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| multipart_read_error(&e, "image data"))?;
+        data.extend_from_slice(&chunk);
+
+        if !validated && data.len() >= IMAGE_SNIFF_BYTES {
+            validate_image_header(&data)?;
+            validated = true;
+        }
+    }
+
+    if data.is_empty() {
+        return Ok(None);
+    }
+
+    // Parts shorter than the sniff window are validated once complete
+    if !validated {
+        validate_image_header(&data)?;
+    }
+
+    tracing::debug!(size = data.len(), "Received image");
+    Ok(Some(data.to_vec()))
+}
+
+/// Check that the leading bytes belong to a supported image format
+pub(crate) fn validate_image_header(data: &[u8]) -> Result<(), AppError> {
+    image::guess_format(data)
+        .map(|_| ())
+        .map_err(|e| AppError::ImageProcessing(format!("Invalid image format: {}", e)))
+}
+
+/// Error message for multipart bodies that end before the closing boundary
+const INCOMPLETE_UPLOAD: &str = "upload was incomplete";
+
+/// Convert a failure reading the multipart body into an `AppError`
+///
+/// A body that ends mid-part, typically because the client disconnected
+/// during the upload, is reported as `upload was incomplete` rather than as a
+/// corrupt image.
+pub(crate) fn multipart_read_error(
+    err: &(dyn std::error::Error + 'static),
+    label: &str,
+) -> AppError {
+    if is_incomplete_upload(err) {
+        tracing::warn!(
+            part = label,
+            "Multipart body ended before the upload was complete"
+        );
+        return AppError::InvalidInput(INCOMPLETE_UPLOAD.to_string());
+    }
+
+    AppError::InvalidInput(format!("Failed to read {}: {}", label, err))
+}
+
+/// Whether the multipart parser reported a truncated stream anywhere in the error chain
+fn is_incomplete_upload(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(multer_err) = err.downcast_ref::<multer::Error>() {
+            return matches!(
+                multer_err,
+                multer::Error::IncompleteStream
+                    | multer::Error::IncompleteFieldData { .. }
+                    | multer::Error::IncompleteHeaders
+            );
+        }
+        current = err.source();
+    }
+    false
+}
+
+/// Read a text part, returning `None` when it is blank
+///
+/// The part must be valid UTF-8; invalid bytes are rejected rather than
+/// replaced, so a prompt never reaches a provider altered.
+pub(crate) async fn read_text_field(
+    field: Field<'_>,
+    label: &str,
+) -> Result<Option<String>, AppError> {
+    let bytes = field
+        .bytes()
+        .await
+        .map_err(|e| multipart_read_error(&e, label))?;
+    let text = String::from_utf8(bytes.into())
+        .map_err(|_| AppError::InvalidInput(format!("{} must be valid UTF-8", label)))?;
+
+    Ok(Some(text).filter(|t| !t.trim().is_empty()))
+}
+
+/// Read a text part and parse it, returning `None` when it is blank
+pub(crate) async fn read_parsed_field<T>(
+    field: Field<'_>,
+    label: &str,
+) -> Result<Option<T>, AppError>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    read_text_field(field, label)
+        .await?
+        .map(|text| {
+            text.trim()
+                .parse()
+                .map_err(|e| AppError::InvalidInput(format!("Invalid value for {}: {}", label, e)))
+        })
+        .transpose()
+}
+
+/// Read a boolean text field with the rules of boolean environment variables
+///
+/// `1`, `true` and `yes` (any case) are true; anything else, including an
+/// empty value, is false.
+pub(crate) async fn read_flag_field(field: Field<'_>, label: &str) -> Result<bool, AppError> {
+    Ok(read_text_field(field, label)
+        .await?
+        .is_some_and(|text| crate::config::is_truthy(&text)))
+}
+
+/// Header selecting the provider, as an alternative to the `provider` field
+const PROVIDER_HEADER: &str = "X-Provider";
+
+/// Provider named by the `X-Provider` header, if present and not blank
+fn provider_header(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(PROVIDER_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|provider| !provider.is_empty())
+        .map(str::to_string)
+}
+
+/// Header carrying several provider keys as one JSON object
+const PROVIDER_KEYS_HEADER: &str = "X-Provider-Keys";
+
+/// Provider keys accepted in the `X-Provider-Keys` header
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderKeys {
+    google: Option<String>,
+    gemini: Option<String>,
+    fal: Option<String>,
+}
+
+/// Parse the `X-Provider-Keys` header, if present
+///
+/// Security: error messages never echo the header value.
+fn parse_provider_keys(headers: &HeaderMap) -> Result<ProviderKeys, AppError> {
+    let Some(value) = headers.get(PROVIDER_KEYS_HEADER) else {
+        return Ok(ProviderKeys::default());
+    };
+
+    let text = value.to_str().map_err(|_| {
+        AppError::InvalidInput(format!(
+            "{} header must be valid UTF-8",
+            PROVIDER_KEYS_HEADER
+        ))
+    })?;
+    let keys: ProviderKeys = serde_json::from_str(text).map_err(|e| {
+        // Only the position is reported; serde messages can quote the input
+        AppError::InvalidInput(format!(
+            "{} header must be a JSON object with string fields google, gemini or fal (invalid at column {})",
+            PROVIDER_KEYS_HEADER,
+            e.column()
+        ))
+    })?;
+
+    for (name, key) in [
+        ("google", &keys.google),
+        ("gemini", &keys.gemini),
+        ("fal", &keys.fal),
+    ] {
+        if key.as_deref().is_some_and(|key| key.trim().is_empty()) {
+            return Err(AppError::InvalidInput(format!(
+                "{} header has an empty '{}' key",
+                PROVIDER_KEYS_HEADER, name
+            )));
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Build the per-request configuration with API key overrides from headers
+///
+/// Supported headers: `X-Provider-Keys` (a JSON object such as
+/// `{"google": "...", "fal": "..."}`), then `X-Google-Api-Key`,
+/// `X-Gemini-Api-Key` and `X-Fal-Key`, which take precedence over the JSON
+/// header. Individual headers that aren't valid UTF-8 are ignored.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` if `X-Provider-Keys` is not a JSON object
+/// of non-empty strings with known provider names.
+pub(crate) fn runtime_config_from_headers(
+    config: &AppConfig,
+    headers: &HeaderMap,
+) -> Result<AppConfig, AppError> {
+    let mut runtime_config = config.clone();
+
+    let keys = parse_provider_keys(headers)?;
+    if keys.google.is_some() || keys.gemini.is_some() || keys.fal.is_some() {
+        tracing::debug!(
+            google = keys.google.is_some(),
+            gemini = keys.gemini.is_some(),
+            fal = keys.fal.is_some(),
+            "Using provider keys from X-Provider-Keys header"
+        );
+    }
+    if let Some(key) = keys.google {
+        runtime_config.google_api_key = Some(key);
+    }
+    if let Some(key) = keys.gemini {
+        runtime_config.gemini_api_key = Some(key);
+    }
+    if let Some(key) = keys.fal {
+        runtime_config.fal_key = Some(key);
+    }
+
+    if let Some(google_key) = headers.get("X-Google-Api-Key") {
+        if let Ok(key_str) = google_key.to_str() {
+            runtime_config.google_api_key = Some(key_str.to_string());
+            tracing::debug!("Using Google API key from header");
+        }
+    }
+
+    if let Some(gemini_key) = headers.get("X-Gemini-Api-Key") {
+        if let Ok(key_str) = gemini_key.to_str() {
+            runtime_config.gemini_api_key = Some(key_str.to_string());
+            tracing::debug!("Using Gemini API key from header");
+        }
+    }
+
+    if let Some(fal_key) = headers.get("X-Fal-Key") {
+        if let Ok(key_str) = fal_key.to_str() {
+            runtime_config.fal_key = Some(key_str.to_string());
+            tracing::debug!("Using Fal API key from header");
+        }
+    }
+
+    Ok(runtime_config)
+}
+
+/// Resize the provider result to the requested output dimensions
+///
+/// Returns the bytes untouched when no output size was requested. Otherwise the
+/// result is decoded, resized with the requested fit mode, and re-encoded in
+/// `output_format`, or else its original format (PNG for formats we don't
+/// re-encode). `ResizeFit::Contain` pads with `jpeg.background` when
+/// `requested_format` is JPEG, and with transparency otherwise.
+fn resize_output(
+    result: Bytes,
+    request: &EditImageRequest,
+    max_dimension: u32,
+    output_format: Option<OutputFormat>,
+    requested_format: Option<OutputFormat>,
+    jpeg: &JpegOptions,
+) -> Result<Bytes, AppError> {
+    if request.out_width.is_none() && request.out_height.is_none() {
+        return Ok(result);
+    }
+
+    let img = image_utils::bytes_to_image(&result)?;
+    let Some((width, height)) = output_size(request, img.dimensions(), max_dimension)? else {
+        return Ok(result);
+    };
+
+    let fit = request.fit.unwrap_or_default();
+    tracing::debug!(width, height, fit = ?fit, "Resizing result to requested dimensions");
+
+    let format = match (output_format, image::guess_format(&result)) {
+        (Some(output_format), _) => output_format.image_format(),
+        (None, Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP))) => format,
+        (None, _) => ImageFormat::Png,
+    };
+    // Letterboxing would only be flattened onto the background for JPEG
+    // anyway; padding with it directly keeps `PRESERVE_ALPHA` from mistaking
+    // the padding for transparency in the result
+    let padding = (requested_format == Some(OutputFormat::Jpeg)).then_some(jpeg.background);
+    let resized = image_utils::resize_image_padded(&img, width, height, fit, padding);
+    image_utils::image_to_bytes_with_options(&resized, format, jpeg)
+}
+
+/// The requested output size for a result of `source` dimensions
+///
+/// Returns `None` when no output size was requested.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` if a side derived from the aspect ratio
+/// exceeds `max_dimension`.
+fn output_size(
+    request: &EditImageRequest,
+    source: (u32, u32),
+    max_dimension: u32,
+) -> Result<Option<(u32, u32)>, AppError> {
+    let Some((width, height)) =
+        image_utils::resolve_output_size(source, request.out_width, request.out_height)
+    else {
+        return Ok(None);
+    };
+
+    if width > max_dimension || height > max_dimension {
+        return Err(AppError::InvalidInput(format!(
+            "Output size {}x{} exceeds the maximum dimension of {}",
+            width, height, max_dimension
+        )));
+    }
+    Ok(Some((width, height)))
+}
+
+/// `output_format`, or PNG instead of JPEG for a result with transparent
+/// pixels when `preserve_alpha` (`PRESERVE_ALPHA`) is enabled
+fn alpha_preserving_format(
+    preserve_alpha: bool,
+    output_format: Option<OutputFormat>,
+    result: &[u8],
+) -> Result<Option<OutputFormat>, AppError> {
+    if !preserve_alpha
+        || output_format != Some(OutputFormat::Jpeg)
+        || image::guess_format(result).ok() == Some(ImageFormat::Jpeg)
+    {
+        return Ok(output_format);
+    }
+
+    if !image_utils::has_transparency(&image_utils::bytes_to_image(result)?) {
+        return Ok(output_format);
+    }
+    tracing::info!("Result has transparency; returning PNG instead of JPEG");
+    Ok(Some(OutputFormat::Png))
+}
+
+/// Re-encode the result in `output_format` unless it is already in that format
+fn encode_output(
+    result: Bytes,
+    output_format: Option<OutputFormat>,
+    jpeg: &JpegOptions,
+) -> Result<Bytes, AppError> {
+    let Some(output_format) = output_format else {
+        return Ok(result);
+    };
+    let target = output_format.image_format();
+    if image::guess_format(&result).ok() == Some(target) {
+        return Ok(result);
+    }
+
+    tracing::debug!(format = ?output_format, "Re-encoding result in the requested output format");
+    let img = image_utils::bytes_to_image(&result)?;
+    image_utils::image_to_bytes_with_options(&img, target, jpeg)
+}
+
+/// Wrap a prompt with the configured prefix and suffix
+///
+/// Parts are trimmed and joined with single spaces. The combined prompt must
+/// not exceed `max_prompt_chars`, so the prefix/suffix count against the limit.
+pub(crate) fn compose_prompt(config: &AppConfig, prompt: &str) -> Result<String, AppError> {
+    let combined = [
+        config.prompt_prefix.as_deref(),
+        Some(prompt),
+        config.prompt_suffix.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(str::trim)
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+
+    let length = combined.chars().count();
+    if length > config.max_prompt_chars {
+        return Err(AppError::InvalidInput(format!(
+            "Prompt is too long: {} characters (maximum {})",
+            length, config.max_prompt_chars
+        )));
+    }
+
+    Ok(combined)
+}
+
+/// Reject a composed prompt longer than the provider's catalog limit
+///
+/// Runs after `compose_prompt`, which applies the global `MAX_PROMPT_CHARS`
+/// limit; providers without a catalog entry only have that one.
+pub(crate) fn check_provider_prompt(provider_name: &str, prompt: &str) -> Result<(), AppError> {
+    let Some(model) = catalog::find_model(provider_name) else {
+        return Ok(());
+    };
+
+    let length = prompt.chars().count();
+    if length > model.max_prompt_chars {
+        return Err(AppError::InvalidInput(format!(
+            "Prompt is too long for {}: {} characters (maximum {})",
+            model.name, length, model.max_prompt_chars
+        )));
+    }
+
+    Ok(())
+}
+
+/// Prepare an input with more than 8 bits per channel for an 8-bit-only editor
+///
+/// Depending on `HIGH_BIT_DEPTH` the input is converted to an 8-bit PNG or
+/// the request rejected. Inputs the editor accepts are returned untouched.
+/// The conversion decodes and re-encodes the image, so it runs on the
+/// blocking thread pool.
+pub(crate) async fn fit_bit_depth(
+    config: &AppConfig,
+    editor: &dyn ImageEditor,
+    provider_name: &str,
+    image: Bytes,
+) -> Result<Bytes, AppError> {
+    if editor.accepts_high_bit_depth() || !image_utils::is_high_bit_depth(&image) {
+        return Ok(image);
+    }
+
+    match config.high_bit_depth {
+        HighBitDepth::Convert => run_blocking(move || image_utils::to_8_bit(image)).await,
+        HighBitDepth::Reject => Err(AppError::InvalidInput(format!(
+            "image has more than 8 bits per channel; provider '{}' only accepts 8-bit images",
+            provider_name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::image_utils::ResizeFit;
+
+    #[test]
+    fn test_edit_image_request_validation() {
+        let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        assert!(request.validate().is_ok());
+
+        let empty_request = EditImageRequest::new(vec![]);
+        assert!(empty_request.validate().is_err());
+    }
+
+    #[test]
+    fn test_default_prompt() {
+        let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        let prompt = request.get_prompt();
+        assert!(!prompt.is_empty());
+        assert!(prompt.contains("minimalist modern furniture"));
+    }
+
+    #[test]
+    fn test_default_provider() {
+        let request = EditImageRequest::new(vec![vec![1, 2, 3]]);
+        assert_eq!(request.get_provider(), "google");
+    }
+
+    /// Editor rejecting inputs whose longest side is over 2048 pixels, like
+    /// Fal.ai does for `fal-ai/qwen-image-edit`
+    #[derive(Default)]
+    struct SizeLimitedEditor {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl ImageEditor for SizeLimitedEditor {
+        async fn edit_image(&self, image_bytes: Bytes, _prompt: &str) -> anyhow::Result<Bytes> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let (width, height) = image_utils::image_dimensions(&image_bytes)?;
+            if width.max(height) > 2048 {
+                anyhow::bail!(
+                    "Fal.ai API returned error 422: image dimensions exceed the maximum of 2048"
+                );
+            }
+            Ok(image_bytes)
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Bytes {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        image_utils::image_to_bytes(&img, ImageFormat::Png).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_size_error_retried_with_downscaled_input() {
+        let editor = SizeLimitedEditor::default();
+
+        let (result, _) = edit_with_downscale_retry(
+            &editor,
+            "fal:fal-ai/qwen-image-edit",
+            vec![png(4096, 1024)],
+            "prompt",
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(image_utils::image_dimensions(&result).unwrap(), (2048, 512));
+        assert_eq!(editor.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    fn png_16_bit() -> Bytes {
+        let img = image::DynamicImage::ImageRgb16(image::ImageBuffer::new(4, 4));
+        image_utils::image_to_bytes(&img, ImageFormat::Png).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_high_bit_depth_converted_or_rejected_per_config() {
+        let editor = SizeLimitedEditor::default();
+        let input = png_16_bit();
+
+        let converted = fit_bit_depth(&AppConfig::default(), &editor, "google", input.clone())
+            .await
+            .unwrap();
+        assert!(!image_utils::is_high_bit_depth(&converted));
+
+        let config = AppConfig {
+            high_bit_depth: HighBitDepth::Reject,
+            ..AppConfig::default()
+        };
+        let err = fit_bit_depth(&config, &editor, "google", input.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+        assert!(
+            err.to_string().contains("only accepts 8-bit images"),
+            "{}",
+            err
+        );
+
+        // Editors that take any bit depth get the input as is
+        let mock = crate::services::mock_editor::MockEditor::new();
+        assert_eq!(
+            fit_bit_depth(&config, &mock, "mock", input.clone())
+                .await
+                .unwrap(),
+            input
+        );
+    }
+
+    #[tokio::test]
+    async fn test_size_error_not_retried_without_catalog_limit() {
+        let editor = SizeLimitedEditor::default();
+
+        let err = edit_with_downscale_retry(
+            &editor,
+            "fal:fal-ai/unknown",
+            vec![png(4096, 16)],
+            "prompt",
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(is_size_error(&err));
+        assert_eq!(editor.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_is_size_error() {
+        assert!(is_size_error(&anyhow::anyhow!(
+            "Image dimensions too large"
+        )));
+        assert!(is_size_error(&anyhow::anyhow!(
+            "Input resolution exceeds 4096x4096"
+        )));
+        assert!(!is_size_error(&anyhow::anyhow!(
+            "Fal.ai API returned error 500: internal error"
+        )));
+        assert!(!is_size_error(&anyhow::anyhow!(
+            "Prompt exceeds the maximum length"
+        )));
+    }
+
+    #[test]
+    fn test_named_images_go_first_in_role_order() {
+        let mut named = NamedImages::default();
+        NamedImages::fill(&mut named.reference, "reference_image", vec![2]).unwrap();
+        NamedImages::fill(&mut named.base, "base_image", vec![1]).unwrap();
+
+        let err = NamedImages::fill(&mut named.base, "base_image", vec![9]).unwrap_err();
+        assert!(err.to_string().contains("base_image may only be sent once"));
+
+        assert_eq!(
+            named.ahead_of(vec![vec![3], vec![4]]),
+            vec![vec![1], vec![2], vec![3], vec![4]]
+        );
+        assert_eq!(
+            NamedImages::default().ahead_of(vec![vec![3]]),
+            vec![vec![3]]
+        );
+    }
+
+    #[test]
+    fn test_compose_prompt_without_prefix_or_suffix() {
+        let config = AppConfig::default();
+        assert_eq!(compose_prompt(&config, "Add a sofa").unwrap(), "Add a sofa");
+    }
+
+    #[test]
+    fn test_compose_prompt_applies_prefix_and_suffix() {
+        let config = AppConfig {
+            prompt_prefix: Some("Keep it family friendly.".to_string()),
+            prompt_suffix: Some("  No text or logos. ".to_string()),
+            ..AppConfig::default()
+        };
+        assert_eq!(
+            compose_prompt(&config, "Add a sofa").unwrap(),
+            "Keep it family friendly. Add a sofa No text or logos."
+        );
+    }
+
+    #[test]
+    fn test_compose_prompt_length_includes_prefix_and_suffix() {
+        let config = AppConfig {
+            prompt_prefix: Some("12345".to_string()),
+            prompt_suffix: Some("67890".to_string()),
+            max_prompt_chars: 15,
+            ..AppConfig::default()
+        };
+        // "12345 abc 67890" is exactly 15 characters
+        assert!(compose_prompt(&config, "abc").is_ok());
+
+        let err = compose_prompt(&config, "abcd").unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+        assert!(err.to_string().contains("too long"));
+    }
+
+    /// Encode a solid-color PNG of the given size
+    fn make_png(width: u32, height: u32) -> Bytes {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        image_utils::image_to_bytes(&img, ImageFormat::Png).unwrap()
+    }
+
+    #[test]
+    fn test_resize_output_passthrough_without_dimensions() {
+        let png = make_png(8, 4);
+        let request = EditImageRequest::new(vec![]);
+        assert_eq!(
+            resize_output(
+                png.clone(),
+                &request,
+                4096,
+                None,
+                None,
+                &JpegOptions::default()
+            )
+            .unwrap(),
+            png
+        );
+    }
+
+    #[test]
+    fn test_resize_output_contain_and_cover_match_requested_size() {
+        for fit in [ResizeFit::Contain, ResizeFit::Cover] {
+            let mut request = EditImageRequest::new(vec![]);
+            request.out_width = Some(16);
+            request.out_height = Some(16);
+            request.fit = Some(fit);
+
+            let resized = resize_output(
+                make_png(40, 20),
+                &request,
+                4096,
+                None,
+                None,
+                &JpegOptions::default(),
+            )
+            .unwrap();
+            let img = image_utils::bytes_to_image(&resized).unwrap();
+            assert_eq!(img.dimensions(), (16, 16), "fit mode {:?}", fit);
+            assert_eq!(image::guess_format(&resized).unwrap(), ImageFormat::Png);
+        }
+    }
+
+    #[test]
+    fn test_resize_output_derived_dimension_over_max() {
+        let mut request = EditImageRequest::new(vec![]);
+        request.out_height = Some(100);
+        // 10:1 aspect ratio derives a width of 1000, above the 500 limit
+        let err = resize_output(
+            make_png(100, 10),
+            &request,
+            500,
+            None,
+            None,
+            &JpegOptions::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_resize_output_uses_output_format() {
+        let mut request = EditImageRequest::new(vec![]);
+        request.out_width = Some(10);
+
+        let resized = resize_output(
+            make_png(40, 20),
+            &request,
+            4096,
+            Some(OutputFormat::Jpeg),
+            Some(OutputFormat::Jpeg),
+            &JpegOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(image::guess_format(&resized).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn test_resize_output_contain_pads_jpeg_with_background() {
+        let mut request = EditImageRequest::new(vec![]);
+        request.out_width = Some(16);
+        request.out_height = Some(16);
+        request.fit = Some(ResizeFit::Contain);
+        let jpeg = JpegOptions {
+            background: [0, 0, 255],
+            ..JpegOptions::default()
+        };
+
+        // Resized without an encoding, as with PRESERVE_ALPHA: padding must be opaque
+        let resized = resize_output(
+            make_png(40, 20),
+            &request,
+            4096,
+            None,
+            Some(OutputFormat::Jpeg),
+            &jpeg,
+        )
+        .unwrap();
+        let img = image_utils::bytes_to_image(&resized).unwrap();
+        assert!(!image_utils::has_transparency(&img));
+        assert_eq!(img.to_rgb8().get_pixel(8, 0).0, [0, 0, 255]);
+
+        // Other formats keep transparent padding
+        let resized = resize_output(
+            make_png(40, 20),
+            &request,
+            4096,
+            None,
+            Some(OutputFormat::Png),
+            &jpeg,
+        )
+        .unwrap();
+        assert!(image_utils::has_transparency(
+            &image_utils::bytes_to_image(&resized).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_encode_output() {
+        let png = make_png(8, 4);
+        assert_eq!(
+            encode_output(png.clone(), None, &JpegOptions::default()).unwrap(),
+            png
+        );
+        assert_eq!(
+            encode_output(
+                png.clone(),
+                Some(OutputFormat::Png),
+                &JpegOptions::default()
+            )
+            .unwrap(),
+            png
+        );
+
+        let webp = encode_output(png, Some(OutputFormat::Webp), &JpegOptions::default()).unwrap();
+        assert_eq!(image::guess_format(&webp).unwrap(), ImageFormat::WebP);
+    }
+
+    #[tokio::test]
+    async fn test_input_dimensions_fall_back_to_decode() {
+        // Stands in for a header the decoder can't size although the image decodes
+        fn unreadable_header(_: &[u8]) -> Result<(u32, u32), AppError> {
+            Err(AppError::ImageProcessing(
+                "Failed to read image dimensions: unsupported header".to_string(),
+            ))
+        }
+        let png =
+            image_utils::image_to_bytes(&image::DynamicImage::new_rgb8(12, 7), ImageFormat::Png)
+                .unwrap();
+
+        assert_eq!(
+            input_dimensions(&png, image_utils::image_dimensions)
+                .await
+                .unwrap(),
+            (12, 7)
+        );
+        assert_eq!(
+            input_dimensions(&png, unreadable_header).await.unwrap(),
+            (12, 7)
+        );
+        // Undecodable data still fails, with the header error
+        let err = input_dimensions(&Bytes::from_static(b"not an image"), unreadable_header)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unsupported header"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_read_image_stream_collects_chunks() {
+        let png = make_png(4, 4);
+        let chunks = png
+            .chunks(10)
+            .map(|chunk| Ok::<_, std::io::Error>(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+
+        let data = read_image_stream(futures::stream::iter(chunks))
+            .await
+            .unwrap();
+        assert_eq!(data.as_deref(), Some(&png[..]));
+    }
+
+    #[tokio::test]
+    async fn test_read_image_stream_rejects_after_header_only() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // A large upload whose first chunk is not an image header
+        let pulled = AtomicUsize::new(0);
+        let chunks = futures::stream::iter(
+            (0..1000).map(|_| Ok::<_, std::io::Error>(Bytes::from(vec![b'x'; 64 * 1024]))),
+        )
+        .inspect(|_| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let err = read_image_stream(chunks).await.unwrap_err();
+        assert!(matches!(err, AppError::ImageProcessing(_)));
+        assert_eq!(pulled.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_image_stream_short_and_empty_parts() {
+        let empty = futures::stream::iter(Vec::<Result<Bytes, std::io::Error>>::new());
+        assert!(read_image_stream(empty).await.unwrap().is_none());
+
+        // Shorter than the sniff window but still a recognizable header
+        let short = futures::stream::iter(vec![Ok::<_, std::io::Error>(Bytes::from_static(
+            b"\x89PNG\r\n\x1a\n",
+        ))]);
+        assert!(read_image_stream(short).await.unwrap().is_some());
+
+        let garbage =
+            futures::stream::iter(vec![Ok::<_, std::io::Error>(Bytes::from_static(b"nope"))]);
+        assert!(read_image_stream(garbage).await.is_err());
+    }
+
+    #[test]
+    fn test_check_result_is_image() {
+        assert!(check_result_is_image(&make_png(2, 2)).is_ok());
+
+        let err =
+            check_result_is_image(b"<!DOCTYPE html><html>502 Bad Gateway</html>").unwrap_err();
+        assert!(
+            matches!(&err, AppError::ProviderError(m) if m == "provider returned non-image data")
+        );
+        assert!(check_result_is_image(b"").is_err());
+
+        let png = make_png(2, 2);
+        let err = check_result_is_image(&png[..png.len() - 4]).unwrap_err();
+        assert!(
+            matches!(&err, AppError::ProviderError(m) if m == "provider returned a truncated image")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_result_scale() {
+        let input = make_png(100, 50);
+        let small = make_png(40, 20);
+        let mut config = AppConfig::default();
+
+        // Disabled by default
+        assert_eq!(
+            check_result_scale(&config, &input, small.clone())
+                .await
+                .unwrap(),
+            small
+        );
+
+        config.min_result_scale = 0.5;
+        let fine = make_png(60, 60);
+        assert_eq!(
+            check_result_scale(&config, &input, fine.clone())
+                .await
+                .unwrap(),
+            fine
+        );
+        // Detected but returned as is without upscaling
+        assert_eq!(
+            check_result_scale(&config, &input, small.clone())
+                .await
+                .unwrap(),
+            small
+        );
+
+        config.upscale_small_results = true;
+        let enlarged = check_result_scale(&config, &input, small).await.unwrap();
+        assert_eq!(image_utils::image_dimensions(&enlarged).unwrap(), (100, 50));
+        assert_eq!(
+            check_result_scale(&config, &input, fine.clone())
+                .await
+                .unwrap(),
+            fine
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_result_changed() {
+        let config = AppConfig {
+            reject_unchanged_results: true,
+            ..AppConfig::default()
+        };
+        let input = make_png(8, 8);
+        let changed = image_utils::image_to_bytes(
+            &image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+                8,
+                8,
+                image::Rgb([200, 10, 10]),
+            )),
+            ImageFormat::Png,
+        )
+        .unwrap();
+
+        // Near-identical output (re-encoded as JPEG) is rejected
+        let reencoded = image_utils::image_to_bytes(
+            &image_utils::bytes_to_image(&input).unwrap(),
+            ImageFormat::Jpeg,
+        )
+        .unwrap();
+        let err = check_result_changed(&config, &input, &reencoded)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::ProviderError(_)));
+        assert!(err
+            .to_string()
+            .contains("provider returned unchanged image"));
+
+        assert!(check_result_changed(&config, &input, &changed)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_result_changed_disabled_by_default() {
+        let input = make_png(8, 8);
+        assert!(check_result_changed(&AppConfig::default(), &input, &input)
+            .await
+            .is_ok());
+    }
+
+    fn provider_keys_headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(PROVIDER_KEYS_HEADER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_provider_keys_header_populates_config() {
+        let headers =
+            provider_keys_headers(r#"{"google": "g-key", "gemini": "m-key", "fal": "f-key"}"#);
+
+        let runtime = runtime_config_from_headers(&AppConfig::default(), &headers).unwrap();
+
+        assert_eq!(runtime.google_api_key.as_deref(), Some("g-key"));
+        assert_eq!(runtime.gemini_api_key.as_deref(), Some("m-key"));
+        assert_eq!(runtime.fal_key.as_deref(), Some("f-key"));
+    }
+
+    #[test]
+    fn test_provider_keys_header_keeps_unset_keys() {
+        let config = AppConfig {
+            google_api_key: Some("env-google".to_string()),
+            ..AppConfig::default()
+        };
+        let headers = provider_keys_headers(r#"{"fal": "f-key"}"#);
+
+        let runtime = runtime_config_from_headers(&config, &headers).unwrap();
+
+        assert_eq!(runtime.google_api_key.as_deref(), Some("env-google"));
+        assert_eq!(runtime.fal_key.as_deref(), Some("f-key"));
+    }
+
+    #[test]
+    fn test_individual_key_header_overrides_provider_keys() {
+        let mut headers = provider_keys_headers(r#"{"fal": "json-key"}"#);
+        headers.insert("X-Fal-Key", "header-key".parse().unwrap());
+
+        let runtime = runtime_config_from_headers(&AppConfig::default(), &headers).unwrap();
+
+        assert_eq!(runtime.fal_key.as_deref(), Some("header-key"));
+    }
+
+    #[test]
+    fn test_malformed_provider_keys_rejected() {
+        for value in [
+            "not json",
+            r#"["g-key"]"#,
+            r#"{"google": 42}"#,
+            r#"{"openai": "o-key"}"#,
+            r#"{"fal": "  "}"#,
+            r#""secret-key""#,
+        ] {
+            let err =
+                runtime_config_from_headers(&AppConfig::default(), &provider_keys_headers(value))
+                    .unwrap_err();
+            assert!(
+                matches!(err, AppError::InvalidInput(_)),
+                "accepted {}",
+                value
+            );
+            assert!(!err.to_string().contains("secret-key"));
+        }
     }
 }
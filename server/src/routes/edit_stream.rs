@@ -0,0 +1,146 @@
+//! Image editing with the result streamed as Server-Sent Events
+//!
+//! `POST /api/edit/stream` runs the same edit as `POST /api/edit` but returns
+//! the result as base64 text in SSE events, for clients that can't consume a
+//! binary body (some proxies, webviews, `EventSource`-based frontends). Each
+//! chunk is independently valid base64, so a client can append chunks to a
+//! `data:` URL as they arrive and decode either piecewise or at the end.
+
+use axum::{
+    body::to_bytes,
+    extract::{Multipart, State},
+    http::{header, HeaderMap},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bytes::Bytes;
+use serde_json::json;
+use std::convert::Infallible;
+use std::time::Instant;
+
+use crate::audit::EditAudit;
+use crate::error::AppError;
+use crate::models::tenant::TenantId;
+use crate::routes::edit::{prepare_edit, record_edit_outcome, run_edit};
+use crate::state::AppState;
+
+/// Result bytes per `chunk` event; a multiple of 3 so chunks encode without padding
+pub const SSE_CHUNK_BYTES: usize = 48 * 1024;
+
+/// Streamed image editing handler
+///
+/// # Endpoint
+///
+/// `POST /api/edit/stream`
+///
+/// # Request Format
+///
+/// The multipart form and headers of `POST /api/edit`.
+///
+/// # Response
+///
+/// `text/event-stream` with these events, in order:
+/// - `start`: `{"content_type": "image/png", "size": 12345}`
+/// - `chunk`: base64 of the next `SSE_CHUNK_BYTES` (48 KiB) of the result,
+///   as plain text; repeated
+/// - `done`: `{"chunks": 3}`
+///
+/// Response headers of `POST /api/edit` that describe the result
+/// (`X-Dev-Mode`, `X-Provider-Metadata`, ...) are kept, except `Content-Length`:
+/// the stream is sent with chunked transfer encoding. Results are not stored
+/// for `GET /api/results/{id}`, and `Prefer: respond-async` is not honored.
+///
+/// # Errors
+///
+/// Those of `POST /api/edit`, returned as a JSON error response before any
+/// event is sent.
+///
+/// # Example
+///
+/// ```bash
+/// curl -N -X POST http://localhost:8000/api/edit/stream \
+///   -F "images=@room.jpg" -F "prompt=Add modern furniture"
+/// ```
+pub async fn edit_image_stream(
+    State(state): State<AppState>,
+    tenant: TenantId,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let AppState {
+        config,
+        metrics,
+        uploads,
+        ..
+    } = state;
+    tracing::info!("Received streamed image edit request");
+
+    let started = Instant::now();
+
+    let response = match prepare_edit(&config, &metrics, &uploads, &headers, multipart).await {
+        Ok(prepared) => run_edit(&config, &metrics, &tenant, prepared, started).await?,
+        Err(e) => {
+            let result = Err(e);
+            record_edit_outcome(
+                &config,
+                &metrics,
+                &tenant,
+                "unknown",
+                &EditAudit::unparsed(),
+                &result,
+                started,
+            );
+            return result;
+        }
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let content_type = parts
+        .headers
+        .remove(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok().map(str::to_string))
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    // Describes the buffered image, not the event stream, which goes out with
+    // chunked transfer encoding and no length
+    parts.headers.remove(header::CONTENT_LENGTH);
+    // The edit result is already in memory
+    let result = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| AppError::InternalServer(format!("Failed to read edit result: {}", e)))?;
+
+    let events = result_events(&content_type, &result)
+        .into_iter()
+        .map(Ok::<_, Infallible>);
+    let mut response = Sse::new(futures::stream::iter(events)).into_response();
+    for (name, value) in parts.headers.iter() {
+        response.headers_mut().insert(name, value.clone());
+    }
+
+    Ok(response)
+}
+
+/// SSE events carrying `result`: `start`, one `chunk` per `SSE_CHUNK_BYTES`, `done`
+fn result_events(content_type: &str, result: &Bytes) -> Vec<Event> {
+    let chunks: Vec<&[u8]> = result.chunks(SSE_CHUNK_BYTES).collect();
+
+    let mut events = Vec::with_capacity(chunks.len() + 2);
+    events.push(
+        Event::default()
+            .event("start")
+            .data(json!({ "content_type": content_type, "size": result.len() }).to_string()),
+    );
+    events.extend(
+        chunks
+            .iter()
+            .map(|chunk| Event::default().event("chunk").data(STANDARD.encode(chunk))),
+    );
+    events.push(
+        Event::default()
+            .event("done")
+            .data(json!({ "chunks": chunks.len() }).to_string()),
+    );
+    events
+}
@@ -0,0 +1,458 @@
+//! Asynchronous edit job endpoints
+//!
+//! Available when the `async_jobs` feature flag is enabled:
+//! - `POST /api/jobs` accepts the same form and headers as `/api/edit`,
+//!   starts the edit in the background and answers `202 Accepted` at once
+//! - `GET /api/jobs/{id}` reports the job status
+//! - `GET /api/jobs/{id}/preview` returns a low-res placeholder while the job
+//!   runs and the edited image once it has finished
+//! - `DELETE /api/jobs/{id}` cancels a running job
+//!
+//! `POST /api/edit` with `Prefer: respond-async` (RFC 7240) takes the same
+//! path as `POST /api/jobs` and confirms it with `Preference-Applied`.
+//!
+//! Submissions carrying an `Idempotency-Key` header are safe to retry: while
+//! a job submitted with the same key (by the same tenant) is running, the
+//! existing job is returned instead of starting another.
+//!
+//! Jobs are kept in the in-memory `JobStore`; see `crate::jobs`.
+
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::Bytes;
+use image::{GenericImageView, ImageFormat};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::audit::EditAudit;
+use crate::config::{AppConfig, REQUEST_TIMEOUT_SECS};
+use crate::error::{AppError, ErrorResponse};
+use crate::jobs::{Job, JobFailure, JobOutput, JobState, JobStatus, JobStore};
+use crate::metrics::Metrics;
+use crate::models::response::JobResponse;
+use crate::models::tenant::TenantId;
+use crate::routes::edit::{prepare_edit, record_edit_outcome, run_blocking, run_edit};
+use crate::uploads::UploadStore;
+use crate::utils::image_utils::{self, ResizeFit};
+
+/// Longest side in pixels of the placeholder served while a job runs
+pub const PREVIEW_MAX_DIMENSION: u32 = 256;
+
+/// Header reporting the job status on preview responses
+pub const JOB_STATUS_HEADER: &str = "X-Job-Status";
+
+/// Preference asking for a 202 and a job instead of waiting for the result
+const RESPOND_ASYNC: &str = "respond-async";
+
+/// Header confirming which preferences of a `Prefer` header were honored
+pub const PREFERENCE_APPLIED_HEADER: &str = "Preference-Applied";
+
+/// Header making a submission safe to retry
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Maximum idempotency key length in characters
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Time a background edit may take before the job fails, matching the
+/// request timeout of `/api/edit`
+const JOB_TIMEOUT: Duration = Duration::from_secs(REQUEST_TIMEOUT_SECS);
+
+/// Submit an edit job
+///
+/// # Endpoint
+///
+/// `POST /api/jobs`
+///
+/// The request is parsed and validated before responding, so malformed forms
+/// are still rejected synchronously with the usual error codes.
+///
+/// # Response
+///
+/// `202 Accepted` with a `Location` header pointing at the job and a
+/// [`JobResponse`] body with status `running`.
+pub async fn submit_job(
+    State(config): State<Arc<AppConfig>>,
+    State(metrics): State<Arc<Metrics>>,
+    State(jobs): State<Arc<JobStore>>,
+    State(uploads): State<Arc<UploadStore>>,
+    tenant: TenantId,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    start_job(config, metrics, jobs, &uploads, tenant, &headers, multipart).await
+}
+
+/// Whether a `Prefer` header asks for `respond-async`
+///
+/// Preferences are matched case-insensitively, across repeated headers and
+/// comma-separated lists; parameters after `;` are ignored.
+pub(crate) fn prefers_respond_async(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("prefer")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|preference| preference.split(';').next())
+        .any(|token| token.trim().eq_ignore_ascii_case(RESPOND_ASYNC))
+}
+
+/// Idempotency key of a submission, scoped to its tenant
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` for empty, overlong or non-ASCII keys.
+fn idempotency_key(headers: &HeaderMap, tenant: &TenantId) -> Result<Option<String>, AppError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    let key = value.to_str().map(str::trim).unwrap_or_default();
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "{} must be between 1 and {} visible ASCII characters",
+            IDEMPOTENCY_KEY_HEADER, MAX_IDEMPOTENCY_KEY_LEN
+        )));
+    }
+
+    // Tenant ids never contain ':', so scoped keys cannot collide
+    Ok(Some(format!("{}:{}", tenant, key)))
+}
+
+/// Parse an edit request, start it in the background and answer `202 Accepted`
+///
+/// A submission whose idempotency key belongs to a running job is answered
+/// with that job without reading the form. Otherwise the form is only read
+/// once a job slot is reserved; with `MAX_RUNNING_JOBS` jobs running the
+/// submission gets a 503.
+pub(crate) async fn start_job(
+    config: Arc<AppConfig>,
+    metrics: Arc<Metrics>,
+    jobs: Arc<JobStore>,
+    uploads: &UploadStore,
+    tenant: TenantId,
+    headers: &HeaderMap,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let started = Instant::now();
+
+    let key = idempotency_key(headers, &tenant)?;
+    if let Some(job_id) = key.as_deref().and_then(|key| jobs.running_for_key(key)) {
+        tracing::info!(job_id = %job_id, "Idempotency key matches a running job");
+        return accepted(job_id);
+    }
+    // Reserved before the form is read, and held by the task until the job ends
+    let permit = jobs.reserve()?;
+
+    let prepared = match prepare_edit(&config, &metrics, uploads, headers, multipart).await {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let result = Err(e);
+            record_edit_outcome(
+                &config,
+                &metrics,
+                &tenant,
+                "unknown",
+                &EditAudit::unparsed(),
+                &result,
+                started,
+            );
+            return result;
+        }
+    };
+
+    let input = Bytes::copy_from_slice(&prepared.request.images[0]);
+    let (job_id, created) = jobs.create_or_join(input, key.as_deref());
+    if !created {
+        // A retry with the same key won the race while this form was parsed
+        tracing::info!(job_id = %job_id, "Idempotency key matches a running job");
+        return accepted(job_id);
+    }
+    tracing::info!(job_id = %job_id, provider = %prepared.provider_name, "Started edit job");
+
+    let task_id = job_id.clone();
+    let task_jobs = Arc::clone(&jobs);
+    let task = tokio::spawn(async move {
+        let _permit = permit;
+        let result = tokio::time::timeout(
+            JOB_TIMEOUT,
+            run_edit(&config, &metrics, &tenant, prepared, started),
+        )
+        .await
+        .unwrap_or_else(|_| Err(AppError::InternalServer("edit job timed out".to_string())));
+
+        let output = match result {
+            Ok(response) => job_output(response, config.max_result_bytes).await,
+            Err(e) => Err(e),
+        };
+        let state = match output {
+            Ok(output) => JobState::Succeeded(output),
+            Err(e) => {
+                tracing::warn!(job_id = %task_id, error = %e, "Edit job failed");
+                JobState::Failed(JobFailure::from(&e))
+            }
+        };
+        task_jobs.finish(&task_id, state);
+    });
+    jobs.attach(&job_id, task.abort_handle());
+
+    accepted(job_id)
+}
+
+/// `202 Accepted` response pointing at a running job
+fn accepted(job_id: String) -> Result<Response, AppError> {
+    let location = HeaderValue::from_str(&format!("/api/jobs/{}", job_id))
+        .map_err(|e| AppError::InternalServer(format!("Invalid job location: {}", e)))?;
+    let body = JobResponse {
+        job_id,
+        status: JobStatus::Running,
+        error: None,
+        error_type: None,
+    };
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(body),
+    )
+        .into_response())
+}
+
+/// Collect the body and content type of a finished edit response, reading at
+/// most `max_bytes` (normally `max_result_bytes`)
+async fn job_output(response: Response, max_bytes: usize) -> Result<JobOutput, AppError> {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("image/png")
+        .to_string();
+    // The body was built in-process, so reading it only fails over the limit
+    let body = axum::body::to_bytes(response.into_body(), max_bytes)
+        .await
+        .map_err(|e| {
+            AppError::ProviderError(format!(
+                "result too large: exceeds the limit of {} bytes ({})",
+                max_bytes, e
+            ))
+        })?;
+
+    Ok(JobOutput { content_type, body })
+}
+
+/// Look up a job, or fail with a 404
+fn find_job(jobs: &JobStore, id: &str) -> Result<Job, AppError> {
+    jobs.get(id)
+        .ok_or_else(|| AppError::NotFound(format!("job '{}'", id)))
+}
+
+/// Job status handler
+///
+/// # Endpoint
+///
+/// `GET /api/jobs/{id}`
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` (404) for unknown job ids.
+pub async fn job_status(
+    State(jobs): State<Arc<JobStore>>,
+    Path(id): Path<String>,
+) -> Result<Json<JobResponse>, AppError> {
+    let job = find_job(&jobs, &id)?;
+    let (error, error_type) = match &job.state {
+        JobState::Failed(failure) => (
+            Some(failure.error.clone()),
+            Some(failure.error_type.to_string()),
+        ),
+        _ => (None, None),
+    };
+
+    Ok(Json(JobResponse {
+        job_id: id,
+        status: job.state.status(),
+        error,
+        error_type,
+    }))
+}
+
+/// Job preview handler
+///
+/// # Endpoint
+///
+/// `GET /api/jobs/{id}/preview`
+///
+/// # Response
+///
+/// - Running: the input image scaled down to at most `PREVIEW_MAX_DIMENSION`
+///   pixels on its longest side, as PNG
+/// - Succeeded: the edited image, exactly as `/api/edit` would have returned it
+/// - Failed: the job's error, with the status code it failed with
+/// - Cancelled: a `409 Conflict` error
+///
+/// Image responses carry an `X-Job-Status` header so clients know when to stop
+/// polling.
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` (404) for unknown job ids.
+pub async fn job_preview(
+    State(jobs): State<Arc<JobStore>>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let job = find_job(&jobs, &id)?;
+    let status = job.state.status().as_str();
+
+    let (content_type, body) = match job.state {
+        JobState::Running => {
+            let input = job.input;
+            (
+                "image/png".to_string(),
+                run_blocking(move || placeholder(&input)).await?,
+            )
+        }
+        JobState::Succeeded(output) => (output.content_type, output.body),
+        JobState::Failed(failure) => {
+            let body = ErrorResponse {
+                error: failure.error,
+                error_type: Some(failure.error_type.to_string()),
+            };
+            return Ok((failure.status, [(JOB_STATUS_HEADER, status)], Json(body)).into_response());
+        }
+        JobState::Cancelled => {
+            let error = AppError::Conflict(format!("job '{}' was cancelled", id));
+            return Ok(([(JOB_STATUS_HEADER, status)], error).into_response());
+        }
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, body.len())
+        .header(JOB_STATUS_HEADER, status)
+        .body(Body::from(body))
+        .map_err(|e| AppError::InternalServer(format!("Failed to build response: {}", e)))
+}
+
+/// Cancel job handler
+///
+/// # Endpoint
+///
+/// `DELETE /api/jobs/{id}`
+///
+/// Aborts the edit of a running job, which then reports status `cancelled`.
+/// A job that has not started yet is removed, so later lookups get a 404.
+/// Cancelling does not refund provider calls already in flight.
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` (404) for unknown job ids and
+/// `AppError::Conflict` (409) for jobs that have already ended.
+pub async fn cancel_job(
+    State(jobs): State<Arc<JobStore>>,
+    Path(id): Path<String>,
+) -> Result<Json<JobResponse>, AppError> {
+    jobs.cancel(&id)?;
+    tracing::info!(job_id = %id, "Cancelled edit job");
+
+    Ok(Json(JobResponse {
+        job_id: id,
+        status: JobStatus::Cancelled,
+        error: None,
+        error_type: None,
+    }))
+}
+
+/// Scale an input image down for use as a preview; small images are kept at
+/// their size but still re-encoded as PNG
+///
+/// Decodes and resizes the image, so callers run it on the blocking pool.
+fn placeholder(input: &[u8]) -> Result<Bytes, AppError> {
+    let img = image_utils::bytes_to_image(input)?;
+    let (width, height) = img.dimensions();
+    let longest = width.max(height).min(PREVIEW_MAX_DIMENSION);
+
+    let size = if width >= height {
+        image_utils::resolve_output_size((width, height), Some(longest), None)
+    } else {
+        image_utils::resolve_output_size((width, height), None, Some(longest))
+    };
+    let (preview_w, preview_h) = size.unwrap_or((width, height));
+    let preview = image_utils::resize_image(&img, preview_w, preview_h, ResizeFit::Fill);
+
+    image_utils::image_to_bytes(&preview, ImageFormat::Png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Bytes {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+        image_utils::image_to_bytes(&img, ImageFormat::Png).unwrap()
+    }
+
+    #[test]
+    fn test_placeholder_keeps_aspect_ratio() {
+        let preview = placeholder(&png(1024, 512)).unwrap();
+        assert_eq!(image_utils::image_dimensions(&preview).unwrap(), (256, 128));
+
+        let preview = placeholder(&png(300, 600)).unwrap();
+        assert_eq!(image_utils::image_dimensions(&preview).unwrap(), (128, 256));
+    }
+
+    #[test]
+    fn test_placeholder_does_not_upscale() {
+        let preview = placeholder(&png(40, 20)).unwrap();
+        assert_eq!(image_utils::image_dimensions(&preview).unwrap(), (40, 20));
+    }
+
+    #[tokio::test]
+    async fn test_job_output_bounded_by_max_bytes() {
+        let response = || {
+            Response::builder()
+                .header(header::CONTENT_TYPE, "image/webp")
+                .body(Body::from("image"))
+                .unwrap()
+        };
+
+        let output = job_output(response(), 5).await.unwrap();
+        assert_eq!(output.content_type, "image/webp");
+        assert_eq!(output.body, Bytes::from_static(b"image"));
+
+        let err = job_output(response(), 4).await.unwrap_err();
+        assert!(matches!(err, AppError::ProviderError(_)));
+    }
+
+    #[test]
+    fn test_prefers_respond_async() {
+        let prefer = |values: &[&str]| {
+            let mut headers = HeaderMap::new();
+            for value in values {
+                headers.append("prefer", HeaderValue::from_str(value).unwrap());
+            }
+            prefers_respond_async(&headers)
+        };
+
+        assert!(prefer(&["respond-async"]));
+        assert!(prefer(&["return=minimal, Respond-Async; wait=10"]));
+        assert!(prefer(&["return=minimal", "respond-async"]));
+        assert!(!prefer(&["return=representation"]));
+        assert!(!prefer(&["respond-asynchronously"]));
+        assert!(!prefer(&[]));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_job_is_not_found() {
+        let jobs = Arc::new(JobStore::new());
+        let err = job_status(State(jobs), Path("missing".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)));
+    }
+}
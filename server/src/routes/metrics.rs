@@ -0,0 +1,95 @@
+//! Metrics endpoint
+//!
+//! This module implements the `/metrics` endpoint, which exposes request
+//! counters in the Prometheus text exposition format. Like the admin
+//! endpoints, the route only exists when `SERVER_API_KEY` is set and scrapers
+//! must send it as a bearer token, since the labels name tenants.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+
+use crate::error::AppError;
+use crate::routes::admin::require_server_key;
+use crate::state::AppState;
+
+/// Prometheus metrics handler
+///
+/// # Endpoint
+///
+/// `GET /metrics`
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer $SERVER_API_KEY" http://localhost:8000/metrics
+/// ```
+///
+/// # Errors
+///
+/// Returns `AppError::Unauthorized` (401) when the bearer token is missing or
+/// doesn't match `SERVER_API_KEY`.
+pub async fn metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    require_server_key(&state.config, &headers)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::metrics::OUTCOME_SUCCESS;
+    use crate::models::tenant::TenantId;
+    use axum::http::StatusCode;
+
+    fn state() -> AppState {
+        AppState::new(AppConfig {
+            server_api_key: Some("secret".to_string()),
+            ..AppConfig::default()
+        })
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_metrics_handler_renders_counters() {
+        let state = state();
+        state
+            .metrics
+            .record_edit(&TenantId::anonymous(), "google", OUTCOME_SUCCESS);
+
+        let response = metrics(State(state), bearer("secret"))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(text.contains("tenant=\"anonymous\""));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_handler_requires_server_key() {
+        for headers in [HeaderMap::new(), bearer("wrong")] {
+            let response = metrics(State(state()), headers).await.into_response();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
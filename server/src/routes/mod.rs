@@ -4,7 +4,16 @@
 //! Routes are organized by functionality:
 //! - Health check endpoints for monitoring
 //! - Provider listing endpoints to show available AI services
-//! - Image editing endpoints for AI-powered image manipulation
+//! - Model catalog listing with dimension and format metadata
+//! - Image editing endpoints for AI-powered image manipulation (single, streamed
+//!   as Server-Sent Events, and batch)
+//! - Provider comparison, running one edit on several providers at once
+//! - Asynchronous edit jobs with status polling and previews
+//! - Uploads of large images ahead of an edit, without multipart
+//! - Content-addressable copies of edit results
+//! - OpenAPI schema export for generating typed clients
+//! - Prometheus metrics export
+//! - Admin statistics, protected by the server API key
 //!
 //! Each route module implements request handling, validation, and response formatting.
 
@@ -14,5 +23,35 @@ pub mod health;
 /// Providers listing endpoint
 pub mod providers;
 
+/// Models listing endpoint
+pub mod models;
+
 /// Image editing endpoint
 pub mod edit;
+
+/// Image editing endpoint streaming the result as Server-Sent Events
+pub mod edit_stream;
+
+/// Batch image editing endpoint
+pub mod batch;
+
+/// Provider comparison endpoint
+pub mod compare;
+
+/// Asynchronous edit job endpoints
+pub mod jobs;
+
+/// Upload endpoints
+pub mod uploads;
+
+/// Stored result endpoint
+pub mod results;
+
+/// OpenAPI schema endpoint
+pub mod openapi;
+
+/// Prometheus metrics endpoint
+pub mod metrics;
+
+/// Admin statistics endpoint
+pub mod admin;
@@ -0,0 +1,82 @@
+//! Models listing endpoint
+//!
+//! This module implements the `/api/models` endpoint, which lists the known
+//! models of every configured provider together with their input dimension
+//! limits and output formats, so clients can pre-resize uploads.
+
+use crate::config::AppConfig;
+use crate::models::response::ModelsResponse;
+use crate::services::catalog;
+use axum::{extract::State, Json};
+
+/// List known models handler
+///
+/// # Endpoint
+///
+/// `GET /api/models`
+///
+/// # Response
+///
+/// Returns a JSON array of catalog entries for providers with a configured API key:
+///
+/// ```json
+/// [
+///   {
+///     "provider": "fal:fal-ai/flux-kontext/dev",
+///     "name": "FLUX.1 Kontext [dev]",
+///     "recommended_input_dimension": 1024,
+///     "max_input_dimension": 2048,
+///     "output_formats": ["png", "jpeg"],
+///     "max_prompt_chars": 2000
+///   }
+/// ]
+/// ```
+///
+/// # Example
+///
+/// ```bash
+/// curl http://localhost:8000/api/models
+/// ```
+pub async fn list_models(State(config): State<AppConfig>) -> Json<ModelsResponse> {
+    let models = catalog::available_models(&config)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    tracing::debug!(model_count = models.len(), "Listing available models");
+
+    Json(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_models_includes_dimension_metadata() {
+        let config = AppConfig {
+            google_api_key: Some("test-key".to_string()),
+            fal_key: Some("test-fal-key".to_string()),
+            ..AppConfig::default()
+        };
+
+        let response = list_models(State(config)).await;
+        let json = serde_json::to_value(&response.0).unwrap();
+        let google = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|model| model["provider"] == "google")
+            .unwrap();
+
+        assert_eq!(google["max_input_dimension"], 3072);
+        assert_eq!(google["recommended_input_dimension"], 1024);
+        assert_eq!(google["output_formats"], serde_json::json!(["png"]));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_no_keys() {
+        let response = list_models(State(AppConfig::default())).await;
+        assert!(response.0.is_empty());
+    }
+}
@@ -0,0 +1,843 @@
+//! OpenAPI schema endpoint
+//!
+//! This module implements the `/api/openapi.json` endpoint, which serves an
+//! OpenAPI 3.0 description of the public API for generating typed clients.
+//! The document is hand-built to mirror the request/response models
+//! (`EditImageRequest`, `HealthResponse`, `ProvidersResponse`, ...) and the
+//! JSON error body produced by `AppError`. Every route of `app::api_routes`
+//! must be listed, including those behind feature flags; a test checks this.
+
+use axum::Json;
+use serde_json::{json, Value};
+
+use crate::models::request::EditImageRequest;
+
+/// OpenAPI document handler
+///
+/// # Endpoint
+///
+/// `GET /api/openapi.json`
+///
+/// # Example
+///
+/// ```bash
+/// curl http://localhost:8000/api/openapi.json
+/// ```
+pub async fn openapi_spec() -> Json<Value> {
+    Json(build_spec())
+}
+
+/// Build the OpenAPI document describing all public endpoints
+pub fn build_spec() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "FrameForge API",
+            "description": "AI-powered image editing through multiple providers",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/api/health": {
+                "get": {
+                    "summary": "Health check",
+                    "operationId": "healthCheck",
+                    "responses": {
+                        "200": json_response("Server is healthy", "HealthResponse"),
+                    },
+                },
+            },
+            "/api/providers": {
+                "get": {
+                    "summary": "List statically configured providers",
+                    "operationId": "listProviders",
+                    "responses": {
+                        "200": json_response("Available provider names", "ProvidersResponse"),
+                    },
+                },
+            },
+            "/api/providers/{name}/params": {
+                "get": {
+                    "summary": "Parameter schema of a known provider model",
+                    "operationId": "providerParams",
+                    "parameters": [{
+                        "name": "name",
+                        "in": "path",
+                        "required": true,
+                        "description": "Provider name; slashes in Fal model paths must be percent-encoded",
+                        "schema": { "type": "string" },
+                    }],
+                    "responses": {
+                        "200": json_response("Accepted parameters", "ProviderParamsResponse"),
+                        "404": error_response("Unknown provider"),
+                    },
+                },
+            },
+            "/api/models": {
+                "get": {
+                    "summary": "List known models with input dimension and output format metadata",
+                    "operationId": "listModels",
+                    "responses": {
+                        "200": json_response("Models of configured providers", "ModelsResponse"),
+                    },
+                },
+            },
+            "/metrics": {
+                "get": {
+                    "summary": "Prometheus metrics (requires SERVER_API_KEY as a bearer token)",
+                    "operationId": "metrics",
+                    "parameters": [server_key_header()],
+                    "responses": {
+                        "200": {
+                            "description": "Metrics in the Prometheus text exposition format",
+                            "content": { "text/plain": { "schema": { "type": "string" } } },
+                        },
+                        "401": error_response("Missing or wrong bearer token"),
+                    },
+                },
+            },
+            "/api/edit/stream": {
+                "post": {
+                    "summary": "Edit an image, streaming the result as server-sent events",
+                    "operationId": "editImageStream",
+                    "parameters": edit_headers([]),
+                    "requestBody": multipart_body("EditImageRequest"),
+                    "responses": {
+                        "200": {
+                            "description": "`start` ({content_type, size}), repeated `chunk` (base64 of the next 48 KiB) and `done` ({chunks}) events",
+                            "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                        },
+                        "400": error_response("Invalid input or image"),
+                        "404": error_response("Provider not found or not configured"),
+                        "500": error_response("Provider or internal error"),
+                        "502": error_response("Provider rejected the configured API key"),
+                        "503": error_response("More than MAX_CONNECTIONS requests in flight"),
+                    },
+                },
+            },
+            "/api/edit/batch": {
+                "post": {
+                    "summary": "Edit several images, each with its own result or error",
+                    "operationId": "editBatch",
+                    "parameters": edit_headers([]),
+                    "requestBody": multipart_body("BatchEditRequest"),
+                    "responses": {
+                        "200": json_response("One result per image, in upload order", "BatchEditResponse"),
+                        "400": error_response("Missing or too many images, mismatched prompt count, or an unreadable archive"),
+                        "404": error_response("Provider not found or not configured"),
+                        "413": error_response("Request body larger than MAX_UPLOAD_BYTES"),
+                        "503": error_response("More than MAX_CONNECTIONS requests in flight"),
+                    },
+                },
+            },
+            "/api/edit/compare": {
+                "post": {
+                    "summary": "Run one edit on several providers",
+                    "operationId": "editCompare",
+                    "parameters": edit_headers([]),
+                    "requestBody": multipart_body("CompareEditRequest"),
+                    "responses": {
+                        "200": json_response("One result per provider, in request order", "CompareEditResponse"),
+                        "400": error_response("Missing or extra image, or no or too many providers"),
+                        "413": error_response("Request body larger than MAX_UPLOAD_BYTES"),
+                        "503": error_response("More than MAX_CONNECTIONS requests in flight"),
+                    },
+                },
+            },
+            "/api/jobs": {
+                "post": {
+                    "summary": "Start an edit in the background (requires the `async_jobs` feature)",
+                    "operationId": "submitJob",
+                    "parameters": edit_headers([
+                        optional_header(
+                            "Idempotency-Key",
+                            "Returns the running job submitted with the same key instead of starting another",
+                        ),
+                    ]),
+                    "requestBody": multipart_body("EditImageRequest"),
+                    "responses": {
+                        "202": json_response("Job started; `Location` points at it", "JobResponse"),
+                        "400": error_response("Invalid input or image"),
+                        "404": error_response("Provider not found or not configured"),
+                        "503": error_response("More than MAX_CONNECTIONS requests in flight, or MAX_RUNNING_JOBS jobs running"),
+                    },
+                },
+            },
+            "/api/jobs/{id}": {
+                "get": {
+                    "summary": "Job status",
+                    "operationId": "jobStatus",
+                    "parameters": [path_parameter("id", "Job id")],
+                    "responses": {
+                        "200": json_response("Current job status", "JobResponse"),
+                        "404": error_response("Unknown job"),
+                    },
+                },
+                "delete": {
+                    "summary": "Cancel a job",
+                    "operationId": "cancelJob",
+                    "parameters": [path_parameter("id", "Job id")],
+                    "responses": {
+                        "200": json_response("The cancelled job", "JobResponse"),
+                        "404": error_response("Unknown job"),
+                    },
+                },
+            },
+            "/api/jobs/{id}/preview": {
+                "get": {
+                    "summary": "Downscaled input while the job runs, then its result",
+                    "operationId": "jobPreview",
+                    "parameters": [path_parameter("id", "Job id")],
+                    "responses": {
+                        "200": {
+                            "description": "Preview or result image; `X-Job-Status` reports the job status",
+                            "content": {
+                                "image/png": { "schema": { "type": "string", "format": "binary" } },
+                                "image/jpeg": { "schema": { "type": "string", "format": "binary" } },
+                                "image/webp": { "schema": { "type": "string", "format": "binary" } },
+                            },
+                        },
+                        "404": error_response("Unknown job"),
+                        "409": error_response("Job was cancelled"),
+                    },
+                },
+            },
+            "/api/uploads": {
+                "post": {
+                    "summary": "Reserve an upload (requires the `uploads` feature)",
+                    "operationId": "createUpload",
+                    "responses": {
+                        "201": json_response("Upload reserved; `Location` points at it", "UploadResponse"),
+                    },
+                },
+            },
+            "/api/uploads/{id}": {
+                "put": {
+                    "summary": "Send the raw image bytes of a reserved upload",
+                    "operationId": "putUpload",
+                    "parameters": [path_parameter("id", "Upload id")],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "image/*": { "schema": { "type": "string", "format": "binary" } } },
+                    },
+                    "responses": {
+                        "204": { "description": "Bytes stored" },
+                        "400": error_response("Empty body or not a supported image"),
+                        "404": error_response("Unknown or expired upload"),
+                        "413": error_response("Request body larger than MAX_UPLOAD_BYTES"),
+                    },
+                },
+            },
+            "/api/results/{id}": {
+                "get": {
+                    "summary": "Stored result of an edit, by its X-Result-Id (requires the `results` feature)",
+                    "operationId": "getResult",
+                    "parameters": [path_parameter("id", "Result id from the X-Result-Id header")],
+                    "responses": {
+                        "200": {
+                            "description": "The stored result, with an immutable Cache-Control",
+                            "content": {
+                                "image/png": { "schema": { "type": "string", "format": "binary" } },
+                                "image/jpeg": { "schema": { "type": "string", "format": "binary" } },
+                                "image/webp": { "schema": { "type": "string", "format": "binary" } },
+                            },
+                        },
+                        "404": error_response("Unknown or evicted result"),
+                    },
+                },
+            },
+            "/api/admin/stats": {
+                "get": {
+                    "summary": "Operational summary (requires SERVER_API_KEY as a bearer token)",
+                    "operationId": "adminStats",
+                    "parameters": [server_key_header()],
+                    "responses": {
+                        "200": json_response("Uptime, edit counts and limiter state", "AdminStatsResponse"),
+                        "401": error_response("Missing or wrong bearer token"),
+                    },
+                },
+            },
+            "/api/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "operationId": "openapiSpec",
+                    "responses": {
+                        "200": {
+                            "description": "OpenAPI 3.0 document",
+                            "content": { "application/json": { "schema": { "type": "object" } } },
+                        },
+                    },
+                },
+            },
+            "/api/edit": {
+                "post": {
+                    "summary": "Edit an image with the selected provider",
+                    "operationId": "editImage",
+                    "parameters": edit_headers([
+                        optional_header(
+                            "Prefer",
+                            "`respond-async` runs the edit as an async job and answers 202 (requires the `async_jobs` feature)",
+                        ),
+                        optional_header(
+                            "Idempotency-Key",
+                            "With `Prefer: respond-async`, returns the running job submitted with the same key instead of starting another",
+                        ),
+                    ]),
+                    "requestBody": multipart_body("EditImageRequest"),
+                    "responses": {
+                        "200": {
+                            "description": "The edited image",
+                            "content": {
+                                "image/png": { "schema": { "type": "string", "format": "binary" } },
+                                "image/jpeg": { "schema": { "type": "string", "format": "binary" } },
+                                "image/webp": { "schema": { "type": "string", "format": "binary" } },
+                            },
+                        },
+                        "400": error_response("Invalid input or image"),
+                        "404": error_response("Provider not found or not configured"),
+                        "413": error_response("Request body larger than MAX_UPLOAD_BYTES"),
+                        "500": error_response("Provider or internal error"),
+                        "502": error_response("Provider rejected the configured API key"),
+                        "503": error_response("More than MAX_CONNECTIONS requests in flight"),
+                    },
+                },
+                "get": {
+                    "summary": "Edit an image fetched from a URL, with query parameters only",
+                    "operationId": "editImageFromQuery",
+                    "parameters": edit_headers([
+                        json!({
+                            "name": "image_url",
+                            "in": "query",
+                            "required": true,
+                            "description": "http(s) URL of the input image (`image/*` only; at most 5 MiB, fetched within 10 seconds)",
+                            "schema": { "type": "string", "format": "uri" },
+                        }),
+                        json!({
+                            "name": "prompt",
+                            "in": "query",
+                            "required": false,
+                            "description": "Editing instructions; a default staging prompt is used when omitted",
+                            "schema": { "type": "string" },
+                        }),
+                        json!({
+                            "name": "provider",
+                            "in": "query",
+                            "required": false,
+                            "description": "Provider name, as for POST",
+                            "schema": { "type": "string", "default": "google" },
+                        }),
+                    ]),
+                    "responses": {
+                        "200": {
+                            "description": "The edited image",
+                            "content": {
+                                "image/png": { "schema": { "type": "string", "format": "binary" } },
+                                "image/jpeg": { "schema": { "type": "string", "format": "binary" } },
+                                "image/webp": { "schema": { "type": "string", "format": "binary" } },
+                            },
+                        },
+                        "400": error_response("Missing image_url, an image_url that could not be fetched, or an invalid image"),
+                        "404": error_response("Provider not found or not configured"),
+                        "500": error_response("Provider or internal error"),
+                        "502": error_response("Provider rejected the configured API key"),
+                        "503": error_response("More than MAX_CONNECTIONS requests in flight"),
+                    },
+                },
+            },
+        },
+        "components": {
+            "schemas": {
+                "EditImageRequest": {
+                    "type": "object",
+                    "description": "At least one image is required, via `images`, `base_image`, `reference_image` or `image_url`",
+                    "properties": {
+                        "images": {
+                            "type": "array",
+                            "description": "One or more image files (`image` is accepted as an alias)",
+                            "items": { "type": "string", "format": "binary" },
+                        },
+                        "base_image": {
+                            "type": "string",
+                            "format": "binary",
+                            "description": "Image being edited; multi-image providers receive it first",
+                        },
+                        "reference_image": {
+                            "type": "string",
+                            "format": "binary",
+                            "description": "Guidance image; sent after `base_image` and before the other inputs",
+                        },
+                        "image_url": {
+                            "type": "array",
+                            "description": "http(s) URLs of images fetched by the server (`image/*` only, size and redirect limited)",
+                            "items": { "type": "string", "format": "uri" },
+                        },
+                        "upload_id": {
+                            "type": "array",
+                            "description": "Ids of images sent beforehand with PUT /api/uploads/{id} (requires the `uploads` feature)",
+                            "items": { "type": "string" },
+                        },
+                        "prompt": {
+                            "type": "string",
+                            "description": "Editing instructions; a default staging prompt is used when omitted",
+                            "default": EditImageRequest::default_prompt(),
+                        },
+                        "provider": {
+                            "type": "string",
+                            "description": "Provider name, e.g. `google`, `nano-banana`, `fal:<model-path>`, or `composite[:cols=N,spacing=PX,bg=RRGGBB]` to tile the images without AI",
+                            "default": "google",
+                        },
+                        "out_width": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Output width in pixels; derived from the aspect ratio when only `out_height` is set",
+                        },
+                        "out_height": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Output height in pixels; derived from the aspect ratio when only `out_width` is set",
+                        },
+                        "fit": {
+                            "type": "string",
+                            "enum": ["contain", "cover", "fill", "smart"],
+                            "description": "How the result is fitted to the requested dimensions. `contain` pads with transparency, or with JPEG_BACKGROUND when JPEG output is requested",
+                            "default": "contain",
+                        },
+                        "output_format": {
+                            "type": "string",
+                            "enum": ["png", "jpeg", "webp"],
+                            "description": "Encoding of the returned image; defaults to DEFAULT_OUTPUT_FORMAT, or the provider's format when unset",
+                        },
+                        "formats": {
+                            "type": "string",
+                            "description": "Comma-separated formats (png, jpeg, webp; at most 3) to return the result in at once, instead of output_format. The response is then a JSON object mapping each format to a base64 data URL",
+                            "example": "webp,png",
+                        },
+                        "optimize": {
+                            "type": "boolean",
+                            "description": "Losslessly optimize PNG output before returning it",
+                            "default": false,
+                        },
+                        "enhance_prompt": {
+                            "type": "boolean",
+                            "description": "Enrich the prompt before editing (only for providers listed in PROMPT_ENHANCE_PROVIDERS)",
+                            "default": false,
+                        },
+                        "include_metadata": {
+                            "type": "boolean",
+                            "description": "Return the seed, inference time and model version the provider reported as JSON in an X-Provider-Metadata header",
+                            "default": false,
+                        },
+                        "phash": {
+                            "type": "boolean",
+                            "description": "Return a 64-bit perceptual hash of the output (16 hex digits) in an X-Image-Phash header, to deduplicate visually identical results",
+                            "default": false,
+                        },
+                        "quality_score": {
+                            "type": "boolean",
+                            "description": "Return a no-reference sharpness score of the output (variance of the Laplacian, higher is sharper) in an X-Quality-Score header, to flag blurry results",
+                            "default": false,
+                        },
+                        "embed_metadata": {
+                            "type": "boolean",
+                            "description": "Write the prompt, provider, seed and model into tEXt/iTXt chunks of PNG output, for reproducibility; other formats are returned without them",
+                            "default": false,
+                        },
+                        "rotate": {
+                            "type": "integer",
+                            "enum": [0, 90, 180, 270],
+                            "description": "Clockwise rotation in degrees applied to the input images before the edit, for images with missing or wrong orientation metadata. `region` refers to the rotated image",
+                            "default": 0,
+                        },
+                        "region": {
+                            "type": "string",
+                            "description": "x,y,width,height as fractions (0-1) of the first image's size, limiting the edit to that rectangle. Only for providers that support regions (Fal.ai inpainting models)",
+                            "example": "0.25,0.5,0.5,0.25",
+                        },
+                        "temperature": {
+                            "type": "number",
+                            "minimum": 0,
+                            "maximum": 2,
+                            "description": "Sampling temperature (Google Gemini; ignored by other providers)",
+                        },
+                        "top_p": {
+                            "type": "number",
+                            "minimum": 0,
+                            "maximum": 1,
+                            "description": "Nucleus sampling probability mass (Google Gemini; ignored by other providers)",
+                        },
+                        "seed": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 4294967295u32,
+                            "description": "Seed for reproducible results (Fal.ai models; ignored by other providers)",
+                        },
+                        "num_inference_steps": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "maximum": 50,
+                            "description": "Number of denoising steps (Fal.ai models; ignored by other providers)",
+                        },
+                        "guidance_scale": {
+                            "type": "number",
+                            "minimum": 1,
+                            "maximum": 20,
+                            "description": "Classifier-free guidance scale (Fal.ai models; ignored by other providers)",
+                        },
+                        "provider_options": {
+                            "type": "string",
+                            "description": "JSON object of extra provider request fields with string, number or boolean values, e.g. {\"guidance_scale\": 3.5}; merged into Fal.ai requests without replacing fields the server sets. prompt, image_url, image_urls, mask_url, output_format and sync_mode are rejected, as are keys in PROVIDER_OPTIONS_DENY (by default enable_safety_checker and num_images)",
+                        },
+                        "steps": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Chained prompts used instead of `prompt`; each edits the previous result. At most MAX_EDIT_STEPS",
+                        },
+                    },
+                },
+                "HealthResponse": {
+                    "type": "object",
+                    "required": ["status"],
+                    "properties": {
+                        "status": { "type": "string", "example": "ok" },
+                    },
+                },
+                "ProvidersResponse": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "example": ["google", "nano-banana"],
+                },
+                "ModelsResponse": {
+                    "type": "array",
+                    "items": { "$ref": "#/components/schemas/ModelInfo" },
+                },
+                "ModelInfo": {
+                    "type": "object",
+                    "required": [
+                        "provider",
+                        "name",
+                        "recommended_input_dimension",
+                        "max_input_dimension",
+                        "output_formats",
+                        "max_prompt_chars",
+                    ],
+                    "properties": {
+                        "provider": { "type": "string", "example": "fal:fal-ai/flux-kontext/dev" },
+                        "name": { "type": "string" },
+                        "recommended_input_dimension": {
+                            "type": "integer",
+                            "description": "Longest input side (pixels) the model works best with",
+                        },
+                        "max_input_dimension": {
+                            "type": "integer",
+                            "description": "Longest input side (pixels) the model accepts",
+                        },
+                        "output_formats": {
+                            "type": "array",
+                            "items": { "type": "string", "example": "png" },
+                        },
+                        "max_prompt_chars": {
+                            "type": "integer",
+                            "description": "Longest prompt (characters, prefix and suffix included) the model accepts; MAX_PROMPT_CHARS applies when lower",
+                        },
+                    },
+                },
+                "ProviderParamsResponse": {
+                    "type": "object",
+                    "required": ["provider", "params"],
+                    "properties": {
+                        "provider": { "type": "string" },
+                        "params": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["name", "type", "description", "required"],
+                                "properties": {
+                                    "name": { "type": "string", "example": "seed" },
+                                    "type": { "type": "string", "enum": ["string", "integer", "number"] },
+                                    "description": { "type": "string" },
+                                    "required": { "type": "boolean" },
+                                    "minimum": { "type": "number" },
+                                    "maximum": { "type": "number" },
+                                    "default": {},
+                                },
+                            },
+                        },
+                    },
+                },
+                "BatchEditRequest": {
+                    "type": "object",
+                    "required": ["images"],
+                    "properties": {
+                        "images": {
+                            "type": "array",
+                            "description": "Image files, up to MAX_BATCH_IMAGES (`image` is accepted as an alias). An empty or unrecognized part fails only its own item with BATCH_MODE=best_effort",
+                            "items": { "type": "string", "format": "binary" },
+                        },
+                        "archive": {
+                            "type": "string",
+                            "format": "binary",
+                            "description": "ZIP file whose entries are added as images, in archive order, where the part appears",
+                        },
+                        "prompt": { "type": "string", "description": "Prompt applied to every image" },
+                        "prompts": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "One prompt per image in upload order; a single entry applies to all images",
+                        },
+                        "provider": { "type": "string", "description": "Provider name, as for /api/edit", "default": "google" },
+                    },
+                },
+                "BatchEditResponse": {
+                    "type": "object",
+                    "required": ["results"],
+                    "properties": {
+                        "results": { "type": "array", "items": { "$ref": "#/components/schemas/BatchItemResult" } },
+                    },
+                },
+                "BatchItemResult": {
+                    "type": "object",
+                    "required": ["index"],
+                    "properties": {
+                        "index": { "type": "integer", "description": "Position of the input image" },
+                        "image": { "type": "string", "description": "Edited image as a base64 data URL (on success)" },
+                        "error": { "type": "string", "description": "Error message (on failure)" },
+                        "error_type": { "type": "string", "description": "Error code (on failure)" },
+                    },
+                },
+                "CompareEditRequest": {
+                    "type": "object",
+                    "required": ["images", "providers"],
+                    "properties": {
+                        "images": { "type": "string", "format": "binary", "description": "The image to edit; exactly one" },
+                        "prompt": { "type": "string", "description": "Prompt sent to every provider" },
+                        "providers": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Providers to run, up to MAX_COMPARE_PROVIDERS; results follow this order",
+                        },
+                    },
+                },
+                "CompareEditResponse": {
+                    "type": "object",
+                    "required": ["results"],
+                    "properties": {
+                        "results": { "type": "array", "items": { "$ref": "#/components/schemas/CompareItemResult" } },
+                    },
+                },
+                "CompareItemResult": {
+                    "type": "object",
+                    "required": ["provider", "latency_ms"],
+                    "properties": {
+                        "provider": { "type": "string" },
+                        "latency_ms": { "type": "integer", "description": "Time the provider took, failures included" },
+                        "image": { "type": "string", "description": "Edited image as a base64 data URL (on success)" },
+                        "error": { "type": "string", "description": "Error message (on failure)" },
+                        "error_type": { "type": "string", "description": "Error code (on failure)" },
+                    },
+                },
+                "JobResponse": {
+                    "type": "object",
+                    "required": ["job_id", "status"],
+                    "properties": {
+                        "job_id": { "type": "string" },
+                        "status": { "type": "string", "enum": ["running", "succeeded", "failed", "cancelled"] },
+                        "error": { "type": "string", "description": "Error message (when the job failed)" },
+                        "error_type": { "type": "string", "description": "Error code (when the job failed)" },
+                    },
+                },
+                "UploadResponse": {
+                    "type": "object",
+                    "required": ["upload_id", "upload_url", "expires_in_secs"],
+                    "properties": {
+                        "upload_id": { "type": "string", "description": "Sent as the `upload_id` field of /api/edit" },
+                        "upload_url": { "type": "string", "description": "Path to PUT the raw image bytes to" },
+                        "expires_in_secs": { "type": "integer" },
+                    },
+                },
+                "AdminStatsResponse": {
+                    "type": "object",
+                    "required": [
+                        "uptime_secs",
+                        "total_edits",
+                        "edits_by_provider",
+                        "in_flight",
+                        "max_connections",
+                        "cache_hit_rate",
+                        "rate_limit_clients",
+                    ],
+                    "properties": {
+                        "uptime_secs": { "type": "integer" },
+                        "total_edits": { "type": "integer" },
+                        "edits_by_provider": {
+                            "type": "object",
+                            "additionalProperties": { "type": "integer" },
+                        },
+                        "in_flight": { "type": "integer" },
+                        "max_connections": { "type": "integer" },
+                        "cache_hit_rate": {
+                            "type": "number",
+                            "nullable": true,
+                            "description": "Share of result download cache lookups that hit; null before the first lookup",
+                        },
+                        "rate_limit_clients": { "type": "integer", "description": "Clients tracked by the rate limiter" },
+                    },
+                },
+                "ErrorResponse": {
+                    "type": "object",
+                    "required": ["error"],
+                    "properties": {
+                        "error": { "type": "string", "description": "Human-readable error message" },
+                        "error_type": {
+                            "type": "string",
+                            "description": "Error code for programmatic handling",
+                            "example": "invalid_input",
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// JSON response object referencing a component schema
+fn json_response(description: &str, schema: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": { "$ref": format!("#/components/schemas/{}", schema) },
+            },
+        },
+    })
+}
+
+/// Error response object using the shared `ErrorResponse` schema
+fn error_response(description: &str) -> Value {
+    json_response(description, "ErrorResponse")
+}
+
+/// Multipart request body referencing a component schema
+fn multipart_body(schema: &str) -> Value {
+    json!({
+        "required": true,
+        "content": {
+            "multipart/form-data": {
+                "schema": { "$ref": format!("#/components/schemas/{}", schema) },
+            },
+        },
+    })
+}
+
+/// Required path parameter
+fn path_parameter(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+/// `Authorization` header of the operator endpoints
+fn server_key_header() -> Value {
+    json!({
+        "name": "Authorization",
+        "in": "header",
+        "required": true,
+        "description": "`Bearer <SERVER_API_KEY>`",
+        "schema": { "type": "string" },
+    })
+}
+
+/// Key override, provider and tenant headers accepted by every edit endpoint,
+/// followed by `extra`
+fn edit_headers<const N: usize>(extra: [Value; N]) -> Value {
+    let mut headers = vec![
+        optional_header("X-Google-Api-Key", "Override GOOGLE_API_KEY"),
+        optional_header("X-Gemini-Api-Key", "Override GEMINI_API_KEY"),
+        optional_header("X-Fal-Key", "Override FAL_KEY"),
+        optional_header(
+            "X-Provider-Keys",
+            "JSON object with any of `google`, `gemini` and `fal` keys; individual key headers take precedence",
+        ),
+        optional_header("X-Provider", "Provider to use when the request doesn't name one; the `provider` field or query parameter takes precedence"),
+        optional_header("X-Tenant-Id", "Tenant id for metrics attribution; defaults to `anonymous`"),
+    ];
+    headers.extend(extra);
+    Value::Array(headers)
+}
+
+/// Optional header parameter
+fn optional_header(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "header",
+        "required": false,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    #[test]
+    fn test_spec_lists_edit_path() {
+        let spec = build_spec();
+        assert!(spec["paths"]["/api/edit"]["post"].is_object());
+        assert!(spec["paths"]["/api/edit"]["get"].is_object());
+        assert!(spec["paths"]["/api/health"]["get"].is_object());
+        assert!(spec["paths"]["/api/providers"]["get"].is_object());
+    }
+
+    #[test]
+    fn test_edit_400_uses_error_schema() {
+        let spec = build_spec();
+        let schema_ref = &spec["paths"]["/api/edit"]["post"]["responses"]["400"]["content"]
+            ["application/json"]["schema"]["$ref"];
+        assert_eq!(schema_ref, "#/components/schemas/ErrorResponse");
+
+        let error_schema = &spec["components"]["schemas"]["ErrorResponse"];
+        assert!(error_schema["properties"]["error"].is_object());
+        assert!(error_schema["properties"]["error_type"].is_object());
+    }
+
+    #[test]
+    fn test_spec_lists_every_route() {
+        let spec = build_spec();
+        let paths = spec["paths"].as_object().unwrap();
+        let routes: Vec<&str> = crate::app::api_routes(&AppConfig::default())
+            .iter()
+            .map(|route| route.path)
+            .collect();
+
+        for path in &routes {
+            assert!(
+                paths.contains_key(*path),
+                "{} is missing from the spec",
+                path
+            );
+        }
+        for path in paths.keys() {
+            assert!(routes.contains(&path.as_str()), "{} is not a route", path);
+        }
+    }
+
+    #[test]
+    fn test_schema_refs_resolve() {
+        let spec = build_spec();
+        let text = spec.to_string();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(spec["components"]["schemas"][name].is_object(), "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openapi_handler() {
+        let response = openapi_spec().await;
+        assert_eq!(response.0["openapi"], "3.0.3");
+    }
+}
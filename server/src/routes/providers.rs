@@ -2,11 +2,16 @@
 //!
 //! This module implements the `/api/providers` endpoint for listing available AI providers.
 //! The endpoint returns all statically configured providers based on available API keys.
+//! It also serves `/api/providers/{name}/params`, the parameter schema of a known model.
 
-use axum::{extract::State, Json};
 use crate::config::AppConfig;
-use crate::models::response::ProvidersResponse;
-use crate::services::factory;
+use crate::error::AppError;
+use crate::models::response::{ProviderParamsResponse, ProvidersResponse};
+use crate::services::{catalog, factory};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
 
 /// List available providers handler
 ///
@@ -25,6 +30,8 @@ use crate::services::factory;
 /// ["google", "nano-banana"]
 /// ```
 ///
+/// Providers are ordered by `PROVIDER_ORDER`, then alphabetically.
+///
 /// # Providers
 ///
 /// ## Static Providers
@@ -44,9 +51,7 @@ use crate::services::factory;
 /// # State
 ///
 /// Requires AppConfig to be in Axum shared state to check which API keys are configured.
-pub async fn list_providers(
-    State(config): State<AppConfig>,
-) -> Json<ProvidersResponse> {
+pub async fn list_providers(State(config): State<AppConfig>) -> Json<ProvidersResponse> {
     let providers = factory::list_providers(&config);
 
     tracing::debug!(
@@ -57,6 +62,39 @@ pub async fn list_providers(
     Json(providers)
 }
 
+/// Provider parameter schema handler
+///
+/// Returns the parameters a provider accepts, derived from its catalog
+/// capabilities, so frontends can build dynamic forms.
+///
+/// # Endpoint
+///
+/// `GET /api/providers/{name}/params`
+///
+/// Fal model paths contain slashes, which must be percent-encoded in `name`
+/// (e.g. `fal:fal-ai%2Fflux-kontext%2Fdev`).
+///
+/// # Errors
+///
+/// - `404 Not Found`: The provider has no catalog entry
+///
+/// # Example
+///
+/// ```bash
+/// curl http://localhost:8000/api/providers/fal:fal-ai%2Fflux-kontext%2Fdev/params
+/// ```
+pub async fn provider_params(
+    Path(name): Path<String>,
+) -> Result<Json<ProviderParamsResponse>, AppError> {
+    let model = catalog::find_model(&name)
+        .ok_or_else(|| AppError::ProviderNotFound(format!("Unknown provider '{}'", name)))?;
+
+    Ok(Json(ProviderParamsResponse {
+        provider: model.provider.to_string(),
+        params: model.params(),
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,6 +108,7 @@ mod tests {
             allowed_origins: vec!["*".to_string()],
             host: "127.0.0.1".to_string(),
             port: 8000,
+            ..AppConfig::default()
         }
     }
 
@@ -93,6 +132,7 @@ mod tests {
             allowed_origins: vec!["*".to_string()],
             host: "127.0.0.1".to_string(),
             port: 8000,
+            ..AppConfig::default()
         };
 
         let response = list_providers(State(config)).await;
@@ -100,4 +140,33 @@ mod tests {
         // Should be empty when no keys configured
         assert!(response.0.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_provider_params_google_minimal() {
+        let response = provider_params(Path("google".to_string())).await.unwrap();
+
+        assert_eq!(response.0.provider, "google");
+        assert_eq!(response.0.params.len(), 1);
+        assert_eq!(response.0.params[0].name, "prompt");
+    }
+
+    #[tokio::test]
+    async fn test_provider_params_fal_model() {
+        let response = provider_params(Path("fal:fal-ai/qwen-image-edit".to_string()))
+            .await
+            .unwrap();
+
+        let names = response.0.params.iter().map(|p| p.name).collect::<Vec<_>>();
+        assert!(names.contains(&"seed"));
+        assert!(names.contains(&"num_inference_steps"));
+        assert!(names.contains(&"guidance_scale"));
+    }
+
+    #[tokio::test]
+    async fn test_provider_params_unknown_is_not_found() {
+        let err = provider_params(Path("unknown".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::ProviderNotFound(_)));
+    }
 }
@@ -0,0 +1,144 @@
+//! Content-addressable result endpoint
+//!
+//! Available when the `results` feature flag is enabled: successful
+//! `/api/edit` responses carry an `X-Result-Id` header, and
+//! `GET /api/results/{id}` returns the same body again. `HEAD` on the same
+//! path answers with the headers only, so clients can cheaply check whether a
+//! result is still held. Results are kept in the in-memory `ResultStore`; see
+//! `crate::results`.
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::error::AppError;
+use crate::results::ResultStore;
+
+/// Response header naming the stored result of an edit
+pub(crate) const RESULT_ID_HEADER: &str = "X-Result-Id";
+
+/// Results are addressed by their content, so they never change
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Stored result handler
+///
+/// # Endpoint
+///
+/// `GET /api/results/{id}` or `HEAD /api/results/{id}`
+///
+/// # Response
+///
+/// The result body with its `Content-Type`, `Content-Length` and an immutable
+/// `Cache-Control`. `HEAD` requests get the same headers without the body;
+/// axum routes them to this handler and drops the body.
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` (404) for unknown or evicted ids.
+pub async fn get_result(
+    State(results): State<Arc<ResultStore>>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let result = results
+        .get(&id)
+        .ok_or_else(|| AppError::NotFound(format!("result '{}'", id)))?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, result.content_type)
+        .header(header::CONTENT_LENGTH, result.body.len())
+        .header(header::CACHE_CONTROL, IMMUTABLE)
+        .body(Body::from(result.body))
+        .map_err(|e| AppError::InternalServer(format!("Failed to build response: {}", e)))
+}
+
+/// Keep a successful edit response in the store and tag it with its id
+///
+/// The body is read up to `max_bytes`, normally `max_result_bytes`. Other
+/// responses are passed through untouched.
+///
+/// # Errors
+///
+/// Returns `AppError::ProviderError` if the body is over `max_bytes`.
+pub(crate) async fn store_result(
+    results: &ResultStore,
+    response: Response,
+    max_bytes: usize,
+) -> Result<Response, AppError> {
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    // The body was built in-process, so reading it only fails over the limit
+    let body = axum::body::to_bytes(body, max_bytes).await.map_err(|e| {
+        AppError::ProviderError(format!(
+            "result too large: exceeds the limit of {} bytes ({})",
+            max_bytes, e
+        ))
+    })?;
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream");
+
+    let id = results.insert(content_type, body.clone());
+    tracing::info!(result_id = %id, size = body.len(), "Stored edit result");
+
+    let id = HeaderValue::from_str(&id)
+        .map_err(|e| AppError::InternalServer(format!("Invalid result id: {}", e)))?;
+    parts.headers.insert(RESULT_ID_HEADER, id);
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_store_result_tags_successful_responses() {
+        let results = ResultStore::new();
+        let response = Response::builder()
+            .header(header::CONTENT_TYPE, "image/webp")
+            .body(Body::from("image"))
+            .unwrap();
+
+        let response = store_result(&results, response, 1024).await.unwrap();
+
+        let id = response.headers()[RESULT_ID_HEADER].to_str().unwrap();
+        let stored = results.get(id).unwrap();
+        assert_eq!(stored.content_type, "image/webp");
+        assert_eq!(stored.body, Bytes::from_static(b"image"));
+    }
+
+    #[tokio::test]
+    async fn test_store_result_ignores_other_statuses() {
+        let results = ResultStore::new();
+        let response = Response::builder()
+            .status(StatusCode::ACCEPTED)
+            .body(Body::from("{}"))
+            .unwrap();
+
+        let response = store_result(&results, response, 1024).await.unwrap();
+
+        assert!(!response.headers().contains_key(RESULT_ID_HEADER));
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_result_refuses_bodies_over_the_limit() {
+        let results = ResultStore::new();
+        let response = Response::builder().body(Body::from("image")).unwrap();
+
+        let err = store_result(&results, response, 4).await.unwrap_err();
+
+        assert!(matches!(err, AppError::ProviderError(_)));
+        assert!(results.is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Upload endpoints for images too large to send comfortably as multipart
+//!
+//! Available when the `uploads` feature flag is enabled:
+//! - `POST /api/uploads` reserves an upload and answers `201 Created` with its
+//!   id and the URL to PUT the bytes to
+//! - `PUT /api/uploads/{id}` stores the raw image bytes as the request body
+//!
+//! `/api/edit` then references the image with an `upload_id` field instead of
+//! an `images` part. Uploads are kept in the in-memory `UploadStore`; see
+//! `crate::uploads`.
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use bytes::Bytes;
+use std::sync::Arc;
+
+use crate::error::AppError;
+use crate::models::response::UploadResponse;
+use crate::uploads::UploadStore;
+use crate::utils::image_utils;
+
+/// Reserve an upload
+///
+/// # Endpoint
+///
+/// `POST /api/uploads`
+///
+/// # Response
+///
+/// `201 Created` with a `Location` header and an [`UploadResponse`] body. The
+/// upload expires after `UPLOAD_TTL_SECS`, whether or not its bytes were sent.
+pub async fn create_upload(State(uploads): State<Arc<UploadStore>>) -> Result<Response, AppError> {
+    let upload_id = uploads.create();
+    let upload_url = format!("/api/uploads/{}", upload_id);
+    tracing::info!(upload_id = %upload_id, "Reserved upload");
+
+    let location = HeaderValue::from_str(&upload_url)
+        .map_err(|e| AppError::InternalServer(format!("Invalid upload location: {}", e)))?;
+    let body = UploadResponse {
+        upload_id,
+        upload_url,
+        expires_in_secs: uploads.ttl().as_secs(),
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(body),
+    )
+        .into_response())
+}
+
+/// Store the bytes of a reserved upload
+///
+/// # Endpoint
+///
+/// `PUT /api/uploads/{id}`, with the raw image as the body. The body is
+/// subject to `MAX_UPLOAD_BYTES` like any other request.
+///
+/// # Response
+///
+/// `204 No Content`. Sending the bytes again replaces them.
+///
+/// # Errors
+///
+/// - `400 Bad Request`: Empty body or not a supported image
+/// - `404 Not Found`: Unknown or expired upload id
+/// - `413 Payload Too Large`: Body over `MAX_UPLOAD_SIZE_BYTES`
+/// - `503 Service Unavailable`: Pending uploads already hold
+///   `MAX_PENDING_UPLOAD_BYTES`; other clients' uploads are never evicted
+pub async fn put_upload(
+    State(uploads): State<Arc<UploadStore>>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    if body.is_empty() {
+        return Err(AppError::InvalidInput("upload body is empty".to_string()));
+    }
+    image_utils::validate_image_bytes(&body)?;
+
+    let size = body.len();
+    uploads.put(&id, body)?;
+    tracing::info!(upload_id = %id, size, "Stored upload");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Image bytes of an upload referenced by an edit request
+///
+/// # Errors
+///
+/// Returns `AppError::NotFound` for unknown or expired ids, and
+/// `AppError::InvalidInput` if the bytes have not been sent yet.
+pub(crate) fn uploaded_image(uploads: &UploadStore, id: &str) -> Result<Bytes, AppError> {
+    let upload = uploads
+        .get(id)
+        .ok_or_else(|| AppError::NotFound(format!("upload '{}'", id)))?;
+    upload
+        .data
+        .ok_or_else(|| AppError::InvalidInput(format!("upload '{}' has no image yet", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_uploaded_image_requires_bytes() {
+        let uploads = UploadStore::new(Duration::from_secs(60));
+        let id = uploads.create();
+
+        assert!(matches!(
+            uploaded_image(&uploads, &id),
+            Err(AppError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            uploaded_image(&uploads, "missing"),
+            Err(AppError::NotFound(_))
+        ));
+
+        uploads.put(&id, Bytes::from_static(b"image")).unwrap();
+        assert_eq!(
+            uploaded_image(&uploads, &id).unwrap(),
+            Bytes::from_static(b"image")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_put_rejects_non_image_body() {
+        let uploads = Arc::new(UploadStore::new(Duration::from_secs(60)));
+        let id = uploads.create();
+
+        let err = put_upload(
+            State(uploads),
+            Path(id),
+            Bytes::from_static(b"not an image"),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, AppError::ImageProcessing(_)));
+    }
+}
@@ -24,11 +24,129 @@
 //! ```
 
 use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Non-sensitive details a provider reported about an edit
+///
+/// Returned to clients that opt in with `include_metadata=true`, to help debug
+/// model behavior. Fields a provider does not report stay `None`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProviderMetadata {
+    /// Seed the model used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Model inference time in milliseconds, as measured by the provider
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inference_time_ms: Option<u64>,
+    /// Model (and version) that produced the result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+}
+
+/// Sampling and generation parameters for providers whose models accept them
+///
+/// Unset fields keep the provider's defaults. Google Gemini takes
+/// `temperature` and `top_p`; Fal.ai models take `seed`,
+/// `num_inference_steps` and `guidance_scale`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SamplingOptions {
+    /// Sampling temperature
+    pub temperature: Option<f64>,
+    /// Nucleus sampling probability mass (top-p)
+    pub top_p: Option<f64>,
+    /// Seed for reproducible results
+    pub seed: Option<u32>,
+    /// Number of denoising steps
+    pub num_inference_steps: Option<u32>,
+    /// Classifier-free guidance scale
+    pub guidance_scale: Option<f64>,
+}
+
+/// Provider-specific request fields passed through as given, keyed by field name
+///
+/// Validated by the edit request (an object of scalars); each editor decides
+/// where, and whether, they go in its outbound request.
+pub type ProviderOptions = serde_json::Map<String, serde_json::Value>;
+
+/// Part of an image to edit, independent of how a provider expresses masks
+///
+/// Coordinates are fractions of the image's width and height, so the same
+/// region applies to an input whatever its resolution (and still applies if
+/// it is downscaled before the edit). Each editor translates the region to
+/// its provider's format: a mask image, an alpha channel or a bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EditRegion {
+    /// Left edge, from 0.0 (left of the image) to 1.0
+    pub x: f64,
+    /// Top edge, from 0.0 (top of the image) to 1.0
+    pub y: f64,
+    /// Width as a fraction of the image width
+    pub width: f64,
+    /// Height as a fraction of the image height
+    pub height: f64,
+}
+
+impl EditRegion {
+    /// Pixel bounds `(left, top, right, bottom)` of the region in a
+    /// `width` x `height` image, right and bottom exclusive
+    ///
+    /// Edges are rounded outwards, so the bounds cover every pixel the region
+    /// touches and at least one pixel.
+    pub fn pixel_bounds(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let scale = |fraction: f64, size: u32| fraction * f64::from(size);
+        let left = (scale(self.x, width).floor() as u32).min(width.saturating_sub(1));
+        let top = (scale(self.y, height).floor() as u32).min(height.saturating_sub(1));
+        let right = (scale(self.x + self.width, width).ceil() as u32).clamp(left + 1, width.max(1));
+        let bottom =
+            (scale(self.y + self.height, height).ceil() as u32).clamp(top + 1, height.max(1));
+        (left, top, right, bottom)
+    }
+}
+
+impl FromStr for EditRegion {
+    type Err = String;
+
+    /// Parse `x,y,width,height` fractions, e.g. `0.25,0.5,0.5,0.25`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("expected x,y,width,height as numbers, got '{}'", s))?;
+        let [x, y, width, height] = values[..] else {
+            return Err(format!(
+                "expected 4 values (x,y,width,height), got {}",
+                values.len()
+            ));
+        };
+
+        let fractions = [x, y, width, height];
+        if fractions.iter().any(|value| !(0.0..=1.0).contains(value)) {
+            return Err("values must be fractions of the image size, between 0 and 1".to_string());
+        }
+        if width == 0.0 || height == 0.0 {
+            return Err("width and height must be greater than 0".to_string());
+        }
+        // Tolerate rounding in client-computed fractions
+        if x + width > 1.0 + f64::EPSILON || y + height > 1.0 + f64::EPSILON {
+            return Err("region must lie within the image".to_string());
+        }
+
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
 
 /// Core trait for image editing services
 ///
 /// This trait defines the interface that all AI image editing providers must implement.
-/// It provides a single method for editing images based on text prompts.
+/// Its core method edits a single image based on a text prompt; multi-image
+/// requests go through `edit_images`.
 ///
 /// # Thread Safety
 ///
@@ -80,4 +198,143 @@ pub trait ImageEditor: Send + Sync {
     /// }
     /// ```
     async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes, anyhow::Error>;
+
+    /// Edit a request with several input images
+    ///
+    /// Editors that only work on one image keep the default, which edits the
+    /// first image and ignores the rest. Editors that combine their inputs,
+    /// such as the composite editor, override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `images` is empty or the edit fails.
+    async fn edit_images(&self, images: Vec<Bytes>, prompt: &str) -> Result<Bytes, anyhow::Error> {
+        let first = images
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("at least one image is required"))?;
+        self.edit_image(first, prompt).await
+    }
+
+    /// Edit like `edit_images`, also returning what the provider reported about the edit
+    ///
+    /// The default reports no metadata; providers whose responses carry seeds,
+    /// timings or model versions override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `images` is empty or the edit fails.
+    async fn edit_images_with_metadata(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+    ) -> Result<(Bytes, ProviderMetadata), anyhow::Error> {
+        let result = self.edit_images(images, prompt).await?;
+        Ok((result, ProviderMetadata::default()))
+    }
+
+    /// Use `sampling` for the edits that follow
+    ///
+    /// Editors whose provider takes no sampling parameters keep the default,
+    /// which ignores them.
+    fn set_sampling(&mut self, _sampling: SamplingOptions) {}
+
+    /// Add `options` to the provider requests of the edits that follow
+    ///
+    /// Editors whose provider takes no free-form parameters keep the default,
+    /// which ignores them.
+    fn set_provider_options(&mut self, _options: ProviderOptions) {}
+
+    /// Whether the editor takes images with more than 8 bits per channel
+    ///
+    /// The default is `false`: provider APIs reject or mangle 16-bit and
+    /// floating-point images, so callers convert or refuse those first.
+    fn accepts_high_bit_depth(&self) -> bool {
+        false
+    }
+
+    /// Whether the editor can limit an edit to an `EditRegion`
+    fn supports_regions(&self) -> bool {
+        false
+    }
+
+    /// Edit like `edit_images_with_metadata`, changing only `region` of the
+    /// first image
+    ///
+    /// Editors that support regions override this together with
+    /// `supports_regions`; the default fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the editor doesn't support regions, `images` is
+    /// empty or the edit fails.
+    async fn edit_region(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+        region: &EditRegion,
+    ) -> Result<(Bytes, ProviderMetadata), anyhow::Error> {
+        let _ = (images, prompt, region);
+        Err(anyhow::anyhow!(
+            "region editing is not supported by this provider"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_parsed_from_fractions() {
+        let region: EditRegion = "0.25, 0.5, 0.5, 0.25".parse().unwrap();
+
+        assert_eq!(
+            region,
+            EditRegion {
+                x: 0.25,
+                y: 0.5,
+                width: 0.5,
+                height: 0.25
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_regions_rejected() {
+        for input in [
+            "0.1,0.1,0.5",
+            "a,0,1,1",
+            "0,0,0,1",
+            "-0.1,0,0.5,0.5",
+            "0.6,0,0.5,0.5",
+            "0,0,1.5,1",
+        ] {
+            assert!(
+                input.parse::<EditRegion>().is_err(),
+                "expected {:?} to be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_pixel_bounds_round_outwards() {
+        let region = EditRegion {
+            x: 0.1,
+            y: 0.0,
+            width: 0.25,
+            height: 1.0,
+        };
+
+        assert_eq!(region.pixel_bounds(10, 4), (1, 0, 4, 4));
+        // A sliver still covers one pixel
+        let sliver = EditRegion {
+            x: 0.5,
+            y: 0.5,
+            width: 0.001,
+            height: 0.001,
+        };
+        assert_eq!(sliver.pixel_bounds(10, 10), (5, 5, 6, 6));
+    }
 }
@@ -0,0 +1,311 @@
+//! Catalog of known provider models
+//!
+//! The catalog describes the models FrameForge knows about ahead of time, with
+//! the metadata clients need before uploading: recommended and maximum input
+//! dimensions, the output formats each model can produce, and the generation
+//! parameters it accepts (see [`Capabilities`]). Any other Fal.ai
+//! model can still be used through the dynamic `fal:<model-path>` provider; it
+//! just has no catalog entry.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::AppConfig;
+use crate::models::request::{GUIDANCE_SCALE_RANGE, INFERENCE_STEPS_RANGE};
+
+/// Metadata for a known model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelInfo {
+    /// Provider name to pass as the `provider` field (e.g. `fal:fal-ai/flux-kontext/dev`)
+    pub provider: &'static str,
+    /// Human-readable model name
+    pub name: &'static str,
+    /// Longest input side (in pixels) the model works best with
+    pub recommended_input_dimension: u32,
+    /// Longest input side (in pixels) the model accepts
+    pub max_input_dimension: u32,
+    /// Output formats the model can return
+    pub output_formats: &'static [&'static str],
+    /// Longest prompt (in characters, prefix and suffix included) the model
+    /// handles; `MAX_PROMPT_CHARS` still applies when it is lower
+    pub max_prompt_chars: usize,
+    /// Optional generation parameters the model accepts
+    pub capabilities: Capabilities,
+}
+
+/// Optional generation parameters a model accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Capabilities {
+    /// Deterministic `seed`
+    pub seed: bool,
+    /// Number of denoising steps (`num_inference_steps`)
+    pub steps: bool,
+    /// Classifier-free guidance scale (`guidance_scale`)
+    pub guidance_scale: bool,
+}
+
+/// Schema of a single request parameter, for building dynamic forms
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamSpec {
+    /// Parameter name
+    pub name: &'static str,
+    /// JSON type: `string`, `integer`, or `number`
+    #[serde(rename = "type")]
+    pub param_type: &'static str,
+    /// Human-readable description
+    pub description: &'static str,
+    /// Whether the parameter must be provided
+    pub required: bool,
+    /// Smallest accepted value (numeric parameters)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    /// Largest accepted value (numeric parameters)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// Value used when the parameter is omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+impl ModelInfo {
+    /// Parameter schema derived from the model's capabilities
+    ///
+    /// Every model accepts `prompt`; capability parameters follow.
+    pub fn params(&self) -> Vec<ParamSpec> {
+        let mut params = vec![ParamSpec {
+            name: "prompt",
+            param_type: "string",
+            description: "Editing instructions",
+            required: false,
+            minimum: None,
+            maximum: None,
+            default: None,
+        }];
+
+        if self.capabilities.seed {
+            params.push(ParamSpec {
+                name: "seed",
+                param_type: "integer",
+                description: "Seed for reproducible results; random when omitted",
+                required: false,
+                minimum: Some(0.0),
+                maximum: Some(f64::from(u32::MAX)),
+                default: None,
+            });
+        }
+
+        if self.capabilities.steps {
+            params.push(ParamSpec {
+                name: "num_inference_steps",
+                param_type: "integer",
+                description: "Number of denoising steps",
+                required: false,
+                minimum: Some(f64::from(*INFERENCE_STEPS_RANGE.start())),
+                maximum: Some(f64::from(*INFERENCE_STEPS_RANGE.end())),
+                default: Some(json!(28)),
+            });
+        }
+
+        if self.capabilities.guidance_scale {
+            params.push(ParamSpec {
+                name: "guidance_scale",
+                param_type: "number",
+                description: "How closely the result follows the prompt (CFG scale)",
+                required: false,
+                minimum: Some(*GUIDANCE_SCALE_RANGE.start()),
+                maximum: Some(*GUIDANCE_SCALE_RANGE.end()),
+                default: Some(json!(2.5)),
+            });
+        }
+
+        params
+    }
+
+    /// Whether the model's provider has an API key configured
+    pub fn is_available(&self, config: &AppConfig) -> bool {
+        if self.provider.starts_with("fal:") {
+            config.fal_key.is_some()
+        } else {
+            config.get_google_api_key().is_some()
+        }
+    }
+}
+
+/// All known models
+pub static MODELS: &[ModelInfo] = &[
+    ModelInfo {
+        provider: "google",
+        name: "Google Gemini (Nano Banana)",
+        recommended_input_dimension: 1024,
+        max_input_dimension: 3072,
+        output_formats: &["png"],
+        max_prompt_chars: 8000,
+        capabilities: Capabilities {
+            seed: false,
+            steps: false,
+            guidance_scale: false,
+        },
+    },
+    ModelInfo {
+        provider: "fal:fal-ai/nano-banana/edit",
+        name: "Nano Banana Edit",
+        recommended_input_dimension: 1024,
+        max_input_dimension: 4096,
+        output_formats: &["png", "jpeg"],
+        max_prompt_chars: 5000,
+        capabilities: Capabilities {
+            seed: false,
+            steps: false,
+            guidance_scale: false,
+        },
+    },
+    ModelInfo {
+        provider: "fal:fal-ai/qwen-image-edit",
+        name: "Qwen Image Edit",
+        recommended_input_dimension: 1024,
+        max_input_dimension: 2048,
+        output_formats: &["png", "jpeg"],
+        max_prompt_chars: 2000,
+        capabilities: Capabilities {
+            seed: true,
+            steps: true,
+            guidance_scale: true,
+        },
+    },
+    ModelInfo {
+        provider: "fal:fal-ai/bytedance/seedream/v4/edit",
+        name: "Seedream v4 Edit",
+        recommended_input_dimension: 2048,
+        max_input_dimension: 4096,
+        output_formats: &["png", "jpeg"],
+        max_prompt_chars: 3000,
+        capabilities: Capabilities {
+            seed: true,
+            steps: false,
+            guidance_scale: false,
+        },
+    },
+    ModelInfo {
+        provider: "fal:fal-ai/flux-kontext/dev",
+        name: "FLUX.1 Kontext [dev]",
+        recommended_input_dimension: 1024,
+        max_input_dimension: 2048,
+        output_formats: &["png", "jpeg"],
+        max_prompt_chars: 2000,
+        capabilities: Capabilities {
+            seed: true,
+            steps: true,
+            guidance_scale: true,
+        },
+    },
+];
+
+/// Look up a model by provider name
+///
+/// Names are normalized the same way as in `factory::get_editor`, and
+/// `nano-banana` resolves to the Google entry.
+pub fn find_model(provider_name: &str) -> Option<&'static ModelInfo> {
+    let normalized_name = provider_name.trim().to_lowercase();
+    let normalized_name = match normalized_name.as_str() {
+        "nano-banana" => "google",
+        other => other,
+    };
+
+    MODELS
+        .iter()
+        .find(|model| model.provider == normalized_name)
+}
+
+/// Longest prompt accepted for `provider_name`: the lower of its catalog
+/// limit and `MAX_PROMPT_CHARS`
+pub fn max_prompt_chars(config: &AppConfig, provider_name: &str) -> usize {
+    find_model(provider_name).map_or(config.max_prompt_chars, |model| {
+        model.max_prompt_chars.min(config.max_prompt_chars)
+    })
+}
+
+/// Models whose provider has an API key configured
+pub fn available_models(config: &AppConfig) -> Vec<&'static ModelInfo> {
+    MODELS
+        .iter()
+        .filter(|model| model.is_available(config))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_model_reports_dimensions() {
+        let model = find_model("fal:fal-ai/flux-kontext/dev").unwrap();
+        assert_eq!(model.recommended_input_dimension, 1024);
+        assert_eq!(model.max_input_dimension, 2048);
+        assert_eq!(model.output_formats, &["png", "jpeg"]);
+    }
+
+    #[test]
+    fn test_find_model_normalizes_names() {
+        assert_eq!(find_model(" Nano-Banana ").unwrap().provider, "google");
+        assert!(find_model("FAL:fal-ai/qwen-image-edit").is_some());
+        assert!(find_model("fal:fal-ai/unknown").is_none());
+    }
+
+    #[test]
+    fn test_catalog_dimensions_are_consistent() {
+        for model in MODELS {
+            assert!(
+                model.recommended_input_dimension <= model.max_input_dimension,
+                "{}",
+                model.provider
+            );
+            assert!(!model.output_formats.is_empty(), "{}", model.provider);
+            assert!(model.max_prompt_chars > 0, "{}", model.provider);
+        }
+    }
+
+    #[test]
+    fn test_prompt_limit_bounded_by_global_limit() {
+        let config = AppConfig {
+            max_prompt_chars: 4000,
+            ..AppConfig::default()
+        };
+
+        assert_eq!(max_prompt_chars(&config, "google"), 4000);
+        assert_eq!(
+            max_prompt_chars(&config, "fal:fal-ai/flux-kontext/dev"),
+            2000
+        );
+        assert_eq!(max_prompt_chars(&config, "fal:fal-ai/unknown"), 4000);
+    }
+
+    #[test]
+    fn test_available_models_follow_configured_keys() {
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            ..AppConfig::default()
+        };
+
+        let models = available_models(&config);
+        assert!(!models.is_empty());
+        assert!(models
+            .iter()
+            .all(|model| model.provider.starts_with("fal:")));
+    }
+
+    #[test]
+    fn test_params_follow_capabilities() {
+        let google = find_model("google").unwrap().params();
+        assert_eq!(
+            google.iter().map(|p| p.name).collect::<Vec<_>>(),
+            ["prompt"]
+        );
+
+        let kontext = find_model("fal:fal-ai/flux-kontext/dev").unwrap().params();
+        let names = kontext.iter().map(|p| p.name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["prompt", "seed", "num_inference_steps", "guidance_scale"]
+        );
+    }
+}
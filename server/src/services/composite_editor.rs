@@ -0,0 +1,278 @@
+//! Composite image editing service
+//!
+//! Arranges the uploaded images in a grid without calling an AI provider, for
+//! simple side-by-side or stacked layouts. Selected with the `"composite"`
+//! provider; layout parameters follow a colon as comma-separated `key=value`
+//! pairs:
+//!
+//! - `cols` - Images per row (default: all images in a single row)
+//! - `spacing` - Gap between cells in pixels (default: 0)
+//! - `bg` - Background as hex `RRGGBB` or `RRGGBBAA` (default: transparent)
+//!
+//! For example `composite:cols=1,spacing=8,bg=ffffff` stacks the images
+//! vertically on white. Every cell is as large as the largest input, and
+//! smaller images are centered in their cell. The prompt is ignored.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+
+use crate::services::base::ImageEditor;
+use crate::utils::image_utils;
+
+/// Grid layout of a composite
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompositeLayout {
+    /// Images per row; `None` puts every image in one row
+    pub cols: Option<u32>,
+    /// Gap between adjacent cells in pixels
+    pub spacing: u32,
+    /// Color of the gaps and of cell areas not covered by an image
+    pub background: [u8; 4],
+}
+
+impl FromStr for CompositeLayout {
+    type Err = anyhow::Error;
+
+    /// Parse `cols=2,spacing=8,bg=ffffff`; an empty string is the default layout
+    fn from_str(s: &str) -> Result<Self> {
+        let mut layout = CompositeLayout::default();
+
+        for param in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| anyhow!("composite parameter '{}' is not key=value", param))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "cols" => {
+                    let cols: u32 = value
+                        .parse()
+                        .with_context(|| format!("invalid composite cols '{}'", value))?;
+                    if cols == 0 {
+                        bail!("composite cols must be at least 1");
+                    }
+                    layout.cols = Some(cols);
+                }
+                "spacing" => {
+                    layout.spacing = value
+                        .parse()
+                        .with_context(|| format!("invalid composite spacing '{}'", value))?;
+                }
+                "bg" => layout.background = parse_hex_color(value)?,
+                other => bail!("unknown composite parameter '{}'", other),
+            }
+        }
+
+        Ok(layout)
+    }
+}
+
+/// Parse `RRGGBB` or `RRGGBBAA`, with an optional leading `#`
+fn parse_hex_color(value: &str) -> Result<[u8; 4]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!(
+            "composite bg '{}' must be a hex RRGGBB or RRGGBBAA color",
+            value
+        );
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap_or(0);
+    let alpha = if hex.len() == 8 { channel(3) } else { 255 };
+    Ok([channel(0), channel(1), channel(2), alpha])
+}
+
+/// Image editor that tiles its inputs into a single PNG
+#[derive(Debug, Clone)]
+pub struct CompositeEditor {
+    layout: CompositeLayout,
+    max_dimension: u32,
+}
+
+impl CompositeEditor {
+    /// Create a composite editor
+    ///
+    /// Composites wider or taller than `max_dimension` pixels are rejected.
+    pub fn new(layout: CompositeLayout, max_dimension: u32) -> Self {
+        Self {
+            layout,
+            max_dimension,
+        }
+    }
+
+    /// Tile decoded images into one canvas
+    fn compose(&self, images: &[DynamicImage]) -> Result<RgbaImage> {
+        let count = images.len() as u64;
+        let cols = u64::from(self.layout.cols.unwrap_or(images.len() as u32)).min(count);
+        let rows = count.div_ceil(cols);
+        let spacing = u64::from(self.layout.spacing);
+
+        let cell_w = images.iter().map(|img| img.width()).max().unwrap_or(0);
+        let cell_h = images.iter().map(|img| img.height()).max().unwrap_or(0);
+        let width = cols * u64::from(cell_w) + (cols - 1) * spacing;
+        let height = rows * u64::from(cell_h) + (rows - 1) * spacing;
+
+        if width > u64::from(self.max_dimension) || height > u64::from(self.max_dimension) {
+            bail!(
+                "composite of {}x{} exceeds the maximum dimension of {}",
+                width,
+                height,
+                self.max_dimension
+            );
+        }
+
+        let mut canvas =
+            RgbaImage::from_pixel(width as u32, height as u32, Rgba(self.layout.background));
+        for (index, img) in images.iter().enumerate() {
+            let (col, row) = (index as u64 % cols, index as u64 / cols);
+            let (img_w, img_h) = img.dimensions();
+            let x = col * (u64::from(cell_w) + spacing) + u64::from((cell_w - img_w) / 2);
+            let y = row * (u64::from(cell_h) + spacing) + u64::from((cell_h - img_h) / 2);
+            image::imageops::overlay(&mut canvas, &img.to_rgba8(), x as i64, y as i64);
+        }
+
+        Ok(canvas)
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageEditor for CompositeEditor {
+    /// A composite of one image: the image re-encoded as PNG
+    async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
+        self.edit_images(vec![image_bytes], prompt).await
+    }
+
+    async fn edit_images(&self, images: Vec<Bytes>, _prompt: &str) -> Result<Bytes> {
+        if images.is_empty() {
+            bail!("composite requires at least one image");
+        }
+
+        let decoded = images
+            .iter()
+            .map(|bytes| image_utils::bytes_to_image(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let canvas = self.compose(&decoded)?;
+
+        tracing::info!(
+            image_count = decoded.len(),
+            width = canvas.width(),
+            height = canvas.height(),
+            "Composited images"
+        );
+
+        Ok(image_utils::image_to_bytes(
+            &DynamicImage::ImageRgba8(canvas),
+            ImageFormat::Png,
+        )?)
+    }
+
+    /// Composites are decoded and re-encoded locally, at any bit depth
+    fn accepts_high_bit_depth(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, color: [u8; 4]) -> Bytes {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(color)));
+        image_utils::image_to_bytes(&img, ImageFormat::Png).unwrap()
+    }
+
+    async fn composite(layout: &str, images: Vec<Bytes>) -> Result<DynamicImage> {
+        let editor = CompositeEditor::new(layout.parse()?, 4096);
+        let output = editor.edit_images(images, "ignored").await?;
+        Ok(image::load_from_memory(&output)?)
+    }
+
+    #[test]
+    fn test_layout_parsing() {
+        assert_eq!(
+            "".parse::<CompositeLayout>().unwrap(),
+            CompositeLayout::default()
+        );
+
+        let layout: CompositeLayout = "cols=2, spacing=8, bg=#ff000080".parse().unwrap();
+        assert_eq!(layout.cols, Some(2));
+        assert_eq!(layout.spacing, 8);
+        assert_eq!(layout.background, [255, 0, 0, 128]);
+        assert_eq!(
+            "bg=ffffff".parse::<CompositeLayout>().unwrap().background,
+            [255; 4]
+        );
+
+        for invalid in [
+            "cols=0",
+            "cols=two",
+            "spacing",
+            "bg=fff",
+            "bg=gggggg",
+            "rows=2",
+        ] {
+            assert!(
+                invalid.parse::<CompositeLayout>().is_err(),
+                "accepted {}",
+                invalid
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_images_side_by_side() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let img = composite("", vec![png(100, 100, red), png(100, 100, blue)])
+            .await
+            .unwrap();
+
+        assert_eq!(img.dimensions(), (200, 100));
+        assert_eq!(img.get_pixel(50, 50).0, red);
+        assert_eq!(img.get_pixel(150, 50).0, blue);
+    }
+
+    #[tokio::test]
+    async fn test_single_column_stacks_vertically() {
+        let images = vec![png(100, 100, [255; 4]), png(100, 100, [0, 0, 0, 255])];
+        let img = composite("cols=1", images).await.unwrap();
+        assert_eq!(img.dimensions(), (100, 200));
+    }
+
+    #[tokio::test]
+    async fn test_grid_spacing_and_background() {
+        let white = [255; 4];
+        let green = [0, 255, 0, 255];
+        let images = (0..3).map(|_| png(10, 10, white)).collect();
+        let img = composite("cols=2,spacing=4,bg=00ff00", images)
+            .await
+            .unwrap();
+
+        // Two rows of two cells with a 4px gap; the missing fourth cell is background
+        assert_eq!(img.dimensions(), (24, 24));
+        assert_eq!(img.get_pixel(12, 5).0, green);
+        assert_eq!(img.get_pixel(5, 18).0, white);
+        assert_eq!(img.get_pixel(18, 18).0, green);
+    }
+
+    #[tokio::test]
+    async fn test_smaller_images_centered_in_cell() {
+        let img = composite("", vec![png(10, 10, [255; 4]), png(4, 4, [0, 0, 0, 255])])
+            .await
+            .unwrap();
+
+        assert_eq!(img.dimensions(), (20, 10));
+        assert_eq!(img.get_pixel(15, 5).0, [0, 0, 0, 255]);
+        assert_eq!(img.get_pixel(15, 0).0[3], 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_composite() {
+        let editor = CompositeEditor::new(CompositeLayout::default(), 150);
+        let images = vec![png(100, 100, [255; 4]), png(100, 100, [255; 4])];
+        assert!(editor.edit_images(images, "").await.is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! Size-bounded LRU cache for downloaded provider results
+//!
+//! Editors are built per request, so the Fal editor shares one process-wide
+//! cache between them. It avoids re-fetching the same result URL when it is
+//! downloaded more than once within the TTL, e.g. on retries, when several
+//! variants point at the same file, or when Fal answers repeated edits with
+//! the same result URL.
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Hits across every download cache in the process
+static HITS: AtomicU64 = AtomicU64::new(0);
+
+/// Misses across every download cache in the process
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// `(hits, misses)` of every download cache since startup
+///
+/// Reported by `GET /api/admin/stats`.
+pub fn lookup_counts() -> (u64, u64) {
+    (HITS.load(Ordering::Relaxed), MISSES.load(Ordering::Relaxed))
+}
+
+/// A cached download
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    url: String,
+    bytes: Bytes,
+    mime_type: Option<String>,
+    inserted: Instant,
+}
+
+/// LRU cache of downloaded bytes keyed on URL
+///
+/// Entries expire after `ttl`, and the least recently used entries are evicted
+/// once the cached bytes exceed `max_bytes`. Downloads larger than `max_bytes`
+/// are never cached.
+#[derive(Debug)]
+pub struct DownloadCache {
+    /// Entries ordered from least to most recently used
+    entries: VecDeque<CacheEntry>,
+    max_bytes: usize,
+    ttl: Duration,
+    total_bytes: usize,
+}
+
+impl DownloadCache {
+    /// Create an empty cache
+    pub fn new(max_bytes: usize, ttl: Duration) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_bytes,
+            ttl,
+            total_bytes: 0,
+        }
+    }
+
+    /// Look up a URL, marking it as most recently used
+    pub fn get(&mut self, url: &str) -> Option<(Bytes, Option<String>)> {
+        self.evict_expired();
+
+        let Some(index) = self.entries.iter().position(|entry| entry.url == url) else {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        HITS.fetch_add(1, Ordering::Relaxed);
+        let entry = self.entries.remove(index)?;
+        let hit = (entry.bytes.clone(), entry.mime_type.clone());
+        self.entries.push_back(entry);
+        Some(hit)
+    }
+
+    /// Cache a download, evicting least recently used entries to stay in budget
+    pub fn insert(&mut self, url: &str, bytes: Bytes, mime_type: Option<String>) {
+        if bytes.len() > self.max_bytes {
+            return;
+        }
+
+        self.remove(url);
+        self.total_bytes += bytes.len();
+        self.entries.push_back(CacheEntry {
+            url: url.to_string(),
+            bytes,
+            mime_type,
+            inserted: Instant::now(),
+        });
+
+        while self.total_bytes > self.max_bytes {
+            match self.entries.pop_front() {
+                Some(evicted) => self.total_bytes -= evicted.bytes.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Total size of the cached downloads
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Number of cached downloads
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no downloads
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.url == url) {
+            if let Some(entry) = self.entries.remove(index) {
+                self.total_bytes -= entry.bytes.len();
+            }
+        }
+    }
+
+    fn evict_expired(&mut self) {
+        let ttl = self.ttl;
+        let total_bytes = &mut self.total_bytes;
+        self.entries.retain(|entry| {
+            let fresh = entry.inserted.elapsed() < ttl;
+            if !fresh {
+                *total_bytes -= entry.bytes.len();
+            }
+            fresh
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn bytes(len: usize) -> Bytes {
+        Bytes::from(vec![0u8; len])
+    }
+
+    #[test]
+    fn test_hit_returns_cached_bytes() {
+        let mut cache = DownloadCache::new(100, TTL);
+        cache.insert(
+            "https://fal.media/a.png",
+            bytes(10),
+            Some("image/png".to_string()),
+        );
+
+        let (hit, mime) = cache.get("https://fal.media/a.png").unwrap();
+
+        assert_eq!(hit.len(), 10);
+        assert_eq!(mime.as_deref(), Some("image/png"));
+        assert!(cache.get("https://fal.media/b.png").is_none());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_over_budget() {
+        let mut cache = DownloadCache::new(25, TTL);
+        cache.insert("a", bytes(10), None);
+        cache.insert("b", bytes(10), None);
+        // Touch "a" so "b" becomes the eviction candidate
+        assert!(cache.get("a").is_some());
+        cache.insert("c", bytes(10), None);
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+        assert_eq!(cache.total_bytes(), 20);
+    }
+
+    #[test]
+    fn test_skips_entries_larger_than_budget() {
+        let mut cache = DownloadCache::new(25, TTL);
+        cache.insert("huge", bytes(26), None);
+
+        assert!(cache.is_empty());
+        assert_eq!(cache.total_bytes(), 0);
+    }
+
+    #[test]
+    fn test_reinsert_replaces_entry() {
+        let mut cache = DownloadCache::new(100, TTL);
+        cache.insert("a", bytes(10), None);
+        cache.insert("a", bytes(30), None);
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.total_bytes(), 30);
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let mut cache = DownloadCache::new(100, Duration::ZERO);
+        cache.insert("a", bytes(10), None);
+
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.total_bytes(), 0);
+    }
+}
@@ -9,41 +9,86 @@
 //! ## Static Providers
 //! - `"google"` - Google Gemini (Nano Banana) editor
 //! - `"nano-banana"` - Alias for Google Gemini editor
+//! - `"local"` - On-server model from `LOCAL_MODEL_PATH`; requires the
+//!   `local-model` cargo feature
 //!
 //! ## Dynamic Providers
 //! - `"fal:*"` - Fal.ai models with dynamic model path
 //!   - Example: `"fal:fal-ai/flux/dev"`
 //!   - Example: `"fal:fal-ai/flux-pro"`
+//!   - Fal URLs pasted instead (`"fal:https://fal.run/fal-ai/flux/dev"`,
+//!     `"https://fal.run/fal-ai/flux/dev"`) are normalized to the model path
+//!     or rejected, per `FAL_URL_PROVIDER`
+//! - `"composite"` / `"composite:*"` - Grid of the uploaded images, no AI and
+//!   no API key; layout parameters follow the colon
+//!   - Example: `"composite:cols=1,spacing=8,bg=ffffff"`
+//! - `"webhook:*"` - User-run HTTP endpoint; the `http`/`https` URL follows
+//!   the colon and keeps its case. Requires the `webhooks` feature
+//!   - Example: `"webhook:https://my-model.example.com/edit"`
 //!
-//! # Default Provider
+//! # Mock Mode
 //!
-//! If an unknown provider is requested, the factory defaults to the Google
-//! Gemini editor to ensure graceful degradation.
+//! When `AppConfig.mock_provider` is enabled, every provider name resolves to
+//! the passthrough `MockEditor`. This is used by the integration tests to run
+//! the full edit pipeline without network access. The field only exists in
+//! tests and builds with the `test-mock` feature.
+//!
+//! # Fallback Provider
+//!
+//! If an unknown provider is requested, the factory uses
+//! `AppConfig.fallback_provider` (Google by default) to ensure graceful
+//! degradation. With no fallback configured, unknown providers are rejected.
+//! `validate_fallback_provider` checks the fallback is usable at startup.
+//!
+//! # Dev Mode
+//!
+//! When `AppConfig.dev_mode` is enabled, `get_editor_or_dev_fallback` serves
+//! requests for unavailable providers with the `MockEditor` instead of
+//! rejecting them, so a frontend can be developed without API keys.
 //!
 //! # Example Usage
 //!
 //! ```rust,no_run
+//! use frameforge_server::config::AppConfig;
 //! use frameforge_server::services::factory::{get_editor, list_providers};
 //!
+//! let config = AppConfig::load().unwrap();
+//!
 //! // List all available providers
-//! let providers = list_providers();
+//! let providers = list_providers(&config);
 //! println!("Available providers: {:?}", providers);
 //!
 //! // Get a specific editor
-//! let editor = get_editor("google")?;
+//! let editor = get_editor("google", &config)?;
+//! # Ok::<(), frameforge_server::error::AppError>(())
 //! ```
 
 use super::base::ImageEditor;
+use super::composite_editor::{CompositeEditor, CompositeLayout};
 use super::fal_editor::FalEditor;
 use super::google_nano_banana::GoogleNanaBananaEditor;
-use crate::config::AppConfig;
+#[cfg(feature = "local-model")]
+use super::local_editor::LocalEditor;
+use super::mock_editor::MockEditor;
+use super::webhook_editor::{self, WebhookEditor};
+use crate::config::{AppConfig, FalUrlProvider, UnknownProviderLog};
 use crate::error::AppError;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+/// Most unknown provider names whose occurrences `UnknownProviderLog::Once` counts
+const MAX_TRACKED_UNKNOWN_PROVIDERS: usize = 1024;
+
+/// Requests so far per unknown provider name, for `UnknownProviderLog::Once`
+static UNKNOWN_PROVIDERS: LazyLock<Mutex<HashMap<String, u64>>> = LazyLock::new(Default::default);
 
 /// List all statically available image editor providers
 ///
-/// This function returns a sorted list of provider names that can be used
+/// This function returns an ordered list of provider names that can be used
 /// with the `get_editor()` function. The list is dynamically generated based
-/// on which API keys are configured in the provided config.
+/// on which API keys are configured in the provided config. Providers named in
+/// `config.provider_order` come first, in that order; the rest follow
+/// alphabetically, so frontends can treat the first entry as the default.
 ///
 /// Note that dynamic `fal:*` providers are NOT listed here - any valid
 /// Fal.ai model path can be used with the `fal:` prefix at runtime.
@@ -61,7 +106,7 @@ use crate::error::AppError;
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,no_run
 /// use frameforge_server::services::factory::list_providers;
 /// use frameforge_server::config::AppConfig;
 ///
@@ -79,7 +124,16 @@ pub fn list_providers(config: &AppConfig) -> Vec<String> {
         providers.push("nano-banana".to_string());
     }
 
-    providers.sort();
+    if local_available(config).is_ok() {
+        providers.push("local".to_string());
+    }
+
+    providers.sort_by(|a, b| {
+        config
+            .provider_rank(a)
+            .cmp(&config.provider_rank(b))
+            .then_with(|| a.cmp(b))
+    });
     providers
 }
 
@@ -116,15 +170,15 @@ pub fn list_providers(config: &AppConfig) -> Vec<String> {
 /// - Requires FAL_KEY to be configured
 ///
 /// ## Unknown Providers
-/// If a provider is not recognized, the function defaults to the Google Gemini editor
-/// to ensure graceful degradation (if Google API key is available).
+/// If a provider is not recognized, the function resolves `config.fallback_provider`
+/// instead (Google by default) to ensure graceful degradation.
 ///
 /// # Errors
 ///
 /// Returns `AppError::ProviderNotFound` if:
 /// - Invalid fal: format (empty model path)
 /// - Required API key is not configured
-/// - Unknown provider and no Google API key for fallback
+/// - Unknown provider and the fallback is disabled or unavailable
 ///
 /// # Examples
 ///
@@ -140,49 +194,57 @@ pub fn list_providers(config: &AppConfig) -> Vec<String> {
 /// // Get Fal.ai editor with specific model
 /// let fal_editor = get_editor("fal:fal-ai/flux/dev", &config)?;
 ///
-/// // Unknown provider defaults to Google (if available)
+/// // Unknown provider uses the configured fallback (Google by default)
 /// let default_editor = get_editor("unknown-provider", &config)?;
 /// # Ok::<(), frameforge_server::error::AppError>(())
 /// ```
-pub fn get_editor(provider_name: &str, config: &AppConfig) -> Result<Box<dyn ImageEditor>, AppError> {
+pub fn get_editor(
+    provider_name: &str,
+    config: &AppConfig,
+) -> Result<Box<dyn ImageEditor>, AppError> {
+    // Mock mode short-circuits provider resolution entirely
+    if mock_provider_enabled(config) {
+        tracing::debug!(
+            provider = provider_name,
+            "Mock provider enabled, using mock editor"
+        );
+        return Ok(Box::new(MockEditor::new()));
+    }
+
     // Normalize provider name: lowercase and trim whitespace (matches Python behavior)
     let normalized_name = provider_name.trim().to_lowercase();
 
-    // Handle dynamic fal: providers
-    if normalized_name.starts_with("fal:") {
-        // Extract model path from "fal:model-path" format using normalized name
-        let model_path = normalized_name
-            .strip_prefix("fal:")
-            .ok_or_else(|| {
-                AppError::ProviderNotFound(format!(
-                    "Invalid fal provider format: {}. Expected format: fal:model-path",
-                    normalized_name
-                ))
-            })?
-            .trim();
-
-        // Validate model path is not empty
-        if model_path.is_empty() {
-            return Err(AppError::ProviderNotFound(
-                "Fal provider requires a model path. Format: fal:model-path".to_string(),
-            ));
-        }
+    if let Some(layout) = composite_layout(&normalized_name)? {
+        tracing::info!(provider = provider_name, layout = ?layout, "Created composite editor");
+        return Ok(Box::new(CompositeEditor::new(
+            layout,
+            config.max_output_dimension,
+        )));
+    }
 
+    if let Some(url) = webhook_url(provider_name, config)? {
+        let editor = WebhookEditor::new(url, config).map_err(|e| {
+            AppError::ProviderNotFound(format!("Failed to create webhook editor: {}", e))
+        })?;
+        return Ok(Box::new(editor));
+    }
+
+    // Handle dynamic fal: providers, including pasted fal URLs
+    if let Some(model_path) = fal_model_path(&normalized_name, config.fal_url_provider)? {
         // Check if FAL_KEY is configured
         if config.fal_key.is_none() {
-            return Err(AppError::ProviderNotFound(
-                "Fal provider requested but FAL_KEY is not configured in environment".to_string(),
-            ));
+            return Err(fal_key_missing());
         }
 
         // Create and return FalEditor
-        let editor = FalEditor::new(model_path.to_string(), config)
-            .map_err(|e| AppError::ProviderNotFound(format!("Failed to create Fal editor: {}", e)))?;
+        let editor = FalEditor::new(model_path.clone(), config).map_err(|e| {
+            AppError::ProviderNotFound(format!("Failed to create Fal editor: {}", e))
+        })?;
 
         tracing::info!(
             provider = provider_name,
             normalized = normalized_name,
-            model_path = model_path,
+            model_path = %model_path,
             "Created Fal.ai editor"
         );
 
@@ -194,9 +256,7 @@ pub fn get_editor(provider_name: &str, config: &AppConfig) -> Result<Box<dyn Ima
         "google" | "nano-banana" => {
             // Check if Google API key is configured
             if config.get_google_api_key().is_none() {
-                return Err(AppError::ProviderNotFound(
-                    "Google provider requested but GOOGLE_API_KEY/GEMINI_API_KEY is not configured in environment".to_string(),
-                ));
+                return Err(google_key_missing());
             }
 
             // Create and return GoogleNanaBananaEditor
@@ -210,33 +270,437 @@ pub fn get_editor(provider_name: &str, config: &AppConfig) -> Result<Box<dyn Ima
 
             Ok(Box::new(editor))
         }
-        // Default to Google provider for unknown names (graceful degradation)
+        "local" => local_editor(config),
+        // Use the configured fallback for unknown names (graceful degradation)
         _ => {
-            tracing::warn!(
-                provider = provider_name,
-                "Unknown provider requested, defaulting to Google Gemini"
-            );
+            let fallback = config.fallback_provider.as_deref().ok_or_else(|| {
+                AppError::ProviderNotFound(format!(
+                    "Provider '{}' not found and no fallback provider is configured",
+                    provider_name
+                ))
+            })?;
 
-            // Check if Google API key is configured for fallback
-            if config.get_google_api_key().is_none() {
+            // Guard against a fallback that would itself fall back
+            if !is_known_provider(fallback) {
                 return Err(AppError::ProviderNotFound(format!(
-                    "Provider '{}' not found and cannot fallback to Google (no API key configured)",
-                    provider_name
+                    "Provider '{}' not found and fallback provider '{}' is not a known provider",
+                    provider_name, fallback
                 )));
             }
 
-            // Return GoogleNanaBananaEditor as default
-            let editor = GoogleNanaBananaEditor::new(config.clone());
+            log_unknown_provider(config.unknown_provider_log, &normalized_name, fallback);
 
-            tracing::info!(
+            get_editor(fallback, config).map_err(|e| {
+                AppError::ProviderNotFound(format!(
+                    "Provider '{}' not found and fallback provider '{}' is unavailable: {}",
+                    provider_name, fallback, e
+                ))
+            })
+        }
+    }
+}
+
+/// Log a request for an unknown provider served by `fallback` at the configured level
+fn log_unknown_provider(mode: UnknownProviderLog, provider_name: &str, fallback: &str) {
+    match mode {
+        UnknownProviderLog::Warn => tracing::warn!(
+            provider = provider_name,
+            fallback = fallback,
+            "Unknown provider requested, using fallback provider"
+        ),
+        UnknownProviderLog::Debug => tracing::debug!(
+            provider = provider_name,
+            fallback = fallback,
+            "Unknown provider requested, using fallback provider"
+        ),
+        UnknownProviderLog::Once => match count_unknown_provider(provider_name) {
+            1 => tracing::warn!(
                 provider = provider_name,
-                fallback = "google",
-                model_id = %config.google_model_id,
-                "Defaulting to Google Nano Banana editor"
+                fallback = fallback,
+                "Unknown provider requested, using fallback provider (repeats are logged at DEBUG)"
+            ),
+            occurrences => tracing::debug!(
+                provider = provider_name,
+                fallback = fallback,
+                occurrences,
+                "Unknown provider requested, using fallback provider"
+            ),
+        },
+    }
+}
+
+/// Count a request for an unknown provider, returning its requests so far
+///
+/// Once `MAX_TRACKED_UNKNOWN_PROVIDERS` names are tracked, new names are not
+/// counted and `0` is returned, so probing clients can't grow the map without bound.
+fn count_unknown_provider(provider_name: &str) -> u64 {
+    let mut seen = UNKNOWN_PROVIDERS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(count) = seen.get_mut(provider_name) {
+        *count += 1;
+        return *count;
+    }
+    if seen.len() >= MAX_TRACKED_UNKNOWN_PROVIDERS {
+        return 0;
+    }
+    seen.insert(provider_name.to_string(), 1);
+    1
+}
+
+/// Check that a provider has the API key it needs, without creating an editor
+///
+/// A cheap early version of the checks `get_editor` performs, so requests for
+/// unusable providers can be rejected before their images are uploaded.
+/// Unknown names are checked against the fallback provider. Always succeeds
+/// in mock and dev mode, where unavailable providers are served by the mock
+/// editor.
+///
+/// # Errors
+///
+/// Returns `AppError::ProviderNotFound` in the same cases as `get_editor`.
+pub fn check_provider_available(provider_name: &str, config: &AppConfig) -> Result<(), AppError> {
+    if mock_provider_enabled(config) || config.dev_mode {
+        return Ok(());
+    }
+
+    let normalized_name = provider_name.trim().to_lowercase();
+    if composite_layout(&normalized_name)?.is_some() {
+        return Ok(());
+    }
+    if webhook_url(provider_name, config)?.is_some() {
+        return Ok(());
+    }
+    if fal_model_path(&normalized_name, config.fal_url_provider)?.is_some() {
+        if config.fal_key.is_none() {
+            return Err(fal_key_missing());
+        }
+        return Ok(());
+    }
+
+    match normalized_name.as_str() {
+        "google" | "nano-banana" if config.get_google_api_key().is_none() => {
+            Err(google_key_missing())
+        }
+        "google" | "nano-banana" => Ok(()),
+        "local" => local_available(config),
+        _ => match config.fallback_provider.as_deref() {
+            Some(fallback) if is_known_provider(fallback) => {
+                check_provider_available(fallback, config).map_err(|e| {
+                    AppError::ProviderNotFound(format!(
+                        "Provider '{}' not found and fallback provider '{}' is unavailable: {}",
+                        provider_name, fallback, e
+                    ))
+                })
+            }
+            // get_editor reports the precise reason
+            _ => get_editor(provider_name, config).map(|_| ()),
+        },
+    }
+}
+
+/// Whether every provider resolves to the mock editor (test builds only)
+#[cfg(any(test, feature = "test-mock"))]
+fn mock_provider_enabled(config: &AppConfig) -> bool {
+    config.mock_provider
+}
+
+/// Whether every provider resolves to the mock editor (test builds only)
+#[cfg(not(any(test, feature = "test-mock")))]
+fn mock_provider_enabled(_config: &AppConfig) -> bool {
+    false
+}
+
+/// Get an image editor, falling back to the mock editor in dev mode
+///
+/// Behaves like `get_editor`, except that when `config.dev_mode` is enabled a
+/// provider that cannot be created (typically because its API key is missing)
+/// resolves to the passthrough `MockEditor`. The returned flag is `true` when
+/// the mock editor was substituted, so callers can mark the response.
+///
+/// # Errors
+///
+/// Returns the `get_editor` error when dev mode is disabled.
+pub fn get_editor_or_dev_fallback(
+    provider_name: &str,
+    config: &AppConfig,
+) -> Result<(Box<dyn ImageEditor>, bool), AppError> {
+    match get_editor(provider_name, config) {
+        Ok(editor) => Ok((editor, false)),
+        Err(AppError::ProviderNotFound(reason)) if config.dev_mode => {
+            tracing::warn!(
+                provider = provider_name,
+                reason = %reason,
+                "Provider unavailable, DEV_MODE serving edit with mock editor"
             );
+            Ok((Box::new(MockEditor::new()), true))
+        }
+        Err(e) => Err(e),
+    }
+}
 
-            Ok(Box::new(editor))
+/// Check that the configured fallback provider can be used
+///
+/// Intended to run at startup so a misconfigured `FALLBACK_PROVIDER` fails fast
+/// instead of on the first request for an unknown provider. A disabled fallback
+/// is always valid. In dev mode a fallback without an API key only logs a
+/// warning, since such requests are served by the mock editor.
+///
+/// # Errors
+///
+/// Returns `AppError::Config` if the fallback is not a known provider or its
+/// API key is not configured outside dev mode.
+pub fn validate_fallback_provider(config: &AppConfig) -> Result<(), AppError> {
+    let Some(fallback) = config.fallback_provider.as_deref() else {
+        return Ok(());
+    };
+
+    if !is_known_provider(fallback) {
+        return Err(AppError::Config(format!(
+            "FALLBACK_PROVIDER '{}' is not a known provider",
+            fallback
+        )));
+    }
+
+    match get_editor(fallback, config) {
+        Ok(_) => Ok(()),
+        Err(e) if config.dev_mode => {
+            tracing::warn!(fallback = fallback, error = %e, "FALLBACK_PROVIDER unavailable in DEV_MODE");
+            Ok(())
+        }
+        Err(e) => Err(AppError::Config(format!(
+            "FALLBACK_PROVIDER '{}' is unavailable: {}",
+            fallback, e
+        ))),
+    }
+}
+
+/// Hosts and paths of fal URLs that are followed by a model path
+const FAL_URL_PREFIXES: &[&str] = &["fal.run/", "queue.fal.run/", "fal.ai/models/"];
+
+/// Model path of a fal provider name, or `None` for other providers
+///
+/// Besides `fal:owner/model`, recognizes fal URLs pasted in place of the
+/// model path (`fal:https://fal.run/owner/model`) or of the whole provider
+/// (`https://fal.run/owner/model`), which `mode` normalizes to the model path
+/// or rejects. URLs elsewhere are only rejected after a `fal:` prefix; without
+/// one they are unknown providers like any other name.
+///
+/// # Errors
+///
+/// Returns `AppError::ProviderNotFound` for an empty model path, a rejected
+/// fal URL, or `fal:` followed by a URL that isn't fal's.
+fn fal_model_path(normalized_name: &str, mode: FalUrlProvider) -> Result<Option<String>, AppError> {
+    let (path, prefixed) = match normalized_name.strip_prefix("fal:") {
+        Some(path) => (path.trim(), true),
+        None => (normalized_name, false),
+    };
+    let without_scheme = path
+        .strip_prefix("https://")
+        .or_else(|| path.strip_prefix("http://"));
+    let url_model_path = FAL_URL_PREFIXES
+        .iter()
+        .find_map(|prefix| without_scheme.unwrap_or(path).strip_prefix(prefix));
+
+    let Some(url_model_path) = url_model_path else {
+        return match (prefixed, without_scheme) {
+            (true, Some(_)) => Err(AppError::ProviderNotFound(format!(
+                "'{}' is not a fal model URL. Expected format: fal:owner/model (e.g. fal:fal-ai/flux/dev)",
+                path
+            ))),
+            (true, None) if path.is_empty() => Err(fal_model_path_missing()),
+            (true, None) => Ok(Some(path.to_string())),
+            (false, _) => Ok(None),
+        };
+    };
+
+    let model_path = url_model_path
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_matches('/');
+    if model_path.is_empty() {
+        return Err(fal_model_path_missing());
+    }
+    match mode {
+        FalUrlProvider::Normalize => {
+            tracing::debug!(
+                provider = normalized_name,
+                model_path = model_path,
+                "Normalized fal URL provider"
+            );
+            Ok(Some(model_path.to_string()))
         }
+        FalUrlProvider::Reject => Err(AppError::ProviderNotFound(format!(
+            "Provider '{}' is a URL, not a provider name. Use fal:{} instead",
+            normalized_name, model_path
+        ))),
+    }
+}
+
+/// Prefix of providers naming a webhook endpoint
+const WEBHOOK_PREFIX: &str = "webhook:";
+
+/// Endpoint URL of a `webhook:` provider name, or `None` for other providers
+///
+/// Taken from the name as given rather than lowercased, since URL paths are
+/// case-sensitive.
+///
+/// # Errors
+///
+/// Returns `AppError::ProviderNotFound` if the `webhooks` feature is disabled,
+/// the URL is not a valid `http`/`https` URL or its host may not be called
+/// (see `webhook_editor::check_webhook_host`).
+fn webhook_url<'a>(
+    provider_name: &'a str,
+    config: &AppConfig,
+) -> Result<Option<&'a str>, AppError> {
+    let name = provider_name.trim();
+    let Some(prefix) = name.get(..WEBHOOK_PREFIX.len()) else {
+        return Ok(None);
+    };
+    if !prefix.eq_ignore_ascii_case(WEBHOOK_PREFIX) {
+        return Ok(None);
+    }
+    if !config.features.webhooks {
+        return Err(AppError::ProviderNotFound(
+            "Webhook providers are disabled; enable the `webhooks` feature in FEATURES".to_string(),
+        ));
+    }
+
+    let url = name[WEBHOOK_PREFIX.len()..].trim();
+    let parsed = webhook_editor::parse_webhook_url(url).map_err(|e| {
+        AppError::ProviderNotFound(format!("{}. Expected format: webhook:https://host/path", e))
+    })?;
+    webhook_editor::check_webhook_host(&parsed, config)
+        .map_err(|e| AppError::ProviderNotFound(e.to_string()))?;
+    Ok(Some(url))
+}
+
+fn fal_model_path_missing() -> AppError {
+    AppError::ProviderNotFound(
+        "Fal provider requires a model path. Format: fal:model-path".to_string(),
+    )
+}
+
+fn fal_key_missing() -> AppError {
+    AppError::ProviderNotFound(
+        "Fal provider requested but FAL_KEY is not configured in environment".to_string(),
+    )
+}
+
+fn google_key_missing() -> AppError {
+    AppError::ProviderNotFound(
+        "Google provider requested but GOOGLE_API_KEY/GEMINI_API_KEY is not configured in environment"
+            .to_string(),
+    )
+}
+
+fn local_feature_missing() -> AppError {
+    AppError::ProviderNotFound(
+        "Local provider requires a server built with the local-model feature".to_string(),
+    )
+}
+
+fn local_model_path_missing() -> AppError {
+    AppError::ProviderNotFound(
+        "Local provider requested but LOCAL_MODEL_PATH is not configured in environment"
+            .to_string(),
+    )
+}
+
+/// Whether the `local` provider can be created, without loading its model
+fn local_available(config: &AppConfig) -> Result<(), AppError> {
+    if !cfg!(feature = "local-model") {
+        return Err(local_feature_missing());
+    }
+    if config.local_model_path.is_none() {
+        return Err(local_model_path_missing());
+    }
+    Ok(())
+}
+
+/// Create the `local` provider's editor from `LOCAL_MODEL_PATH`
+#[cfg(feature = "local-model")]
+fn local_editor(config: &AppConfig) -> Result<Box<dyn ImageEditor>, AppError> {
+    let model_path = config
+        .local_model_path
+        .as_deref()
+        .ok_or_else(local_model_path_missing)?;
+    let editor = LocalEditor::new(model_path).map_err(|e| {
+        AppError::ProviderNotFound(format!("Failed to create local editor: {:#}", e))
+    })?;
+
+    tracing::info!(model_path, "Created local editor");
+    Ok(Box::new(editor))
+}
+
+/// Create the `local` provider's editor
+///
+/// Built without the `local-model` feature, so this always fails.
+#[cfg(not(feature = "local-model"))]
+fn local_editor(_config: &AppConfig) -> Result<Box<dyn ImageEditor>, AppError> {
+    Err(local_feature_missing())
+}
+
+/// Layout of a normalized `composite` / `composite:<params>` provider name
+///
+/// Returns `None` for other providers.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` if the layout parameters are invalid.
+fn composite_layout(normalized_name: &str) -> Result<Option<CompositeLayout>, AppError> {
+    let params = match normalized_name.split_once(':') {
+        Some(("composite", params)) => params,
+        None if normalized_name == "composite" => "",
+        _ => return Ok(None),
+    };
+
+    params
+        .parse()
+        .map(Some)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid composite layout: {}", e)))
+}
+
+/// Whether a provider name resolves without falling back
+fn is_known_provider(provider_name: &str) -> bool {
+    let normalized_name = provider_name.trim().to_lowercase();
+    matches!(
+        normalized_name.as_str(),
+        "google" | "nano-banana" | "composite" | "local"
+    ) || normalized_name.starts_with("fal:")
+        || normalized_name.starts_with("composite:")
+        || normalized_name.starts_with(WEBHOOK_PREFIX)
+}
+
+/// Provider family used as the metrics label for a provider name
+///
+/// Collapses the open-ended provider names (fal model paths, webhook URLs,
+/// composite layouts) to a fixed set, so metrics have bounded cardinality and
+/// never publish client-supplied URLs. Names that don't resolve to a provider
+/// of their own are `"unknown"`.
+pub fn provider_family(provider_name: &str) -> &'static str {
+    let normalized_name = provider_name.trim().to_lowercase();
+    let without_scheme = normalized_name
+        .strip_prefix("https://")
+        .or_else(|| normalized_name.strip_prefix("http://"))
+        .unwrap_or(&normalized_name);
+
+    match normalized_name
+        .split_once(':')
+        .map_or(normalized_name.as_str(), |(prefix, _)| prefix)
+    {
+        "google" | "nano-banana" => "google",
+        "composite" => "composite",
+        "local" => "local",
+        "fal" => "fal",
+        "webhook" => "webhook",
+        _ if FAL_URL_PREFIXES
+            .iter()
+            .any(|prefix| without_scheme.starts_with(prefix)) =>
+        {
+            "fal"
+        }
+        _ => "unknown",
     }
 }
 
@@ -253,6 +717,7 @@ mod tests {
             allowed_origins: vec!["*".to_string()],
             host: "127.0.0.1".to_string(),
             port: 8000,
+            ..AppConfig::default()
         }
     }
 
@@ -265,9 +730,25 @@ mod tests {
             allowed_origins: vec!["*".to_string()],
             host: "127.0.0.1".to_string(),
             port: 8000,
+            ..AppConfig::default()
         }
     }
 
+    #[test]
+    fn test_provider_family() {
+        assert_eq!(provider_family("Nano-Banana"), "google");
+        assert_eq!(provider_family("fal:fal-ai/flux/dev"), "fal");
+        assert_eq!(provider_family("https://fal.run/fal-ai/flux/dev"), "fal");
+        assert_eq!(
+            provider_family("webhook:https://10.0.0.1/edit?token=x"),
+            "webhook"
+        );
+        assert_eq!(provider_family("composite:cols=2"), "composite");
+        assert_eq!(provider_family("local"), "local");
+        assert_eq!(provider_family("https://example.com/model"), "unknown");
+        assert_eq!(provider_family("made-up"), "unknown");
+    }
+
     #[test]
     fn test_list_providers_with_all_keys() {
         let config = make_test_config();
@@ -322,6 +803,25 @@ mod tests {
         assert_eq!(providers, sorted);
     }
 
+    #[test]
+    fn test_list_providers_custom_order() {
+        let mut config = make_test_config();
+        config.provider_order = vec!["nano-banana".to_string(), "google".to_string()];
+
+        assert_eq!(list_providers(&config), vec!["nano-banana", "google"]);
+    }
+
+    #[test]
+    fn test_list_providers_unlisted_follow_alphabetically() {
+        let mut config = make_test_config();
+        config.provider_order = vec!["fal".to_string(), "nano-banana".to_string()];
+        assert_eq!(list_providers(&config), vec!["nano-banana", "google"]);
+
+        // Entries that match nothing leave the alphabetical order in place
+        config.provider_order = vec!["fal".to_string()];
+        assert_eq!(list_providers(&config), vec!["google", "nano-banana"]);
+    }
+
     #[test]
     fn test_get_google_editor() {
         let config = make_test_config();
@@ -363,6 +863,159 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pasted_fal_urls_normalized_to_model_path() {
+        for provider in [
+            "fal:https://fal.run/fal-ai/flux/dev",
+            "https://fal.run/fal-ai/flux/dev/",
+            "fal:queue.fal.run/fal-ai/flux/dev?sync=1",
+            "http://fal.ai/models/fal-ai/flux/dev",
+        ] {
+            let model_path = fal_model_path(provider, FalUrlProvider::Normalize).unwrap();
+
+            assert_eq!(
+                model_path.as_deref(),
+                Some("fal-ai/flux/dev"),
+                "{}",
+                provider
+            );
+        }
+        assert_eq!(
+            fal_model_path("fal:fal-ai/flux/dev", FalUrlProvider::Reject)
+                .unwrap()
+                .as_deref(),
+            Some("fal-ai/flux/dev")
+        );
+        assert_eq!(
+            fal_model_path("https://example.com/model", FalUrlProvider::Normalize).unwrap(),
+            None
+        );
+        assert!(get_editor("fal:https://fal.run/fal-ai/flux/dev", &make_test_config()).is_ok());
+    }
+
+    #[test]
+    fn test_pasted_fal_urls_rejected_with_expected_format() {
+        let config = AppConfig {
+            fal_url_provider: FalUrlProvider::Reject,
+            ..make_test_config()
+        };
+
+        let err = get_editor("fal:https://fal.run/fal-ai/flux/dev", &config)
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().contains("Use fal:fal-ai/flux/dev instead"),
+            "{}",
+            err
+        );
+        let err = check_provider_available("https://fal.run/fal-ai/flux/dev", &config).unwrap_err();
+        assert!(matches!(err, AppError::ProviderNotFound(_)));
+
+        // Not fal's URL, whatever the mode
+        let err = get_editor(
+            "fal:https://example.com/fal-ai/flux/dev",
+            &make_test_config(),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            err.to_string().contains("Expected format: fal:owner/model"),
+            "{}",
+            err
+        );
+        let err = get_editor("fal:https://fal.run/", &make_test_config())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("requires a model path"), "{}", err);
+    }
+
+    #[test]
+    fn test_webhook_provider_parsing() {
+        let config = AppConfig {
+            features: crate::config::FeatureFlags {
+                webhooks: true,
+                ..Default::default()
+            },
+            ..make_config_no_keys()
+        };
+
+        // No API key needed, and the URL keeps its case
+        assert_eq!(
+            webhook_url(" Webhook:https://My-Model.example.com/Edit ", &config).unwrap(),
+            Some("https://My-Model.example.com/Edit")
+        );
+        assert!(get_editor("webhook:https://my-model.example.com/edit", &config).is_ok());
+        assert!(
+            check_provider_available("webhook:http://93.184.216.34:9000/edit", &config).is_ok()
+        );
+        assert_eq!(webhook_url("fal:fal-ai/flux/dev", &config).unwrap(), None);
+
+        for provider in [
+            "webhook:ftp://my-model/edit",
+            "webhook:my-model/edit",
+            "webhook:",
+        ] {
+            let err = get_editor(provider, &config).err().unwrap();
+            assert!(matches!(err, AppError::ProviderNotFound(_)), "{}", provider);
+            assert!(
+                err.to_string().contains("webhook:https://host/path"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_webhook_provider_hosts_restricted() {
+        let config = AppConfig {
+            features: crate::config::FeatureFlags {
+                webhooks: true,
+                ..Default::default()
+            },
+            ..make_config_no_keys()
+        };
+
+        // Without an allow-list, literal private and link-local addresses are refused
+        for provider in [
+            "webhook:http://127.0.0.1:9000/edit",
+            "webhook:http://169.254.169.254/latest",
+        ] {
+            let err = check_provider_available(provider, &config).unwrap_err();
+            assert!(matches!(err, AppError::ProviderNotFound(_)), "{}", provider);
+            assert!(err.to_string().contains("WEBHOOK_ALLOWED_HOSTS"), "{}", err);
+        }
+
+        // With one, only listed hosts are called, private or not
+        let config = AppConfig {
+            webhook_allowed_hosts: vec![
+                "127.0.0.1".to_string(),
+                "My-Model.example.com".to_string(),
+            ],
+            ..config
+        };
+        assert!(check_provider_available("webhook:http://127.0.0.1:9000/edit", &config).is_ok());
+        assert!(get_editor("webhook:https://my-model.example.com/edit", &config).is_ok());
+        let err = check_provider_available("webhook:https://other.example.com/edit", &config)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("not in WEBHOOK_ALLOWED_HOSTS"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_webhook_provider_requires_feature() {
+        let err = get_editor(
+            "webhook:https://my-model.example.com/edit",
+            &make_test_config(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(err.to_string().contains("`webhooks` feature"), "{}", err);
+    }
+
     #[test]
     fn test_fal_provider_no_key() {
         let config = make_config_no_keys();
@@ -381,6 +1034,38 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    /// WARN events logged by three requests for the same unknown provider
+    fn unknown_provider_warnings(mode: UnknownProviderLog, provider_name: &str) -> usize {
+        let capture = crate::log_capture::LogCapture::default();
+        let _guard = capture.install();
+        let config = AppConfig {
+            unknown_provider_log: mode,
+            ..make_test_config()
+        };
+        for _ in 0..3 {
+            assert!(get_editor(provider_name, &config).is_ok());
+        }
+        capture.count_level(tracing::Level::WARN)
+    }
+
+    #[test]
+    fn test_unknown_provider_log_levels() {
+        assert_eq!(
+            unknown_provider_warnings(UnknownProviderLog::Warn, "probe-warn"),
+            3
+        );
+        assert_eq!(
+            unknown_provider_warnings(UnknownProviderLog::Debug, "probe-debug"),
+            0
+        );
+        // Counted per name, so only the first request warns
+        assert_eq!(
+            unknown_provider_warnings(UnknownProviderLog::Once, "probe-once"),
+            1
+        );
+        assert_eq!(count_unknown_provider("probe-once"), 4);
+    }
+
     #[test]
     fn test_unknown_provider_no_fallback() {
         let config = make_config_no_keys();
@@ -392,6 +1077,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mock_provider_overrides_all_names() {
+        let mut config = make_config_no_keys();
+        config.mock_provider = true;
+        // No keys are needed, and every name resolves to the mock editor
+        assert!(get_editor("google", &config).is_ok());
+        assert!(get_editor("fal:fal-ai/flux/dev", &config).is_ok());
+        assert!(get_editor("unknown-provider", &config).is_ok());
+    }
+
     #[test]
     fn test_provider_name_normalization_uppercase() {
         let config = make_test_config();
@@ -418,4 +1113,166 @@ mod tests {
         assert!(get_editor(" Nano-BANANA ", &config).is_ok());
         assert!(get_editor("  FAL:fal-ai/FLUX/dev  ", &config).is_ok());
     }
+
+    #[test]
+    fn test_unknown_provider_uses_fal_fallback() {
+        let mut config = make_config_no_keys();
+        config.fal_key = Some("test-fal-key".to_string());
+        config.fallback_provider = Some("fal:fal-ai/flux/dev".to_string());
+
+        assert!(get_editor("unknown-provider", &config).is_ok());
+        assert!(validate_fallback_provider(&config).is_ok());
+    }
+
+    #[test]
+    fn test_disabled_fallback_rejects_unknown_provider() {
+        let mut config = make_test_config();
+        config.fallback_provider = None;
+
+        let err = get_editor("unknown-provider", &config).err().unwrap();
+        assert!(matches!(err, AppError::ProviderNotFound(_)));
+        assert!(err.to_string().contains("no fallback provider"));
+        // Known providers are unaffected
+        assert!(get_editor("google", &config).is_ok());
+        assert!(validate_fallback_provider(&config).is_ok());
+    }
+
+    #[test]
+    fn test_validate_fallback_provider_unavailable() {
+        // Google fallback without a Google key
+        let mut config = make_config_no_keys();
+        config.fal_key = Some("test-fal-key".to_string());
+        assert!(matches!(
+            validate_fallback_provider(&config),
+            Err(AppError::Config(_))
+        ));
+
+        // Fallback that is not a known provider
+        config.fallback_provider = Some("unknown-provider".to_string());
+        let err = validate_fallback_provider(&config).unwrap_err();
+        assert!(err.to_string().contains("not a known provider"));
+        assert!(get_editor("other", &config).is_err());
+    }
+
+    #[test]
+    fn test_dev_mode_falls_back_to_mock_without_keys() {
+        let mut config = make_config_no_keys();
+        assert!(get_editor_or_dev_fallback("google", &config).is_err());
+
+        config.dev_mode = true;
+        let (_, dev_fallback) = get_editor_or_dev_fallback("google", &config).unwrap();
+        assert!(dev_fallback);
+        assert!(validate_fallback_provider(&config).is_ok());
+    }
+
+    #[test]
+    fn test_dev_mode_prefers_real_provider() {
+        let config = AppConfig {
+            dev_mode: true,
+            ..make_test_config()
+        };
+
+        let (_, dev_fallback) = get_editor_or_dev_fallback("google", &config).unwrap();
+        assert!(!dev_fallback);
+    }
+
+    #[test]
+    fn test_check_provider_available() {
+        let config = make_test_config();
+        assert!(check_provider_available("google", &config).is_ok());
+        assert!(check_provider_available("FAL:fal-ai/flux/dev", &config).is_ok());
+        assert!(check_provider_available("unknown-provider", &config).is_ok());
+        assert!(check_provider_available("fal:", &config).is_err());
+
+        let config = make_config_no_keys();
+        for provider in [
+            "google",
+            "nano-banana",
+            "fal:fal-ai/flux/dev",
+            "unknown-provider",
+        ] {
+            let err = check_provider_available(provider, &config).unwrap_err();
+            assert!(
+                matches!(err, AppError::ProviderNotFound(_)),
+                "{} accepted",
+                provider
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_provider_available_in_mock_and_dev_mode() {
+        let mut config = make_config_no_keys();
+        config.mock_provider = true;
+        assert!(check_provider_available("google", &config).is_ok());
+
+        config.mock_provider = false;
+        config.dev_mode = true;
+        assert!(check_provider_available("fal:fal-ai/flux/dev", &config).is_ok());
+    }
+
+    #[test]
+    fn test_composite_needs_no_keys() {
+        let config = make_config_no_keys();
+
+        assert!(get_editor("composite", &config).is_ok());
+        assert!(get_editor(" Composite:cols=1,bg=FFFFFF ", &config).is_ok());
+        assert!(check_provider_available("composite:spacing=4", &config).is_ok());
+    }
+
+    #[cfg(not(feature = "local-model"))]
+    #[test]
+    fn test_local_provider_requires_feature() {
+        let config = AppConfig {
+            local_model_path: Some("/models/edit.onnx".to_string()),
+            ..make_config_no_keys()
+        };
+
+        let err = get_editor("local", &config).err().unwrap();
+        assert!(err.to_string().contains("local-model feature"));
+        assert!(check_provider_available("local", &config).is_err());
+        assert!(!list_providers(&config).contains(&"local".to_string()));
+    }
+
+    #[cfg(feature = "local-model")]
+    #[test]
+    fn test_local_provider_selection() {
+        let model =
+            std::env::temp_dir().join(format!("frameforge-factory-{}.onnx", std::process::id()));
+        std::fs::write(&model, b"").unwrap();
+        let config = AppConfig {
+            local_model_path: Some(model.display().to_string()),
+            ..make_config_no_keys()
+        };
+
+        assert!(get_editor(" LOCAL ", &config).is_ok());
+        assert!(check_provider_available("local", &config).is_ok());
+        assert_eq!(list_providers(&config), vec!["local".to_string()]);
+
+        let missing = AppConfig {
+            local_model_path: Some("/nonexistent/model.onnx".to_string()),
+            ..make_config_no_keys()
+        };
+        let err = get_editor("local", &missing).err().unwrap();
+        assert!(matches!(err, AppError::ProviderNotFound(_)));
+    }
+
+    #[test]
+    fn test_local_provider_requires_model_path() {
+        let config = make_config_no_keys();
+
+        let err = get_editor("local", &config).err().unwrap();
+        assert!(matches!(err, AppError::ProviderNotFound(_)));
+        assert!(check_provider_available("local", &config).is_err());
+        assert!(list_providers(&config).is_empty());
+    }
+
+    #[test]
+    fn test_composite_rejects_invalid_layout() {
+        let config = make_config_no_keys();
+
+        let err = get_editor("composite:cols=0", &config).err().unwrap();
+        assert!(matches!(err, AppError::InvalidInput(_)));
+        assert!(check_provider_available("composite:rows=2", &config).is_err());
+    }
 }
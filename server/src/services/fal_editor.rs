@@ -6,14 +6,23 @@
 //! # Architecture
 //!
 //! The Fal.ai workflow consists of several steps:
-//! 1. **Upload**: Convert images to base64 data URIs (no separate upload needed)
+//! 1. **Upload**: Encode images as base64 data URIs, streamed into the request
+//!    body (no separate upload needed). Multi-image models receive every
+//!    distinct upload; duplicates are sent once
 //! 2. **Submit**: POST request to the model endpoint with image data and prompt
-//! 3. **Poll**: Use fal-client's subscribe mechanism which handles polling automatically
+//! 3. **Poll**: Poll the queue's status URL until the request completes (queue
+//!    endpoint), or wait on a direct `fal.run` call (see `FalEndpoint`). Time
+//!    spent waiting in the queue and time spent running have separate limits
 //! 4. **Download**: Fetch the result image from the returned URL or decode data URI
 //!
+//! Inpainting models (see `FalEditor::is_inpaint_model`) support region edits:
+//! the `EditRegion` is sent as a black and white `mask_url` image the size of
+//! the input, white where the model may paint.
+//!
 //! # Example
 //!
 //! ```rust,no_run
+//! use frameforge_server::services::base::ImageEditor;
 //! use frameforge_server::services::fal_editor::FalEditor;
 //! use frameforge_server::config::AppConfig;
 //! use bytes::Bytes;
@@ -24,13 +33,166 @@
 //! }
 //! ```
 
-use crate::config::AppConfig;
-use crate::services::base::ImageEditor;
-use anyhow::{anyhow, Context, Result};
+use crate::config::{AppConfig, FalEndpoint, LogRedaction};
+use crate::error::ProviderAuthError;
+use crate::models::request::PROTECTED_PROVIDER_OPTIONS;
+use crate::services::base::{
+    EditRegion, ImageEditor, ProviderMetadata, ProviderOptions, SamplingOptions,
+};
+use crate::services::download_cache::DownloadCache;
+use crate::services::http_client::HttpClientSettings;
+use crate::utils::{image_utils, log_redaction};
+use anyhow::{anyhow, bail, Context, Result};
 use base64::Engine;
 use bytes::Bytes;
+use image::ImageFormat;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::borrow::Cow;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Maximum number of response body characters quoted in parse errors
+const RESPONSE_SNIPPET_CHARS: usize = 500;
+
+/// Bytes of a Fal.ai JSON response allowed beside an inline data URI result
+/// (seed, timings and other fields)
+const RESPONSE_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// Total attempts made to download a result image
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Delay before the first download retry; doubles for each further retry
+const DOWNLOAD_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Result format requested from every model (`output_format`)
+const REQUESTED_OUTPUT_FORMAT: ImageFormat = ImageFormat::Png;
+
+/// Memory budget for cached result downloads, shared by every editor
+const DOWNLOAD_CACHE_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// How long a cached result download stays valid
+///
+/// Long enough to cover retries and repeated edits that Fal answers with the
+/// same result URL, short enough that the cache doesn't hold stale results.
+const DOWNLOAD_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Result downloads shared by every Fal editor, keyed on the exact result URL
+///
+/// Editors are built per request, so the cache lives outside them. It is
+/// distinct from the content-addressed result store (`crate::results`).
+fn shared_download_cache() -> &'static Mutex<DownloadCache> {
+    static CACHE: OnceLock<Mutex<DownloadCache>> = OnceLock::new();
+    CACHE.get_or_init(|| {
+        Mutex::new(DownloadCache::new(
+            DOWNLOAD_CACHE_MAX_BYTES,
+            DOWNLOAD_CACHE_TTL,
+        ))
+    })
+}
+
+/// Delay between status polls of a queued request
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Failure of a single download attempt
+#[derive(Debug)]
+enum DownloadError {
+    /// Transient failure (connection reset, truncated body, 5xx); worth a fresh GET
+    Retryable(anyhow::Error),
+    /// Permanent failure (e.g. 404); retrying won't help
+    Fatal(anyhow::Error),
+}
+
+impl DownloadError {
+    /// Classify a reqwest error from sending the request or reading the body
+    fn from_reqwest(error: reqwest::Error, context: &'static str) -> Self {
+        let retryable = error.is_connect()
+            || error.is_timeout()
+            || error.is_body()
+            || error.is_request()
+            || error.is_decode();
+        let error = anyhow::Error::new(error).context(context);
+
+        if retryable {
+            DownloadError::Retryable(error)
+        } else {
+            DownloadError::Fatal(error)
+        }
+    }
+}
+
+/// A queued request that exceeded the limit of the phase it was in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+enum QueueTimeout {
+    /// Still waiting for a worker after `FAL_QUEUE_TIMEOUT_SECS`
+    #[error("Fal.ai queue timeout: request waited in the queue for more than {}s", .0.as_secs())]
+    Queue(Duration),
+    /// Still running after `FAL_PROCESSING_TIMEOUT_SECS`
+    #[error("Fal.ai processing timeout: request ran for more than {}s", .0.as_secs())]
+    Processing(Duration),
+}
+
+/// Phase of a queued request, as reported by its status URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum QueuePhase {
+    InQueue,
+    InProgress,
+    Completed,
+}
+
+/// Time a queued request spent waiting and running, checked against
+/// separate limits so a long queue never counts as processing time
+#[derive(Debug)]
+struct QueuePhaseTracker {
+    queue_timeout: Duration,
+    processing_timeout: Duration,
+    /// Phase seen at the last observation
+    phase: QueuePhase,
+    /// When the last observation was made
+    observed_at: Instant,
+    queued: Duration,
+    running: Duration,
+}
+
+impl QueuePhaseTracker {
+    /// Start tracking a request submitted at `submitted_at`; it starts queued
+    fn new(queue_timeout: Duration, processing_timeout: Duration, submitted_at: Instant) -> Self {
+        Self {
+            queue_timeout,
+            processing_timeout,
+            phase: QueuePhase::InQueue,
+            observed_at: submitted_at,
+            queued: Duration::ZERO,
+            running: Duration::ZERO,
+        }
+    }
+
+    /// Record the phase reported at `now`
+    ///
+    /// The time since the previous observation is charged to the phase seen
+    /// then, since a transition is only noticed by the next poll. Fails when
+    /// the request is still in a phase whose limit it has exceeded.
+    fn observe(&mut self, phase: QueuePhase, now: Instant) -> Result<(), QueueTimeout> {
+        let elapsed = now.saturating_duration_since(self.observed_at);
+        match self.phase {
+            QueuePhase::InQueue => self.queued += elapsed,
+            QueuePhase::InProgress => self.running += elapsed,
+            QueuePhase::Completed => {}
+        }
+        self.phase = phase;
+        self.observed_at = now;
+
+        match phase {
+            QueuePhase::InQueue if self.queued > self.queue_timeout => {
+                Err(QueueTimeout::Queue(self.queue_timeout))
+            }
+            QueuePhase::InProgress if self.running > self.processing_timeout => {
+                Err(QueueTimeout::Processing(self.processing_timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+}
 
 /// Fal.ai image editor implementation
 ///
@@ -49,25 +211,107 @@ pub struct FalEditor {
     model_path: String,
     /// API key for Fal.ai authentication
     api_key: String,
+    /// Endpoint style used for submissions
+    endpoint: FalEndpoint,
+    /// Transcode inputs other than PNG/JPEG to PNG before building the data URI
+    transcode_data_uri: bool,
+    /// Send byte-identical inputs only once
+    dedupe_inputs: bool,
+    /// Replaces the fal.run hosts when set (used to point tests at a local server)
+    base_url: Option<String>,
+    /// Redaction applied to data URIs and prompts in debug logs
+    log_redaction: LogRedaction,
+    /// Downloaded results keyed on URL, when the `caching` feature is enabled
+    download_cache: Option<&'static Mutex<DownloadCache>>,
+    /// Longest data URI result decoded, in bytes
+    max_data_uri_bytes: usize,
+    /// Largest result downloaded, in bytes (`MAX_RESULT_BYTES`)
+    max_result_bytes: usize,
+    /// Client-supplied fields added to every request body
+    provider_options: ProviderOptions,
+    /// Provider option keys never sent (`PROVIDER_OPTIONS_DENY`)
+    denied_options: Vec<String>,
+    /// Generation parameters added to every request body
+    sampling: SamplingOptions,
+    /// Time a queued request may wait for a worker
+    queue_timeout: Duration,
+    /// Time a queued request may run once started
+    processing_timeout: Duration,
     /// HTTP client for making requests
     client: reqwest::Client,
 }
 
+/// MIME types every Fal.ai model accepts in data URIs
+const PORTABLE_DATA_URI_MIME_TYPES: [&str; 2] = ["image/png", "image/jpeg"];
+
+/// Input bytes per base64 chunk; a multiple of 3 so padding only ends the data
+const BASE64_CHUNK_BYTES: usize = 3 * 1024;
+
+/// Image as a `data:{mime};base64,{data}` URI, encoded only when written
+///
+/// Serializing streams the base64 text in small chunks straight into the JSON
+/// body, so the request holds one encoded copy of the image instead of
+/// separate base64, data URI and JSON body strings (roughly 1.3x the image
+/// size at peak instead of 4x).
+#[derive(Debug, Clone, Copy)]
+struct DataUri<'a> {
+    mime: &'static str,
+    data: &'a [u8],
+}
+
+impl DataUri<'_> {
+    /// Length of the URI in bytes
+    fn encoded_len(&self) -> usize {
+        "data:;base64,".len() + self.mime.len() + self.data.len().div_ceil(3) * 4
+    }
+}
+
+impl std::fmt::Display for DataUri<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "data:{};base64,", self.mime)?;
+
+        let mut buffer = [0u8; BASE64_CHUNK_BYTES / 3 * 4];
+        for chunk in self.data.chunks(BASE64_CHUNK_BYTES) {
+            let written = base64::engine::general_purpose::STANDARD
+                .encode_slice(chunk, &mut buffer)
+                .map_err(|_| std::fmt::Error)?;
+            // Base64 output is always ASCII
+            f.write_str(std::str::from_utf8(&buffer[..written]).map_err(|_| std::fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for DataUri<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 /// Request payload for Fal.ai image editing
 #[derive(Debug, Serialize)]
-struct FalRequest {
+struct FalRequest<'a> {
     /// Text prompt for image editing
-    prompt: String,
+    prompt: &'a str,
     /// Image URL(s) or data URI for single image models
     #[serde(skip_serializing_if = "Option::is_none")]
-    image_url: Option<String>,
+    image_url: Option<DataUri<'a>>,
     /// Image URLs for multi-image models
     #[serde(skip_serializing_if = "Option::is_none")]
-    image_urls: Option<Vec<String>>,
+    image_urls: Option<Vec<DataUri<'a>>>,
+    /// Mask of the area to repaint, white on black (inpainting models)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mask_url: Option<DataUri<'a>>,
     /// Output format (png, jpeg)
     output_format: String,
     /// Synchronous mode (returns result directly when complete)
     sync_mode: bool,
+    /// Client-supplied fields the request doesn't model (see `FalEditor::extra_options`)
+    #[serde(flatten)]
+    options: ProviderOptions,
 }
 
 /// Response from Fal.ai API
@@ -82,6 +326,61 @@ struct FalResponse {
     /// Result wrapper (some models)
     #[serde(default)]
     result: Option<FalImage>,
+    /// Seed used for generation (most models)
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Server-side timings in seconds (most models)
+    #[serde(default)]
+    timings: Option<FalTimings>,
+    /// Payload wrapper used by some queue responses: `{"data": {"images": [...]}}`
+    #[serde(default)]
+    data: Option<Box<FalResponse>>,
+}
+
+impl FalResponse {
+    /// The payload inside `data` wrappers, or the response itself when it
+    /// carries an image at the top level
+    fn unwrap_data(self) -> FalResponse {
+        let has_image = self.image.is_some() || self.images.is_some() || self.result.is_some();
+        match self.data {
+            Some(data) if !has_image => data.unwrap_data(),
+            _ => self,
+        }
+    }
+}
+
+/// Receipt returned when a request is queued instead of answered directly
+///
+/// The queue endpoint always answers with one. Models without sync support
+/// may return one from either endpoint despite `sync_mode`, sometimes as a
+/// bare `{"status": "IN_QUEUE", "request_id": ...}` without URLs; those are
+/// then derived from the request id (see `FalEditor::queue_urls`).
+#[derive(Debug, Deserialize)]
+struct FalQueueSubmission {
+    request_id: String,
+    /// Polled for the request's phase
+    #[serde(default)]
+    status_url: Option<String>,
+    /// Serves the result once the request completed
+    #[serde(default)]
+    response_url: Option<String>,
+}
+
+/// Status of a queued request
+#[derive(Debug, Deserialize)]
+struct FalQueueStatus {
+    status: QueuePhase,
+    /// Requests ahead of this one, while queued
+    #[serde(default)]
+    queue_position: Option<u64>,
+}
+
+/// Timings reported by Fal.ai
+#[derive(Debug, Deserialize)]
+struct FalTimings {
+    /// Model inference time in seconds
+    #[serde(default)]
+    inference: Option<f64>,
 }
 
 /// Image data from Fal.ai response
@@ -124,23 +423,47 @@ impl FalEditor {
             .ok_or_else(|| anyhow!("FAL_KEY not configured"))?
             .clone();
 
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(300)) // 5 minutes for long-running generations
-            .build()
-            .context("Failed to create HTTP client")?;
+        // 5 minutes for long-running generations, with pool tuning from config
+        let client = HttpClientSettings::from_config(config, Duration::from_secs(300)).client()?;
+
+        let endpoint = config.fal_endpoint_for(&model_path);
+        let transcode_data_uri = config.fal_transcodes_data_uri(&model_path);
+        let download_cache = config.features.caching.then(shared_download_cache);
 
         tracing::info!(
             model_path = %model_path,
+            endpoint = ?endpoint,
+            transcode_data_uri,
             "Initialized Fal.ai editor"
         );
 
         Ok(Self {
             model_path,
             api_key,
+            endpoint,
+            transcode_data_uri,
+            dedupe_inputs: config.dedupe_input_images,
+            base_url: config.fal_base_url.clone(),
+            log_redaction: config.log_redaction,
+            download_cache,
+            max_data_uri_bytes: config.max_data_uri_bytes,
+            max_result_bytes: config.max_result_bytes,
+            provider_options: ProviderOptions::new(),
+            denied_options: config.provider_options_deny.clone(),
+            sampling: SamplingOptions::default(),
+            queue_timeout: Duration::from_secs(config.fal_queue_timeout_secs),
+            processing_timeout: Duration::from_secs(config.fal_processing_timeout_secs),
             client,
         })
     }
 
+    /// Submit to `base_url` instead of the fal.run hosts
+    #[cfg(test)]
+    fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = Some(base_url);
+        self
+    }
+
     /// Determine the MIME type from image bytes
     ///
     /// Inspects the magic bytes at the start of the image data to determine format.
@@ -158,79 +481,243 @@ impl FalEditor {
         }
     }
 
-    /// Convert image bytes to a base64 data URI
+    /// Wrap image bytes as a base64 data URI
     ///
     /// This creates a self-contained data URI that can be sent directly to Fal.ai
-    /// without requiring a separate upload step.
+    /// without requiring a separate upload step. The base64 text is produced
+    /// while the request is serialized (see `DataUri`).
+    fn data_uri(image_bytes: &[u8]) -> DataUri<'_> {
+        DataUri {
+            mime: Self::detect_mime_type(image_bytes),
+            data: image_bytes,
+        }
+    }
+
+    /// Image bytes in a format the model accepts in data URIs
     ///
-    /// # Arguments
+    /// Models listed in `FAL_TRANSCODE_MODELS` reject e.g. GIF or WebP data
+    /// URIs, so those inputs are re-encoded as PNG, on the blocking thread
+    /// pool; everything else is passed through untouched.
+    async fn data_uri_bytes<'a>(&self, image_bytes: &'a Bytes) -> Result<Cow<'a, [u8]>> {
+        let mime = Self::detect_mime_type(image_bytes);
+        if !self.transcode_data_uri || PORTABLE_DATA_URI_MIME_TYPES.contains(&mime) {
+            return Ok(Cow::Borrowed(image_bytes));
+        }
+
+        let input = image_bytes.clone();
+        let png = tokio::task::spawn_blocking(move || {
+            let img = image_utils::bytes_to_image(&input)
+                .with_context(|| format!("Failed to decode {} input for transcoding", mime))?;
+            image_utils::image_to_bytes(&img, ImageFormat::Png)
+                .context("Failed to transcode input to PNG")
+        })
+        .await
+        .context("Transcoding task failed")??;
+
+        tracing::debug!(
+            model = %self.model_path,
+            from = mime,
+            original_size = image_bytes.len(),
+            png_size = png.len(),
+            "Transcoded input to PNG for data URI"
+        );
+        Ok(Cow::Owned(png.to_vec()))
+    }
+
+    /// Serialize a request into a buffer sized for its data URI up front
+    ///
+    /// `data_uri_len` is the encoded length of the image; the buffer is only
+    /// regrown if the remaining fields exceed the small allowance.
+    fn encode_request_body(request: &FalRequest<'_>, data_uri_len: usize) -> Result<Vec<u8>> {
+        let mut body = Vec::with_capacity(data_uri_len + request.prompt.len() * 2 + 256);
+        serde_json::to_writer(&mut body, request).context("Failed to serialize Fal.ai request")?;
+        Ok(body)
+    }
+
+    /// Input images as sent to the model
     ///
-    /// * `image_bytes` - Raw image data
+    /// Byte-identical uploads are dropped (when enabled) and each remaining
+    /// image is transcoded if the model requires it.
+    async fn request_images<'a>(&self, images: &'a [Bytes]) -> Result<Vec<Cow<'a, [u8]>>> {
+        let unique = if self.dedupe_inputs {
+            image_utils::dedupe_images(images)
+        } else {
+            images.iter().collect()
+        };
+
+        let removed = images.len() - unique.len();
+        if removed > 0 {
+            tracing::info!(
+                model = %self.model_path,
+                removed,
+                remaining = unique.len(),
+                "Removed duplicate input images"
+            );
+        }
+
+        let mut request_images = Vec::with_capacity(unique.len());
+        for image in unique {
+            request_images.push(self.data_uri_bytes(image).await?);
+        }
+        Ok(request_images)
+    }
+
+    /// Build the request payload for the model's image parameter style
     ///
-    /// # Returns
+    /// Single-image models only receive the first image.
+    fn build_request<'a>(
+        &self,
+        prompt: &'a str,
+        data_uris: &[DataUri<'a>],
+        mask: Option<DataUri<'a>>,
+    ) -> FalRequest<'a> {
+        // Different models use different parameter names
+        let use_single_image = self.model_path.contains("flux-kontext")
+            || self.model_path.contains("qwen-image-edit")
+            || self.is_inpaint_model();
+
+        let (image_url, image_urls) = if use_single_image {
+            if data_uris.len() > 1 {
+                tracing::debug!(
+                    model = %self.model_path,
+                    image_count = data_uris.len(),
+                    "Single-image model; sending only the first image"
+                );
+            }
+            (data_uris.first().copied(), None)
+        } else {
+            (None, Some(data_uris.to_vec()))
+        };
+
+        FalRequest {
+            prompt,
+            image_url,
+            image_urls,
+            mask_url: mask,
+            output_format: REQUESTED_OUTPUT_FORMAT.extensions_str()[0].to_string(),
+            sync_mode: true,
+            options: self.extra_options(),
+        }
+    }
+
+    /// Provider options for fields `FalRequest` doesn't model, plus the
+    /// generation parameters
     ///
-    /// Returns a data URI string in the format: `data:{mime};base64,{base64_data}`
-    fn bytes_to_data_uri(image_bytes: &Bytes) -> String {
-        let mime = Self::detect_mime_type(image_bytes);
-        let base64_data = base64::engine::general_purpose::STANDARD.encode(image_bytes);
-        format!("data:{};base64,{}", mime, base64_data)
+    /// Requests naming a field in `PROTECTED_PROVIDER_OPTIONS` are rejected
+    /// before they get here; such options are still dropped, so the prompt,
+    /// images and output settings the server sends can't be overridden, as are
+    /// keys the operator denied. Generation parameters that are set replace provider
+    /// options of the same name.
+    fn extra_options(&self) -> ProviderOptions {
+        let mut options: ProviderOptions = self
+            .provider_options
+            .iter()
+            .filter(|(name, _)| {
+                if PROTECTED_PROVIDER_OPTIONS.contains(&name.as_str()) {
+                    tracing::warn!(field = %name, "Ignoring provider option for a field the server sets");
+                    return false;
+                }
+                if self.denied_options.iter().any(|key| key == *name) {
+                    tracing::warn!(field = %name, "Ignoring provider option denied by PROVIDER_OPTIONS_DENY");
+                    return false;
+                }
+                true
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        let SamplingOptions {
+            seed,
+            num_inference_steps,
+            guidance_scale,
+            ..
+        } = self.sampling;
+        if let Some(seed) = seed {
+            options.insert("seed".to_string(), seed.into());
+        }
+        if let Some(steps) = num_inference_steps {
+            options.insert("num_inference_steps".to_string(), steps.into());
+        }
+        if let Some(scale) = guidance_scale {
+            options.insert("guidance_scale".to_string(), scale.into());
+        }
+        options
+    }
+
+    /// Whether the model repaints a masked area (`mask_url`), as Fal.ai's
+    /// inpainting and fill models do
+    fn is_inpaint_model(&self) -> bool {
+        self.model_path.contains("inpaint") || self.model_path.ends_with("/fill")
+    }
+
+    /// Mask for `region` of `image`: a grayscale PNG of the same size, white
+    /// inside the region and black elsewhere
+    fn region_mask(image: &[u8], region: &EditRegion) -> Result<Vec<u8>> {
+        let (width, height) = image_utils::image_dimensions(image)?;
+        let (left, top, right, bottom) = region.pixel_bounds(width, height);
+        let mask = image::GrayImage::from_fn(width, height, |x, y| {
+            let inside = (left..right).contains(&x) && (top..bottom).contains(&y);
+            image::Luma([if inside { u8::MAX } else { 0 }])
+        });
+
+        let mut png = Vec::new();
+        mask.write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+            .context("Failed to encode region mask")?;
+        Ok(png)
     }
 
     /// Submit an image editing request to Fal.ai
     ///
     /// This method handles the complete workflow:
-    /// 1. Converts image to data URI
+    /// 1. Converts the images to data URIs
     /// 2. Submits to the model endpoint with sync_mode=true
     /// 3. Returns the result when complete
     ///
     /// # Arguments
     ///
-    /// * `image_bytes` - The input image data
+    /// * `images` - The input image data
     /// * `prompt` - Text prompt describing desired edits
+    /// * `mask` - Region mask PNG for inpainting models (see `region_mask`)
     ///
     /// # Returns
     ///
-    /// Returns the result from Fal.ai, which may contain image URLs or data URIs
+    /// Returns the result from Fal.ai, which may contain image URLs or data URIs.
+    /// When the queue endpoint answers with a receipt instead, the request is
+    /// polled until it completes.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - The HTTP request fails
-    /// - The API returns an error status
+    /// - The API returns an error status (401/403 become `ProviderAuthError`)
     /// - The response cannot be parsed
-    async fn submit_request(&self, image_bytes: &Bytes, prompt: &str) -> Result<FalResponse> {
-        // Convert image to data URI
-        let data_uri = Self::bytes_to_data_uri(image_bytes);
-
-        // Different models use different parameter names
-        let use_single_image = self.model_path.contains("flux-kontext")
-            || self.model_path.contains("qwen-image-edit");
-
-        let request_body = if use_single_image {
-            FalRequest {
-                prompt: prompt.to_string(),
-                image_url: Some(data_uri),
-                image_urls: None,
-                output_format: "png".to_string(),
-                sync_mode: true,
-            }
-        } else {
-            FalRequest {
-                prompt: prompt.to_string(),
-                image_url: None,
-                image_urls: Some(vec![data_uri]),
-                output_format: "png".to_string(),
-                sync_mode: true,
-            }
-        };
+    /// - A queued request exceeds the queue or processing timeout
+    async fn submit_request(
+        &self,
+        images: &[Bytes],
+        prompt: &str,
+        mask: Option<&[u8]>,
+    ) -> Result<FalResponse> {
+        // Convert images to data URIs
+        let images = self.request_images(images).await?;
+        let data_uris: Vec<DataUri<'_>> =
+            images.iter().map(|image| Self::data_uri(image)).collect();
+        let mask = mask.map(Self::data_uri);
+        let request_body = self.build_request(prompt, &data_uris, mask);
 
-        // Fal.ai uses a subscribe endpoint that handles polling automatically when sync_mode is true
-        let url = format!("https://queue.fal.run/{}/subscribe", self.model_path);
+        let data_uri_len = data_uris
+            .iter()
+            .chain(&mask)
+            .map(DataUri::encoded_len)
+            .sum();
+        let body = Self::encode_request_body(&request_body, data_uri_len)?;
+        let url = self.endpoint_url();
 
         tracing::debug!(
             url = %url,
             model = %self.model_path,
             sync_mode = request_body.sync_mode,
+            prompt = %log_redaction::prompt(prompt, self.log_redaction),
             "Submitting request to Fal.ai"
         );
 
@@ -239,17 +726,150 @@ impl FalEditor {
             .post(&url)
             .header("Authorization", format!("Key {}", self.api_key))
             .header("Content-Type", "application/json")
-            .json(&request_body)
+            .body(body)
             .send()
             .await
             .context("Failed to send request to Fal.ai")?;
+        let body = self.response_body(response).await?;
+
+        let result = match serde_json::from_str::<FalQueueSubmission>(&body) {
+            Ok(submission) => self.await_queued(submission).await?,
+            Err(_) => Self::parse_response(&body)?,
+        };
+
+        tracing::debug!("Received response from Fal.ai");
+
+        Ok(result)
+    }
+
+    /// Poll a queued request until it completes, then fetch its result
+    ///
+    /// Time reported as `IN_QUEUE` and as `IN_PROGRESS` is limited separately
+    /// (see `QueuePhaseTracker`).
+    async fn await_queued(&self, submission: FalQueueSubmission) -> Result<FalResponse> {
+        let (status_url, response_url) = self.queue_urls(&submission)?;
+        let mut tracker =
+            QueuePhaseTracker::new(self.queue_timeout, self.processing_timeout, Instant::now());
+
+        loop {
+            let response = self
+                .client
+                .get(&status_url)
+                .header("Authorization", format!("Key {}", self.api_key))
+                .send()
+                .await
+                .context("Failed to poll Fal.ai queue status")?;
+            let body = self.response_body(response).await?;
+            let status: FalQueueStatus = serde_json::from_str(&body).with_context(|| {
+                format!(
+                    "Failed to parse Fal.ai queue status (response body: {})",
+                    Self::snippet(&body)
+                )
+            })?;
+
+            if let Err(timeout) = tracker.observe(status.status, Instant::now()) {
+                tracing::warn!(
+                    request_id = %submission.request_id,
+                    model = %self.model_path,
+                    queued_ms = tracker.queued.as_millis() as u64,
+                    running_ms = tracker.running.as_millis() as u64,
+                    "{}",
+                    timeout
+                );
+                return Err(timeout.into());
+            }
+            if status.status == QueuePhase::Completed {
+                break;
+            }
+
+            tracing::debug!(
+                request_id = %submission.request_id,
+                status = ?status.status,
+                queue_position = ?status.queue_position,
+                "Fal.ai request pending"
+            );
+            tokio::time::sleep(QUEUE_POLL_INTERVAL).await;
+        }
+
+        tracing::debug!(
+            request_id = %submission.request_id,
+            queued_ms = tracker.queued.as_millis() as u64,
+            running_ms = tracker.running.as_millis() as u64,
+            "Fal.ai queued request completed"
+        );
+
+        let response = self
+            .client
+            .get(&response_url)
+            .header("Authorization", format!("Key {}", self.api_key))
+            .send()
+            .await
+            .context("Failed to fetch Fal.ai queue result")?;
+        let body = self.response_body(response).await?;
+        Self::parse_response(&body)
+    }
+
+    /// Status and result URLs of a queued request
+    ///
+    /// URLs missing from the receipt follow the queue API layout,
+    /// `{queue}/{model}/requests/{request_id}[/status]`, on `base_url` when
+    /// set and the queue host otherwise (whichever endpoint was submitted to).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receipt names a URL on another origin than the
+    /// queue's: the API key is sent to these URLs.
+    fn queue_urls(&self, submission: &FalQueueSubmission) -> Result<(String, String)> {
+        let queue = self.base_url.as_deref().unwrap_or("https://queue.fal.run");
+        let request_url = format!(
+            "{}/{}/requests/{}",
+            queue, self.model_path, submission.request_id
+        );
+        let status_url = submission
+            .status_url
+            .clone()
+            .unwrap_or_else(|| format!("{}/status", request_url));
+        let response_url = submission.response_url.clone().unwrap_or(request_url);
 
+        let queue_origin = reqwest::Url::parse(queue)?.origin();
+        for url in [&status_url, &response_url] {
+            let same_origin =
+                reqwest::Url::parse(url).is_ok_and(|url| url.origin() == queue_origin);
+            if !same_origin {
+                tracing::warn!(url = %url, queue = %queue, "Fal.ai queue receipt points to another host");
+                bail!(
+                    "Fal.ai queue receipt URL '{}' is not on the queue host {}",
+                    url,
+                    queue
+                );
+            }
+        }
+        Ok((status_url, response_url))
+    }
+
+    /// Body of a Fal.ai API response, or an error for a failed status
+    ///
+    /// 401/403 become `ProviderAuthError`; the body of those is dropped. The
+    /// body is read up to `MAX_DATA_URI_BYTES` plus `RESPONSE_OVERHEAD_BYTES`,
+    /// the most an inline result can need, and refused beyond that while it
+    /// streams in. Error bodies are only read as far as their first chunk.
+    async fn response_body(&self, mut response: reqwest::Response) -> Result<String> {
         let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            // The body is deliberately dropped; only the status reaches the client
+            tracing::warn!(status = %status, model = %self.model_path, "Fal.ai rejected the API key");
+            return Err(ProviderAuthError {
+                provider: "Fal.ai",
+                status: Some(status.as_u16()),
+                key_source: "FAL_KEY or the X-Fal-Key header",
+            }
+            .into());
+        }
         if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unable to read error response".to_string());
+            let error_text = match response.chunk().await {
+                Ok(start) => Self::snippet(&String::from_utf8_lossy(&start.unwrap_or_default())),
+                Err(_) => "Unable to read error response".to_string(),
+            };
             return Err(anyhow!(
                 "Fal.ai API returned error {}: {}",
                 status,
@@ -257,19 +877,109 @@ impl FalEditor {
             ));
         }
 
-        let result: FalResponse = response
-            .json()
+        let max_len = self
+            .max_data_uri_bytes
+            .saturating_add(RESPONSE_OVERHEAD_BYTES);
+        let too_large = || {
+            anyhow!(
+                "Fal.ai response exceeds the limit of {} bytes (MAX_DATA_URI_BYTES plus {} bytes)",
+                max_len,
+                RESPONSE_OVERHEAD_BYTES
+            )
+        };
+        if response
+            .content_length()
+            .is_some_and(|len| len > max_len as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
             .await
-            .context("Failed to parse Fal.ai response")?;
+            .context("Failed to read Fal.ai response body")?
+        {
+            if body.len() + chunk.len() > max_len {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        String::from_utf8(body).context("Fal.ai response body is not UTF-8")
+    }
 
-        tracing::debug!("Received response from Fal.ai");
+    /// Non-sensitive details of a response, for `X-Provider-Metadata`
+    fn metadata(&self, response: &FalResponse) -> ProviderMetadata {
+        ProviderMetadata {
+            seed: response.seed,
+            inference_time_ms: response
+                .timings
+                .as_ref()
+                .and_then(|timings| timings.inference)
+                .map(|secs| (secs * 1000.0).round() as u64),
+            model_version: Some(self.model_path.clone()),
+        }
+    }
 
-        Ok(result)
+    /// URL requests are submitted to, according to the endpoint style
+    ///
+    /// The queue variant answers with a receipt whose status URL is polled
+    /// (see `await_queued`).
+    fn endpoint_url(&self) -> String {
+        match (&self.base_url, self.endpoint) {
+            (Some(base), _) => format!("{}/{}", base, self.model_path),
+            (None, FalEndpoint::Queue) => format!("https://queue.fal.run/{}", self.model_path),
+            (None, FalEndpoint::Direct) => format!("https://fal.run/{}", self.model_path),
+        }
+    }
+
+    /// Parse a Fal.ai response body
+    ///
+    /// On failure the error names the JSON path that didn't match the expected
+    /// shape and quotes a truncated snippet of the body, to make provider schema
+    /// drift easy to diagnose.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is not JSON or doesn't match `FalResponse`.
+    /// A payload wrapped in a `data` object is returned unwrapped.
+    fn parse_response(body: &str) -> Result<FalResponse> {
+        let deserializer = &mut serde_json::Deserializer::from_str(body);
+
+        let response: FalResponse =
+            serde_path_to_error::deserialize(deserializer).map_err(|e| {
+                let path = e.path().to_string();
+                tracing::warn!(
+                    path = %path,
+                    error = %e.inner(),
+                    body_len = body.len(),
+                    "Fal.ai response did not match the expected schema"
+                );
+                anyhow!(
+                    "Failed to parse Fal.ai response at `{}`: {} (response body: {})",
+                    path,
+                    e.inner(),
+                    Self::snippet(body)
+                )
+            })?;
+
+        Ok(response.unwrap_data())
+    }
+
+    /// Truncate a response body for inclusion in error messages
+    fn snippet(body: &str) -> String {
+        match body.char_indices().nth(RESPONSE_SNIPPET_CHARS) {
+            Some((end, _)) => format!("{}... ({} bytes total)", &body[..end], body.len()),
+            None => body.to_string(),
+        }
     }
 
     /// Download an image from a URL
     ///
     /// Fetches the image data from an HTTP/HTTPS URL and returns it as bytes.
+    /// Transient failures such as a connection reset mid-download are retried
+    /// with a fresh GET (up to `DOWNLOAD_ATTEMPTS` in total); partially read
+    /// bodies are always discarded. With the `caching` feature enabled, repeat
+    /// downloads of a URL within the editor's lifetime are served from memory.
     ///
     /// # Arguments
     ///
@@ -281,35 +991,125 @@ impl FalEditor {
     ///
     /// # Errors
     ///
-    /// Returns an error if the download fails or the response is invalid
+    /// Returns an error if the download fails fatally or every attempt fails
     async fn download_image(&self, url: &str) -> Result<(Bytes, Option<String>)> {
+        if let Some(hit) = self.cached_download(url) {
+            tracing::debug!(url = %url, size = hit.0.len(), "Serving image from download cache");
+            return Ok(hit);
+        }
+
+        let mut attempt = 1;
+
+        loop {
+            match self.download_once(url).await {
+                Ok(result) => {
+                    self.cache_download(url, &result);
+                    return Ok(result);
+                }
+                Err(DownloadError::Fatal(e)) => return Err(e),
+                Err(DownloadError::Retryable(e)) if attempt >= DOWNLOAD_ATTEMPTS => {
+                    return Err(e.context(format!("Download failed after {} attempts", attempt)));
+                }
+                Err(DownloadError::Retryable(e)) => {
+                    let delay = DOWNLOAD_RETRY_DELAY * 2u32.pow(attempt - 1);
+                    tracing::warn!(
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %format!("{:#}", e),
+                        "Retrying Fal.ai image download"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Look up a previous download of `url`
+    fn cached_download(&self, url: &str) -> Option<(Bytes, Option<String>)> {
+        let cache = self.download_cache.as_ref()?;
+        cache.lock().unwrap_or_else(|e| e.into_inner()).get(url)
+    }
+
+    /// Remember a successful download of `url`
+    fn cache_download(&self, url: &str, (bytes, mime_type): &(Bytes, Option<String>)) {
+        if let Some(cache) = &self.download_cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).insert(
+                url,
+                bytes.clone(),
+                mime_type.clone(),
+            );
+        }
+    }
+
+    /// Make a single download attempt
+    async fn download_once(&self, url: &str) -> Result<(Bytes, Option<String>), DownloadError> {
         tracing::debug!(url = %url, "Downloading image from URL");
 
-        let response = self
+        let mut response = self
             .client
             .get(url)
             .timeout(Duration::from_secs(120))
             .send()
             .await
-            .context("Failed to download image from Fal.ai URL")?;
+            .map_err(|e| {
+                DownloadError::from_reqwest(e, "Failed to download image from Fal.ai URL")
+            })?;
 
-        if !response.status().is_success() {
-            return Err(anyhow!(
-                "Failed to download image: HTTP {}",
-                response.status()
-            ));
+        let status = response.status();
+        if !status.is_success() {
+            let error = anyhow!("Failed to download image: HTTP {}", status);
+            return Err(
+                if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    DownloadError::Retryable(error)
+                } else {
+                    DownloadError::Fatal(error)
+                },
+            );
         }
 
+        // The only header read from the download; the rest (Set-Cookie, Server,
+        // ...) are dropped with the response and never reach clients
         let mime_type = response
             .headers()
             .get("content-type")
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string());
+        let expected_len = response.content_length();
 
-        let bytes = response
-            .bytes()
+        // Oversized results are refused before and while reading, not once buffered
+        let too_large = || {
+            DownloadError::Fatal(anyhow!(
+                "Fal.ai result exceeds the limit of {} bytes (MAX_RESULT_BYTES)",
+                self.max_result_bytes
+            ))
+        };
+        if expected_len.is_some_and(|len| len > self.max_result_bytes as u64) {
+            return Err(too_large());
+        }
+        let mut bytes = Vec::with_capacity(expected_len.unwrap_or(0) as usize);
+        while let Some(chunk) = response
+            .chunk()
             .await
-            .context("Failed to read image bytes")?;
+            .map_err(|e| DownloadError::from_reqwest(e, "Failed to read image bytes"))?
+        {
+            if bytes.len() + chunk.len() > self.max_result_bytes {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let bytes = Bytes::from(bytes);
+
+        // Never hand back a truncated body
+        if let Some(expected) = expected_len {
+            if bytes.len() as u64 != expected {
+                return Err(DownloadError::Retryable(anyhow!(
+                    "Incomplete image download: received {} of {} bytes",
+                    bytes.len(),
+                    expected
+                )));
+            }
+        }
 
         tracing::debug!(
             size = bytes.len(),
@@ -325,6 +1125,7 @@ impl FalEditor {
     /// # Arguments
     ///
     /// * `data_uri` - A data URI string (e.g., "data:image/png;base64,...")
+    /// * `max_len` - Longest data URI accepted, in bytes
     ///
     /// # Returns
     ///
@@ -332,11 +1133,20 @@ impl FalEditor {
     ///
     /// # Errors
     ///
-    /// Returns an error if the data URI is malformed or base64 decoding fails
-    fn decode_data_uri(data_uri: &str) -> Result<(Bytes, Option<String>)> {
+    /// Returns an error if the data URI is longer than `max_len`, malformed,
+    /// or base64 decoding fails
+    fn decode_data_uri(data_uri: &str, max_len: usize) -> Result<(Bytes, Option<String>)> {
         if !data_uri.starts_with("data:") {
             return Err(anyhow!("Not a data URI"));
         }
+        // Checked before decoding, so an oversized result is never copied
+        if data_uri.len() > max_len {
+            return Err(anyhow!(
+                "Data URI is {} bytes, over the {} byte limit (MAX_DATA_URI_BYTES)",
+                data_uri.len(),
+                max_len
+            ));
+        }
 
         let parts: Vec<&str> = data_uri.splitn(2, ',').collect();
         if parts.len() != 2 {
@@ -368,6 +1178,39 @@ impl FalEditor {
         Ok((Bytes::from(decoded), mime_type))
     }
 
+    /// Result image in the requested output format
+    ///
+    /// Some models ignore `output_format` and return e.g. WebP; such results
+    /// are transcoded so the content is never mislabeled downstream. Bytes in
+    /// an unrecognized format are passed through for later validation.
+    /// Transcoding runs on the blocking thread pool.
+    async fn ensure_requested_format(&self, bytes: Bytes) -> Result<Bytes> {
+        let actual = match image::guess_format(&bytes) {
+            Ok(format) if format != REQUESTED_OUTPUT_FORMAT => format,
+            _ => return Ok(bytes),
+        };
+
+        tracing::info!(
+            model = %self.model_path,
+            actual = ?actual,
+            requested = ?REQUESTED_OUTPUT_FORMAT,
+            "Fal.ai ignored the requested output format; transcoding result"
+        );
+
+        tokio::task::spawn_blocking(move || {
+            let img = image_utils::bytes_to_image(&bytes)
+                .with_context(|| format!("Failed to decode {:?} result for transcoding", actual))?;
+            image_utils::image_to_bytes(&img, REQUESTED_OUTPUT_FORMAT).with_context(|| {
+                format!(
+                    "Failed to transcode result to {:?}",
+                    REQUESTED_OUTPUT_FORMAT
+                )
+            })
+        })
+        .await
+        .context("Transcoding task failed")?
+    }
+
     /// Extract the image URL from a Fal.ai response
     ///
     /// Fal.ai responses can have different structures depending on the model.
@@ -400,60 +1243,29 @@ impl FalEditor {
 
         None
     }
-}
 
-#[async_trait::async_trait]
-impl ImageEditor for FalEditor {
-    /// Edit an image using Fal.ai models
-    ///
-    /// This method implements the complete Fal.ai workflow:
-    /// 1. Converts image to base64 data URI
-    /// 2. Submits to Fal.ai with sync_mode=true (handles polling automatically)
-    /// 3. Extracts result URL from response
-    /// 4. Downloads or decodes the result image
-    ///
-    /// # Arguments
-    ///
-    /// * `image_bytes` - The input image data
-    /// * `prompt` - Text description of desired edits
-    ///
-    /// # Returns
-    ///
-    /// Returns the edited image as bytes
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if:
-    /// - The image cannot be converted to a data URI
-    /// - The Fal.ai API request fails
-    /// - No image URL is found in the response
-    /// - The result image cannot be downloaded or decoded
-    ///
-    /// # Example
-    ///
-    /// ```rust,no_run
-    /// use frameforge_server::services::base::ImageEditor;
-    /// use frameforge_server::services::fal_editor::FalEditor;
-    /// use frameforge_server::config::AppConfig;
-    /// use bytes::Bytes;
-    ///
-    /// async fn edit(config: &AppConfig, image: Bytes) -> anyhow::Result<Bytes> {
-    ///     let editor = FalEditor::new("fal-ai/flux/dev".to_string(), config)?;
-    ///     let prompt = "Add modern furniture to this room";
-    ///     editor.edit_image(image, prompt).await
-    /// }
-    /// ```
-    async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
+    /// Run an edit, with a region mask for inpainting models
+    async fn edit(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+        mask: Option<&[u8]>,
+    ) -> Result<(Bytes, ProviderMetadata)> {
+        if images.is_empty() {
+            return Err(anyhow!("at least one image is required"));
+        }
+
         tracing::info!(
             model = %self.model_path,
-            prompt = %prompt,
-            image_size = image_bytes.len(),
+            prompt = %log_redaction::prompt(prompt, self.log_redaction),
+            image_count = images.len(),
+            image_size = images.iter().map(Bytes::len).sum::<usize>(),
             "Starting Fal.ai image editing"
         );
 
-        // Submit request to Fal.ai (sync_mode handles polling automatically)
+        // Submit request to Fal.ai (queued requests are polled to completion)
         let response = self
-            .submit_request(&image_bytes, prompt)
+            .submit_request(&images, prompt, mask)
             .await
             .context("Failed to submit request to Fal.ai")?;
 
@@ -461,12 +1273,15 @@ impl ImageEditor for FalEditor {
         let image_url = Self::extract_image_url(&response)
             .ok_or_else(|| anyhow!("No image URL found in Fal.ai response"))?;
 
-        tracing::debug!(url = %image_url, "Got image URL from Fal.ai");
+        tracing::debug!(
+            url = %log_redaction::url(&image_url, self.log_redaction),
+            "Got image URL from Fal.ai"
+        );
 
         // Handle different URL types
         let (result_bytes, _mime_type) = if image_url.starts_with("data:") {
             // Data URI - decode locally
-            Self::decode_data_uri(&image_url)
+            Self::decode_data_uri(&image_url, self.max_data_uri_bytes)
                 .context("Failed to decode data URI from Fal.ai")?
         } else {
             // HTTP(S) URL - download
@@ -474,13 +1289,116 @@ impl ImageEditor for FalEditor {
                 .await
                 .context("Failed to download result image")?
         };
+        let result_bytes = self.ensure_requested_format(result_bytes).await?;
 
         tracing::info!(
             result_size = result_bytes.len(),
             "Successfully completed Fal.ai image editing"
         );
 
-        Ok(result_bytes)
+        Ok((result_bytes, self.metadata(&response)))
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageEditor for FalEditor {
+    /// Edit an image using Fal.ai models
+    ///
+    /// This method implements the complete Fal.ai workflow:
+    /// 1. Converts image to base64 data URI
+    /// 2. Submits to Fal.ai, polling the queue until a queued request completes
+    /// 3. Extracts result URL from response
+    /// 4. Downloads or decodes the result image
+    ///
+    /// # Arguments
+    ///
+    /// * `image_bytes` - The input image data
+    /// * `prompt` - Text description of desired edits
+    ///
+    /// # Returns
+    ///
+    /// Returns the edited image as bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The image cannot be converted to a data URI
+    /// - The Fal.ai API request fails
+    /// - No image URL is found in the response
+    /// - The result image cannot be downloaded or decoded
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use frameforge_server::services::base::ImageEditor;
+    /// use frameforge_server::services::fal_editor::FalEditor;
+    /// use frameforge_server::config::AppConfig;
+    /// use bytes::Bytes;
+    ///
+    /// async fn edit(config: &AppConfig, image: Bytes) -> anyhow::Result<Bytes> {
+    ///     let editor = FalEditor::new("fal-ai/flux/dev".to_string(), config)?;
+    ///     let prompt = "Add modern furniture to this room";
+    ///     editor.edit_image(image, prompt).await
+    /// }
+    /// ```
+    async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
+        self.edit_images(vec![image_bytes], prompt).await
+    }
+
+    /// Edit several images in one request
+    ///
+    /// Multi-image models receive every distinct image; single-image models
+    /// only the first.
+    async fn edit_images(&self, images: Vec<Bytes>, prompt: &str) -> Result<Bytes> {
+        let (result, _metadata) = self.edit_images_with_metadata(images, prompt).await?;
+        Ok(result)
+    }
+
+    /// Edit several images, reporting the seed and inference time Fal.ai returned
+    async fn edit_images_with_metadata(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+    ) -> Result<(Bytes, ProviderMetadata)> {
+        self.edit(images, prompt, None).await
+    }
+
+    fn supports_regions(&self) -> bool {
+        self.is_inpaint_model()
+    }
+
+    /// Send `seed`, `num_inference_steps` and `guidance_scale` when set;
+    /// Fal.ai models take no `temperature` or `top_p`
+    fn set_sampling(&mut self, sampling: SamplingOptions) {
+        self.sampling = sampling;
+    }
+
+    /// Merge `options` into the JSON body sent to the model
+    fn set_provider_options(&mut self, options: ProviderOptions) {
+        self.provider_options = options;
+    }
+
+    /// Edit `region` of the first image, sent to inpainting models as `mask_url`
+    async fn edit_region(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+        region: &EditRegion,
+    ) -> Result<(Bytes, ProviderMetadata)> {
+        if !self.is_inpaint_model() {
+            return Err(anyhow!(
+                "{} does not support region editing",
+                self.model_path
+            ));
+        }
+        let first = images
+            .first()
+            .ok_or_else(|| anyhow!("at least one image is required"))?;
+        let (first, region) = (first.clone(), *region);
+        let mask = tokio::task::spawn_blocking(move || Self::region_mask(&first, &region))
+            .await
+            .context("Region mask task failed")??;
+        self.edit(images, prompt, Some(&mask)).await
     }
 }
 
@@ -507,29 +1425,889 @@ mod tests {
     }
 
     #[test]
-    fn test_bytes_to_data_uri() {
+    fn test_data_uri() {
         let image_data = Bytes::from_static(b"\x89PNG\r\n\x1a\ntest data");
-        let data_uri = FalEditor::bytes_to_data_uri(&image_data);
+        let data_uri = FalEditor::data_uri(&image_data).to_string();
         assert!(data_uri.starts_with("data:image/png;base64,"));
     }
 
+    /// The data URI as built before streaming encoding: one full base64 string
+    fn buffered_data_uri(image_bytes: &[u8]) -> String {
+        let mime = FalEditor::detect_mime_type(image_bytes);
+        let base64_data = base64::engine::general_purpose::STANDARD.encode(image_bytes);
+        format!("data:{};base64,{}", mime, base64_data)
+    }
+
+    #[test]
+    fn test_streamed_data_uri_matches_buffered_encoding() {
+        // Sizes around the chunk boundary exercise every padding case
+        for len in [
+            0,
+            1,
+            2,
+            3,
+            BASE64_CHUNK_BYTES - 1,
+            BASE64_CHUNK_BYTES,
+            BASE64_CHUNK_BYTES + 1,
+            100_003,
+        ] {
+            let mut image = b"\xff\xd8\xff".to_vec();
+            image.extend((0..len).map(|i| (i * 31 % 251) as u8));
+
+            let streamed = FalEditor::data_uri(&image);
+            let expected = buffered_data_uri(&image);
+            assert_eq!(streamed.to_string(), expected, "length {}", len);
+            assert_eq!(streamed.encoded_len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_request_body_matches_buffered_encoding_in_one_allocation() {
+        let image: Vec<u8> = b"\x89PNG\r\n\x1a\n"
+            .iter()
+            .copied()
+            .cycle()
+            .take(50_000)
+            .collect();
+        let data_uri = FalEditor::data_uri(&image);
+        let request = FalRequest {
+            prompt: "Add a \"sofa\"",
+            image_url: None,
+            image_urls: Some(vec![data_uri]),
+            mask_url: None,
+            output_format: REQUESTED_OUTPUT_FORMAT.extensions_str()[0].to_string(),
+            sync_mode: true,
+            options: ProviderOptions::new(),
+        };
+
+        let body = FalEditor::encode_request_body(&request, data_uri.encoded_len()).unwrap();
+
+        let expected = serde_json::json!({
+            "prompt": "Add a \"sofa\"",
+            "image_urls": [buffered_data_uri(&image)],
+            "output_format": "png",
+            "sync_mode": true,
+        });
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            expected
+        );
+        // The pre-sized buffer was large enough, so the body was never copied while growing
+        assert!(body.len() <= data_uri.encoded_len() + request.prompt.len() * 2 + 256);
+    }
+
+    fn encoded(format: ImageFormat) -> Vec<u8> {
+        let img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(3, 2));
+        image_utils::image_to_bytes(&img, format).unwrap().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_gif_transcoded_to_png_data_uri_for_listed_model() {
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            fal_transcode_models: vec!["fal-ai/flux-kontext/dev".to_string()],
+            ..AppConfig::default()
+        };
+        let editor = FalEditor::new("fal-ai/flux-kontext/dev".to_string(), &config).unwrap();
+        let gif = Bytes::from(encoded(ImageFormat::Gif));
+
+        let bytes = editor.data_uri_bytes(&gif).await.unwrap();
+        assert!(FalEditor::data_uri(&bytes)
+            .to_string()
+            .starts_with("data:image/png;base64,"));
+        assert_eq!(image_utils::image_dimensions(&bytes).unwrap(), (3, 2));
+
+        // Already portable inputs are passed through as-is
+        let png = Bytes::from(encoded(ImageFormat::Png));
+        assert!(matches!(
+            editor.data_uri_bytes(&png).await.unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_gif_data_uri_kept_for_unlisted_model() {
+        let gif = Bytes::from(encoded(ImageFormat::Gif));
+
+        let bytes = make_editor().data_uri_bytes(&gif).await.unwrap();
+        assert!(FalEditor::data_uri(&bytes)
+            .to_string()
+            .starts_with("data:image/gif;base64,"));
+    }
+
+    /// Data URIs in the serialized request for `images`
+    async fn submitted_data_uris(editor: &FalEditor, images: &[Bytes]) -> Vec<String> {
+        let images = editor.request_images(images).await.unwrap();
+        let data_uris: Vec<_> = images
+            .iter()
+            .map(|image| FalEditor::data_uri(image))
+            .collect();
+        let request =
+            serde_json::to_value(editor.build_request("prompt", &data_uris, None)).unwrap();
+
+        request["image_urls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|uri| uri.as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_provider_options_merged_without_overriding_modeled_fields() {
+        let mut editor = make_editor();
+        let options = serde_json::json!({
+            "guidance_scale": 3.5,
+            "acceleration": "high",
+            "enable_safety_checker": false,
+            "num_images": 50,
+            "prompt": "something else",
+            "output_format": "jpeg",
+        });
+        editor.set_provider_options(options.as_object().unwrap().clone());
+        let image = encoded(ImageFormat::Png);
+
+        let request = serde_json::to_value(editor.build_request(
+            "Add a rug",
+            &[FalEditor::data_uri(&image)],
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(request["guidance_scale"], 3.5);
+        assert_eq!(request["acceleration"], "high");
+        // Denied by default PROVIDER_OPTIONS_DENY
+        assert!(request.get("enable_safety_checker").is_none());
+        assert!(request.get("num_images").is_none());
+        assert_eq!(request["prompt"], "Add a rug");
+        assert_eq!(request["output_format"], "png");
+    }
+
+    #[test]
+    fn test_generation_parameters_sent_in_request() {
+        let mut editor = make_editor();
+        let options = serde_json::json!({ "guidance_scale": 3.5, "acceleration": "high" });
+        editor.set_provider_options(options.as_object().unwrap().clone());
+        editor.set_sampling(SamplingOptions {
+            seed: Some(42),
+            num_inference_steps: Some(20),
+            guidance_scale: Some(4.0),
+            temperature: Some(0.5),
+            ..SamplingOptions::default()
+        });
+        let image = encoded(ImageFormat::Png);
+
+        let request = serde_json::to_value(editor.build_request(
+            "Add a rug",
+            &[FalEditor::data_uri(&image)],
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(request["seed"], 42);
+        assert_eq!(request["num_inference_steps"], 20);
+        assert_eq!(request["guidance_scale"], 4.0);
+        assert_eq!(request["acceleration"], "high");
+        assert!(request.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_region_sent_as_mask_url() {
+        let mut editor = make_editor();
+        editor.model_path = "fal-ai/flux-pro/v1/fill".to_string();
+        assert!(editor.supports_regions());
+        let image =
+            image_utils::image_to_bytes(&image::DynamicImage::new_rgb8(8, 4), ImageFormat::Png)
+                .unwrap();
+        let region: EditRegion = "0.5,0.25,0.25,0.5".parse().unwrap();
+
+        let mask = FalEditor::region_mask(&image, &region).unwrap();
+        let request = serde_json::to_value(editor.build_request(
+            "prompt",
+            &[FalEditor::data_uri(&image)],
+            Some(FalEditor::data_uri(&mask)),
+        ))
+        .unwrap();
+
+        assert!(request["image_url"].is_string());
+        let mask_url = request["mask_url"].as_str().unwrap();
+        let (mask, _) = FalEditor::decode_data_uri(mask_url, usize::MAX).unwrap();
+        let mask = image::load_from_memory(&mask).unwrap().to_luma8();
+        assert_eq!(mask.dimensions(), (8, 4));
+        let white: Vec<(u32, u32)> = mask
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0[0] == u8::MAX)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert_eq!(white, vec![(4, 1), (5, 1), (4, 2), (5, 2)]);
+        assert!(mask
+            .pixels()
+            .all(|pixel| pixel.0[0] == 0 || pixel.0[0] == u8::MAX));
+    }
+
+    #[test]
+    fn test_regions_only_supported_by_inpaint_models() {
+        let editor = make_editor();
+        assert!(!editor.supports_regions());
+
+        let request = serde_json::to_value(editor.build_request("prompt", &[], None)).unwrap();
+        assert!(request.get("mask_url").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_identical_uploads_collapse_to_one_data_uri() {
+        let image = Bytes::from(encoded(ImageFormat::Png));
+        let other = Bytes::from(encoded(ImageFormat::Jpeg));
+
+        let uris =
+            submitted_data_uris(&make_editor(), &[image.clone(), image.clone(), other]).await;
+
+        assert_eq!(uris.len(), 2);
+        assert_eq!(uris[0], buffered_data_uri(&image));
+        assert!(uris[1].starts_with("data:image/jpeg;base64,"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_kept_when_dedupe_disabled() {
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            dedupe_input_images: false,
+            ..AppConfig::default()
+        };
+        let editor = FalEditor::new("fal-ai/nano-banana/edit".to_string(), &config).unwrap();
+        let image = Bytes::from(encoded(ImageFormat::Png));
+
+        assert_eq!(
+            submitted_data_uris(&editor, &[image.clone(), image])
+                .await
+                .len(),
+            2
+        );
+    }
+
     #[test]
     fn test_decode_data_uri() {
         let test_data = b"Hello, World!";
         let base64_data = base64::engine::general_purpose::STANDARD.encode(test_data);
         let data_uri = format!("data:text/plain;base64,{}", base64_data);
 
-        let result = FalEditor::decode_data_uri(&data_uri);
+        let result = FalEditor::decode_data_uri(&data_uri, data_uri.len());
         assert!(result.is_ok());
 
         let (decoded, mime) = result.unwrap();
         assert_eq!(&decoded[..], test_data);
         assert_eq!(mime, Some("text/plain".to_string()));
+
+        let err = FalEditor::decode_data_uri(&data_uri, data_uri.len() - 1).unwrap_err();
+        assert!(err.to_string().contains("byte limit"), "{}", err);
     }
 
     #[test]
     fn test_decode_invalid_data_uri() {
-        assert!(FalEditor::decode_data_uri("not a data uri").is_err());
-        assert!(FalEditor::decode_data_uri("data:text/plain").is_err());
+        assert!(FalEditor::decode_data_uri("not a data uri", usize::MAX).is_err());
+        assert!(FalEditor::decode_data_uri("data:text/plain", usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_parse_response_valid() {
+        let response =
+            FalEditor::parse_response(r#"{"images":[{"url":"https://fal.media/a.png"}]}"#).unwrap();
+        assert_eq!(
+            FalEditor::extract_image_url(&response).as_deref(),
+            Some("https://fal.media/a.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_response_unwraps_data() {
+        let body = r#"{"data":{"images":[{"url":"https://fal.media/wrapped.png"}],"seed":7},"request_id":"abc"}"#;
+        let response = FalEditor::parse_response(body).unwrap();
+
+        assert_eq!(
+            FalEditor::extract_image_url(&response).as_deref(),
+            Some("https://fal.media/wrapped.png")
+        );
+        assert_eq!(response.seed, Some(7));
+
+        // A top-level image wins over the wrapper
+        let body = r#"{"image":{"url":"https://fal.media/top.png"},"data":{"image":{"url":"https://fal.media/inner.png"}}}"#;
+        let response = FalEditor::parse_response(body).unwrap();
+        assert_eq!(
+            FalEditor::extract_image_url(&response).as_deref(),
+            Some("https://fal.media/top.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_response_reports_path_and_snippet() {
+        let body = r#"{"images":[{"uri":"https://fal.media/a.png"}]}"#;
+        let message = FalEditor::parse_response(body).unwrap_err().to_string();
+
+        assert!(
+            message.contains("images[0]"),
+            "missing path in: {}",
+            message
+        );
+        assert!(
+            message.contains("missing field `url`"),
+            "missing cause in: {}",
+            message
+        );
+        assert!(
+            message.contains(r#""uri":"https://fal.media/a.png""#),
+            "missing snippet in: {}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_parse_response_truncates_long_bodies() {
+        let body = format!("<html>{}</html>", "x".repeat(2000));
+        let message = FalEditor::parse_response(&body).unwrap_err().to_string();
+
+        assert!(message.contains("<html>xxx"));
+        assert!(message.contains(&format!("({} bytes total)", body.len())));
+        assert!(message.len() < 1000);
+    }
+
+    /// Serve canned HTTP responses, one per connection, on a local port
+    ///
+    /// Each response is written raw and the connection is then closed, so a
+    /// response whose body is shorter than its `Content-Length` simulates a
+    /// connection dropped mid-download. Returns the base URL and a counter of
+    /// accepted connections.
+    async fn serve_responses(
+        responses: Vec<Vec<u8>>,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                // Read the request head before answering
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                let _ = stream.write_all(&response).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        (format!("http://{}", addr), connections)
+    }
+
+    fn http_response(status: &str, declared_len: usize, body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status, declared_len
+        )
+        .into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn make_editor() -> FalEditor {
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            ..AppConfig::default()
+        };
+        FalEditor::new("fal-ai/flux/dev".to_string(), &config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_download_retries_after_mid_stream_reset() {
+        let image = b"\x89PNG\r\n\x1a\nfull image body".to_vec();
+        let (url, connections) = serve_responses(vec![
+            // Declares the full length but drops the connection after a few bytes
+            http_response("200 OK", image.len(), &image[..6]),
+            http_response("200 OK", image.len(), &image),
+        ])
+        .await;
+
+        let (bytes, mime) = make_editor()
+            .download_image(&format!("{}/result.png", url))
+            .await
+            .unwrap();
+
+        assert_eq!(&bytes[..], &image[..]);
+        assert_eq!(mime.as_deref(), Some("image/png"));
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_download_does_not_retry_fatal_status() {
+        let (url, connections) = serve_responses(vec![
+            http_response("404 Not Found", 0, b""),
+            http_response("200 OK", 4, b"\x89PNG"),
+        ])
+        .await;
+
+        let err = make_editor()
+            .download_image(&format!("{}/missing.png", url))
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("404"));
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_download_refuses_results_over_max_result_bytes() {
+        let image = b"\x89PNG\r\n\x1a\nfull image body".to_vec();
+        // Declared too large, then too large without a declared length
+        let mut unsized_response =
+            b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nConnection: close\r\n\r\n".to_vec();
+        unsized_response.extend_from_slice(&image);
+        let (url, connections) = serve_responses(vec![
+            http_response("200 OK", image.len(), &image),
+            unsized_response,
+        ])
+        .await;
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            max_result_bytes: image.len() - 1,
+            ..AppConfig::default()
+        };
+        let editor = FalEditor::new("fal-ai/flux/dev".to_string(), &config).unwrap();
+
+        for _ in 0..2 {
+            let err = editor
+                .download_image(&format!("{}/result.png", url))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("MAX_RESULT_BYTES"), "{:#}", err);
+        }
+        // Refusals are not retried
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_download_gives_up_after_max_attempts() {
+        let truncated = http_response("200 OK", 100, b"\x89PNG");
+        let (url, connections) = serve_responses(vec![truncated; DOWNLOAD_ATTEMPTS as usize]).await;
+
+        let err = make_editor()
+            .download_image(&format!("{}/result.png", url))
+            .await
+            .unwrap_err();
+
+        assert!(format!("{:#}", err).contains("after 3 attempts"));
+        assert_eq!(
+            connections.load(std::sync::atomic::Ordering::SeqCst),
+            DOWNLOAD_ATTEMPTS as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repeat_download_hits_cache() {
+        let image = b"\x89PNG\r\n\x1a\ncached body".to_vec();
+        let (url, connections) = serve_responses(vec![
+            http_response("200 OK", image.len(), &image),
+            http_response("200 OK", image.len(), &image),
+        ])
+        .await;
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            features: crate::config::FeatureFlags {
+                caching: true,
+                ..Default::default()
+            },
+            ..AppConfig::default()
+        };
+        let editor = FalEditor::new("fal-ai/flux/dev".to_string(), &config).unwrap();
+        let url = format!("{}/result.png", url);
+
+        let (first, _) = editor.download_image(&url).await.unwrap();
+        let (second, mime) = editor.download_image(&url).await.unwrap();
+
+        assert_eq!(&first[..], &image[..]);
+        assert_eq!(&second[..], &image[..]);
+        assert_eq!(mime.as_deref(), Some("image/png"));
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_edits_sharing_a_result_url_download_it_once() {
+        let png = encoded(ImageFormat::Png);
+        let mut download = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            png.len()
+        )
+        .into_bytes();
+        download.extend_from_slice(&png);
+        let (download_url, downloads) = serve_responses(vec![download]).await;
+
+        // The cache is process-wide, so the path keeps this URL distinct from other tests'
+        let body = serde_json::json!({
+            "images": [{ "url": format!("{}/shared-result.png", download_url) }],
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (url, _) =
+            serve_responses(vec![response.clone().into_bytes(), response.into_bytes()]).await;
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            features: crate::config::FeatureFlags {
+                caching: true,
+                ..Default::default()
+            },
+            ..AppConfig::default()
+        };
+
+        // Editors are built per request, so each edit gets its own
+        for _ in 0..2 {
+            let editor = FalEditor::new("fal-ai/flux/dev".to_string(), &config)
+                .unwrap()
+                .with_base_url(url.clone());
+            let result = editor
+                .edit_image(Bytes::from(png.clone()), "prompt")
+                .await
+                .unwrap();
+            assert_eq!(result, png);
+        }
+
+        assert_eq!(downloads.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_download_cache_disabled_by_default() {
+        let image = b"\x89PNG\r\n\x1a\nbody".to_vec();
+        let (url, connections) = serve_responses(vec![
+            http_response("200 OK", image.len(), &image),
+            http_response("200 OK", image.len(), &image),
+        ])
+        .await;
+        let editor = make_editor();
+        let url = format!("{}/result.png", url);
+
+        editor.download_image(&url).await.unwrap();
+        editor.download_image(&url).await.unwrap();
+
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_submit_is_auth_error() {
+        let body = br#"{"detail":"Invalid Key test-fal-key"}"#;
+        let (url, _) =
+            serve_responses(vec![http_response("401 Unauthorized", body.len(), body)]).await;
+        let editor = make_editor().with_base_url(url);
+
+        let err = editor
+            .edit_image(Bytes::from_static(b"\x89PNG\r\n\x1a\n"), "prompt")
+            .await
+            .unwrap_err();
+
+        let auth = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ProviderAuthError>())
+            .expect("expected a ProviderAuthError in the chain");
+        assert_eq!(auth.status, Some(401));
+
+        let app_error = crate::error::AppError::from_provider(err);
+        let message = app_error.to_string();
+        assert_eq!(app_error.error_type(), "provider_auth_error");
+        assert!(
+            message.contains("check FAL_KEY"),
+            "unexpected message: {}",
+            message
+        );
+        assert!(
+            !message.contains("test-fal-key"),
+            "key leaked in: {}",
+            message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_submit_is_auth_error() {
+        let (url, _) = serve_responses(vec![http_response("403 Forbidden", 0, b"")]).await;
+        let editor = make_editor().with_base_url(url);
+
+        let err = editor
+            .submit_request(&[Bytes::from_static(b"\x89PNG")], "prompt", None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<ProviderAuthError>()
+                .map(|auth| auth.status),
+            Some(Some(403))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_reports_seed_and_inference_time() {
+        let png = encoded(ImageFormat::Png);
+        let body = serde_json::json!({
+            "images": [{ "url": buffered_data_uri(&png) }],
+            "seed": 424242,
+            "timings": { "inference": 1.2345 },
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (url, _) = serve_responses(vec![response.into_bytes()]).await;
+        let editor = make_editor().with_base_url(url);
+
+        let (result, metadata) = editor
+            .edit_images_with_metadata(vec![Bytes::from(png.clone())], "prompt")
+            .await
+            .unwrap();
+
+        assert_eq!(result, png);
+        assert_eq!(metadata.seed, Some(424242));
+        assert_eq!(metadata.inference_time_ms, Some(1235));
+        assert_eq!(metadata.model_version.as_deref(), Some("fal-ai/flux/dev"));
+    }
+
+    #[tokio::test]
+    async fn test_webp_result_transcoded_to_requested_png() {
+        let webp = encoded(ImageFormat::WebP);
+        let mut download = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/webp\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            webp.len()
+        )
+        .into_bytes();
+        download.extend_from_slice(&webp);
+        let (download_url, _) = serve_responses(vec![download]).await;
+
+        let body = serde_json::json!({
+            "images": [{ "url": format!("{}/result.webp", download_url) }],
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (url, _) = serve_responses(vec![response.into_bytes()]).await;
+        let editor = make_editor().with_base_url(url);
+
+        let result = editor
+            .edit_image(Bytes::from(encoded(ImageFormat::Png)), "prompt")
+            .await
+            .unwrap();
+
+        assert_eq!(image::guess_format(&result).unwrap(), ImageFormat::Png);
+        assert_eq!(image_utils::image_dimensions(&result).unwrap(), (3, 2));
+    }
+
+    #[test]
+    fn test_endpoint_url_variants() {
+        assert_eq!(
+            make_editor().endpoint_url(),
+            "https://queue.fal.run/fal-ai/flux/dev"
+        );
+
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            fal_direct_models: vec!["fal-ai/flux/schnell".to_string()],
+            ..AppConfig::default()
+        };
+        let direct = FalEditor::new("fal-ai/flux/schnell".to_string(), &config).unwrap();
+        assert_eq!(direct.endpoint_url(), "https://fal.run/fal-ai/flux/schnell");
+    }
+
+    #[test]
+    fn test_queue_wait_not_charged_to_processing() {
+        let start = Instant::now();
+        let secs = |n| start + Duration::from_secs(n);
+        let mut tracker =
+            QueuePhaseTracker::new(Duration::from_secs(60), Duration::from_secs(10), start);
+
+        // 50s in the queue, then 8s running: within both limits
+        assert_eq!(tracker.observe(QueuePhase::InQueue, secs(30)), Ok(()));
+        assert_eq!(tracker.observe(QueuePhase::InProgress, secs(50)), Ok(()));
+        assert_eq!(tracker.observe(QueuePhase::Completed, secs(58)), Ok(()));
+
+        assert_eq!(tracker.queued, Duration::from_secs(50));
+        assert_eq!(tracker.running, Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_queue_and_processing_timeouts_are_distinct() {
+        let start = Instant::now();
+        let secs = |n| start + Duration::from_secs(n);
+
+        let mut queued =
+            QueuePhaseTracker::new(Duration::from_secs(60), Duration::from_secs(10), start);
+        let err = queued.observe(QueuePhase::InQueue, secs(61)).unwrap_err();
+        assert_eq!(err, QueueTimeout::Queue(Duration::from_secs(60)));
+        assert!(err.to_string().starts_with("Fal.ai queue timeout"));
+
+        let mut running =
+            QueuePhaseTracker::new(Duration::from_secs(60), Duration::from_secs(10), start);
+        assert_eq!(running.observe(QueuePhase::InProgress, secs(59)), Ok(()));
+        let err = running
+            .observe(QueuePhase::InProgress, secs(70))
+            .unwrap_err();
+        assert_eq!(err, QueueTimeout::Processing(Duration::from_secs(10)));
+        assert!(err.to_string().starts_with("Fal.ai processing timeout"));
+    }
+
+    #[test]
+    fn test_completion_after_limit_is_not_a_timeout() {
+        let start = Instant::now();
+        let mut tracker =
+            QueuePhaseTracker::new(Duration::from_secs(60), Duration::from_secs(10), start);
+
+        assert_eq!(tracker.observe(QueuePhase::InProgress, start), Ok(()));
+        assert_eq!(
+            tracker.observe(QueuePhase::Completed, start + Duration::from_secs(30)),
+            Ok(())
+        );
+    }
+
+    fn json_response(body: &serde_json::Value) -> Vec<u8> {
+        let body = body.to_string();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_response_over_data_uri_limit_refused_while_reading() {
+        let body = serde_json::json!({ "images": [{ "url": format!("data:image/png;base64,{}", "A".repeat(RESPONSE_OVERHEAD_BYTES + 8)) }] });
+        // Declared too large, then too large without a declared length
+        let mut unsized_response =
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n"
+                .to_vec();
+        unsized_response.extend_from_slice(body.to_string().as_bytes());
+        let (url, _) = serve_responses(vec![json_response(&body), unsized_response]).await;
+        let config = AppConfig {
+            fal_key: Some("test-fal-key".to_string()),
+            max_data_uri_bytes: 4,
+            ..AppConfig::default()
+        };
+        let editor = FalEditor::new("fal-ai/flux/dev".to_string(), &config)
+            .unwrap()
+            .with_base_url(url);
+
+        for _ in 0..2 {
+            let err = editor
+                .edit_image(Bytes::from(encoded(ImageFormat::Png)), "prompt")
+                .await
+                .unwrap_err();
+            assert!(
+                format!("{:#}", err).contains("MAX_DATA_URI_BYTES"),
+                "{:#}",
+                err
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_polled_until_completed() {
+        let png = encoded(ImageFormat::Png);
+        // Without receipt URLs, polling follows the queue layout on base_url
+        let (url, connections) = serve_responses(vec![
+            json_response(&serde_json::json!({ "request_id": "req-1" })),
+            json_response(&serde_json::json!({ "status": "IN_QUEUE", "queue_position": 2 })),
+            json_response(&serde_json::json!({ "status": "IN_PROGRESS" })),
+            json_response(&serde_json::json!({ "status": "COMPLETED" })),
+            json_response(&serde_json::json!({ "images": [{ "url": buffered_data_uri(&png) }] })),
+        ])
+        .await;
+        let editor = make_editor().with_base_url(url);
+
+        let result = editor
+            .edit_image(Bytes::from(png.clone()), "prompt")
+            .await
+            .unwrap();
+
+        assert_eq!(result, png);
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_queue_receipt_pointing_to_another_host_refused() {
+        let png = encoded(ImageFormat::Png);
+        let (foreign_url, foreign_connections) = serve_responses(vec![json_response(
+            &serde_json::json!({ "status": "COMPLETED" }),
+        )])
+        .await;
+        let (url, _) = serve_responses(vec![json_response(&serde_json::json!({
+            "request_id": "req-1",
+            "status_url": format!("{}/requests/req-1/status", foreign_url),
+            "response_url": format!("{}/requests/req-1", foreign_url),
+        }))])
+        .await;
+        let editor = make_editor().with_base_url(url);
+
+        let err = editor
+            .edit_image(Bytes::from(png), "prompt")
+            .await
+            .unwrap_err();
+
+        assert!(
+            format!("{:#}", err).contains("not on the queue host"),
+            "{:#}",
+            err
+        );
+        // The API key never reaches the foreign host
+        assert_eq!(
+            foreign_connections.load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_request_answered_with_queue_status_is_polled() {
+        let png = encoded(ImageFormat::Png);
+        // Submission, status poll and result fetch all go to the same host
+        let (url, connections) = serve_responses(vec![
+            json_response(&serde_json::json!({ "status": "IN_QUEUE", "request_id": "req-2", "queue_position": 0 })),
+            json_response(&serde_json::json!({ "status": "COMPLETED" })),
+            json_response(&serde_json::json!({ "images": [{ "url": buffered_data_uri(&png) }] })),
+        ])
+        .await;
+        let editor = make_editor().with_base_url(url.clone());
+
+        let result = editor
+            .edit_image(Bytes::from(png.clone()), "prompt")
+            .await
+            .unwrap();
+
+        assert_eq!(result, png);
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 3);
+        let submission = FalQueueSubmission {
+            request_id: "req-2".to_string(),
+            status_url: None,
+            response_url: None,
+        };
+        assert_eq!(
+            editor.queue_urls(&submission).unwrap(),
+            (
+                format!("{}/fal-ai/flux/dev/requests/req-2/status", url),
+                format!("{}/fal-ai/flux/dev/requests/req-2", url)
+            )
+        );
     }
 }
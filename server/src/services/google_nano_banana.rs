@@ -29,14 +29,21 @@
 //! - Currently supports single image input (per the ImageEditor trait)
 //! - Extracts base64-encoded images from the response stream
 
-use crate::config::AppConfig;
-use crate::services::base::ImageEditor;
-use anyhow::{anyhow, Context, Result};
+use crate::config::{AppConfig, LogRedaction};
+use crate::error::ProviderAuthError;
+use crate::services::base::{ImageEditor, ProviderMetadata, SamplingOptions};
+use crate::utils::log_redaction;
+use anyhow::{anyhow, bail, Context, Result};
 use base64::Engine;
 use bytes::Bytes;
 use futures::StreamExt;
-use genai::chat::{ChatMessage, ChatRequest, ContentPart, MessageContent};
+use genai::chat::ChatStreamEvent;
+use genai::chat::{ChatMessage, ChatOptions, ChatRequest, ContentPart, MessageContent};
 use genai::Client;
+use std::time::Duration;
+
+/// Error markers Gemini uses for rejected credentials when no status code is exposed
+const AUTH_FAILURE_MARKERS: [&str; 3] = ["UNAUTHENTICATED", "PERMISSION_DENIED", "API_KEY_INVALID"];
 
 /// Google Gemini Flash image editor implementation
 ///
@@ -56,6 +63,14 @@ pub struct GoogleNanaBananaEditor {
     model_id: String,
     /// API key for authentication
     api_key: Option<String>,
+    /// Time allowed for reading the response stream to its end
+    stream_timeout: Duration,
+    /// Redaction applied to prompts in debug logs
+    log_redaction: LogRedaction,
+    /// Sampling parameters sent with each request
+    sampling: SamplingOptions,
+    /// Largest result image accepted, in bytes (`MAX_RESULT_BYTES`)
+    max_result_bytes: usize,
 }
 
 impl GoogleNanaBananaEditor {
@@ -82,6 +97,7 @@ impl GoogleNanaBananaEditor {
     pub fn new(config: AppConfig) -> Self {
         let api_key = config.get_google_api_key().map(|s| s.to_string());
         let model_id = config.google_model_id.clone();
+        let stream_timeout = Duration::from_secs(config.google_timeout_secs);
 
         // Initialize client only if we have an API key
         let client = api_key.as_ref().map(|_key| {
@@ -99,9 +115,29 @@ impl GoogleNanaBananaEditor {
             client,
             model_id,
             api_key,
+            stream_timeout,
+            log_redaction: config.log_redaction,
+            sampling: SamplingOptions::default(),
+            max_result_bytes: config.max_result_bytes,
         }
     }
 
+    /// Chat options carrying the sampling parameters, if any are set
+    fn chat_options(&self) -> Option<ChatOptions> {
+        let SamplingOptions {
+            temperature, top_p, ..
+        } = self.sampling;
+        if temperature.is_none() && top_p.is_none() {
+            return None;
+        }
+
+        Some(ChatOptions {
+            temperature,
+            top_p,
+            ..ChatOptions::default()
+        })
+    }
+
     /// Guess MIME type from raw image bytes
     ///
     /// This function inspects the magic bytes at the start of the image data
@@ -130,22 +166,161 @@ impl GoogleNanaBananaEditor {
         }
 
         // GIF magic bytes
-        if data.len() >= 6 {
-            if &data[0..6] == b"GIF87a" || &data[0..6] == b"GIF89a" {
-                return "image/gif";
-            }
+        if data.len() >= 6 && (&data[0..6] == b"GIF87a" || &data[0..6] == b"GIF89a") {
+            return "image/gif";
         }
 
         // WebP magic bytes
-        if data.len() >= 12
-            && &data[0..4] == b"RIFF"
-            && &data[8..12] == b"WEBP"
-        {
+        if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
             return "image/webp";
         }
 
         "application/octet-stream"
     }
+
+    /// Recognize a genai error caused by Gemini rejecting the API key
+    ///
+    /// Web call errors carry the HTTP status; errors raised mid-stream only
+    /// carry the response text, so those are matched on Gemini's error markers.
+    fn auth_failure(err: &genai::Error) -> Option<ProviderAuthError> {
+        let status = match err {
+            genai::Error::WebModelCall { webc_error, .. }
+            | genai::Error::WebAdapterCall { webc_error, .. } => match webc_error {
+                genai::webc::Error::ResponseFailedStatus { status, .. } => Some(status.as_u16()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let rejected = match status {
+            Some(401 | 403) => true,
+            _ => {
+                let text = err.to_string();
+                AUTH_FAILURE_MARKERS
+                    .iter()
+                    .any(|marker| text.contains(marker))
+            }
+        };
+
+        rejected.then_some(ProviderAuthError {
+            provider: "Google Gemini",
+            status,
+            key_source: "GOOGLE_API_KEY / GEMINI_API_KEY or the X-Google-Api-Key header",
+        })
+    }
+
+    /// Convert a genai error, surfacing authentication failures as `ProviderAuthError`
+    fn map_genai_error(err: genai::Error, context: &'static str) -> anyhow::Error {
+        match Self::auth_failure(&err) {
+            Some(auth) => {
+                // Keep the upstream details in the logs only
+                tracing::warn!(error = %err, "Google Gemini rejected the API key");
+                auth.into()
+            }
+            None => anyhow::Error::new(err).context(context),
+        }
+    }
+
+    /// Read a Gemini response stream to its end and return the last image in it
+    ///
+    /// The whole stream must finish within `timeout`; the HTTP client's own
+    /// timeout does not cover a stream that keeps the connection open without
+    /// ever ending.
+    async fn read_image_stream<S>(stream: S, timeout: Duration, max_bytes: usize) -> Result<Bytes>
+    where
+        S: futures::Stream<Item = genai::Result<ChatStreamEvent>> + Unpin,
+    {
+        tokio::time::timeout(timeout, Self::collect_stream_image(stream, max_bytes))
+            .await
+            .map_err(|_| {
+                tracing::warn!(timeout_secs = timeout.as_secs(), "Gemini stream timed out");
+                anyhow!(
+                    "Gemini stream did not finish within {} seconds",
+                    timeout.as_secs()
+                )
+            })?
+    }
+
+    /// Consume stream events, keeping the last image found in them
+    ///
+    /// Images decoding to more than `max_bytes` are refused before decoding.
+    async fn collect_stream_image<S>(mut stream: S, max_bytes: usize) -> Result<Bytes>
+    where
+        S: futures::Stream<Item = genai::Result<ChatStreamEvent>> + Unpin,
+    {
+        let mut last_image_bytes: Option<Vec<u8>> = None;
+        let mut last_image_mime: Option<String> = None;
+
+        // Process streaming response chunks
+        // Note: ChatStream implements the Stream trait, so we can use next() via StreamExt
+        while let Some(event_result) = stream.next().await {
+            let event =
+                event_result.map_err(|e| Self::map_genai_error(e, "Error reading stream event"))?;
+
+            // We're looking for binary content in the stream events
+            // The genai crate's ChatStreamEvent may contain content in different forms
+            match event {
+                ChatStreamEvent::Chunk(_) => {
+                    // Text chunks don't contain image data, skip
+                    continue;
+                }
+                ChatStreamEvent::End(end) => {
+                    // Check captured_content for binary data
+                    if let Some(content) = end.captured_content {
+                        for part in content.parts() {
+                            if let Some(binary) = part.as_binary() {
+                                // Extract base64 image data and decode it
+                                if let genai::chat::BinarySource::Base64(ref base64_str) =
+                                    binary.source
+                                {
+                                    last_image_bytes =
+                                        Some(Self::decode_image(base64_str, max_bytes)?);
+                                    last_image_mime = Some(binary.content_type.clone());
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    // Other event types (Start, ReasoningChunk, etc.) don't contain image data
+                    continue;
+                }
+            }
+        }
+
+        // Ensure we received an image
+        let image_bytes = last_image_bytes
+            .ok_or_else(|| anyhow!("No edited image returned from Gemini stream"))?;
+
+        tracing::debug!(
+            size = image_bytes.len(),
+            mime_type = ?last_image_mime,
+            "Received edited image from Gemini stream"
+        );
+
+        Ok(Bytes::from(image_bytes))
+    }
+
+    /// Decode a base64 image, refusing one over `max_bytes` without decoding it
+    fn decode_image(base64_str: &str, max_bytes: usize) -> Result<Vec<u8>> {
+        let data = base64_str.trim_end_matches('=');
+        let decoded_len = data.len() / 4 * 3 + (data.len() % 4).saturating_sub(1);
+        if decoded_len > max_bytes {
+            tracing::warn!(
+                size = decoded_len,
+                max_result_bytes = max_bytes,
+                "Gemini result over MAX_RESULT_BYTES"
+            );
+            bail!(
+                "Gemini result is {} bytes, over the limit of {} bytes (MAX_RESULT_BYTES)",
+                decoded_len,
+                max_bytes
+            );
+        }
+        base64::engine::general_purpose::STANDARD
+            .decode(base64_str)
+            .context("Failed to decode base64 image data")
+    }
 }
 
 #[async_trait::async_trait]
@@ -174,9 +349,7 @@ impl ImageEditor for GoogleNanaBananaEditor {
     async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
         // Development mode fallback: no API key
         if self.api_key.is_none() || self.client.is_none() {
-            tracing::warn!(
-                "Google provider fallback: no API key found; returning original image."
-            );
+            tracing::warn!("Google provider fallback: no API key found; returning original image.");
             return Ok(image_bytes);
         }
 
@@ -192,7 +365,7 @@ impl ImageEditor for GoogleNanaBananaEditor {
         let base64_data = base64::engine::general_purpose::STANDARD.encode(&image_data);
 
         // Build content parts: image (as base64 binary) + text prompt
-        let mut parts = vec![
+        let parts = vec![
             ContentPart::from_binary_base64(input_mime, base64_data, None),
             ContentPart::from_text(&prompt),
         ];
@@ -203,57 +376,50 @@ impl ImageEditor for GoogleNanaBananaEditor {
         // Build chat request
         let chat_request = ChatRequest::new(vec![message]);
 
+        tracing::debug!(
+            model = %model_id,
+            mime_type = input_mime,
+            prompt = %log_redaction::prompt(&prompt, self.log_redaction),
+            temperature = ?self.sampling.temperature,
+            top_p = ?self.sampling.top_p,
+            "Submitting request to Gemini"
+        );
+
         // Execute the chat stream request
         let stream_response = client
-            .exec_chat_stream(&model_id, chat_request, None)
+            .exec_chat_stream(&model_id, chat_request, self.chat_options().as_ref())
             .await
-            .context("Failed to execute chat stream request")?;
-
-        let mut stream = stream_response.stream;
-        let mut last_image_bytes: Option<Vec<u8>> = None;
-        let mut last_image_mime: Option<String> = None;
-
-        // Process streaming response chunks
-        // Note: ChatStream implements the Stream trait, so we can use next() via StreamExt
-        while let Some(event_result) = stream.next().await {
-            let event = event_result.context("Error reading stream event")?;
-
-            // We're looking for binary content in the stream events
-            // The genai crate's ChatStreamEvent may contain content in different forms
-            match event {
-                genai::chat::ChatStreamEvent::Chunk(chunk) => {
-                    // Text chunks don't contain image data, skip
-                    continue;
-                }
-                genai::chat::ChatStreamEvent::End(end) => {
-                    // Check captured_content for binary data
-                    if let Some(content) = end.captured_content {
-                        for part in content.parts() {
-                            if let Some(binary) = part.as_binary() {
-                                // Extract base64 image data and decode it
-                                if let genai::chat::BinarySource::Base64(ref base64_str) = binary.source {
-                                    let decoded = base64::engine::general_purpose::STANDARD
-                                        .decode(base64_str.as_ref())
-                                        .context("Failed to decode base64 image data")?;
-                                    last_image_bytes = Some(decoded);
-                                    last_image_mime = Some(binary.content_type.clone());
-                                }
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    // Other event types (Start, ReasoningChunk, etc.) don't contain image data
-                    continue;
-                }
-            }
-        }
+            .map_err(|e| Self::map_genai_error(e, "Failed to execute chat stream request"))?;
+
+        Self::read_image_stream(
+            stream_response.stream,
+            self.stream_timeout,
+            self.max_result_bytes,
+        )
+        .await
+    }
 
-        // Ensure we received an image
-        let image_bytes = last_image_bytes
-            .ok_or_else(|| anyhow!("No edited image returned from Gemini stream"))?;
+    /// Send `sampling` as Gemini's temperature and top-p
+    fn set_sampling(&mut self, sampling: SamplingOptions) {
+        self.sampling = sampling;
+    }
 
-        Ok(Bytes::from(image_bytes))
+    /// Edit the first image, reporting the Gemini model it was sent to
+    ///
+    /// Gemini reports neither seeds nor timings. In development mode nothing
+    /// is sent, so no model is reported.
+    async fn edit_images_with_metadata(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+    ) -> Result<(Bytes, ProviderMetadata)> {
+        let result = self.edit_images(images, prompt).await?;
+        let metadata = ProviderMetadata {
+            model_version: (self.api_key.is_some() && self.client.is_some())
+                .then(|| self.model_id.clone()),
+            ..ProviderMetadata::default()
+        };
+        Ok((result, metadata))
     }
 }
 
@@ -261,10 +427,33 @@ impl ImageEditor for GoogleNanaBananaEditor {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sampling_attached_as_chat_options() {
+        let mut editor = GoogleNanaBananaEditor::new(AppConfig::default());
+        assert!(editor.chat_options().is_none());
+
+        editor.set_sampling(SamplingOptions {
+            temperature: Some(0.4),
+            ..SamplingOptions::default()
+        });
+        let options = editor.chat_options().unwrap();
+        assert_eq!(options.temperature, Some(0.4));
+        assert_eq!(options.top_p, None);
+
+        editor.set_sampling(SamplingOptions {
+            top_p: Some(0.9),
+            ..SamplingOptions::default()
+        });
+        assert_eq!(editor.chat_options().unwrap().top_p, Some(0.9));
+    }
+
     #[test]
     fn test_guess_mime_jpeg() {
         let jpeg_bytes = vec![0xFF, 0xD8, 0xFF, 0xE0];
-        assert_eq!(GoogleNanaBananaEditor::guess_mime(&jpeg_bytes), "image/jpeg");
+        assert_eq!(
+            GoogleNanaBananaEditor::guess_mime(&jpeg_bytes),
+            "image/jpeg"
+        );
     }
 
     #[test]
@@ -284,7 +473,10 @@ mod tests {
         let mut webp_bytes = b"RIFF".to_vec();
         webp_bytes.extend_from_slice(&[0, 0, 0, 0]); // File size placeholder
         webp_bytes.extend_from_slice(b"WEBP");
-        assert_eq!(GoogleNanaBananaEditor::guess_mime(&webp_bytes), "image/webp");
+        assert_eq!(
+            GoogleNanaBananaEditor::guess_mime(&webp_bytes),
+            "image/webp"
+        );
     }
 
     #[test]
@@ -304,4 +496,92 @@ mod tests {
             "application/octet-stream"
         );
     }
+
+    fn gemini_iden() -> genai::ModelIden {
+        genai::ModelIden::new(
+            genai::adapter::AdapterKind::Gemini,
+            "gemini-2.5-flash-image-preview",
+        )
+    }
+
+    #[tokio::test]
+    async fn test_stream_that_never_ends_times_out() {
+        let stream = futures::stream::pending::<genai::Result<ChatStreamEvent>>();
+
+        let err = GoogleNanaBananaEditor::read_image_stream(
+            stream,
+            Duration::from_millis(50),
+            usize::MAX,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("did not finish within"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_image_is_an_error() {
+        let stream = futures::stream::iter(vec![Ok(ChatStreamEvent::Start)]);
+
+        let err =
+            GoogleNanaBananaEditor::read_image_stream(stream, Duration::from_secs(5), usize::MAX)
+                .await
+                .unwrap_err();
+
+        assert!(err.to_string().contains("No edited image"));
+    }
+
+    #[test]
+    fn test_decode_image_refuses_results_over_the_limit() {
+        let image = b"\x89PNG\r\n\x1a\nsome image";
+        for len in 0..image.len() {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(&image[..len]);
+            assert_eq!(
+                GoogleNanaBananaEditor::decode_image(&encoded, len).unwrap(),
+                &image[..len]
+            );
+            if len > 0 {
+                let err = GoogleNanaBananaEditor::decode_image(&encoded, len - 1).unwrap_err();
+                assert!(err.to_string().contains("MAX_RESULT_BYTES"), "{}", err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_auth_failure_from_stream_error_body() {
+        let err = genai::Error::ChatResponse {
+            model_iden: gemini_iden(),
+            body: serde_json::json!({
+                "error": {"code": 401, "status": "UNAUTHENTICATED", "message": "Request had invalid credentials."}
+            }),
+        };
+
+        let auth = GoogleNanaBananaEditor::auth_failure(&err).expect("expected auth failure");
+        assert_eq!(auth.provider, "Google Gemini");
+        assert!(auth.to_string().contains("check GOOGLE_API_KEY"));
+    }
+
+    #[test]
+    fn test_auth_failure_ignores_other_errors() {
+        let err = genai::Error::WebStream {
+            model_iden: gemini_iden(),
+            cause: "connection reset by peer".to_string(),
+        };
+
+        assert!(GoogleNanaBananaEditor::auth_failure(&err).is_none());
+    }
+
+    #[test]
+    fn test_map_genai_error_yields_provider_auth() {
+        let err = genai::Error::WebStream {
+            model_iden: gemini_iden(),
+            cause: "403 PERMISSION_DENIED: API key not valid".to_string(),
+        };
+
+        let mapped = GoogleNanaBananaEditor::map_genai_error(err, "Error reading stream event");
+        assert!(matches!(
+            crate::error::AppError::from_provider(mapped),
+            crate::error::AppError::ProviderAuth(_)
+        ));
+    }
 }
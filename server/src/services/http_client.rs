@@ -0,0 +1,459 @@
+//! HTTP client construction for provider integrations
+//!
+//! Providers that talk to their APIs over `reqwest` get their clients through
+//! `HttpClientSettings`, so connection pool tuning from `AppConfig` is applied
+//! consistently in one place.
+//!
+//! Editors are built per request, so a client owned by an editor would never
+//! reuse a connection. `HttpClientSettings::client` instead hands out one
+//! process-wide client per distinct settings value.
+//!
+//! Clients fetching URLs chosen by API clients are built with `public_only`,
+//! so they can't be pointed at the server's own network (see
+//! [`is_public_ip`]).
+
+use crate::config::AppConfig;
+use anyhow::{Context, Result};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::{Attempt, Policy};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Redirects followed unless settings say otherwise, as in reqwest
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Settings applied to a provider's `reqwest::ClientBuilder`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpClientSettings {
+    /// Overall request timeout
+    pub timeout: Duration,
+    /// Maximum idle connections kept per host
+    pub pool_max_idle_per_host: usize,
+    /// How long idle connections stay in the pool (`None` keeps them indefinitely)
+    pub pool_idle_timeout: Option<Duration>,
+    /// Redirects followed, only to `http`/`https` URLs; `0` returns the redirect response itself
+    pub max_redirects: usize,
+    /// Refuse to connect to loopback, private, link-local and other
+    /// non-public addresses, whether resolved or given literally in a redirect
+    pub public_only: bool,
+}
+
+impl HttpClientSettings {
+    /// Derive settings from configuration with the given request timeout
+    ///
+    /// A configured idle timeout of `0` seconds disables the idle timeout.
+    pub fn from_config(config: &AppConfig, timeout: Duration) -> Self {
+        let pool_idle_timeout = match config.http_pool_idle_timeout_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+
+        Self {
+            timeout,
+            pool_max_idle_per_host: config.http_pool_max_idle_per_host,
+            pool_idle_timeout,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            public_only: false,
+        }
+    }
+
+    /// Follow at most `max_redirects` redirects
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Only connect to public addresses when `public_only` is set
+    ///
+    /// Callers must still check the initial URL with [`check_public_url`],
+    /// since a literal IP address is never resolved.
+    pub fn with_public_only(mut self, public_only: bool) -> Self {
+        self.public_only = public_only;
+        self
+    }
+
+    /// Apply these settings to a client builder
+    pub fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let builder = builder
+            .timeout(self.timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .redirect(redirect_policy(self.max_redirects, self.public_only));
+        if self.public_only {
+            builder.dns_resolver(Arc::new(PublicResolver))
+        } else {
+            builder
+        }
+    }
+
+    /// Build a `reqwest::Client` with these settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TLS backend or client cannot be initialized.
+    pub fn build_client(&self) -> Result<reqwest::Client> {
+        self.apply(reqwest::Client::builder())
+            .build()
+            .context("Failed to create HTTP client")
+    }
+
+    /// The shared client for these settings, built on first use
+    ///
+    /// Clients are cheap handles onto one connection pool, so every caller
+    /// with equal settings reuses the same connections.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client has to be built and cannot be.
+    pub fn client(&self) -> Result<reqwest::Client> {
+        static CLIENTS: OnceLock<Mutex<HashMap<HttpClientSettings, reqwest::Client>>> =
+            OnceLock::new();
+
+        let mut clients = CLIENTS.get_or_init(Mutex::default).lock().unwrap();
+        if let Some(client) = clients.get(self) {
+            return Ok(client.clone());
+        }
+        let client = self.build_client()?;
+        clients.insert(self.clone(), client.clone());
+        Ok(client)
+    }
+}
+
+/// Redirect policy limiting the hop count and refusing non-http(s) targets,
+/// and with `public_only` literal non-public addresses
+fn redirect_policy(max_redirects: usize, public_only: bool) -> Policy {
+    if max_redirects == 0 {
+        return Policy::none();
+    }
+    Policy::custom(move |attempt: Attempt| {
+        if attempt.previous().len() > max_redirects {
+            attempt.error(format!("more than {} redirects", max_redirects))
+        } else if !matches!(attempt.url().scheme(), "http" | "https") {
+            let scheme = attempt.url().scheme().to_string();
+            attempt.error(format!("redirect to unsupported scheme '{}'", scheme))
+        } else if public_only && check_public_url(attempt.url()).is_err() {
+            attempt.error(NonPublicAddress)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Error for a destination that resolves to a non-public address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonPublicAddress;
+
+impl fmt::Display for NonPublicAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("destination is not a public address")
+    }
+}
+
+impl std::error::Error for NonPublicAddress {}
+
+impl NonPublicAddress {
+    /// Whether `err` or any error in its source chain is a `NonPublicAddress`
+    pub fn caused(err: &(dyn std::error::Error + 'static)) -> bool {
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if err.is::<NonPublicAddress>() {
+                return true;
+            }
+            source = err.source();
+        }
+        false
+    }
+}
+
+/// Whether `ip` is a globally routable unicast address
+///
+/// Loopback, private (RFC 1918, IPv6 unique local), link-local (including the
+/// 169.254.169.254 cloud metadata endpoint), carrier-grade NAT, unspecified,
+/// broadcast and multicast addresses are not. IPv4-mapped, NAT64
+/// (`64:ff9b::/96`) and 6to4 (`2002::/16`) IPv6 addresses are judged by the
+/// IPv4 address they embed.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match embedded_ipv4(ip) {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// IPv4 address an IPv4-mapped, NAT64 or 6to4 IPv6 address reaches
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = ip.octets();
+    let segments = ip.segments();
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        Some(ip)
+    } else if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        Some(Ipv4Addr::new(
+            octets[12], octets[13], octets[14], octets[15],
+        ))
+    } else if segments[0] == 0x2002 {
+        Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]))
+    } else {
+        None
+    }
+}
+
+/// Reject a URL whose host is a literal non-public IP address
+///
+/// Host names pass; `public_only` clients check what they resolve to.
+///
+/// # Errors
+///
+/// Returns `NonPublicAddress` for a literal loopback, private, link-local or
+/// otherwise non-public address.
+pub fn check_public_url(url: &Url) -> std::result::Result<(), NonPublicAddress> {
+    let host = url.host_str().unwrap_or_default();
+    let Ok(ip) = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    else {
+        return Ok(());
+    };
+    if is_public_ip(ip) {
+        Ok(())
+    } else {
+        Err(NonPublicAddress)
+    }
+}
+
+/// DNS resolver that only returns public addresses
+///
+/// Checking the resolved addresses here, rather than resolving the URL's host
+/// up front, means the address connected to is the address checked.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(NonPublicAddress.into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_settings_from_config() {
+        let config = AppConfig {
+            http_pool_max_idle_per_host: 8,
+            http_pool_idle_timeout_secs: 30,
+            ..AppConfig::default()
+        };
+
+        let settings = HttpClientSettings::from_config(&config, Duration::from_secs(300));
+
+        assert_eq!(settings.timeout, Duration::from_secs(300));
+        assert_eq!(settings.pool_max_idle_per_host, 8);
+        assert_eq!(settings.pool_idle_timeout, Some(Duration::from_secs(30)));
+        assert!(settings.build_client().is_ok());
+    }
+
+    #[test]
+    fn test_zero_idle_timeout_disables_timeout() {
+        let config = AppConfig {
+            http_pool_idle_timeout_secs: 0,
+            ..AppConfig::default()
+        };
+
+        let settings = HttpClientSettings::from_config(&config, Duration::from_secs(5));
+
+        assert_eq!(settings.pool_idle_timeout, None);
+    }
+
+    #[test]
+    fn test_default_pool_settings() {
+        let settings =
+            HttpClientSettings::from_config(&AppConfig::default(), Duration::from_secs(5));
+
+        assert_eq!(settings.pool_max_idle_per_host, 32);
+        assert_eq!(settings.pool_idle_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(settings.max_redirects, DEFAULT_MAX_REDIRECTS);
+    }
+
+    /// Serve `200 ok` keep-alive responses on a local port; returns the base
+    /// URL and a counter of accepted connections
+    async fn serve_keep_alive() -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    loop {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                        // Answer each complete request head, keeping the connection open
+                        while let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                            request.drain(..end + 4);
+                            let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                            if stream.write_all(response).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        (format!("http://{}", addr), connections)
+    }
+
+    #[tokio::test]
+    async fn test_shared_client_reuses_connections() {
+        let (url, connections) = serve_keep_alive().await;
+        let settings =
+            HttpClientSettings::from_config(&AppConfig::default(), Duration::from_secs(5));
+
+        // Two lookups, as two per-request editors would make
+        for _ in 0..2 {
+            let response = settings.client().unwrap().get(&url).send().await.unwrap();
+            assert_eq!(response.text().await.unwrap(), "ok");
+        }
+
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_redirects_returns_redirect_response() {
+        let app = axum::Router::new().route(
+            "/moved",
+            axum::routing::get(|| async { axum::response::Redirect::temporary("/elsewhere") }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let settings =
+            HttpClientSettings::from_config(&AppConfig::default(), Duration::from_secs(5))
+                .with_max_redirects(0);
+        let response = settings
+            .client()
+            .unwrap()
+            .get(format!("http://{}/moved", addr))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in [
+            "8.8.8.8",
+            "93.184.216.34",
+            "2606:4700::1111",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:7f00:1::1",
+            "2002:a9fe:a9fe::",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_check_public_url() {
+        let check = |url: &str| check_public_url(&Url::parse(url).unwrap());
+        assert!(check("https://example.com/a.png").is_ok());
+        assert!(check("http://8.8.8.8/a.png").is_ok());
+        assert_eq!(check("http://127.0.0.1:8000/a.png"), Err(NonPublicAddress));
+        assert_eq!(check("http://[fe80::1]/a.png"), Err(NonPublicAddress));
+    }
+
+    #[tokio::test]
+    async fn test_public_only_refuses_private_redirects_and_names() {
+        let app = axum::Router::new().route(
+            "/moved",
+            axum::routing::get(|| async {
+                axum::response::Redirect::temporary("http://10.0.0.1/")
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = HttpClientSettings::from_config(&AppConfig::default(), Duration::from_secs(5))
+            .with_public_only(true)
+            .client()
+            .unwrap();
+
+        // Literal addresses are the caller's to check; redirect hops are checked here
+        let err = client
+            .get(format!("http://{}/moved", addr))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(NonPublicAddress::caused(&err), "{:?}", err);
+
+        let err = client
+            .get(format!("http://localhost:{}/moved", addr.port()))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(NonPublicAddress::caused(&err), "{:?}", err);
+    }
+}
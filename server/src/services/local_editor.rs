@@ -0,0 +1,136 @@
+//! Local image editing service
+//!
+//! Runs edits on the server itself, with no external API, for offline and
+//! on-prem deployments. Selected with the `"local"` provider and only compiled
+//! with the `local-model` cargo feature; the model file is read from
+//! `LOCAL_MODEL_PATH`.
+//!
+//! Model inference (e.g. through `ort` or `candle`) is not wired in yet: the
+//! editor checks that the model file exists and applies a grayscale transform
+//! as a stand-in, so the provider plumbing can be exercised end-to-end.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use image::ImageFormat;
+
+use crate::services::base::{ImageEditor, ProviderMetadata};
+use crate::utils::image_utils;
+
+/// Image editor backed by a model file on the server
+#[derive(Debug, Clone)]
+pub struct LocalEditor {
+    model_path: PathBuf,
+}
+
+impl LocalEditor {
+    /// Create a local editor for the model at `model_path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `model_path` is not an existing file.
+    pub fn new(model_path: impl AsRef<Path>) -> Result<Self> {
+        let model_path = model_path.as_ref();
+        let metadata = std::fs::metadata(model_path)
+            .with_context(|| format!("cannot read local model '{}'", model_path.display()))?;
+        if !metadata.is_file() {
+            bail!("local model '{}' is not a file", model_path.display());
+        }
+
+        Ok(Self {
+            model_path: model_path.to_path_buf(),
+        })
+    }
+
+    /// Name of the loaded model, for provider metadata
+    fn model_name(&self) -> String {
+        self.model_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.model_path.display().to_string())
+    }
+
+    /// Stand-in for model inference: a grayscale copy of the image as PNG
+    fn transform(image_bytes: &[u8]) -> Result<Bytes> {
+        let img = image_utils::bytes_to_image(image_bytes)?;
+        Ok(image_utils::image_to_bytes(
+            &img.grayscale(),
+            ImageFormat::Png,
+        )?)
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageEditor for LocalEditor {
+    /// Edit an image on the server; the prompt is not used yet
+    async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
+        tracing::info!(
+            model = %self.model_path.display(),
+            image_size = image_bytes.len(),
+            prompt_len = prompt.len(),
+            "Running local model"
+        );
+
+        // Inference is CPU-bound, so keep it off the async workers
+        tokio::task::spawn_blocking(move || Self::transform(&image_bytes))
+            .await
+            .context("local model task failed")?
+    }
+
+    /// Edit the first image, reporting the local model file
+    async fn edit_images_with_metadata(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+    ) -> Result<(Bytes, ProviderMetadata)> {
+        let result = self.edit_images(images, prompt).await?;
+        let metadata = ProviderMetadata {
+            model_version: Some(self.model_name()),
+            ..ProviderMetadata::default()
+        };
+        Ok((result, metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+    /// Write an empty stand-in model file
+    fn model_file(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("frameforge-{}-{}.onnx", name, std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_missing_model_is_rejected() {
+        let missing = std::env::temp_dir().join("frameforge-missing-model.onnx");
+        assert!(LocalEditor::new(&missing).is_err());
+        assert!(LocalEditor::new(std::env::temp_dir()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_edit_returns_grayscale_png() {
+        let editor = LocalEditor::new(model_file("grayscale")).unwrap();
+        let red = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255])));
+        let input = image_utils::image_to_bytes(&red, ImageFormat::Png).unwrap();
+
+        let (output, metadata) = editor
+            .edit_images_with_metadata(vec![input], "prompt")
+            .await
+            .unwrap();
+
+        let img = image::load_from_memory(&output).unwrap();
+        let [r, g, b, _] = img.get_pixel(0, 0).0;
+        assert_eq!((r, g), (g, b));
+        assert_eq!(img.dimensions(), (4, 4));
+        assert!(metadata
+            .model_version
+            .unwrap()
+            .starts_with("frameforge-grayscale"));
+    }
+}
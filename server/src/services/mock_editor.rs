@@ -0,0 +1,92 @@
+//! Mock image editing service
+//!
+//! This module provides a deterministic, network-free `ImageEditor` that returns
+//! the input image unchanged. It is selected by the factory when
+//! `AppConfig.mock_provider` is enabled (tests and the `test-mock` feature
+//! only), which lets the full edit pipeline (multipart parsing, header
+//! handling, validation, response building) be exercised end-to-end without
+//! real provider credentials. Dev mode also falls back to it.
+
+use crate::services::base::{ImageEditor, ProviderMetadata};
+use anyhow::Result;
+use bytes::Bytes;
+
+/// Passthrough image editor used for tests and local development
+///
+/// The output is always byte-identical to the input, so callers can assert on
+/// the exact response body. It doesn't support `EditRegion`s: a passthrough
+/// can't keep an edit inside one, so region requests are refused.
+#[derive(Debug, Default, Clone)]
+pub struct MockEditor;
+
+impl MockEditor {
+    /// Create a new mock editor
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageEditor for MockEditor {
+    /// Return the input image unchanged
+    async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
+        tracing::debug!(
+            image_size = image_bytes.len(),
+            prompt_len = prompt.len(),
+            "Mock editor returning input image unchanged"
+        );
+
+        Ok(image_bytes)
+    }
+
+    /// Return the first input unchanged, reporting `mock` as the model
+    async fn edit_images_with_metadata(
+        &self,
+        images: Vec<Bytes>,
+        prompt: &str,
+    ) -> Result<(Bytes, ProviderMetadata)> {
+        let result = self.edit_images(images, prompt).await?;
+        let metadata = ProviderMetadata {
+            model_version: Some("mock".to_string()),
+            ..ProviderMetadata::default()
+        };
+        Ok((result, metadata))
+    }
+
+    fn accepts_high_bit_depth(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_editor_is_passthrough() {
+        let editor = MockEditor::new();
+        let input = Bytes::from_static(b"\x89PNG\r\n\x1a\nimage");
+
+        let output = editor
+            .edit_image(input.clone(), "any prompt")
+            .await
+            .unwrap();
+
+        assert_eq!(output, input);
+    }
+
+    #[tokio::test]
+    async fn test_mock_editor_reports_model() {
+        let editor = MockEditor::new();
+        let input = Bytes::from_static(b"\x89PNG\r\n\x1a\nimage");
+
+        let (output, metadata) = editor
+            .edit_images_with_metadata(vec![input.clone()], "any prompt")
+            .await
+            .unwrap();
+
+        assert_eq!(output, input);
+        assert_eq!(metadata.model_version.as_deref(), Some("mock"));
+        assert_eq!(metadata.seed, None);
+    }
+}
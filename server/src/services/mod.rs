@@ -4,6 +4,13 @@
 //! It provides a unified interface (ImageEditor trait) for multiple AI providers:
 //! - Google Gemini (Nano Banana) - Primary provider
 //! - Fal.ai - Dynamic model support with fal: prefix
+//! - Mock - Deterministic passthrough editor for tests and local development
+//! - Composite - Grid layout of the uploaded images, without AI
+//! - Webhook - User-run HTTP endpoint with webhook: prefix
+//! - Local - On-server model inference (with the `local-model` feature)
+//!
+//! A static catalog describes known models (input dimension limits, output
+//! formats) for clients.
 //!
 //! The factory pattern is used to instantiate the appropriate service based on
 //! provider selection. Services handle API communication, image processing,
@@ -13,6 +20,23 @@
 pub mod base;
 pub mod factory;
 
+// Shared HTTP client construction
+pub mod http_client;
+
+// Request-scoped cache of downloaded results
+pub mod download_cache;
+
+// Optional prompt enrichment before edits
+pub mod prompt_enhancer;
+
+// Known model metadata
+pub mod catalog;
+
 // Provider implementations
-pub mod google_nano_banana; // Tasks 13-14, 21
+pub mod composite_editor;
 pub mod fal_editor; // Tasks 15-20, 22
+pub mod google_nano_banana; // Tasks 13-14, 21
+#[cfg(feature = "local-model")]
+pub mod local_editor;
+pub mod mock_editor;
+pub mod webhook_editor;
@@ -0,0 +1,181 @@
+//! Optional prompt enhancement before an image edit
+//!
+//! Some providers produce noticeably better edits from richer prompts. When a
+//! request opts in with `enhance_prompt=true` and the provider is listed in
+//! `PROMPT_ENHANCE_PROVIDERS`, the client prompt is passed through a
+//! `PromptEnhancer` first. Enhancement is best effort: a failing or oversized
+//! enhancement falls back to the original prompt.
+//!
+//! `TemplateEnhancer` is the built-in implementation; the trait is async so an
+//! implementation backed by a lightweight text model can be plugged in later.
+
+use crate::config::{AppConfig, PROMPT_PLACEHOLDER};
+use crate::services::catalog;
+use anyhow::{anyhow, Result};
+
+/// Rewrites a client prompt into a more detailed one
+#[async_trait::async_trait]
+pub trait PromptEnhancer: Send + Sync {
+    /// Return the enhanced prompt
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prompt cannot be enhanced; callers fall back
+    /// to the original prompt.
+    async fn enhance(&self, prompt: &str) -> Result<String>;
+}
+
+/// Expands prompts by substituting them into a fixed template
+#[derive(Debug, Clone)]
+pub struct TemplateEnhancer {
+    template: String,
+    max_chars: usize,
+}
+
+impl TemplateEnhancer {
+    /// Create an enhancer from a template containing `{prompt}`
+    ///
+    /// Expansions longer than `max_chars` characters are rejected.
+    pub fn new(template: impl Into<String>, max_chars: usize) -> Self {
+        Self {
+            template: template.into(),
+            max_chars,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptEnhancer for TemplateEnhancer {
+    async fn enhance(&self, prompt: &str) -> Result<String> {
+        // Avoid a doubled full stop when the template punctuates after the prompt
+        let prompt = prompt.trim().trim_end_matches('.');
+        let enhanced = self.template.replace(PROMPT_PLACEHOLDER, prompt);
+
+        let chars = enhanced.chars().count();
+        if chars > self.max_chars {
+            return Err(anyhow!(
+                "enhanced prompt is {} characters, over the {} character limit",
+                chars,
+                self.max_chars
+            ));
+        }
+
+        Ok(enhanced)
+    }
+}
+
+/// Enhancer to use for `provider`, or `None` when enhancement is not enabled for it
+///
+/// Expansions are bounded by the provider's prompt limit (see
+/// `catalog::max_prompt_chars`), leaving room for the configured prompt
+/// prefix and suffix.
+pub fn enhancer_for(config: &AppConfig, provider: &str) -> Option<Box<dyn PromptEnhancer>> {
+    if !config.prompt_enhancement_enabled(provider) {
+        return None;
+    }
+
+    let wrapper_chars = [&config.prompt_prefix, &config.prompt_suffix]
+        .into_iter()
+        .flatten()
+        .map(|text| text.trim().chars().count() + 1)
+        .sum::<usize>();
+    let max_chars = catalog::max_prompt_chars(config, provider).saturating_sub(wrapper_chars);
+
+    Some(Box::new(TemplateEnhancer::new(
+        config.prompt_enhance_template.clone(),
+        max_chars,
+    )))
+}
+
+/// Enhance `prompt` for `provider`, returning it unchanged when enhancement is
+/// unavailable or fails
+pub async fn enhance_prompt(config: &AppConfig, provider: &str, prompt: String) -> String {
+    let Some(enhancer) = enhancer_for(config, provider) else {
+        tracing::debug!(provider = %provider, "Prompt enhancement not enabled for provider");
+        return prompt;
+    };
+
+    match enhancer.enhance(&prompt).await {
+        Ok(enhanced) => {
+            tracing::info!(
+                provider = %provider,
+                original_chars = prompt.chars().count(),
+                enhanced_chars = enhanced.chars().count(),
+                "Enhanced prompt"
+            );
+            enhanced
+        }
+        Err(e) => {
+            tracing::warn!(provider = %provider, error = %e, "Prompt enhancement failed; using original prompt");
+            prompt
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_PROMPT_ENHANCE_TEMPLATE;
+
+    #[tokio::test]
+    async fn test_template_expands_short_prompt() {
+        let enhancer = TemplateEnhancer::new(DEFAULT_PROMPT_ENHANCE_TEMPLATE, 4000);
+
+        let enhanced = enhancer.enhance("Add a sofa.").await.unwrap();
+
+        assert!(enhanced.starts_with("Add a sofa. Keep the original camera angle"));
+        assert!(enhanced.ends_with("photorealistic, high detail."));
+        assert!(!enhanced.contains(PROMPT_PLACEHOLDER));
+    }
+
+    #[tokio::test]
+    async fn test_template_rejects_expansion_over_limit() {
+        let enhancer = TemplateEnhancer::new("{prompt}, in great detail", 20);
+        assert!(enhancer.enhance("Add a sofa").await.is_err());
+    }
+
+    #[test]
+    fn test_enhancer_only_for_enabled_providers() {
+        let config = AppConfig {
+            prompt_enhance_providers: vec!["fal".to_string(), "google".to_string()],
+            ..AppConfig::default()
+        };
+
+        assert!(enhancer_for(&config, "google").is_some());
+        assert!(enhancer_for(&config, "fal:fal-ai/flux/dev").is_some());
+        assert!(enhancer_for(&config, "nano-banana").is_none());
+        assert!(enhancer_for(&AppConfig::default(), "google").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_enhance_prompt_falls_back_to_original() {
+        let config = AppConfig {
+            prompt_enhance_providers: vec!["google".to_string()],
+            prompt_enhance_template: "{prompt} with many extra words".to_string(),
+            max_prompt_chars: 12,
+            ..AppConfig::default()
+        };
+
+        assert_eq!(
+            enhance_prompt(&config, "google", "Add a sofa".into()).await,
+            "Add a sofa"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enhance_prompt_leaves_room_for_prefix_and_suffix() {
+        let config = AppConfig {
+            prompt_enhance_providers: vec!["google".to_string()],
+            prompt_enhance_template: "{prompt}, cozy".to_string(),
+            prompt_prefix: Some("Be safe.".to_string()),
+            max_prompt_chars: 24,
+            ..AppConfig::default()
+        };
+
+        // "Add a sofa, cozy" (16) + "Be safe." (8) + separator exceeds 24
+        assert_eq!(
+            enhance_prompt(&config, "google", "Add a sofa".into()).await,
+            "Add a sofa"
+        );
+    }
+}
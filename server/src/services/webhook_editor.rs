@@ -0,0 +1,452 @@
+//! Webhook image editing service
+//!
+//! Calls a user-run HTTP endpoint as the editor, for custom and self-hosted
+//! models. Selected with `webhook:` followed by the endpoint URL, e.g.
+//! `webhook:https://my-model.example.com/edit`, when the `webhooks` feature is
+//! enabled. Only `http` and `https` URLs are accepted.
+//!
+//! The input images and prompt are POSTed to the URL, encoded per
+//! `WEBHOOK_ENCODING`:
+//! - `multipart`: `multipart/form-data` with one `images` file part per input
+//!   and a `prompt` text field
+//! - `base64`: JSON `{"prompt": "...", "images": ["data:image/png;base64,..."]}`
+//!
+//! The endpoint answers with the edited image as the raw response body. Any
+//! status other than 2xx fails the edit; the body is logged, not returned to
+//! the client.
+//!
+//! The URL comes from the client, so the server must not become a proxy into
+//! its own network: with `WEBHOOK_ALLOWED_HOSTS` set only the listed hosts are
+//! called, otherwise any host is called but only on a public address (checked
+//! after DNS resolution, see `http_client::is_public_ip`). Redirects are not
+//! followed and results over `MAX_RESULT_BYTES` are refused while reading.
+
+use crate::config::{AppConfig, WebhookEncoding};
+use crate::services::base::ImageEditor;
+use crate::services::http_client::{check_public_url, HttpClientSettings, NonPublicAddress};
+use crate::utils::image_utils;
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
+use reqwest::Url;
+use std::time::Duration;
+
+/// Maximum number of response body characters logged for failed calls
+const RESPONSE_SNIPPET_CHARS: usize = 500;
+
+/// Parse and validate the endpoint URL of a `webhook:` provider
+///
+/// # Errors
+///
+/// Returns an error if `url` is not an absolute `http` or `https` URL with a host.
+pub fn parse_webhook_url(url: &str) -> Result<Url> {
+    let parsed =
+        Url::parse(url.trim()).with_context(|| format!("invalid webhook URL '{}'", url))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!(
+            "webhook URL '{}' must use http or https, not {}",
+            url,
+            parsed.scheme()
+        );
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        bail!("webhook URL '{}' has no host", url);
+    }
+    Ok(parsed)
+}
+
+/// Check the host of a webhook URL may be called
+///
+/// With `WEBHOOK_ALLOWED_HOSTS` set the host must be listed (compared case
+/// insensitively). Otherwise a literal IP address must be public; host names
+/// are checked when they are resolved.
+///
+/// # Errors
+///
+/// Returns an error naming the host if it is not allowed.
+pub fn check_webhook_host(url: &Url, config: &AppConfig) -> Result<()> {
+    let host = url.host_str().unwrap_or_default();
+    if config.webhook_allowed_hosts.is_empty() {
+        if check_public_url(url).is_err() {
+            bail!(
+                "webhook host '{}' is not a public address; list it in WEBHOOK_ALLOWED_HOSTS to allow it",
+                host
+            );
+        }
+        return Ok(());
+    }
+
+    let bare = host.trim_start_matches('[').trim_end_matches(']');
+    let allowed = config.webhook_allowed_hosts.iter().any(|allowed| {
+        allowed
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .eq_ignore_ascii_case(bare)
+    });
+    if !allowed {
+        bail!("webhook host '{}' is not in WEBHOOK_ALLOWED_HOSTS", host);
+    }
+    Ok(())
+}
+
+/// Editor that delegates edits to a user-defined HTTP endpoint
+pub struct WebhookEditor {
+    /// Endpoint the images and prompt are POSTed to
+    url: Url,
+    /// Request body format
+    encoding: WebhookEncoding,
+    /// Largest response body read, from `MAX_RESULT_BYTES`
+    max_result_bytes: usize,
+    /// HTTP client for making requests
+    client: reqwest::Client,
+}
+
+impl WebhookEditor {
+    /// Create an editor POSTing to `url`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid (see `parse_webhook_url`), its
+    /// host is not allowed (see `check_webhook_host`) or the HTTP client
+    /// cannot be created.
+    pub fn new(url: &str, config: &AppConfig) -> Result<Self> {
+        let url = parse_webhook_url(url)?;
+        check_webhook_host(&url, config)?;
+        // Same allowance as other providers for slow self-hosted models. Hosts
+        // the operator listed may be private; redirects could leave them.
+        let client = HttpClientSettings::from_config(config, Duration::from_secs(300))
+            .with_max_redirects(0)
+            .with_public_only(config.webhook_allowed_hosts.is_empty())
+            .client()?;
+
+        tracing::info!(
+            host = url.host_str().unwrap_or_default(),
+            encoding = ?config.webhook_encoding,
+            "Initialized webhook editor"
+        );
+
+        Ok(Self {
+            url,
+            encoding: config.webhook_encoding,
+            max_result_bytes: config.max_result_bytes,
+            client,
+        })
+    }
+
+    /// Build the request carrying `images` and `prompt`
+    fn build_request(&self, images: Vec<Bytes>, prompt: &str) -> Result<reqwest::RequestBuilder> {
+        let request = self.client.post(self.url.clone());
+        match self.encoding {
+            WebhookEncoding::Multipart => {
+                let mut form = reqwest::multipart::Form::new().text("prompt", prompt.to_string());
+                for (index, image) in images.into_iter().enumerate() {
+                    let mime = image_utils::get_mime_type(&image)?;
+                    let extension = mime.strip_prefix("image/").unwrap_or("bin");
+                    let part = reqwest::multipart::Part::stream(image)
+                        .file_name(format!("image-{}.{}", index, extension))
+                        .mime_str(&mime)?;
+                    form = form.part("images", part);
+                }
+                Ok(request.multipart(form))
+            }
+            WebhookEncoding::Base64 => {
+                let images = images
+                    .iter()
+                    .map(|image| image_utils::bytes_to_base64(image, None))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(request.json(&serde_json::json!({ "prompt": prompt, "images": images })))
+            }
+        }
+    }
+
+    /// Truncate a response body for logging
+    fn snippet(body: &[u8]) -> String {
+        let body = String::from_utf8_lossy(body);
+        match body.char_indices().nth(RESPONSE_SNIPPET_CHARS) {
+            Some((end, _)) => format!("{}... ({} bytes total)", &body[..end], body.len()),
+            None => body.into_owned(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageEditor for WebhookEditor {
+    async fn edit_image(&self, image_bytes: Bytes, prompt: &str) -> Result<Bytes> {
+        self.edit_images(vec![image_bytes], prompt).await
+    }
+
+    /// POST every input image with the prompt and return the response body
+    async fn edit_images(&self, images: Vec<Bytes>, prompt: &str) -> Result<Bytes> {
+        if images.is_empty() {
+            bail!("at least one image is required");
+        }
+
+        let mut response = match self.build_request(images, prompt)?.send().await {
+            Ok(response) => response,
+            Err(e) if NonPublicAddress::caused(&e) => {
+                bail!("webhook host does not resolve to a public address; list it in WEBHOOK_ALLOWED_HOSTS to allow it")
+            }
+            Err(e) => return Err(anyhow!(e).context("Failed to call webhook")),
+        };
+        let status = response.status();
+        if !status.is_success() {
+            // The body may be anything the endpoint serves, so it stays in the logs
+            let start = response.chunk().await.ok().flatten().unwrap_or_default();
+            tracing::warn!(%status, body = %Self::snippet(&start), "Webhook call failed");
+            bail!("Webhook returned {}", status);
+        }
+
+        let too_large = || {
+            anyhow!(
+                "Webhook result exceeds the limit of {} bytes",
+                self.max_result_bytes
+            )
+        };
+        if response
+            .content_length()
+            .is_some_and(|len| len > self.max_result_bytes as u64)
+        {
+            return Err(too_large());
+        }
+        // Content-Length may be absent or wrong, so enforce the cap while reading
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Failed to read webhook response")?
+        {
+            if body.len() + chunk.len() > self.max_result_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        tracing::debug!(result_size = body.len(), "Webhook returned a result");
+        Ok(Bytes::from(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{FromRequest, Multipart};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_webhook_url_scheme_validated() {
+        assert_eq!(
+            parse_webhook_url("https://my-model.example.com/edit")
+                .unwrap()
+                .as_str(),
+            "https://my-model.example.com/edit"
+        );
+        assert!(parse_webhook_url("http://127.0.0.1:9000/Edit").is_ok());
+
+        for url in [
+            "ftp://my-model/edit",
+            "file:///etc/passwd",
+            "my-model/edit",
+            "",
+        ] {
+            assert!(parse_webhook_url(url).is_err(), "{}", url);
+        }
+    }
+
+    /// What a webhook endpoint received: content type, prompt and images
+    type Received = Arc<Mutex<Option<(String, String, Vec<Vec<u8>>)>>>;
+
+    /// Serve a webhook endpoint on a local port that records the request and
+    /// answers with `status` and `body`; returns the endpoint URL
+    async fn serve_webhook(status: StatusCode, body: &'static [u8], received: Received) -> String {
+        let handler = move |headers: HeaderMap, request: axum::extract::Request| {
+            let received = received.clone();
+            async move {
+                let content_type = headers["content-type"].to_str().unwrap().to_string();
+                let (prompt, images) = if content_type.starts_with("multipart/form-data") {
+                    let mut multipart = Multipart::from_request(request, &()).await.unwrap();
+                    let (mut prompt, mut images) = (String::new(), Vec::new());
+                    while let Some(field) = multipart.next_field().await.unwrap() {
+                        match field.name().unwrap() {
+                            "prompt" => prompt = field.text().await.unwrap(),
+                            "images" => images.push(field.bytes().await.unwrap().to_vec()),
+                            other => panic!("unexpected field {}", other),
+                        }
+                    }
+                    (prompt, images)
+                } else {
+                    let bytes = axum::body::to_bytes(request.into_body(), usize::MAX)
+                        .await
+                        .unwrap();
+                    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+                    let images = json["images"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|uri| {
+                            image_utils::base64_to_bytes(uri.as_str().unwrap())
+                                .unwrap()
+                                .to_vec()
+                        })
+                        .collect();
+                    (json["prompt"].as_str().unwrap().to_string(), images)
+                };
+                *received.lock().unwrap() = Some((content_type, prompt, images));
+                (status, body)
+            }
+        };
+        let app = axum::Router::new().route("/edit", post(handler));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/edit", addr)
+    }
+
+    /// Config allowing the local test endpoints
+    fn local_config() -> AppConfig {
+        AppConfig {
+            webhook_allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..AppConfig::default()
+        }
+    }
+
+    fn sample_png() -> Bytes {
+        image_utils::image_to_bytes(
+            &image::DynamicImage::new_rgb8(2, 2),
+            image::ImageFormat::Png,
+        )
+        .unwrap()
+    }
+
+    async fn edit_via_webhook(
+        encoding: WebhookEncoding,
+    ) -> (Bytes, (String, String, Vec<Vec<u8>>)) {
+        let received = Received::default();
+        let url = serve_webhook(StatusCode::OK, b"edited image", received.clone()).await;
+        let config = AppConfig {
+            webhook_encoding: encoding,
+            ..local_config()
+        };
+        let editor = WebhookEditor::new(&url, &config).unwrap();
+
+        let result = editor
+            .edit_images(vec![sample_png(), sample_png()], "Add a rug")
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap().take().unwrap();
+        (result, received)
+    }
+
+    #[tokio::test]
+    async fn test_multipart_request_carries_images_and_prompt() {
+        let (result, (content_type, prompt, images)) =
+            edit_via_webhook(WebhookEncoding::Multipart).await;
+
+        assert_eq!(&result[..], b"edited image");
+        assert!(
+            content_type.starts_with("multipart/form-data"),
+            "{}",
+            content_type
+        );
+        assert_eq!(prompt, "Add a rug");
+        assert_eq!(images, vec![sample_png().to_vec(); 2]);
+    }
+
+    #[tokio::test]
+    async fn test_base64_request_carries_images_and_prompt() {
+        let (result, (content_type, prompt, images)) =
+            edit_via_webhook(WebhookEncoding::Base64).await;
+
+        assert_eq!(&result[..], b"edited image");
+        assert_eq!(content_type, "application/json");
+        assert_eq!(prompt, "Add a rug");
+        assert_eq!(images, vec![sample_png().to_vec(); 2]);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_fails_edit_without_body() {
+        let url = serve_webhook(
+            StatusCode::BAD_GATEWAY,
+            b"model offline",
+            Received::default(),
+        )
+        .await;
+        let editor = WebhookEditor::new(&url, &local_config()).unwrap();
+
+        let err = editor
+            .edit_image(sample_png(), "Add a rug")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("502"), "{}", err);
+        assert!(!format!("{:#}", err).contains("model offline"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn test_private_destinations_refused_without_allow_list() {
+        let url = serve_webhook(StatusCode::OK, b"edited image", Received::default()).await;
+
+        // Literal addresses are refused up front
+        let err = WebhookEditor::new(&url, &AppConfig::default())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("not a public address"), "{}", err);
+
+        // Names are refused once resolved
+        let url = url.replace("127.0.0.1", "localhost");
+        let editor = WebhookEditor::new(&url, &AppConfig::default()).unwrap();
+        let err = editor
+            .edit_image(sample_png(), "Add a rug")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("public address"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn test_redirects_not_followed() {
+        let hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new()
+            .route(
+                "/edit",
+                post(|| async { axum::response::Redirect::temporary("/target") }),
+            )
+            .route(
+                "/target",
+                post(move || {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    async { "edited image" }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let editor = WebhookEditor::new(&format!("http://{}/edit", addr), &local_config()).unwrap();
+
+        let err = editor
+            .edit_image(sample_png(), "Add a rug")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("307"), "{}", err);
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_result_over_max_result_bytes_refused() {
+        let url = serve_webhook(StatusCode::OK, b"edited image", Received::default()).await;
+        let config = AppConfig {
+            max_result_bytes: 4,
+            ..local_config()
+        };
+        let editor = WebhookEditor::new(&url, &config).unwrap();
+
+        let err = editor
+            .edit_image(sample_png(), "Add a rug")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("limit of 4 bytes"), "{}", err);
+    }
+}
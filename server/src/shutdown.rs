@@ -0,0 +1,151 @@
+//! Flushing of buffered state once the server has stopped serving
+//!
+//! After graceful shutdown, everything that buffers output (the metrics
+//! registry, log writers) gets one last chance to write it out. Each step
+//! implements `Flush`; `flush_all` runs them in order within one timeout so a
+//! stuck exporter cannot keep the process from exiting.
+
+use std::io::Write as _;
+use std::time::Duration;
+
+use crate::metrics::Metrics;
+
+/// Time all shutdown flushes may take together
+pub const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Something holding output that must be written before the process exits
+#[async_trait::async_trait]
+pub trait Flush: Send + Sync {
+    /// Short name used in shutdown logs
+    fn name(&self) -> &'static str;
+
+    /// Write out anything buffered
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffered output could not be written; the
+    /// remaining flushes still run.
+    async fn flush(&self) -> anyhow::Result<()>;
+}
+
+/// Metrics are only kept in memory, so the final values are logged before
+/// they are lost with the process
+#[async_trait::async_trait]
+impl Flush for Metrics {
+    fn name(&self) -> &'static str {
+        "metrics"
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        tracing::info!(metrics = %self.render(), "Final metrics snapshot");
+        Ok(())
+    }
+}
+
+/// Flushes the process's stdout and stderr, where logs are written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogFlusher;
+
+#[async_trait::async_trait]
+impl Flush for LogFlusher {
+    fn name(&self) -> &'static str {
+        "logs"
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        std::io::stdout().flush()?;
+        std::io::stderr().flush()?;
+        Ok(())
+    }
+}
+
+/// Run every flush in order, giving up once `timeout` has elapsed
+///
+/// Failures are logged and do not stop later flushes. Returns `true` if all
+/// flushes finished in time, whether or not they succeeded.
+pub async fn flush_all(flushes: &[&dyn Flush], timeout: Duration) -> bool {
+    let run = async {
+        for flush in flushes {
+            match flush.flush().await {
+                Ok(()) => tracing::debug!(step = flush.name(), "Flushed on shutdown"),
+                Err(e) => tracing::warn!(step = flush.name(), error = %e, "Shutdown flush failed"),
+            }
+        }
+    };
+
+    match tokio::time::timeout(timeout, run).await {
+        Ok(()) => true,
+        Err(_) => {
+            tracing::warn!(
+                timeout_ms = timeout.as_millis() as u64,
+                "Shutdown flush timed out"
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Flush that counts its calls, optionally failing or never finishing
+    #[derive(Default)]
+    struct Recorder {
+        calls: AtomicUsize,
+        fail: bool,
+        hang: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Flush for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn flush(&self) -> anyhow::Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+            if self.fail {
+                anyhow::bail!("exporter unavailable");
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_all_calls_every_flush() {
+        let failing = Recorder {
+            fail: true,
+            ..Recorder::default()
+        };
+        let ok = Recorder::default();
+        let metrics = Metrics::new();
+
+        assert!(
+            flush_all(
+                &[&failing, &ok, &metrics, &LogFlusher],
+                SHUTDOWN_FLUSH_TIMEOUT
+            )
+            .await
+        );
+        assert_eq!(failing.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(ok.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_flush_all_is_bounded_by_timeout() {
+        let stuck = Recorder {
+            hang: true,
+            ..Recorder::default()
+        };
+        let after = Recorder::default();
+
+        assert!(!flush_all(&[&stuck, &after], Duration::from_millis(20)).await);
+        assert_eq!(stuck.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(after.calls.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,92 @@
+//! Shared application state
+//!
+//! `AppState` bundles everything handlers share across requests. Handlers
+//! extract only the piece they need (e.g. `State<AppConfig>`) through the
+//! `FromRef` implementations below.
+
+use axum::extract::FromRef;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::AppConfig;
+use crate::jobs::JobStore;
+use crate::metrics::Metrics;
+use crate::middleware::{ConnectionLimit, RateLimiter};
+use crate::results::ResultStore;
+use crate::uploads::UploadStore;
+
+/// State shared by all request handlers
+#[derive(Debug, Clone)]
+pub struct AppState {
+    /// Application configuration, shared rather than cloned per request
+    pub config: Arc<AppConfig>,
+    /// Request metrics registry
+    pub metrics: Arc<Metrics>,
+    /// Asynchronous edit jobs
+    pub jobs: Arc<JobStore>,
+    /// Images uploaded ahead of an edit
+    pub uploads: Arc<UploadStore>,
+    /// Content-addressable copies of edit results
+    pub results: Arc<ResultStore>,
+    /// Permits for requests in flight (`MAX_CONNECTIONS`)
+    pub connections: ConnectionLimit,
+    /// Per-IP request counts (`RATE_LIMIT_ALGORITHM`)
+    pub rate_limiter: RateLimiter,
+    /// When the state was created, i.e. server start
+    pub started_at: Instant,
+}
+
+impl AppState {
+    /// Create state for the given configuration with empty metrics and no jobs, uploads or results
+    pub fn new(config: AppConfig) -> Self {
+        let upload_ttl = Duration::from_secs(config.upload_ttl_secs);
+        let connections = ConnectionLimit::new(config.max_connections);
+        let rate_limiter = RateLimiter::with_algorithm(config.rate_limit_algorithm);
+        Self {
+            config: Arc::new(config),
+            metrics: Arc::new(Metrics::new()),
+            jobs: Arc::new(JobStore::new()),
+            uploads: Arc::new(UploadStore::new(upload_ttl)),
+            results: Arc::new(ResultStore::new()),
+            connections,
+            rate_limiter,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl FromRef<AppState> for AppConfig {
+    fn from_ref(state: &AppState) -> Self {
+        AppConfig::clone(&state.config)
+    }
+}
+
+impl FromRef<AppState> for Arc<AppConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.config)
+    }
+}
+
+impl FromRef<AppState> for Arc<Metrics> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.metrics)
+    }
+}
+
+impl FromRef<AppState> for Arc<JobStore> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.jobs)
+    }
+}
+
+impl FromRef<AppState> for Arc<UploadStore> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.uploads)
+    }
+}
+
+impl FromRef<AppState> for Arc<ResultStore> {
+    fn from_ref(state: &AppState) -> Self {
+        Arc::clone(&state.results)
+    }
+}
@@ -0,0 +1,284 @@
+//! In-memory store of images uploaded ahead of an edit
+//!
+//! With the `uploads` feature flag, very large images can skip multipart:
+//! `POST /api/uploads` reserves an upload id, the raw bytes are sent with
+//! `PUT /api/uploads/{id}`, and `/api/edit` references them with an
+//! `upload_id` field. Uploads expire `UPLOAD_TTL_SECS` after they were
+//! reserved, can be referenced any number of times until then, and live only
+//! in process memory. At most `MAX_PENDING_UPLOADS` uploads are kept, the
+//! oldest reservation being evicted first. Uploads hold at most
+//! `MAX_PENDING_UPLOAD_BYTES` between them and `MAX_UPLOAD_SIZE_BYTES` each;
+//! bytes that don't fit are refused rather than evicting other clients'
+//! uploads.
+//!
+//! Time is read through a `Clock`, so tests can move it forward.
+
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+use crate::middleware::rate_limit::{Clock, SystemClock};
+
+/// Maximum number of uploads kept in memory
+pub const MAX_PENDING_UPLOADS: usize = 100;
+
+/// Maximum bytes of uploaded images kept in memory (512 MiB)
+pub const MAX_PENDING_UPLOAD_BYTES: usize = 512 * 1024 * 1024;
+
+/// Maximum bytes of a single upload (64 MiB)
+pub const MAX_UPLOAD_SIZE_BYTES: usize = 64 * 1024 * 1024;
+
+/// A reserved upload
+#[derive(Debug, Clone)]
+pub struct Upload {
+    /// Image bytes, once they have been PUT
+    pub data: Option<Bytes>,
+    /// When the upload stops being usable
+    pub expires_at: Instant,
+}
+
+impl Upload {
+    fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+
+    fn len(&self) -> usize {
+        self.data.as_ref().map_or(0, Bytes::len)
+    }
+}
+
+#[derive(Debug, Default)]
+struct UploadStoreInner {
+    uploads: HashMap<String, Upload>,
+    /// Upload ids in reservation order, for eviction
+    order: VecDeque<String>,
+}
+
+/// Thread-safe registry of uploads, shared through `AppState`
+#[derive(Debug)]
+pub struct UploadStore {
+    ttl: Duration,
+    max_bytes: usize,
+    max_upload_bytes: usize,
+    clock: Arc<dyn Clock>,
+    inner: Mutex<UploadStoreInner>,
+}
+
+impl UploadStore {
+    /// Create an empty store whose uploads expire after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_limits(ttl, MAX_PENDING_UPLOAD_BYTES, MAX_UPLOAD_SIZE_BYTES)
+    }
+
+    /// Create an empty store whose uploads expire after `ttl`, hold at most
+    /// `max_bytes` between them and at most `max_upload_bytes` each
+    pub fn with_limits(ttl: Duration, max_bytes: usize, max_upload_bytes: usize) -> Self {
+        Self {
+            ttl,
+            max_bytes,
+            max_upload_bytes,
+            clock: Arc::new(SystemClock),
+            inner: Mutex::new(UploadStoreInner::default()),
+        }
+    }
+
+    /// Read time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Time an upload stays usable after it was reserved
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Reserve an empty upload and return its id
+    pub fn create(&self) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = self.clock.now();
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.evict(now);
+        inner.uploads.insert(
+            id.clone(),
+            Upload {
+                data: None,
+                expires_at: now + self.ttl,
+            },
+        );
+        inner.order.push_back(id.clone());
+
+        id
+    }
+
+    /// Store the bytes of a reserved upload, replacing earlier ones
+    ///
+    /// Other uploads are never evicted to make room.
+    ///
+    /// # Errors
+    ///
+    /// - `AppError::NotFound` (404) if the upload is unknown or has expired
+    /// - `AppError::PayloadTooLarge` (413) if `data` is over the per-upload limit
+    /// - `AppError::ServiceUnavailable` (503) if the store has no room left for
+    ///   `data` until other uploads expire
+    pub fn put(&self, id: &str, data: Bytes) -> Result<(), AppError> {
+        let now = self.clock.now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.expire(now);
+
+        if !inner.uploads.contains_key(id) {
+            return Err(AppError::NotFound(format!("upload '{}'", id)));
+        }
+        if data.len() > self.max_upload_bytes {
+            return Err(AppError::PayloadTooLarge(format!(
+                "upload of {} bytes exceeds the limit of {} bytes",
+                data.len(),
+                self.max_upload_bytes
+            )));
+        }
+        let others: usize = inner
+            .uploads
+            .iter()
+            .filter(|(other, _)| other.as_str() != id)
+            .map(|(_, upload)| upload.len())
+            .sum();
+        if others + data.len() > self.max_bytes {
+            return Err(AppError::ServiceUnavailable(
+                "upload storage is full; retry once pending uploads expire".to_string(),
+            ));
+        }
+
+        if let Some(upload) = inner.uploads.get_mut(id) {
+            upload.data = Some(data);
+        }
+        Ok(())
+    }
+
+    /// Snapshot of an upload that has not expired
+    pub fn get(&self, id: &str) -> Option<Upload> {
+        let now = self.clock.now();
+        let inner = self.inner.lock().unwrap();
+        inner
+            .uploads
+            .get(id)
+            .filter(|upload| !upload.is_expired(now))
+            .cloned()
+    }
+}
+
+impl UploadStoreInner {
+    /// Drop expired uploads
+    fn expire(&mut self, now: Instant) {
+        self.uploads.retain(|_, upload| !upload.is_expired(now));
+        let uploads = &self.uploads;
+        self.order.retain(|id| uploads.contains_key(id));
+    }
+
+    /// Drop expired uploads, then the oldest ones while at `MAX_PENDING_UPLOADS`
+    fn evict(&mut self, now: Instant) {
+        self.expire(now);
+        while self.uploads.len() >= MAX_PENDING_UPLOADS {
+            match self.order.pop_front() {
+                Some(id) => {
+                    self.uploads.remove(&id);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::rate_limit::MockClock;
+
+    #[test]
+    fn test_upload_lifecycle() {
+        let store = UploadStore::new(Duration::from_secs(60));
+        let id = store.create();
+
+        assert!(store.get(&id).unwrap().data.is_none());
+        store.put(&id, Bytes::from_static(b"image")).unwrap();
+        assert_eq!(
+            store.get(&id).unwrap().data,
+            Some(Bytes::from_static(b"image"))
+        );
+
+        assert!(store.get("missing").is_none());
+        assert!(matches!(
+            store.put("missing", Bytes::new()),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_expired_upload_is_gone() {
+        let clock = Arc::new(MockClock::new());
+        let store = UploadStore::new(Duration::from_secs(60)).with_clock(clock.clone());
+        let id = store.create();
+
+        clock.advance(Duration::from_secs(59));
+        store.put(&id, Bytes::from_static(b"image")).unwrap();
+        assert!(store.get(&id).is_some());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(store.get(&id).is_none());
+        assert!(matches!(
+            store.put(&id, Bytes::from_static(b"image")),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_oldest_uploads_evicted_at_capacity() {
+        let store = UploadStore::new(Duration::from_secs(60));
+        let first = store.create();
+
+        for _ in 0..MAX_PENDING_UPLOADS {
+            store.create();
+        }
+
+        assert!(store.get(&first).is_none());
+        assert_eq!(
+            store.inner.lock().unwrap().uploads.len(),
+            MAX_PENDING_UPLOADS
+        );
+    }
+
+    #[test]
+    fn test_upload_over_byte_budget_refused_without_evicting_others() {
+        let store = UploadStore::with_limits(Duration::from_secs(60), 10, 6);
+        let first = store.create();
+        let second = store.create();
+        let third = store.create();
+        store.put(&first, Bytes::from_static(b"aaaa")).unwrap();
+        store.put(&second, Bytes::from_static(b"bbbb")).unwrap();
+
+        // 4 + 4 + 6 bytes is over the budget; earlier uploads are kept
+        let err = store
+            .put(&third, Bytes::from_static(b"cccccc"))
+            .unwrap_err();
+        assert!(matches!(err, AppError::ServiceUnavailable(_)));
+        assert!(store.get(&first).unwrap().data.is_some());
+        assert!(store.get(&second).unwrap().data.is_some());
+        assert!(store.get(&third).unwrap().data.is_none());
+
+        // Replacing an upload's own bytes doesn't count them twice
+        store.put(&first, Bytes::from_static(b"aaaaaa")).unwrap();
+    }
+
+    #[test]
+    fn test_upload_over_size_limit_refused() {
+        let store = UploadStore::with_limits(Duration::from_secs(60), 10, 6);
+        let id = store.create();
+
+        let err = store.put(&id, Bytes::from_static(b"1234567")).unwrap_err();
+
+        assert!(matches!(err, AppError::PayloadTooLarge(_)));
+        assert!(store.get(&id).unwrap().data.is_none());
+    }
+}
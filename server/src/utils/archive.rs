@@ -0,0 +1,181 @@
+//! Extraction of batch input images from ZIP archives
+//!
+//! Archives are unzipped in memory; callers run the extraction on the
+//! blocking pool. The number of entries, skipped ones included, the number of
+//! files and their total uncompressed size are capped. The size cap is
+//! checked against the bytes actually inflated rather than the sizes the
+//! archive declares, so a zip bomb is abandoned after at most the cap has
+//! been read.
+
+use crate::error::{AppError, Result};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+/// Limits applied while extracting an archive
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveLimits {
+    /// Maximum number of entries, counting directories and metadata entries
+    pub max_entries: usize,
+    /// Maximum number of files extracted
+    pub max_files: usize,
+    /// Maximum total uncompressed size of those files, in bytes
+    pub max_bytes: usize,
+}
+
+/// Files of a ZIP archive as `(name, contents)`, in archive order
+///
+/// Directories and macOS metadata (`__MACOSX/`, `._*` and other dotfiles)
+/// are skipped. Contents are not validated.
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` when the data is not a readable ZIP
+/// archive, an entry is encrypted or corrupt, or a limit is exceeded.
+pub fn extract_files(data: &[u8], limits: ArchiveLimits) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| AppError::InvalidInput(format!("archive is not a valid ZIP file: {}", e)))?;
+    // Every entry counts, so skipped ones can't be used to stall extraction
+    if archive.len() > limits.max_entries {
+        return Err(AppError::InvalidInput(format!(
+            "archive has more than {} entries",
+            limits.max_entries
+        )));
+    }
+
+    let mut files = Vec::new();
+    let mut total = 0usize;
+
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| AppError::InvalidInput(format!("unreadable archive entry: {}", e)))?;
+        let name = entry.name().to_string();
+        if entry.is_dir() || is_metadata(&name) {
+            continue;
+        }
+
+        if files.len() == limits.max_files {
+            return Err(AppError::InvalidInput(format!(
+                "archive has more than {} images",
+                limits.max_files
+            )));
+        }
+
+        // Read one byte past the remaining budget to detect overflow without trusting headers
+        let budget = limits.max_bytes - total;
+        let mut contents = Vec::new();
+        (&mut entry)
+            .take(budget as u64 + 1)
+            .read_to_end(&mut contents)
+            .map_err(|e| {
+                AppError::InvalidInput(format!("unreadable archive entry {}: {}", name, e))
+            })?;
+        if contents.len() > budget {
+            return Err(AppError::InvalidInput(format!(
+                "archive contents exceed {} bytes uncompressed",
+                limits.max_bytes
+            )));
+        }
+
+        total += contents.len();
+        files.push((name, contents));
+    }
+
+    Ok(files)
+}
+
+/// Whether an entry is archiver metadata rather than user content
+fn is_metadata(name: &str) -> bool {
+    name.starts_with("__MACOSX/")
+        || name
+            .rsplit('/')
+            .next()
+            .is_some_and(|file_name| file_name.starts_with('.'))
+}
+
+/// Deflated ZIP archive of `entries`, for tests; names ending in `/` are
+/// added as directories
+#[cfg(any(test, feature = "test-mock"))]
+pub fn zip_of(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, data) in entries {
+        if name.ends_with('/') {
+            writer.add_directory(*name, options).unwrap();
+        } else {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: ArchiveLimits = ArchiveLimits {
+        max_entries: 8,
+        max_files: 4,
+        max_bytes: 1024,
+    };
+
+    #[test]
+    fn test_files_extracted_in_order_without_metadata() {
+        let data = zip_of(&[
+            ("photos/", b""),
+            ("photos/a.png", b"first"),
+            ("__MACOSX/photos/._a.png", b"resource fork"),
+            (".DS_Store", b"finder"),
+            ("photos/b.png", b"second"),
+        ]);
+
+        let files = extract_files(&data, LIMITS).unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                ("photos/a.png".to_string(), b"first".to_vec()),
+                ("photos/b.png".to_string(), b"second".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limits_enforced() {
+        let many = zip_of(&[
+            ("1", b"a"),
+            ("2", b"b"),
+            ("3", b"c"),
+            ("4", b"d"),
+            ("5", b"e"),
+        ]);
+        let err = extract_files(&many, LIMITS).unwrap_err();
+        assert!(err.to_string().contains("more than 4 images"), "{}", err);
+
+        // Skipped directories and metadata count as entries
+        let names: Vec<String> = (0..9).map(|i| format!("__MACOSX/._{}", i)).collect();
+        let skipped: Vec<(&str, &[u8])> =
+            names.iter().map(|name| (name.as_str(), &b""[..])).collect();
+        let err = extract_files(&zip_of(&skipped), LIMITS).unwrap_err();
+        assert!(err.to_string().contains("more than 8 entries"), "{}", err);
+
+        // Highly compressible, so the archive itself stays small
+        let zeros = vec![0u8; 600];
+        let large = zip_of(&[("1", &zeros), ("2", &zeros)]);
+        assert!(large.len() < LIMITS.max_bytes);
+        let err = extract_files(&large, LIMITS).unwrap_err();
+        assert!(err.to_string().contains("exceed 1024 bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_non_zip_rejected() {
+        let err = extract_files(b"not a zip", LIMITS).unwrap_err();
+
+        assert!(matches!(err, AppError::InvalidInput(_)));
+    }
+}
@@ -4,7 +4,11 @@
 //! - Validation of image format
 //! - MIME type detection
 //! - Base64 encoding/decoding
-//! - Image format conversion
+//! - Image format conversion, including JPEG flattening and chroma subsampling
+//! - Resizing to requested output dimensions, including an edge-based smart crop
+//! - Lossless PNG optimization (with the `png-optimize` feature)
+//! - Pixel difference between two images
+//! - Normalization of CMYK JPEGs to RGB
 //!
 //! All functions are designed to work with `bytes::Bytes` for efficient
 //! zero-copy operations.
@@ -12,8 +16,234 @@
 use crate::error::{AppError, Result};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use bytes::Bytes;
-use image::{ImageFormat, ImageReader};
+use image::{
+    imageops::FilterType, DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader,
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::io::Cursor;
+use std::str::FromStr;
+
+/// How an image is fitted into requested output dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFit {
+    /// Scale to fit entirely inside the box, padding the remainder with transparency
+    #[default]
+    Contain,
+    /// Scale to fill the box, cropping the overflow around the center
+    Cover,
+    /// Stretch to the exact box, ignoring aspect ratio
+    Fill,
+    /// Scale to fill the box, cropping around the most detailed region
+    Smart,
+}
+
+impl FromStr for ResizeFit {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "contain" => Ok(ResizeFit::Contain),
+            "cover" => Ok(ResizeFit::Cover),
+            "fill" => Ok(ResizeFit::Fill),
+            "smart" => Ok(ResizeFit::Smart),
+            other => Err(AppError::InvalidInput(format!(
+                "Invalid fit mode '{}'. Expected one of: contain, cover, fill, smart",
+                other
+            ))),
+        }
+    }
+}
+
+/// Clockwise rotation applied to input images before they are edited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    /// Rotation in degrees, as accepted by `from_str`
+    pub fn degrees(&self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Cw90 => 90,
+            Rotation::Cw180 => 180,
+            Rotation::Cw270 => 270,
+        }
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = AppError;
+
+    fn try_from(degrees: u16) -> Result<Self> {
+        match degrees {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Cw90),
+            180 => Ok(Rotation::Cw180),
+            270 => Ok(Rotation::Cw270),
+            other => Err(AppError::InvalidInput(format!(
+                "Invalid rotation '{}'. Expected one of: 0, 90, 180, 270",
+                other
+            ))),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> u16 {
+        rotation.degrees()
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let trimmed = s.trim();
+        trimmed
+            .parse::<u16>()
+            .map_err(|_| {
+                AppError::InvalidInput(format!(
+                    "Invalid rotation '{}'. Expected one of: 0, 90, 180, 270",
+                    trimmed
+                ))
+            })
+            .and_then(Rotation::try_from)
+    }
+}
+
+/// Encoding of the image returned to the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl OutputFormat {
+    /// Lowercase name, as accepted by `from_str`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Webp => "webp",
+        }
+    }
+
+    /// Corresponding `image` crate format
+    pub fn image_format(&self) -> ImageFormat {
+        match self {
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Webp => ImageFormat::WebP,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            "webp" => Ok(OutputFormat::Webp),
+            other => Err(AppError::InvalidInput(format!(
+                "Invalid output format '{}'. Expected one of: png, jpeg, webp",
+                other
+            ))),
+        }
+    }
+}
+
+/// Chroma subsampling of JPEG output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum JpegSubsampling {
+    /// Full color resolution; largest files, sharpest colored edges
+    #[default]
+    #[serde(rename = "4:4:4")]
+    S444,
+    /// Color at half horizontal resolution
+    #[serde(rename = "4:2:2")]
+    S422,
+    /// Color at half horizontal and vertical resolution; smallest files
+    #[serde(rename = "4:2:0")]
+    S420,
+}
+
+impl JpegSubsampling {
+    fn sampling_factor(self) -> jpeg_encoder::SamplingFactor {
+        match self {
+            JpegSubsampling::S444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+            JpegSubsampling::S422 => jpeg_encoder::SamplingFactor::R_4_2_2,
+            JpegSubsampling::S420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+impl FromStr for JpegSubsampling {
+    type Err = AppError;
+
+    /// Parse `4:4:4`, `4:2:2` or `4:2:0` (the colons are optional)
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().replace(':', "").as_str() {
+            "444" => Ok(JpegSubsampling::S444),
+            "422" => Ok(JpegSubsampling::S422),
+            "420" => Ok(JpegSubsampling::S420),
+            _ => Err(AppError::InvalidInput(format!(
+                "Invalid JPEG subsampling '{}'. Expected one of: 4:4:4, 4:2:2, 4:2:0",
+                s.trim()
+            ))),
+        }
+    }
+}
+
+/// Quality of JPEG output (the `image` crate's default)
+const JPEG_QUALITY: u8 = 75;
+
+/// How images are encoded as JPEG
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegOptions {
+    /// Color that transparent areas are flattened onto
+    pub background: [u8; 3],
+    pub subsampling: JpegSubsampling,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        Self {
+            background: [255, 255, 255],
+            subsampling: JpegSubsampling::default(),
+        }
+    }
+}
+
+/// Parse an `RRGGBB` color, with an optional leading `#`
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` unless the value is six hex digits.
+pub fn parse_hex_rgb(value: &str) -> Result<[u8; 3]> {
+    let hex = value.trim();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid color '{}'. Expected a hex RRGGBB color",
+            value
+        )));
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap_or(0);
+    Ok([channel(0), channel(1), channel(2)])
+}
 
 /// Validate that the provided bytes represent a valid image
 ///
@@ -79,9 +309,253 @@ pub fn bytes_to_image(data: &[u8]) -> Result<image::DynamicImage> {
     Ok(img)
 }
 
+/// Read an image's dimensions from its header without decoding the pixels
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the format is unknown or the header
+/// cannot be read.
+pub fn image_dimensions(data: &[u8]) -> Result<(u32, u32)> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to read image format: {}", e)))?
+        .into_dimensions()
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to read image dimensions: {}", e)))
+}
+
+/// Read an image's dimensions by decoding it in full
+///
+/// For the rare valid images whose header `image_dimensions` can't size.
+/// Decoding is CPU-heavy, so async callers run this on the blocking pool.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded.
+pub fn decoded_dimensions(data: &[u8]) -> Result<(u32, u32)> {
+    Ok(bytes_to_image(data)?.dimensions())
+}
+
+/// JPEG quality used when re-encoding normalized CMYK input
+const NORMALIZED_JPEG_QUALITY: u8 = 92;
+
+/// Number of color components declared by a JPEG's frame header
+///
+/// Walks the marker segments up to the first start-of-frame marker. Returns
+/// `None` for non-JPEG data or a truncated header.
+fn jpeg_component_count(data: &[u8]) -> Option<u8> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        // Fill bytes before a marker
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        // SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            // Segment: length(2) precision(1) height(2) width(2) components(1)
+            return data.get(pos + 9).copied();
+        }
+        pos += 2 + length;
+    }
+
+    None
+}
+
+/// Whether the bytes are a JPEG encoded with four (CMYK/YCCK) components
+pub fn is_cmyk_jpeg(data: &[u8]) -> bool {
+    jpeg_component_count(data) == Some(4)
+}
+
+/// Convert CMYK JPEGs to RGB JPEGs, passing every other image through untouched
+///
+/// CMYK JPEGs from print workflows decode with wrong colors or are rejected
+/// outright by some providers, so they are converted before submission.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if a CMYK JPEG cannot be decoded or
+/// re-encoded.
+pub fn normalize_color_space(data: Bytes) -> Result<Bytes> {
+    if !is_cmyk_jpeg(&data) {
+        return Ok(data);
+    }
+
+    let rgb = bytes_to_image(&data)?.to_rgb8();
+    let mut buffer = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, NORMALIZED_JPEG_QUALITY)
+        .encode_image(&rgb)
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode RGB image: {}", e)))?;
+
+    tracing::info!(
+        width = rgb.width(),
+        height = rgb.height(),
+        original_size = data.len(),
+        converted_size = buffer.len(),
+        "Converted CMYK JPEG input to RGB"
+    );
+
+    Ok(Bytes::from(buffer))
+}
+
+/// Final chunk of every PNG: zero length, `IEND`, CRC
+const PNG_IEND: &[u8] = b"\0\0\0\0IEND\xae\x42\x60\x82";
+
+/// Whether encoded image data ends where its format says it should
+///
+/// A cheap structural check for bodies cut short in transit, which still
+/// carry a valid signature: PNGs must end with their `IEND` chunk, JPEGs with
+/// the end-of-image marker, GIFs with the trailer byte, and WebP files must be
+/// as long as their RIFF header declares. Other formats are assumed complete.
+pub fn is_complete_image(data: &[u8]) -> bool {
+    match image::guess_format(data) {
+        Ok(ImageFormat::Png) => data.ends_with(PNG_IEND),
+        // Some encoders pad the file after the end-of-image marker
+        Ok(ImageFormat::Jpeg) => {
+            let end = data
+                .iter()
+                .rposition(|&byte| byte != 0)
+                .map_or(0, |last| last + 1);
+            data[..end].ends_with(&[0xFF, 0xD9])
+        }
+        Ok(ImageFormat::Gif) => data.last() == Some(&0x3B),
+        Ok(ImageFormat::WebP) => data
+            .get(4..8)
+            .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize)
+            .is_some_and(|size| data.len() >= size.saturating_add(8)),
+        _ => true,
+    }
+}
+
+/// Whether an image stores more than 8 bits per channel (16-bit or float)
+///
+/// Only the header is read. Undecodable data counts as 8-bit.
+pub fn is_high_bit_depth(data: &[u8]) -> bool {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .is_some_and(|decoder| {
+            let color = decoder.color_type();
+            color.bytes_per_pixel() > color.channel_count()
+        })
+}
+
+/// Convert an image with more than 8 bits per channel to an 8-bit PNG
+///
+/// Alpha is kept when the image has it. Other images are returned untouched.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded or
+/// re-encoded.
+pub fn to_8_bit(data: Bytes) -> Result<Bytes> {
+    if !is_high_bit_depth(&data) {
+        return Ok(data);
+    }
+
+    let img = bytes_to_image(&data)?;
+    let converted = if img.color().has_alpha() {
+        DynamicImage::ImageRgba8(img.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+    let buffer = image_to_bytes(&converted, ImageFormat::Png)?;
+
+    tracing::info!(
+        width = converted.width(),
+        height = converted.height(),
+        original_color = ?img.color(),
+        original_size = data.len(),
+        converted_size = buffer.len(),
+        "Converted high bit depth input to 8-bit"
+    );
+
+    Ok(buffer)
+}
+
+/// Rotate an image clockwise by `rotation`
+///
+/// `Rotation::None` returns the image untouched. Otherwise it is re-encoded in
+/// its original format (PNG when that format isn't one the server writes).
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded or re-encoded.
+pub fn rotate(data: Bytes, rotation: Rotation) -> Result<Bytes> {
+    let img = match rotation {
+        Rotation::None => return Ok(data),
+        Rotation::Cw90 => bytes_to_image(&data)?.rotate90(),
+        Rotation::Cw180 => bytes_to_image(&data)?.rotate180(),
+        Rotation::Cw270 => bytes_to_image(&data)?.rotate270(),
+    };
+
+    image_to_bytes(&img, writable_format(&data))
+}
+
+/// Shrink an image so its longest side is at most `max_dimension`
+///
+/// Images already within the limit are returned untouched. Others are scaled
+/// down, keeping their aspect ratio, and re-encoded in their original format
+/// (PNG when that format isn't one the server writes).
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded or re-encoded.
+pub fn fit_within(data: Bytes, max_dimension: u32) -> Result<Bytes> {
+    let (width, height) = image_dimensions(&data)?;
+    if width.max(height) <= max_dimension {
+        return Ok(data);
+    }
+
+    let scaled = bytes_to_image(&data)?.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+    image_to_bytes(&scaled, writable_format(&data))
+}
+
+/// Enlarge an image so its longest side is `longest_side`, keeping its aspect ratio
+///
+/// Images already at least that large are returned untouched. Others are
+/// re-encoded in their original format (PNG when that format isn't one the
+/// server writes).
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded or re-encoded.
+pub fn enlarge_to(data: Bytes, longest_side: u32) -> Result<Bytes> {
+    let (width, height) = image_dimensions(&data)?;
+    if width.max(height) >= longest_side {
+        return Ok(data);
+    }
+
+    let scaled = bytes_to_image(&data)?.resize(longest_side, longest_side, FilterType::Lanczos3);
+    image_to_bytes(&scaled, writable_format(&data))
+}
+
+/// Format an image is re-encoded in: its own when the server writes it, else PNG
+fn writable_format(data: &[u8]) -> ImageFormat {
+    image::guess_format(data)
+        .ok()
+        .filter(|format| {
+            matches!(
+                format,
+                ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP
+            )
+        })
+        .unwrap_or(ImageFormat::Png)
+}
+
 /// Convert an image to bytes in the specified format
 ///
 /// This function encodes a `DynamicImage` into bytes using the specified format.
+/// JPEG output uses the default [`JpegOptions`].
 ///
 /// # Arguments
 ///
@@ -93,15 +567,485 @@ pub fn bytes_to_image(data: &[u8]) -> Result<image::DynamicImage> {
 /// * `Ok(Bytes)` containing the encoded image
 /// * `Err(AppError)` if encoding fails
 pub fn image_to_bytes(img: &image::DynamicImage, format: ImageFormat) -> Result<Bytes> {
+    image_to_bytes_with_options(img, format, &JpegOptions::default())
+}
+
+/// Convert an image to bytes, encoding JPEG output with `jpeg`
+///
+/// JPEG has no alpha channel, so translucent images are first composited
+/// onto `jpeg.background`.
+pub fn image_to_bytes_with_options(
+    img: &image::DynamicImage,
+    format: ImageFormat,
+    jpeg: &JpegOptions,
+) -> Result<Bytes> {
+    if format == ImageFormat::Jpeg {
+        return encode_jpeg(img, jpeg);
+    }
+
     let mut buffer = Vec::new();
-    let mut cursor = Cursor::new(&mut buffer);
+    img.write_to(&mut Cursor::new(&mut buffer), format)
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
+
+    Ok(Bytes::from(buffer))
+}
 
-    img.write_to(&mut cursor, format)
+/// Flatten an image onto the background color and encode it as JPEG
+fn encode_jpeg(img: &DynamicImage, jpeg: &JpegOptions) -> Result<Bytes> {
+    let (width, height) = img.dimensions();
+    let (Ok(width16), Ok(height16)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(AppError::ImageProcessing(format!(
+            "Image of {}x{} is too large for JPEG",
+            width, height
+        )));
+    };
+
+    let rgb = if img.color().has_alpha() {
+        flatten(&img.to_rgba8(), jpeg.background)
+    } else {
+        img.to_rgb8()
+    };
+
+    let mut buffer = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut buffer, JPEG_QUALITY);
+    encoder.set_sampling_factor(jpeg.subsampling.sampling_factor());
+    encoder
+        .encode(
+            rgb.as_raw(),
+            width16,
+            height16,
+            jpeg_encoder::ColorType::Rgb,
+        )
         .map_err(|e| AppError::ImageProcessing(format!("Failed to encode image: {}", e)))?;
 
     Ok(Bytes::from(buffer))
 }
 
+/// Composite an RGBA image over an opaque background color
+fn flatten(rgba: &image::RgbaImage, background: [u8; 3]) -> image::RgbImage {
+    image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |fg: u8, bg: u8| {
+            ((u32::from(fg) * u32::from(a) + u32::from(bg) * (255 - u32::from(a)) + 127) / 255)
+                as u8
+        };
+        image::Rgb([
+            blend(r, background[0]),
+            blend(g, background[1]),
+            blend(b, background[2]),
+        ])
+    })
+}
+
+/// Whether an image has any pixel that is not fully opaque
+pub fn has_transparency(img: &DynamicImage) -> bool {
+    img.color().has_alpha() && img.to_rgba8().pixels().any(|pixel| pixel.0[3] < u8::MAX)
+}
+
+/// Resize an image to exactly `width` x `height` using the given fit mode
+///
+/// # Arguments
+///
+/// * `img` - The image to resize
+/// * `width` - Target width in pixels (must be non-zero)
+/// * `height` - Target height in pixels (must be non-zero)
+/// * `fit` - How the source aspect ratio is reconciled with the target box
+///
+/// # Returns
+///
+/// A new image with the requested dimensions. `Contain` returns an RGBA image
+/// with transparent padding around the scaled content.
+pub fn resize_image(img: &DynamicImage, width: u32, height: u32, fit: ResizeFit) -> DynamicImage {
+    resize_image_padded(img, width, height, fit, None)
+}
+
+/// Resize like `resize_image`, padding `ResizeFit::Contain` with an opaque
+/// `padding` color instead of transparency when one is given
+pub fn resize_image_padded(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    fit: ResizeFit,
+    padding: Option<[u8; 3]>,
+) -> DynamicImage {
+    match fit {
+        ResizeFit::Fill => img.resize_exact(width, height, FilterType::Lanczos3),
+        ResizeFit::Cover => img.resize_to_fill(width, height, FilterType::Lanczos3),
+        ResizeFit::Smart => smart_crop(img, width, height),
+        ResizeFit::Contain => {
+            let scaled = img.resize(width, height, FilterType::Lanczos3);
+            let (scaled_w, scaled_h) = scaled.dimensions();
+            let background = match padding {
+                Some([r, g, b]) => image::Rgba([r, g, b, u8::MAX]),
+                None => image::Rgba([0, 0, 0, 0]),
+            };
+            let mut canvas = image::RgbaImage::from_pixel(width, height, background);
+            image::imageops::overlay(
+                &mut canvas,
+                &scaled.to_rgba8(),
+                i64::from((width - scaled_w) / 2),
+                i64::from((height - scaled_h) / 2),
+            );
+            DynamicImage::ImageRgba8(canvas)
+        }
+    }
+}
+
+/// Scale an image to cover `width` x `height`, cropping around its most detailed region
+///
+/// Like `ResizeFit::Cover`, except the crop window is not centered: it slides
+/// along the overflowing axis to the position with the highest edge energy
+/// (sum of absolute luma gradients), so the subject of an off-center photo
+/// survives the crop. Ties, such as flat images, keep the centered window.
+///
+/// # Arguments
+///
+/// * `img` - The image to crop
+/// * `width` - Target width in pixels (must be non-zero)
+/// * `height` - Target height in pixels (must be non-zero)
+pub fn smart_crop(img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    let (src_w, src_h) = img.dimensions();
+    let scale = f64::max(
+        f64::from(width) / f64::from(src_w.max(1)),
+        f64::from(height) / f64::from(src_h.max(1)),
+    );
+    let scaled_w = ((f64::from(src_w) * scale).round() as u32).max(width);
+    let scaled_h = ((f64::from(src_h) * scale).round() as u32).max(height);
+    let scaled = img.resize_exact(scaled_w, scaled_h, FilterType::Lanczos3);
+
+    let (column_energy, row_energy) = edge_energy(&scaled.to_luma8());
+    let x = best_window(&column_energy, width as usize);
+    let y = best_window(&row_energy, height as usize);
+
+    scaled.crop_imm(x as u32, y as u32, width, height)
+}
+
+/// Per-column and per-row sums of absolute horizontal and vertical luma gradients
+fn edge_energy(luma: &image::GrayImage) -> (Vec<u64>, Vec<u64>) {
+    let (width, height) = luma.dimensions();
+    let mut columns = vec![0u64; width as usize];
+    let mut rows = vec![0u64; height as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let here = i32::from(luma.get_pixel(x, y)[0]);
+            let right = if x + 1 < width {
+                i32::from(luma.get_pixel(x + 1, y)[0])
+            } else {
+                here
+            };
+            let below = if y + 1 < height {
+                i32::from(luma.get_pixel(x, y + 1)[0])
+            } else {
+                here
+            };
+            let energy = u64::from((right - here).unsigned_abs() + (below - here).unsigned_abs());
+            columns[x as usize] += energy;
+            rows[y as usize] += energy;
+        }
+    }
+
+    (columns, rows)
+}
+
+/// Offset of the `window`-long run of `energy` with the largest sum
+///
+/// Among equally good offsets, the one closest to the center wins.
+fn best_window(energy: &[u64], window: usize) -> usize {
+    let Some(last) = energy.len().checked_sub(window) else {
+        return 0;
+    };
+    let center = last / 2;
+
+    let mut sum: u64 = energy[..window].iter().sum();
+    let mut best: (u64, usize) = (sum, 0);
+    for offset in 1..=last {
+        sum = sum + energy[offset + window - 1] - energy[offset - 1];
+        let closer = offset.abs_diff(center) < best.1.abs_diff(center);
+        if sum > best.0 || (sum == best.0 && closer) {
+            best = (sum, offset);
+        }
+    }
+
+    best.1
+}
+
+/// Resolve requested output dimensions against the source size
+///
+/// When only one dimension is requested, the other is derived from the
+/// source aspect ratio. Returns `None` when neither dimension is requested.
+pub fn resolve_output_size(
+    source: (u32, u32),
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Option<(u32, u32)> {
+    let (src_w, src_h) = (u64::from(source.0.max(1)), u64::from(source.1.max(1)));
+    let scale = |value: u64, num: u64, den: u64| ((value * num + den / 2) / den).max(1) as u32;
+
+    match (width, height) {
+        (Some(w), Some(h)) => Some((w, h)),
+        (Some(w), None) => Some((w, scale(u64::from(w), src_h, src_w))),
+        (None, Some(h)) => Some((scale(u64::from(h), src_w, src_h), h)),
+        (None, None) => None,
+    }
+}
+
+/// Drop byte-identical images, keeping the first occurrence of each
+///
+/// Images are compared by SHA-256 digest, so only exact duplicates collapse;
+/// order is otherwise preserved.
+pub fn dedupe_images(images: &[Bytes]) -> Vec<&Bytes> {
+    let mut seen = HashSet::with_capacity(images.len());
+    images
+        .iter()
+        .filter(|image| seen.insert(Sha256::digest(image)))
+        .collect()
+}
+
+/// Losslessly optimize PNG bytes
+///
+/// Re-encodes the PNG with `oxipng`, keeping the pixel data identical. If the
+/// optimized output is not smaller, the original bytes are returned.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the input is not a valid PNG.
+#[cfg(feature = "png-optimize")]
+pub fn optimize_png(data: Bytes) -> Result<Bytes> {
+    let optimized = oxipng::optimize_from_memory(&data, &oxipng::Options::default())
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to optimize PNG: {}", e)))?;
+
+    if optimized.len() < data.len() {
+        Ok(Bytes::from(optimized))
+    } else {
+        Ok(data)
+    }
+}
+
+/// Losslessly optimize PNG bytes
+///
+/// Built without the `png-optimize` feature, so the bytes are returned unchanged.
+#[cfg(not(feature = "png-optimize"))]
+pub fn optimize_png(data: Bytes) -> Result<Bytes> {
+    tracing::warn!("PNG optimization requested but the png-optimize feature is disabled");
+    Ok(data)
+}
+
+/// Add text chunks to a PNG without changing its pixels
+///
+/// Each `(keyword, text)` pair becomes a `tEXt` chunk, or a UTF-8 `iTXt`
+/// chunk when the text can't be written in Latin-1 (e.g. a prompt in another
+/// script). The image is decoded and re-encoded losslessly; palette and
+/// low bit depth images come back expanded to 8-bit RGB(A) or grayscale.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if `data` is not a readable PNG or a
+/// keyword is not 1-79 Latin-1 characters.
+pub fn embed_png_text(data: &[u8], entries: &[(&str, String)]) -> Result<Bytes> {
+    let png_error = |e: &dyn std::fmt::Display| {
+        AppError::ImageProcessing(format!("Failed to embed PNG text: {}", e))
+    };
+
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|e| png_error(&e))?;
+    let mut pixels = vec![
+        0;
+        reader
+            .output_buffer_size()
+            .ok_or_else(|| png_error(&"image too large"))?
+    ];
+    let frame = reader.next_frame(&mut pixels).map_err(|e| png_error(&e))?;
+    pixels.truncate(frame.buffer_size());
+
+    let mut buffer = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buffer, frame.width, frame.height);
+    encoder.set_color(frame.color_type);
+    encoder.set_depth(frame.bit_depth);
+    for (keyword, text) in entries {
+        let added = if text.chars().all(|c| u32::from(c) <= 0xFF) {
+            encoder.add_text_chunk(keyword.to_string(), text.clone())
+        } else {
+            encoder.add_itxt_chunk(keyword.to_string(), text.clone())
+        };
+        added.map_err(|e| png_error(&e))?;
+    }
+    let mut writer = encoder.write_header().map_err(|e| png_error(&e))?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| png_error(&e))?;
+    writer.finish().map_err(|e| png_error(&e))?;
+
+    Ok(Bytes::from(buffer))
+}
+
+/// Text chunks (`tEXt` and `iTXt`) of a PNG as `(keyword, text)` pairs
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if `data` is not a readable PNG.
+pub fn png_text(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let reader = png::Decoder::new(Cursor::new(data))
+        .read_info()
+        .map_err(|e| AppError::ImageProcessing(format!("Failed to read PNG: {}", e)))?;
+    let info = reader.info();
+
+    let mut entries: Vec<_> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+    for chunk in &info.utf8_text {
+        let text = chunk
+            .get_text()
+            .map_err(|e| AppError::ImageProcessing(format!("Failed to read PNG text: {}", e)))?;
+        entries.push((chunk.keyword.clone(), text));
+    }
+    Ok(entries)
+}
+
+/// Measure how different two encoded images are
+///
+/// Both images are decoded and compared as RGBA pixels; if the dimensions
+/// differ, `b` is scaled to the size of `a` first. The result is the mean
+/// absolute per-channel difference normalized to `0.0` (identical) through
+/// `1.0` (maximally different).
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if either image cannot be decoded.
+pub fn image_difference(a: &[u8], b: &[u8]) -> Result<f64> {
+    if a == b {
+        return Ok(0.0);
+    }
+
+    let a = bytes_to_image(a)?.to_rgba8();
+    let b = bytes_to_image(b)?;
+    let b = if b.dimensions() == a.dimensions() {
+        b.to_rgba8()
+    } else {
+        b.resize_exact(a.width(), a.height(), FilterType::Triangle)
+            .to_rgba8()
+    };
+
+    let total: u64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(x, y)| u64::from(x.abs_diff(*y)))
+        .sum();
+    let samples = a.as_raw().len().max(1) as f64;
+
+    Ok(total as f64 / (samples * 255.0))
+}
+
+/// Side of the grayscale thumbnail a perceptual hash is computed from
+const PHASH_SAMPLE_SIZE: usize = 32;
+
+/// Side of the block of low-frequency DCT coefficients kept in the hash
+const PHASH_BLOCK_SIZE: usize = 8;
+
+/// 64-bit DCT perceptual hash of an image, as 16 hex digits
+///
+/// The image is reduced to a 32x32 grayscale thumbnail and transformed with
+/// a 2D DCT; each bit records whether one of the 8x8 lowest-frequency
+/// coefficients lies above their median. Re-encodings, resizes and small
+/// edits keep most bits, so visually identical images share a hash and
+/// similar ones differ in few bits.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded.
+pub fn perceptual_hash(data: &[u8]) -> Result<String> {
+    let size = PHASH_SAMPLE_SIZE;
+    let thumbnail = bytes_to_image(data)?
+        .resize_exact(size as u32, size as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = thumbnail.as_raw().iter().map(|&p| f64::from(p)).collect();
+
+    // Separable DCT-II: rows, then the columns of the result
+    let cosines: Vec<f64> = (0..size * size)
+        .map(|i| {
+            let (k, n) = (i / size, i % size);
+            (std::f64::consts::PI / size as f64 * (n as f64 + 0.5) * k as f64).cos()
+        })
+        .collect();
+    let dct = |input: &[f64], stride: usize, offset: usize, k: usize| -> f64 {
+        (0..size)
+            .map(|n| input[offset + n * stride] * cosines[k * size + n])
+            .sum()
+    };
+    let mut rows = vec![0.0; size * size];
+    for y in 0..size {
+        for k in 0..size {
+            rows[y * size + k] = dct(&pixels, 1, y * size, k);
+        }
+    }
+    let block = PHASH_BLOCK_SIZE;
+    let mut coefficients = Vec::with_capacity(block * block);
+    for v in 0..block {
+        for u in 0..block {
+            coefficients.push(dct(&rows, size, u, v));
+        }
+    }
+
+    // The DC term only reflects overall brightness, so it is left out of the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    let hash = coefficients
+        .iter()
+        .fold(0u64, |hash, &c| (hash << 1) | u64::from(c > median));
+    Ok(format!("{:016x}", hash))
+}
+
+/// Longest side an image is reduced to before its sharpness is measured
+const SHARPNESS_SAMPLE_SIZE: u32 = 1024;
+
+/// No-reference sharpness score: variance of the Laplacian of the image
+///
+/// The image is converted to grayscale (and reduced to at most 1024 pixels
+/// on its longest side) and filtered with the 4-neighbour Laplacian kernel;
+/// the variance of the response is returned. Blurry images have few strong
+/// edges and score low, sharp ones score high. The scale is unbounded and
+/// content-dependent, so scores are meant to be compared between images of
+/// similar content rather than against a fixed threshold.
+///
+/// # Errors
+///
+/// Returns `AppError::ImageProcessing` if the image cannot be decoded.
+pub fn sharpness_score(data: &[u8]) -> Result<f64> {
+    let img = bytes_to_image(data)?;
+    let img = if img.width().max(img.height()) > SHARPNESS_SAMPLE_SIZE {
+        img.resize(
+            SHARPNESS_SAMPLE_SIZE,
+            SHARPNESS_SAMPLE_SIZE,
+            FilterType::Triangle,
+        )
+    } else {
+        img
+    };
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width < 3 || height < 3 {
+        return Ok(0.0);
+    }
+
+    let pixel = |x: u32, y: u32| f64::from(luma.get_pixel(x, y)[0]);
+    let responses: Vec<f64> = (1..height - 1)
+        .flat_map(|y| (1..width - 1).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            pixel(x - 1, y) + pixel(x + 1, y) + pixel(x, y - 1) + pixel(x, y + 1)
+                - 4.0 * pixel(x, y)
+        })
+        .collect();
+    let count = responses.len() as f64;
+    let mean = responses.iter().sum::<f64>() / count;
+    Ok(responses.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / count)
+}
+
 /// Convert image bytes to a base64-encoded data URL
 ///
 /// This function creates a data URL suitable for embedding in HTML or sending
@@ -247,11 +1191,10 @@ mod tests {
             0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, // PNG signature
             0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, // IHDR chunk
             0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, // 1x1 dimensions
-            0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, 0xDE,
-            0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, 0x54, // IDAT chunk
-            0x08, 0xD7, 0x63, 0xF8, 0xFF, 0xFF, 0x3F, 0x00, 0x05, 0xFE, 0x02, 0xFE,
-            0xDC, 0xCC, 0x59, 0xE7,
-            0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, // IEND chunk
+            0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53, 0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49,
+            0x44, 0x41, 0x54, // IDAT chunk
+            0x08, 0xD7, 0x63, 0xF8, 0xFF, 0xFF, 0x3F, 0x00, 0x05, 0xFE, 0x02, 0xFE, 0xDC, 0xCC,
+            0x59, 0xE7, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, // IEND chunk
             0xAE, 0x42, 0x60, 0x82,
         ]
     }
@@ -299,10 +1242,608 @@ mod tests {
         assert!(validate_image_bytes(&bytes).is_ok());
     }
 
+    /// Create a solid-color RGB image of the given size
+    fn solid_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            width,
+            height,
+            image::Rgb([10, 20, 30]),
+        ))
+    }
+
+    #[test]
+    fn test_resize_fit_parsing() {
+        assert_eq!("contain".parse::<ResizeFit>().unwrap(), ResizeFit::Contain);
+        assert_eq!(" COVER ".parse::<ResizeFit>().unwrap(), ResizeFit::Cover);
+        assert_eq!("fill".parse::<ResizeFit>().unwrap(), ResizeFit::Fill);
+        assert_eq!("smart".parse::<ResizeFit>().unwrap(), ResizeFit::Smart);
+        assert!("stretch".parse::<ResizeFit>().is_err());
+    }
+
+    #[test]
+    fn test_output_format_parsing() {
+        assert_eq!("PNG".parse::<OutputFormat>().unwrap(), OutputFormat::Png);
+        assert_eq!("jpg".parse::<OutputFormat>().unwrap(), OutputFormat::Jpeg);
+        assert_eq!(
+            " webp ".parse::<OutputFormat>().unwrap(),
+            OutputFormat::Webp
+        );
+        assert!("gif".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_resize_contain_pads_to_requested_size() {
+        let img = solid_image(200, 100);
+        let resized = resize_image(&img, 50, 50, ResizeFit::Contain);
+        assert_eq!(resized.dimensions(), (50, 50));
+
+        // Content is 50x25 centered vertically; top rows are transparent padding
+        let rgba = resized.to_rgba8();
+        assert_eq!(rgba.get_pixel(25, 2)[3], 0);
+        assert_eq!(rgba.get_pixel(25, 25)[3], 255);
+    }
+
+    #[test]
+    fn test_resize_cover_fills_requested_size() {
+        let img = solid_image(200, 100);
+        let resized = resize_image(&img, 50, 50, ResizeFit::Cover);
+        assert_eq!(resized.dimensions(), (50, 50));
+        assert!(resized.to_rgba8().pixels().all(|p| p[3] == 255));
+    }
+
+    #[test]
+    fn test_resize_fill_stretches() {
+        let img = solid_image(200, 100);
+        assert_eq!(
+            resize_image(&img, 30, 70, ResizeFit::Fill).dimensions(),
+            (30, 70)
+        );
+    }
+
+    /// Flat image with a detailed checkerboard patch near its right edge
+    fn lopsided_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, y| {
+            if x >= width * 3 / 4 && (x / 2 + y / 2) % 2 == 0 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([40, 40, 40])
+            }
+        }))
+    }
+
+    #[test]
+    fn test_smart_crop_has_requested_dimensions() {
+        let img = lopsided_image(200, 100);
+        assert_eq!(smart_crop(&img, 50, 50).dimensions(), (50, 50));
+        assert_eq!(smart_crop(&img, 80, 20).dimensions(), (80, 20));
+        assert_eq!(
+            smart_crop(&solid_image(7, 300), 3, 11).dimensions(),
+            (3, 11)
+        );
+        assert_eq!(
+            resize_image(&img, 40, 60, ResizeFit::Smart).dimensions(),
+            (40, 60)
+        );
+    }
+
+    #[test]
+    fn test_smart_crop_follows_detail_not_center() {
+        let img = lopsided_image(200, 100);
+
+        let smart = smart_crop(&img, 100, 100).to_rgb8();
+        let center = resize_image(&img, 100, 100, ResizeFit::Cover).to_rgb8();
+
+        assert_ne!(smart, center);
+        // The detailed patch (x >= 150) ends up inside the smart crop only
+        let bright = |crop: &image::RgbImage| crop.pixels().filter(|p| p[0] > 200).count();
+        assert!(bright(&smart) > 0);
+        assert_eq!(bright(&center), 0);
+    }
+
+    #[test]
+    fn test_smart_crop_flat_image_stays_centered() {
+        assert_eq!(best_window(&[0; 10], 4), 3);
+        assert_eq!(best_window(&[0, 0, 0, 9, 9, 0], 2), 3);
+        assert_eq!(best_window(&[1, 2], 2), 0);
+    }
+
+    #[test]
+    fn test_dedupe_images_keeps_first_occurrences() {
+        let a = Bytes::from_static(b"image a");
+        let b = Bytes::from_static(b"image b");
+        let images = vec![a.clone(), b.clone(), a.clone(), b.clone(), a.clone()];
+
+        assert_eq!(dedupe_images(&images), vec![&a, &b]);
+        assert!(dedupe_images(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_output_size() {
+        assert_eq!(resolve_output_size((200, 100), None, None), None);
+        assert_eq!(
+            resolve_output_size((200, 100), Some(64), Some(32)),
+            Some((64, 32))
+        );
+        assert_eq!(
+            resolve_output_size((200, 100), Some(100), None),
+            Some((100, 50))
+        );
+        assert_eq!(
+            resolve_output_size((200, 100), None, Some(25)),
+            Some((50, 25))
+        );
+    }
+
+    #[test]
+    fn test_image_to_bytes_jpeg_drops_alpha() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let bytes = image_to_bytes(&img, ImageFormat::Jpeg).unwrap();
+        assert_eq!(get_mime_type(&bytes).unwrap(), "image/jpeg");
+    }
+
+    #[test]
+    fn test_has_transparency() {
+        let mut rgba = image::RgbaImage::from_pixel(4, 4, image::Rgba([1, 2, 3, 255]));
+        assert!(!has_transparency(&DynamicImage::ImageRgba8(rgba.clone())));
+
+        rgba.put_pixel(3, 3, image::Rgba([1, 2, 3, 254]));
+        assert!(has_transparency(&DynamicImage::ImageRgba8(rgba)));
+        assert!(!has_transparency(&solid_image(4, 4)));
+    }
+
+    #[test]
+    fn test_png_text_round_trips_latin1_and_utf8() {
+        let png = image_to_bytes(&solid_image(3, 2), ImageFormat::Png).unwrap();
+        let entries = [
+            ("prompt", "Add a café table".to_string()),
+            ("model", "模型".to_string()),
+        ];
+
+        let tagged = embed_png_text(&png, &entries).unwrap();
+
+        assert_eq!(
+            png_text(&tagged).unwrap(),
+            vec![
+                ("prompt".to_string(), "Add a café table".to_string()),
+                ("model".to_string(), "模型".to_string()),
+            ]
+        );
+        let (original, tagged) = (
+            bytes_to_image(&png).unwrap(),
+            bytes_to_image(&tagged).unwrap(),
+        );
+        assert_eq!(original.to_rgba8(), tagged.to_rgba8());
+    }
+
+    #[test]
+    fn test_fit_within_shrinks_only_oversized_images() {
+        let jpeg = image_to_bytes(&solid_image(400, 100), ImageFormat::Jpeg).unwrap();
+
+        let shrunk = fit_within(jpeg.clone(), 200).unwrap();
+        assert_eq!(image_dimensions(&shrunk).unwrap(), (200, 50));
+        assert_eq!(image::guess_format(&shrunk).unwrap(), ImageFormat::Jpeg);
+
+        assert_eq!(fit_within(jpeg.clone(), 400).unwrap(), jpeg);
+    }
+
+    /// Center pixel of an encoded image
+    fn center_pixel(bytes: &[u8]) -> [u8; 3] {
+        let img = bytes_to_image(bytes).unwrap().to_rgb8();
+        img.get_pixel(img.width() / 2, img.height() / 2).0
+    }
+
+    fn assert_close(actual: [u8; 3], expected: [u8; 3]) {
+        let close = actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 4);
+        assert!(close, "{:?} is not close to {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_jpeg_flattens_transparency_onto_background() {
+        let transparent = DynamicImage::ImageRgba8(image::RgbaImage::new(16, 16));
+        assert_close(
+            center_pixel(&image_to_bytes(&transparent, ImageFormat::Jpeg).unwrap()),
+            [255, 255, 255],
+        );
+
+        let jpeg = JpegOptions {
+            background: parse_hex_rgb("#3366cc").unwrap(),
+            ..JpegOptions::default()
+        };
+        let bytes = image_to_bytes_with_options(&transparent, ImageFormat::Jpeg, &jpeg).unwrap();
+        assert_close(center_pixel(&bytes), [0x33, 0x66, 0xcc]);
+
+        // Half-transparent red over blue blends the two
+        let half_red = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            16,
+            16,
+            image::Rgba([255, 0, 0, 128]),
+        ));
+        let jpeg = JpegOptions {
+            background: [0, 0, 255],
+            ..JpegOptions::default()
+        };
+        let bytes = image_to_bytes_with_options(&half_red, ImageFormat::Jpeg, &jpeg).unwrap();
+        assert_close(center_pixel(&bytes), [128, 0, 127]);
+
+        // Opaque images are unaffected by the background
+        let opaque = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            16,
+            16,
+            image::Rgba([10, 200, 30, 255]),
+        ));
+        let bytes = image_to_bytes_with_options(&opaque, ImageFormat::Jpeg, &jpeg).unwrap();
+        assert_close(center_pixel(&bytes), [10, 200, 30]);
+    }
+
+    #[test]
+    fn test_jpeg_subsampling_shrinks_colorful_output() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([
+                ((x * 37) % 256) as u8,
+                ((y * 53) % 256) as u8,
+                ((x * y) % 256) as u8,
+            ])
+        }));
+        let size = |subsampling| {
+            let jpeg = JpegOptions {
+                subsampling,
+                ..JpegOptions::default()
+            };
+            image_to_bytes_with_options(&img, ImageFormat::Jpeg, &jpeg)
+                .unwrap()
+                .len()
+        };
+
+        assert!(size(JpegSubsampling::S420) < size(JpegSubsampling::S444));
+    }
+
+    #[test]
+    fn test_jpeg_subsampling_and_color_parsing() {
+        assert_eq!(
+            "4:2:0".parse::<JpegSubsampling>().unwrap(),
+            JpegSubsampling::S420
+        );
+        assert_eq!(
+            " 422 ".parse::<JpegSubsampling>().unwrap(),
+            JpegSubsampling::S422
+        );
+        assert!("4:1:1".parse::<JpegSubsampling>().is_err());
+
+        assert_eq!(parse_hex_rgb("FF8000").unwrap(), [255, 128, 0]);
+        assert_eq!(parse_hex_rgb("#000000").unwrap(), [0, 0, 0]);
+        for invalid in ["", "fff", "#ff80001", "gg0000", "ff8000ff"] {
+            assert!(parse_hex_rgb(invalid).is_err(), "{}", invalid);
+        }
+    }
+
     #[test]
     fn test_format_to_mime_type() {
         assert_eq!(format_to_mime_type(ImageFormat::Png), "image/png");
         assert_eq!(format_to_mime_type(ImageFormat::Jpeg), "image/jpeg");
         assert_eq!(format_to_mime_type(ImageFormat::WebP), "image/webp");
     }
+
+    #[test]
+    fn test_optimize_png_keeps_image_valid_and_not_larger() {
+        // A flat image encoded by the image crate leaves room for optimization
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            64,
+            64,
+            image::Rgba([20, 40, 60, 255]),
+        ));
+        let original = image_to_bytes(&img, ImageFormat::Png).unwrap();
+
+        let optimized = optimize_png(original.clone()).unwrap();
+
+        assert!(optimized.len() <= original.len());
+        let decoded = bytes_to_image(&optimized).unwrap();
+        assert_eq!(decoded.to_rgba8(), img.to_rgba8());
+    }
+
+    #[cfg(feature = "png-optimize")]
+    #[test]
+    fn test_optimize_png_rejects_invalid_data() {
+        assert!(optimize_png(Bytes::from_static(b"not a png")).is_err());
+    }
+
+    #[test]
+    fn test_image_difference() {
+        let gray = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            8,
+            8,
+            image::Rgb([100, 100, 100]),
+        ));
+        let nudged = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            8,
+            8,
+            image::Rgb([101, 100, 100]),
+        ));
+        let white = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            16,
+            16,
+            image::Rgb([255, 255, 255]),
+        ));
+
+        let gray_png = image_to_bytes(&gray, ImageFormat::Png).unwrap();
+        let nudged_png = image_to_bytes(&nudged, ImageFormat::Png).unwrap();
+        let white_png = image_to_bytes(&white, ImageFormat::Png).unwrap();
+
+        assert_eq!(image_difference(&gray_png, &gray_png).unwrap(), 0.0);
+        assert!(image_difference(&gray_png, &nudged_png).unwrap() < 0.01);
+        // Different sizes are compared after scaling
+        assert!(image_difference(&gray_png, &white_png).unwrap() > 0.3);
+        assert!(image_difference(&gray_png, b"not an image").is_err());
+    }
+
+    /// Gray image whose brightness follows `shade(x, y)`
+    fn shaded_image(width: u32, height: u32, shade: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, y| {
+            let v = shade(x, y);
+            image::Rgb([v, v, v])
+        }))
+    }
+
+    /// Two soft blobs on a gradient, a stand-in for a photo
+    fn blobs_image(size: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(size, size, |x, y| {
+            let (fx, fy) = (
+                f64::from(x) / f64::from(size),
+                f64::from(y) / f64::from(size),
+            );
+            let d1 = ((fx - 0.3).powi(2) + (fy - 0.35).powi(2)).sqrt();
+            let d2 = ((fx - 0.7).powi(2) + (fy - 0.6).powi(2)).sqrt();
+            let v = (40.0 + 180.0 * (-d1 * 6.0).exp() + 120.0 * (-d2 * 9.0).exp() + 30.0 * fx)
+                .min(255.0) as u8;
+            image::Rgb([v, v / 2, 255 - v])
+        }))
+    }
+
+    #[test]
+    fn test_sharpness_score_lower_for_blurred_image() {
+        let sharp = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            if (x / 4 + y / 4) % 2 == 0 {
+                image::Rgb([255, 255, 255])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        }));
+        let blurred = sharp.blur(2.0);
+
+        let sharp_score =
+            sharpness_score(&image_to_bytes(&sharp, ImageFormat::Png).unwrap()).unwrap();
+        let blurred_score =
+            sharpness_score(&image_to_bytes(&blurred, ImageFormat::Png).unwrap()).unwrap();
+
+        assert!(
+            blurred_score < sharp_score,
+            "{} >= {}",
+            blurred_score,
+            sharp_score
+        );
+        let flat = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            16,
+            16,
+            image::Rgb([90, 90, 90]),
+        ));
+        assert_eq!(
+            sharpness_score(&image_to_bytes(&flat, ImageFormat::Png).unwrap()).unwrap(),
+            0.0
+        );
+        assert!(sharpness_score(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_perceptual_hash_matches_identical_images() {
+        let img = blobs_image(64);
+        let png = image_to_bytes(&img, ImageFormat::Png).unwrap();
+        let hash = perceptual_hash(&png).unwrap();
+
+        assert_eq!(hash.len(), 16);
+        assert_eq!(perceptual_hash(&png).unwrap(), hash);
+        // The same picture at another size hashes the same
+        let larger = resize_image(&img, 128, 128, ResizeFit::Fill);
+        assert_eq!(
+            perceptual_hash(&image_to_bytes(&larger, ImageFormat::Png).unwrap()).unwrap(),
+            hash
+        );
+    }
+
+    #[test]
+    fn test_perceptual_hash_differs_for_different_images() {
+        let horizontal = shaded_image(64, 64, |x, _| (x * 4) as u8);
+        let vertical = shaded_image(64, 64, |_, y| (y * 4) as u8);
+        let checkers = shaded_image(
+            64,
+            64,
+            |x, y| if (x / 16 + y / 16) % 2 == 0 { 0 } else { 255 },
+        );
+
+        let hashes: Vec<String> = [horizontal, vertical, checkers, blobs_image(64)]
+            .iter()
+            .map(|img| perceptual_hash(&image_to_bytes(img, ImageFormat::Png).unwrap()).unwrap())
+            .collect();
+
+        assert_ne!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+        assert_ne!(hashes[1], hashes[2]);
+        assert_ne!(hashes[2], hashes[3]);
+        assert!(perceptual_hash(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(12, 7));
+        let png = image_to_bytes(&img, ImageFormat::Png).unwrap();
+
+        assert_eq!(image_dimensions(&png).unwrap(), (12, 7));
+        assert!(image_dimensions(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_decoded_dimensions() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::new(12, 7));
+        let png = image_to_bytes(&img, ImageFormat::Png).unwrap();
+
+        assert_eq!(decoded_dimensions(&png).unwrap(), (12, 7));
+        assert!(decoded_dimensions(b"not an image").is_err());
+    }
+
+    /// Encode a solid-color CMYK JPEG, as produced by print workflows
+    fn make_cmyk_jpeg(width: u16, height: u16, cmyk: [u8; 4]) -> Vec<u8> {
+        let pixels: Vec<u8> = cmyk.repeat(width as usize * height as usize);
+        let mut buffer = Vec::new();
+        jpeg_encoder::Encoder::new(&mut buffer, 95)
+            .encode(&pixels, width, height, jpeg_encoder::ColorType::Cmyk)
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_detects_cmyk_jpeg() {
+        let cmyk = make_cmyk_jpeg(8, 8, [0, 0, 0, 0]);
+        assert!(is_cmyk_jpeg(&cmyk));
+
+        let rgb = image_to_bytes(&DynamicImage::new_rgb8(8, 8), ImageFormat::Jpeg).unwrap();
+        assert!(!is_cmyk_jpeg(&rgb));
+        assert!(!is_cmyk_jpeg(&create_test_png()));
+    }
+
+    #[test]
+    fn test_normalize_converts_cmyk_to_rgb() {
+        // Pure cyan: no red, full green and blue
+        let cmyk = make_cmyk_jpeg(16, 8, [255, 0, 0, 0]);
+
+        let normalized = normalize_color_space(Bytes::from(cmyk)).unwrap();
+
+        assert_eq!(jpeg_component_count(&normalized), Some(3));
+        let img = image::load_from_memory(&normalized).unwrap();
+        assert_eq!((img.width(), img.height()), (16, 8));
+        let [r, g, b] = img.to_rgb8().get_pixel(4, 4).0;
+        assert!(
+            r < 40 && g > 215 && b > 215,
+            "unexpected color: {:?}",
+            (r, g, b)
+        );
+    }
+
+    #[test]
+    fn test_is_complete_image_detects_truncation() {
+        let gradient = DynamicImage::ImageRgb8(image::RgbImage::from_fn(32, 32, |x, y| {
+            image::Rgb([(x * 8) as u8, (y * 8) as u8, 0])
+        }));
+        for format in [
+            ImageFormat::Png,
+            ImageFormat::Jpeg,
+            ImageFormat::WebP,
+            ImageFormat::Gif,
+        ] {
+            let data = image_to_bytes(&gradient, format).unwrap();
+            assert!(is_complete_image(&data), "{:?}", format);
+            assert!(!is_complete_image(&data[..data.len() - 10]), "{:?}", format);
+        }
+
+        let mut padded = image_to_bytes(&gradient, ImageFormat::Jpeg)
+            .unwrap()
+            .to_vec();
+        padded.extend([0; 16]);
+        assert!(is_complete_image(&padded));
+    }
+
+    fn png_16_bit(alpha: bool) -> Vec<u8> {
+        let img = if alpha {
+            DynamicImage::ImageRgba16(image::ImageBuffer::from_pixel(
+                4,
+                2,
+                image::Rgba([65535, 0, 32768, 65535]),
+            ))
+        } else {
+            DynamicImage::ImageRgb16(image::ImageBuffer::from_pixel(
+                4,
+                2,
+                image::Rgb([65535, 0, 32768]),
+            ))
+        };
+        image_to_bytes(&img, ImageFormat::Png).unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_enlarge_to_grows_only_smaller_images() {
+        let small = image_to_bytes(&DynamicImage::new_rgb8(30, 15), ImageFormat::Jpeg).unwrap();
+
+        let enlarged = enlarge_to(small.clone(), 60).unwrap();
+
+        assert_eq!(image_dimensions(&enlarged).unwrap(), (60, 30));
+        assert_eq!(image::guess_format(&enlarged).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(enlarge_to(small.clone(), 30).unwrap(), small);
+    }
+
+    #[test]
+    fn test_rotation_parsing() {
+        assert_eq!(" 90 ".parse::<Rotation>().unwrap(), Rotation::Cw90);
+        assert_eq!("0".parse::<Rotation>().unwrap(), Rotation::None);
+        assert!("45".parse::<Rotation>().is_err());
+        assert!("-90".parse::<Rotation>().is_err());
+        assert!("left".parse::<Rotation>().is_err());
+    }
+
+    #[test]
+    fn test_rotate_swaps_dimensions_for_quarter_turns() {
+        // Red top-left corner, to follow where it ends up
+        let mut img = image::RgbImage::new(4, 2);
+        img.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        let png = image_to_bytes(&DynamicImage::ImageRgb8(img), ImageFormat::Png).unwrap();
+
+        for (rotation, size, corner) in [
+            (Rotation::Cw90, (2, 4), (1, 0)),
+            (Rotation::Cw180, (4, 2), (3, 1)),
+            (Rotation::Cw270, (2, 4), (0, 3)),
+        ] {
+            let rotated = rotate(png.clone(), rotation).unwrap();
+
+            assert_eq!(image::guess_format(&rotated).unwrap(), ImageFormat::Png);
+            let img = bytes_to_image(&rotated).unwrap().to_rgb8();
+            assert_eq!(img.dimensions(), size, "{:?}", rotation);
+            assert_eq!(
+                img.get_pixel(corner.0, corner.1).0,
+                [255, 0, 0],
+                "{:?}",
+                rotation
+            );
+        }
+
+        assert_eq!(rotate(png.clone(), Rotation::None).unwrap(), png);
+    }
+
+    #[test]
+    fn test_detects_high_bit_depth() {
+        assert!(is_high_bit_depth(&png_16_bit(false)));
+        assert!(is_high_bit_depth(&png_16_bit(true)));
+        assert!(!is_high_bit_depth(&create_test_png()));
+        assert!(!is_high_bit_depth(b"not an image"));
+    }
+
+    #[test]
+    fn test_to_8_bit_converts_and_keeps_alpha() {
+        for alpha in [false, true] {
+            let converted = to_8_bit(Bytes::from(png_16_bit(alpha))).unwrap();
+
+            assert!(!is_high_bit_depth(&converted));
+            let img = image::load_from_memory(&converted).unwrap();
+            assert_eq!(img.color().has_alpha(), alpha);
+            assert_eq!((img.width(), img.height()), (4, 2));
+            assert_eq!(img.to_rgb8().get_pixel(0, 0).0, [255, 0, 128]);
+        }
+
+        let png = Bytes::from(create_test_png());
+        assert_eq!(to_8_bit(png.clone()).unwrap(), png);
+    }
+
+    #[test]
+    fn test_normalize_passes_other_images_through() {
+        let png = Bytes::from(create_test_png());
+        assert_eq!(normalize_color_space(png.clone()).unwrap(), png);
+
+        let rgb = image_to_bytes(&DynamicImage::new_rgb8(8, 8), ImageFormat::Jpeg).unwrap();
+        assert_eq!(normalize_color_space(rgb.clone()).unwrap(), rgb);
+    }
 }
@@ -0,0 +1,90 @@
+//! Redaction of provider request/response details in logs
+//!
+//! Data URIs can be megabytes long and prompts may contain sensitive user
+//! content; these helpers shorten or hash them according to `LOG_REDACTION`
+//! before they reach a `tracing` call.
+
+use crate::audit::sha256_hex;
+use crate::config::LogRedaction;
+use std::borrow::Cow;
+
+/// Characters of a data URI kept in logs (enough for the MIME type and a few bytes)
+const DATA_URI_PREFIX_CHARS: usize = 48;
+
+/// Hex characters of the prompt hash kept in logs
+const PROMPT_HASH_CHARS: usize = 16;
+
+/// URL as it should appear in logs
+///
+/// Data URIs longer than the kept prefix are cut to that prefix plus their
+/// total length; other URLs are returned unchanged.
+pub fn url(url: &str, mode: LogRedaction) -> Cow<'_, str> {
+    if mode == LogRedaction::Off || !url.starts_with("data:") || url.len() <= DATA_URI_PREFIX_CHARS
+    {
+        return Cow::Borrowed(url);
+    }
+
+    // Data URIs from providers are ASCII, but never split a character
+    let mut end = DATA_URI_PREFIX_CHARS;
+    while !url.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}...({} chars)", &url[..end], url.len()))
+}
+
+/// Prompt as it should appear in logs
+///
+/// With `LogRedaction::Hash` only a short SHA-256 prefix is logged, which
+/// still lets identical prompts be correlated.
+pub fn prompt(prompt: &str, mode: LogRedaction) -> Cow<'_, str> {
+    match mode {
+        LogRedaction::Hash => {
+            let mut hash = sha256_hex(prompt.as_bytes());
+            hash.truncate(PROMPT_HASH_CHARS);
+            Cow::Owned(format!("sha256:{}", hash))
+        }
+        LogRedaction::Off | LogRedaction::Truncate => Cow::Borrowed(prompt),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_uri_is_truncated() {
+        let data_uri = format!("data:image/png;base64,{}", "A".repeat(10_000));
+        let logged = url(&data_uri, LogRedaction::Truncate);
+
+        assert!(logged.starts_with("data:image/png;base64,AAAA"));
+        assert!(logged.ends_with("...(10022 chars)"));
+        assert!(logged.len() < 100);
+    }
+
+    #[test]
+    fn test_urls_kept_unless_data_uri_redaction_applies() {
+        let data_uri = format!("data:image/png;base64,{}", "A".repeat(100));
+        assert_eq!(url(&data_uri, LogRedaction::Off), data_uri);
+        assert_eq!(
+            url("data:image/png;base64,AAAA", LogRedaction::Hash),
+            "data:image/png;base64,AAAA"
+        );
+
+        let https = "https://fal.media/files/result.png";
+        assert_eq!(url(https, LogRedaction::Hash), https);
+    }
+
+    #[test]
+    fn test_prompt_hashed_only_in_hash_mode() {
+        assert_eq!(
+            prompt("add a red door", LogRedaction::Truncate),
+            "add a red door"
+        );
+
+        let hashed = prompt("add a red door", LogRedaction::Hash);
+        assert!(hashed.starts_with("sha256:"));
+        assert_eq!(hashed.len(), "sha256:".len() + PROMPT_HASH_CHARS);
+        assert!(!hashed.contains("door"));
+        assert_eq!(hashed, prompt("add a red door", LogRedaction::Hash));
+    }
+}
@@ -10,3 +10,12 @@
 
 /// Image processing utilities for validation, conversion, and encoding
 pub mod image_utils;
+
+/// Downloading of input images referenced by URL
+pub mod remote_image;
+
+/// Redaction of data URIs and prompts in provider logs
+pub mod log_redaction;
+
+/// In-memory extraction of ZIP archives uploaded to the batch endpoint
+pub mod archive;
@@ -0,0 +1,288 @@
+//! Fetching of images passed by URL
+//!
+//! Clients may reference an input image by URL instead of uploading it. The
+//! server downloads it under the limits configured in `AppConfig`:
+//! - a timeout covering the whole fetch, redirects included
+//! - a maximum body size, enforced on `Content-Length` and while streaming
+//! - an `image/*` content type
+//! - at most `url_input_max_redirects` redirects, all to `http`/`https` URLs
+//! - only public destination addresses, checked after DNS resolution and on
+//!   every redirect hop, unless `url_input_allow_private` is set
+//!
+//! Every failure is reported as `AppError::InvalidInput`, since the URL comes
+//! from the client.
+
+use crate::config::AppConfig;
+use crate::error::{AppError, Result};
+use crate::services::http_client::{self, HttpClientSettings, NonPublicAddress};
+use reqwest::Url;
+use std::time::Duration;
+
+/// Whether a URL uses a scheme we are willing to fetch
+fn is_http(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+/// The shared client used for URL inputs
+fn client(config: &AppConfig) -> Result<reqwest::Client> {
+    let timeout = Duration::from_secs(config.url_input_timeout_secs);
+
+    HttpClientSettings::from_config(config, timeout)
+        .with_max_redirects(config.url_input_max_redirects)
+        .with_public_only(!config.url_input_allow_private)
+        .client()
+        .map_err(|e| AppError::InternalServer(e.to_string()))
+}
+
+/// Describe a failed fetch without echoing reqwest's full URL-laden message
+fn fetch_error(err: reqwest::Error, timeout_secs: u64) -> AppError {
+    let reason = if NonPublicAddress::caused(&err) {
+        NonPublicAddress.to_string()
+    } else if err.is_timeout() {
+        format!("timed out after {} seconds", timeout_secs)
+    } else if err.is_redirect() {
+        match std::error::Error::source(&err) {
+            Some(cause) => format!("redirect rejected: {}", cause),
+            None => "redirect rejected".to_string(),
+        }
+    } else if err.is_connect() {
+        "connection failed".to_string()
+    } else {
+        err.without_url().to_string()
+    };
+
+    AppError::InvalidInput(format!("Failed to fetch image URL: {}", reason))
+}
+
+/// Download an image referenced by URL
+///
+/// # Errors
+///
+/// Returns `AppError::InvalidInput` if the URL is not http(s), the fetch
+/// fails or times out, a redirect is rejected, the destination is not a
+/// public address (without `url_input_allow_private`), the response is not a
+/// successful `image/*` response, or the body exceeds `url_input_max_bytes`.
+pub async fn fetch_image(config: &AppConfig, url: &str) -> Result<Vec<u8>> {
+    let parsed = Url::parse(url.trim())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid image URL: {}", e)))?;
+    if !is_http(&parsed) {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported image URL scheme '{}'; expected http or https",
+            parsed.scheme()
+        )));
+    }
+    if !config.url_input_allow_private {
+        http_client::check_public_url(&parsed)
+            .map_err(|e| AppError::InvalidInput(format!("Failed to fetch image URL: {}", e)))?;
+    }
+
+    let max_bytes = config.url_input_max_bytes;
+    let too_large = || {
+        AppError::InvalidInput(format!(
+            "Image URL response exceeds the {} byte limit",
+            max_bytes
+        ))
+    };
+
+    let mut response = client(config)?
+        .get(parsed)
+        .send()
+        .await
+        .map_err(|e| fetch_error(e, config.url_input_timeout_secs))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(AppError::InvalidInput(format!(
+            "Image URL returned HTTP {}",
+            status
+        )));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    if !mime.starts_with("image/") {
+        return Err(AppError::InvalidInput(format!(
+            "Image URL returned content type '{}'; expected image/*",
+            content_type
+        )));
+    }
+
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+
+    // Content-Length may be absent or wrong, so enforce the cap while reading
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| fetch_error(e, config.url_input_timeout_secs))?
+    {
+        if data.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    tracing::debug!(size = data.len(), content_type = %mime, "Fetched image URL");
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::Router;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    /// Serve a small set of fixture routes on a local port
+    async fn serve() -> String {
+        let app = Router::new()
+            .route(
+                "/small.png",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], PNG_SIGNATURE) }),
+            )
+            .route(
+                "/large.png",
+                get(|| async { ([(header::CONTENT_TYPE, "image/png")], vec![0u8; 4096]) }),
+            )
+            .route(
+                "/page.html",
+                get(|| async { ([(header::CONTENT_TYPE, "text/html")], "<html></html>") }),
+            )
+            .route("/missing.png", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/to-ftp",
+                get(|| async { redirect("ftp://example.com/a.png") }),
+            )
+            .route("/hop/3", get(|| async { redirect("/hop/2") }))
+            .route("/hop/2", get(|| async { redirect("/hop/1") }))
+            .route("/hop/1", get(|| async { redirect("/small.png") }));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn redirect(location: &'static str) -> impl IntoResponse {
+        (StatusCode::FOUND, [(header::LOCATION, location)])
+    }
+
+    /// Config for the local fixture server, so private addresses are allowed
+    fn config(max_bytes: usize, max_redirects: usize) -> AppConfig {
+        AppConfig {
+            url_input_max_bytes: max_bytes,
+            url_input_max_redirects: max_redirects,
+            url_input_allow_private: true,
+            ..AppConfig::default()
+        }
+    }
+
+    fn assert_invalid_input(result: Result<Vec<u8>>, expected: &str) {
+        match result {
+            Err(AppError::InvalidInput(message)) => {
+                assert!(
+                    message.contains(expected),
+                    "unexpected message: {}",
+                    message
+                )
+            }
+            other => panic!("expected InvalidInput, got {:?}", other.map(|d| d.len())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetches_image() {
+        let base = serve().await;
+        let data = fetch_image(&config(1024, 3), &format!("{}/small.png", base))
+            .await
+            .unwrap();
+        assert_eq!(data, PNG_SIGNATURE);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_download_over_size_cap() {
+        let base = serve().await;
+        let result = fetch_image(&config(1024, 3), &format!("{}/large.png", base)).await;
+        assert_invalid_input(result, "exceeds the 1024 byte limit");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_image_content_type() {
+        let base = serve().await;
+        let result = fetch_image(&config(1024, 3), &format!("{}/page.html", base)).await;
+        assert_invalid_input(result, "content type 'text/html'");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_error_status() {
+        let base = serve().await;
+        let result = fetch_image(&config(1024, 3), &format!("{}/missing.png", base)).await;
+        assert_invalid_input(result, "HTTP 404");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_http_urls_and_redirects() {
+        let base = serve().await;
+        assert_invalid_input(
+            fetch_image(&config(1024, 3), "file:///etc/passwd").await,
+            "scheme 'file'",
+        );
+        assert_invalid_input(
+            fetch_image(&config(1024, 3), &format!("{}/to-ftp", base)).await,
+            "redirect",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limits_redirect_count() {
+        let base = serve().await;
+        let url = format!("{}/hop/3", base);
+
+        assert_eq!(
+            fetch_image(&config(1024, 3), &url).await.unwrap(),
+            PNG_SIGNATURE
+        );
+        assert_invalid_input(
+            fetch_image(&config(1024, 2), &url).await,
+            "more than 2 redirects",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_private_destinations() {
+        let base = serve().await;
+        let port = base.rsplit(':').next().unwrap();
+        let public_only = AppConfig::default();
+
+        for url in [
+            format!("{}/small.png", base),
+            format!("http://localhost:{}/small.png", port),
+            "http://169.254.169.254/latest/meta-data/".to_string(),
+            "http://[::1]/a.png".to_string(),
+        ] {
+            assert_invalid_input(
+                fetch_image(&public_only, &url).await,
+                "not a public address",
+            );
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! End-to-end tests for the admin statistics endpoint
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{mock_config, sample_png, send, MultipartBuilder};
+use frameforge_server::app::{build_router, build_router_with_state};
+use frameforge_server::state::AppState;
+
+const SERVER_KEY: &str = "admin-secret";
+
+fn stats_request(token: Option<&str>) -> Request<Body> {
+    let mut request = Request::get("/api/admin/stats");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn test_admin_stats_summarizes_edits() {
+    let state = AppState::new(frameforge_server::config::AppConfig {
+        server_api_key: Some(SERVER_KEY.to_string()),
+        ..mock_config()
+    });
+    let app = build_router_with_state(state);
+
+    for _ in 0..2 {
+        let request = MultipartBuilder::new()
+            .file("images", "room.png", "image/png", &sample_png(4, 4))
+            .text("prompt", "Add a rug")
+            .into_request("/api/edit");
+        assert_eq!(send(app.clone(), request).await.status, StatusCode::OK);
+    }
+
+    let response = send(app, stats_request(Some(SERVER_KEY))).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let stats = response.json();
+    assert!(stats["uptime_secs"].is_u64());
+    assert_eq!(stats["total_edits"], 2);
+    assert_eq!(
+        stats["edits_by_provider"],
+        serde_json::json!({ "google": 2 })
+    );
+    // The stats request itself is in flight
+    assert_eq!(stats["in_flight"], 1);
+    assert!(stats["max_connections"].as_u64().unwrap() > 0);
+    assert!(stats["cache_hit_rate"].is_null());
+    // Requests without a peer address are counted as one client
+    assert_eq!(stats["rate_limit_clients"], 1);
+}
+
+#[tokio::test]
+async fn test_admin_stats_requires_server_key() {
+    let app = build_router(frameforge_server::config::AppConfig {
+        server_api_key: Some(SERVER_KEY.to_string()),
+        ..mock_config()
+    });
+
+    for token in [None, Some("wrong")] {
+        let response = send(app.clone(), stats_request(token)).await;
+
+        assert_eq!(
+            response.status,
+            StatusCode::UNAUTHORIZED,
+            "token {:?}",
+            token
+        );
+        assert_eq!(response.json()["error_type"], "unauthorized");
+    }
+}
+
+#[tokio::test]
+async fn test_admin_stats_absent_without_server_key() {
+    let response = send(build_router(mock_config()), stats_request(Some(SERVER_KEY))).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,92 @@
+//! The `audit` tracing target receives one event per edit, with hashed inputs
+
+mod common;
+
+use axum::http::StatusCode;
+use common::{mock_app, sample_png, send, LogCapture, MultipartBuilder};
+use frameforge_server::audit::{sha256_hex, TARGET};
+
+#[tokio::test]
+async fn test_edit_emits_audit_event_with_hashes() {
+    let capture = LogCapture::default();
+    let _guard = capture.install();
+
+    let png = sample_png(4, 4);
+    let prompt = "Add a reading nook by the window";
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .text("prompt", prompt)
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert("X-Tenant-Id", "acme-prod".parse().unwrap());
+    request
+        .headers_mut()
+        .insert("X-Google-Api-Key", "secret-google-key".parse().unwrap());
+
+    assert_eq!(send(mock_app(), request).await.status, StatusCode::OK);
+
+    let events = capture.target_fields(TARGET);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event["tenant"], "acme-prod");
+    assert_eq!(event["provider"], "google");
+    assert_eq!(event["outcome"], "success");
+    assert_eq!(event["image_hash"], sha256_hex(&png));
+    assert_eq!(event["prompt_hash"], sha256_hex(prompt.as_bytes()));
+    assert!(!event["timestamp"].is_empty());
+    for value in event.values() {
+        assert!(!value.contains(prompt), "prompt leaked in {:?}", event);
+        assert!(
+            !value.contains("secret-google-key"),
+            "key leaked in {:?}",
+            event
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_batch_emits_one_audit_event_per_item() {
+    let capture = LogCapture::default();
+    let _guard = capture.install();
+
+    let first = sample_png(4, 4);
+    let second = sample_png(6, 6);
+    let request = MultipartBuilder::new()
+        .file("images", "a.png", "image/png", &first)
+        .file("images", "b.png", "image/png", &second)
+        .text("prompt", "Stage both rooms")
+        .into_request("/api/edit/batch");
+
+    assert_eq!(send(mock_app(), request).await.status, StatusCode::OK);
+
+    let mut hashes: Vec<String> = capture
+        .target_fields(TARGET)
+        .iter()
+        .map(|event| event["image_hash"].clone())
+        .collect();
+    hashes.sort();
+    let mut expected = vec![sha256_hex(&first), sha256_hex(&second)];
+    expected.sort();
+    assert_eq!(hashes, expected);
+}
+
+#[tokio::test]
+async fn test_rejected_request_is_audited_without_hashes() {
+    let capture = LogCapture::default();
+    let _guard = capture.install();
+
+    let request = MultipartBuilder::new()
+        .text("prompt", "no images")
+        .into_request("/api/edit");
+
+    assert_eq!(
+        send(mock_app(), request).await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    let events = capture.target_fields(TARGET);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["outcome"], "invalid_input");
+    assert_eq!(events[0]["image_hash"], "none");
+}
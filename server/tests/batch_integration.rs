@@ -0,0 +1,237 @@
+//! End-to-end tests for `POST /api/edit/batch` running against the mock provider
+
+mod common;
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use common::{mock_app, mock_config, sample_png, send, MultipartBuilder};
+use frameforge_server::app::{build_router, build_router_with_state};
+use frameforge_server::config::{AppConfig, BatchMode};
+use frameforge_server::state::AppState;
+use frameforge_server::utils::archive::zip_of;
+
+/// Decode a `data:<mime>;base64,<data>` URL into raw bytes
+fn decode_data_url(data_url: &str) -> Vec<u8> {
+    let (_, data) = data_url.split_once(',').expect("data URL has a comma");
+    STANDARD.decode(data).expect("valid base64")
+}
+
+#[tokio::test]
+async fn test_batch_with_matched_prompts_returns_ordered_results() {
+    let first = sample_png(4, 4);
+    let second = sample_png(6, 2);
+    let request = MultipartBuilder::new()
+        .file("images", "a.png", "image/png", &first)
+        .text("prompts", "Add a sofa")
+        .file("images", "b.png", "image/png", &second)
+        .text("prompts", "Add a lamp")
+        .into_request("/api/edit/batch");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["index"], 0);
+    assert_eq!(results[1]["index"], 1);
+    assert_eq!(
+        decode_data_url(results[0]["image"].as_str().unwrap()),
+        first
+    );
+    assert_eq!(
+        decode_data_url(results[1]["image"].as_str().unwrap()),
+        second
+    );
+}
+
+#[tokio::test]
+async fn test_batch_prompt_count_mismatch_is_bad_request() {
+    let png = sample_png(4, 4);
+    let request = MultipartBuilder::new()
+        .file("images", "a.png", "image/png", &png)
+        .file("images", "b.png", "image/png", &png)
+        .file("images", "c.png", "image/png", &png)
+        .text("prompts", "one")
+        .text("prompts", "two")
+        .into_request("/api/edit/batch");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("must match"));
+}
+
+#[tokio::test]
+async fn test_batch_records_every_input_in_size_histograms() {
+    let state = AppState::new(mock_config());
+    let metrics = state.metrics.clone();
+    let first = sample_png(4, 4);
+    let second = sample_png(6, 2);
+    let request = MultipartBuilder::new()
+        .file("images", "a.png", "image/png", &first)
+        .file("images", "b.png", "image/png", &second)
+        .into_request("/api/edit/batch");
+
+    let response = send(build_router_with_state(state), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let bytes = metrics.input_bytes();
+    assert_eq!(bytes.count(), 2);
+    assert_eq!(bytes.sum(), (first.len() + second.len()) as f64);
+    assert_eq!(metrics.input_pixels().sum(), 28.0);
+}
+
+/// Batch of a valid image, an invalid one and another valid one, with matched prompts
+fn mixed_batch_request(first: &[u8], third: &[u8]) -> axum::http::Request<axum::body::Body> {
+    MultipartBuilder::new()
+        .file("images", "a.png", "image/png", first)
+        .text("prompts", "Add a sofa")
+        .file(
+            "images",
+            "notes.png",
+            "image/png",
+            b"definitely not an image",
+        )
+        .text("prompts", "Add a rug")
+        .file("images", "c.png", "image/png", third)
+        .text("prompts", "Add a lamp")
+        .into_request("/api/edit/batch")
+}
+
+fn batch_mode_config(batch_mode: BatchMode) -> AppConfig {
+    AppConfig {
+        batch_mode,
+        ..mock_config()
+    }
+}
+
+#[tokio::test]
+async fn test_best_effort_batch_reports_invalid_images_per_item() {
+    let first = sample_png(4, 4);
+    let third = sample_png(6, 2);
+    let app = build_router(batch_mode_config(BatchMode::BestEffort));
+
+    let response = send(app, mixed_batch_request(&first, &third)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        decode_data_url(results[0]["image"].as_str().unwrap()),
+        first
+    );
+    assert_eq!(results[1]["index"], 1);
+    assert!(results[1].get("image").is_none());
+    assert_eq!(results[1]["error_type"], "image_processing_error");
+    assert_eq!(
+        decode_data_url(results[2]["image"].as_str().unwrap()),
+        third
+    );
+}
+
+#[tokio::test]
+async fn test_empty_image_part_keeps_its_prompt_slot() {
+    let first = sample_png(4, 4);
+    let third = sample_png(6, 2);
+    let request = MultipartBuilder::new()
+        .file("images", "a.png", "image/png", &first)
+        .text("prompts", "Add a sofa")
+        .file("images", "", "application/octet-stream", b"")
+        .text("prompts", "Add a rug")
+        .file("images", "c.png", "image/png", &third)
+        .text("prompts", "Add a lamp")
+        .into_request("/api/edit/batch");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        decode_data_url(results[0]["image"].as_str().unwrap()),
+        first
+    );
+    assert_eq!(results[1]["error_type"], "image_processing_error");
+    assert!(results[1]["error"].as_str().unwrap().contains("empty"));
+    assert_eq!(results[2]["index"], 2);
+    assert_eq!(
+        decode_data_url(results[2]["image"].as_str().unwrap()),
+        third
+    );
+}
+
+#[tokio::test]
+async fn test_all_or_nothing_batch_rejects_invalid_images() {
+    let app = build_router(batch_mode_config(BatchMode::AllOrNothing));
+
+    let response = send(
+        app,
+        mixed_batch_request(&sample_png(4, 4), &sample_png(6, 2)),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "image_processing_error");
+}
+
+#[tokio::test]
+async fn test_batch_archive_entries_are_edited_in_order() {
+    let first = sample_png(4, 4);
+    let second = sample_png(6, 2);
+    let archive = zip_of(&[("a.png", &first), ("b.png", &second)]);
+    let request = MultipartBuilder::new()
+        .file("archive", "rooms.zip", "application/zip", &archive)
+        .text("prompt", "Add a plant")
+        .into_request("/api/edit/batch");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        decode_data_url(results[0]["image"].as_str().unwrap()),
+        first
+    );
+    assert_eq!(
+        decode_data_url(results[1]["image"].as_str().unwrap()),
+        second
+    );
+}
+
+#[tokio::test]
+async fn test_batch_archive_over_limits_is_rejected() {
+    let png = sample_png(4, 4);
+    let too_many = AppConfig {
+        max_batch_images: 2,
+        ..mock_config()
+    };
+    let too_large = AppConfig {
+        zip_max_bytes: png.len() * 2 - 1,
+        ..mock_config()
+    };
+
+    for (config, entries) in [(too_many, 3), (too_large, 2)] {
+        let names: Vec<String> = (0..entries).map(|i| format!("{}.png", i)).collect();
+        let files: Vec<(&str, &[u8])> = names
+            .iter()
+            .map(|name| (name.as_str(), png.as_slice()))
+            .collect();
+        let request = MultipartBuilder::new()
+            .file("archive", "rooms.zip", "application/zip", &zip_of(&files))
+            .text("prompt", "Add a plant")
+            .into_request("/api/edit/batch");
+
+        let response = send(build_router(config), request).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+        assert_eq!(response.json()["error_type"], "invalid_input");
+    }
+}
@@ -0,0 +1,142 @@
+//! Shared helpers for integration tests
+//!
+//! Provides a mock-mode configuration, a small multipart body builder, and
+//! helpers to drive the fully-layered router in-process with `tower::ServiceExt`.
+
+#![allow(dead_code)]
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::Router;
+use frameforge_server::app::build_router;
+use frameforge_server::config::AppConfig;
+use http_body_util::BodyExt;
+use std::io::Cursor;
+use tower::ServiceExt;
+
+/// Layer recording log events, from the library's `test-mock` feature
+#[allow(unused_imports)]
+pub use frameforge_server::log_capture::LogCapture;
+
+/// Multipart boundary used by `MultipartBuilder`
+const BOUNDARY: &str = "frameforge-test-boundary";
+
+/// Configuration with the mock provider enabled and no real API keys
+pub fn mock_config() -> AppConfig {
+    AppConfig {
+        host: "127.0.0.1".to_string(),
+        mock_provider: true,
+        ..AppConfig::default()
+    }
+}
+
+/// Router built from `mock_config()`
+pub fn mock_app() -> Router {
+    build_router(mock_config())
+}
+
+/// Encode a solid-color RGB image as PNG
+pub fn sample_png(width: u32, height: u32) -> Vec<u8> {
+    let img = image::RgbImage::from_pixel(width, height, image::Rgb([120, 80, 200]));
+    let mut buffer = Vec::new();
+    image::DynamicImage::ImageRgb8(img)
+        .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .expect("encode test PNG");
+    buffer
+}
+
+/// Builder for `multipart/form-data` request bodies
+#[derive(Default)]
+pub struct MultipartBuilder {
+    body: Vec<u8>,
+}
+
+impl MultipartBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a plain text field
+    pub fn text(self, name: &str, value: &str) -> Self {
+        self.raw_text(name, value.as_bytes())
+    }
+
+    /// Append a text field whose value is written as-is, even if not UTF-8
+    pub fn raw_text(mut self, name: &str, value: &[u8]) -> Self {
+        self.body.extend_from_slice(
+            format!("--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n")
+                .as_bytes(),
+        );
+        self.body.extend_from_slice(value);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Append a file field with the given content type
+    pub fn file(mut self, name: &str, filename: &str, content_type: &str, data: &[u8]) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Value for the request `Content-Type` header
+    pub fn content_type() -> String {
+        format!("multipart/form-data; boundary={BOUNDARY}")
+    }
+
+    /// Finish the body with the closing boundary
+    pub fn build(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+        self.body
+    }
+
+    /// Build a `POST` request to `uri` carrying this multipart body
+    pub fn into_request(self, uri: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(header::CONTENT_TYPE, Self::content_type())
+            .body(Body::from(self.build()))
+            .unwrap()
+    }
+}
+
+/// Response parts collected from a oneshot call
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    /// Parse the body as JSON
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).expect("response body is JSON")
+    }
+}
+
+/// Send a single request through the router and collect the full response
+pub async fn send(app: Router, request: Request<Body>) -> TestResponse {
+    let response = app.oneshot(request).await.expect("router is infallible");
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .expect("collect response body")
+        .to_bytes();
+
+    TestResponse {
+        status,
+        headers,
+        body,
+    }
+}
@@ -0,0 +1,130 @@
+//! End-to-end tests for `POST /api/edit/compare`
+
+mod common;
+
+use axum::http::StatusCode;
+use common::{mock_app, mock_config, sample_png, send, MultipartBuilder};
+use frameforge_server::app::build_router;
+use frameforge_server::config::AppConfig;
+
+fn compare_request(providers: &[&str]) -> axum::http::Request<axum::body::Body> {
+    let mut builder = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .text("prompt", "Add a rug");
+    for provider in providers {
+        builder = builder.text("providers", provider);
+    }
+    builder.into_request("/api/edit/compare")
+}
+
+#[tokio::test]
+async fn test_compare_reports_each_provider_in_request_order() {
+    // No API keys: the composite editor succeeds, Google is unavailable
+    let config = AppConfig {
+        host: "127.0.0.1".to_string(),
+        ..AppConfig::default()
+    };
+
+    let response = send(
+        build_router(config),
+        compare_request(&["composite", "google"]),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+
+    assert_eq!(results[0]["provider"], "composite");
+    assert!(results[0]["image"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:image/png;base64,"));
+    assert!(results[0]["latency_ms"].is_u64());
+    assert!(results[0].get("error").is_none());
+
+    assert_eq!(results[1]["provider"], "google");
+    assert!(results[1].get("image").is_none());
+    assert_eq!(results[1]["error_type"], "provider_not_found");
+    assert!(results[1]["error"]
+        .as_str()
+        .unwrap()
+        .contains("not configured"));
+    assert!(results[1]["latency_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn test_compare_runs_every_provider_on_the_same_image() {
+    let response = send(
+        mock_app(),
+        compare_request(&["google", "fal:fal-ai/flux/dev"]),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["image"], results[1]["image"]);
+    assert_eq!(results[1]["provider"], "fal:fal-ai/flux/dev");
+}
+
+#[tokio::test]
+async fn test_compare_rejects_too_many_or_no_providers() {
+    let app = build_router(AppConfig {
+        max_compare_providers: 2,
+        ..mock_config()
+    });
+
+    let response = send(
+        app.clone(),
+        compare_request(&["google", "composite", "local"]),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("maximum 2"));
+
+    let response = send(app, compare_request(&[])).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_compare_rejects_too_many_multipart_fields() {
+    let app = build_router(AppConfig {
+        max_multipart_fields: 3,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .text("providers", "google")
+        .text("unknown", "a")
+        .text("unknown", "b")
+        .into_request("/api/edit/compare");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("too many multipart fields"));
+}
+
+#[tokio::test]
+async fn test_compare_takes_exactly_one_image() {
+    let png = sample_png(4, 4);
+    let request = MultipartBuilder::new()
+        .file("images", "a.png", "image/png", &png)
+        .file("images", "b.png", "image/png", &png)
+        .text("providers", "google")
+        .into_request("/api/edit/compare");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+}
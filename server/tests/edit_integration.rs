@@ -0,0 +1,1536 @@
+//! End-to-end tests for `POST /api/edit` and `GET /api/edit` running against the mock provider
+
+mod common;
+
+use axum::http::{header, StatusCode};
+use common::{mock_app, mock_config, sample_png, send, LogCapture, MultipartBuilder};
+use frameforge_server::app::{build_router, build_router_with_state};
+use frameforge_server::config::{AppConfig, LogRedaction};
+use frameforge_server::state::AppState;
+use frameforge_server::utils::image_utils::{self, OutputFormat};
+use futures::StreamExt;
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_edit_round_trip_returns_mock_result() {
+    let png = sample_png(8, 8);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .text("prompt", "Add modern furniture")
+        .text("provider", "google")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/png");
+    assert_eq!(
+        response.headers[header::CONTENT_LENGTH],
+        png.len().to_string().as_str()
+    );
+    assert_eq!(&response.body[..], &png[..]);
+}
+
+#[tokio::test]
+async fn test_edit_accepts_key_override_headers() {
+    // Real providers, no server keys: only a header key makes them available.
+    // The requests carry no image, so an accepted key surfaces as the missing
+    // image error instead of reaching the network.
+    let app = build_router(AppConfig {
+        host: "127.0.0.1".to_string(),
+        ..AppConfig::default()
+    });
+    for (provider, key_header) in [
+        ("fal:fal-ai/flux/dev", "X-Fal-Key"),
+        ("google", "X-Google-Api-Key"),
+    ] {
+        let request = || {
+            MultipartBuilder::new()
+                .text("provider", provider)
+                .into_request("/api/edit")
+        };
+
+        let response = send(app.clone(), request()).await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", provider);
+        assert_eq!(response.json()["error_type"], "provider_not_found");
+
+        let mut with_key = request();
+        with_key
+            .headers_mut()
+            .insert(key_header, "header-key".parse().unwrap());
+        let response = send(app.clone(), with_key).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{}", provider);
+        assert!(response.json()["error"]
+            .as_str()
+            .unwrap()
+            .contains("At least one image"));
+    }
+}
+
+#[tokio::test]
+async fn test_edit_without_images_is_bad_request() {
+    let request = MultipartBuilder::new()
+        .text("prompt", "Add modern furniture")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+}
+
+#[tokio::test]
+async fn test_edit_rejects_non_image_upload() {
+    let request = MultipartBuilder::new()
+        .file(
+            "images",
+            "notes.txt",
+            "text/plain",
+            b"definitely not an image",
+        )
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "image_processing_error");
+}
+
+#[tokio::test]
+async fn test_edit_rejects_too_many_multipart_fields() {
+    let app = build_router(AppConfig {
+        max_multipart_fields: 5,
+        ..mock_config()
+    });
+    let fields = |count: usize| {
+        (0..count)
+            .fold(MultipartBuilder::new(), |builder, i| {
+                builder.text(&format!("unknown_{}", i), "x")
+            })
+            .file("images", "room.png", "image/png", &sample_png(4, 4))
+            .into_request("/api/edit")
+    };
+
+    let response = send(app.clone(), fields(4)).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    // Unrecognized fields count against the limit too
+    let response = send(app, fields(1000)).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("too many multipart fields"));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_prompt_over_limit() {
+    let app = build_router(AppConfig {
+        max_prompt_chars: 10,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("prompt", "This prompt is far longer than ten characters")
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+}
+
+#[tokio::test]
+async fn test_edit_prompt_limit_depends_on_provider() {
+    // Within Gemini's limit but over FLUX Kontext's
+    let prompt = "Add a sofa. ".repeat(250);
+
+    for (provider, expected) in [
+        ("google", StatusCode::OK),
+        ("fal:fal-ai/flux-kontext/dev", StatusCode::BAD_REQUEST),
+    ] {
+        let request = MultipartBuilder::new()
+            .text("provider", provider)
+            .file("images", "room.png", "image/png", &sample_png(4, 4))
+            .text("prompt", &prompt)
+            .into_request("/api/edit");
+
+        let response = send(mock_app(), request).await;
+
+        assert_eq!(response.status, expected, "provider {}", provider);
+        if expected == StatusCode::BAD_REQUEST {
+            assert_eq!(
+                response.json()["error"],
+                "Invalid input: Prompt is too long for FLUX.1 Kontext [dev]: 2999 characters (maximum 2000)"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_edit_rejects_non_utf8_prompt() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .raw_text("prompt", b"Add a \xff\xfe rug")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+    assert_eq!(
+        response.json()["error"],
+        "Invalid input: prompt must be valid UTF-8"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_without_prompt_uses_default_prompt() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_edit_without_prompt_rejected_when_required() {
+    let app = build_router(AppConfig {
+        require_prompt: true,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("prompt", "   ")
+        .into_request("/api/edit");
+
+    let response = send(app.clone(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("prompt is required"));
+
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("prompt", "Add a sofa")
+        .into_request("/api/edit");
+    assert_eq!(send(app, request).await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_edit_resizes_to_requested_dimensions() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(40, 20))
+        .text("out_width", "16")
+        .text("out_height", "12")
+        .text("fit", "cover")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let img = image::load_from_memory(&response.body).unwrap();
+    assert_eq!((img.width(), img.height()), (16, 12));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_derived_output_size_before_provider_call() {
+    // A real provider with a key: reaching it would fail on the network, not validation
+    let app = build_router(AppConfig {
+        host: "127.0.0.1".to_string(),
+        fal_key: Some("test-fal-key".to_string()),
+        max_output_dimension: 500,
+        ..AppConfig::default()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(100, 10))
+        .text("provider", "fal:fal-ai/flux/dev")
+        .text("out_height", "100")
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("1000x100 exceeds the maximum dimension of 500"));
+}
+
+#[tokio::test]
+async fn test_edit_rotates_input_before_submission() {
+    // The mock provider echoes its input, so the result shows what was submitted
+    for (degrees, size) in [
+        ("0", (40, 20)),
+        ("90", (20, 40)),
+        ("180", (40, 20)),
+        ("270", (20, 40)),
+    ] {
+        let request = MultipartBuilder::new()
+            .file("images", "room.png", "image/png", &sample_png(40, 20))
+            .text("rotate", degrees)
+            .into_request("/api/edit");
+
+        let response = send(mock_app(), request).await;
+
+        assert_eq!(response.status, StatusCode::OK, "rotate {}", degrees);
+        let img = image::load_from_memory(&response.body).unwrap();
+        assert_eq!((img.width(), img.height()), size, "rotate {}", degrees);
+    }
+}
+
+#[tokio::test]
+async fn test_edit_rejects_unsupported_rotation() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(40, 20))
+        .text("rotate", "45")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("0, 90, 180, 270"));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_invalid_output_dimensions() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("out_width", "100000")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_edit_uses_configured_default_output_format() {
+    let app = build_router(AppConfig {
+        default_output_format: Some(OutputFormat::Webp),
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["content-type"], "image/webp");
+    assert_eq!(
+        image::guess_format(&response.body).unwrap(),
+        image::ImageFormat::WebP
+    );
+}
+
+#[tokio::test]
+async fn test_edit_output_format_field_overrides_default() {
+    let app = build_router(AppConfig {
+        default_output_format: Some(OutputFormat::Webp),
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .text("output_format", "jpeg")
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["content-type"], "image/jpeg");
+}
+
+/// PNG with a transparent left half
+fn half_transparent_png(width: u32, height: u32) -> Vec<u8> {
+    let img = image::RgbaImage::from_fn(width, height, |x, _| {
+        let alpha = if x < width / 2 { 0 } else { 255 };
+        image::Rgba([120, 80, 200, alpha])
+    });
+    image_utils::image_to_bytes(
+        &image::DynamicImage::ImageRgba8(img),
+        image::ImageFormat::Png,
+    )
+    .unwrap()
+    .to_vec()
+}
+
+/// Edit `image` with `output_format=jpeg` and return the response content type
+async fn jpeg_request_content_type(preserve_alpha: bool, image: &[u8]) -> String {
+    let app = build_router(AppConfig {
+        preserve_alpha,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", image)
+        .text("output_format", "jpeg")
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let content_type = response.headers["content-type"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        image_utils::get_mime_type(&response.body).unwrap(),
+        content_type
+    );
+    content_type
+}
+
+#[tokio::test]
+async fn test_preserve_alpha_returns_png_for_transparent_jpeg_request() {
+    let transparent = half_transparent_png(8, 8);
+
+    assert_eq!(
+        jpeg_request_content_type(true, &transparent).await,
+        "image/png"
+    );
+    assert_eq!(
+        jpeg_request_content_type(false, &transparent).await,
+        "image/jpeg"
+    );
+    // Opaque results are still returned as JPEG
+    assert_eq!(
+        jpeg_request_content_type(true, &sample_png(8, 8)).await,
+        "image/jpeg"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_keeps_provider_format_without_default() {
+    let png = sample_png(8, 8);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.headers["content-type"], "image/png");
+    assert_eq!(response.body, png);
+}
+
+#[tokio::test]
+async fn test_edit_rejects_unknown_output_format() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("output_format", "gif")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_edit_formats_returns_each_format_as_data_url() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .text("formats", "webp, png")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "application/json");
+    let json = response.json();
+    assert_eq!(json.as_object().unwrap().len(), 2);
+
+    let webp = json["webp"].as_str().unwrap();
+    assert!(webp.starts_with("data:image/webp;base64,"));
+    let webp = image_utils::base64_to_bytes(webp).unwrap();
+    assert_eq!(&webp[..4], b"RIFF");
+    assert_eq!(&webp[8..12], b"WEBP");
+
+    let png = json["png"].as_str().unwrap();
+    assert!(png.starts_with("data:image/png;base64,"));
+    let png = image_utils::base64_to_bytes(png).unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+}
+
+#[tokio::test]
+async fn test_edit_rejects_too_many_formats() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("formats", "png,jpeg,webp,png")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("Too many formats"));
+}
+
+#[tokio::test]
+async fn test_edit_optimize_returns_valid_png() {
+    let png = sample_png(32, 32);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .text("optimize", "true")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.headers["X-Original-Content-Length"],
+        png.len().to_string().as_str()
+    );
+    assert!(response.body.len() <= png.len());
+    let original = image::load_from_memory(&png).unwrap();
+    let optimized = image::load_from_memory(&response.body).unwrap();
+    assert_eq!(optimized.to_rgba8(), original.to_rgba8());
+}
+
+#[tokio::test]
+async fn test_edit_optimize_accepts_env_style_booleans() {
+    for (value, optimized) in [("1", true), ("YES", true), ("0", false), ("no", false)] {
+        let request = MultipartBuilder::new()
+            .file("images", "room.png", "image/png", &sample_png(32, 32))
+            .text("optimize", value)
+            .into_request("/api/edit");
+
+        let response = send(mock_app(), request).await;
+
+        assert_eq!(response.status, StatusCode::OK, "optimize={}", value);
+        assert_eq!(
+            response.headers.contains_key("X-Original-Content-Length"),
+            optimized,
+            "optimize={}",
+            value
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_edit_metrics_are_labelled_by_tenant() {
+    let state = AppState::new(mock_config());
+    let metrics = state.metrics.clone();
+    let app = build_router_with_state(state);
+
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert("X-Tenant-Id", "acme-prod".parse().unwrap());
+    assert_eq!(send(app.clone(), request).await.status, StatusCode::OK);
+
+    let request = MultipartBuilder::new()
+        .text("prompt", "no images")
+        .into_request("/api/edit");
+    assert_eq!(send(app, request).await.status, StatusCode::BAD_REQUEST);
+
+    assert_eq!(metrics.edit_count("acme-prod", "google", "success"), 1);
+    assert_eq!(
+        metrics.edit_count("anonymous", "unknown", "invalid_input"),
+        1
+    );
+    assert!(metrics
+        .render()
+        .contains("tenant=\"acme-prod\",provider=\"google\",outcome=\"success\"} 1"));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_invalid_tenant_id() {
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert("X-Tenant-Id", "acme prod!".parse().unwrap());
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+}
+
+#[tokio::test]
+async fn test_edit_rejects_result_over_max_result_bytes() {
+    let image = sample_png(64, 64);
+    let app = build_router(AppConfig {
+        // The mock provider echoes the input, so its result is the upload
+        max_result_bytes: image.len() - 1,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &image)
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.json()["error_type"], "provider_error");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("result too large"));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_truncated_result_before_responding() {
+    // The mock provider echoes its input, so a cut-off upload stands in for a
+    // result whose download dropped mid-transfer
+    let image = sample_png(64, 64);
+    let request = MultipartBuilder::new()
+        .file(
+            "images",
+            "room.png",
+            "image/png",
+            &image[..image.len() - 12],
+        )
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.json()["error_type"], "provider_error");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("truncated image"));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_unchanged_result_when_enabled() {
+    // The mock provider echoes its input, which is exactly a provider no-op
+    let app = build_router(AppConfig {
+        reject_unchanged_results: true,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.json()["error_type"], "provider_error");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("provider returned unchanged image"));
+}
+
+#[tokio::test]
+async fn test_edit_converts_cmyk_jpeg_before_submission() {
+    let (width, height) = (8u16, 8u16);
+    let pixels = [255u8, 0, 0, 0].repeat(width as usize * height as usize);
+    let mut cmyk = Vec::new();
+    jpeg_encoder::Encoder::new(&mut cmyk, 95)
+        .encode(&pixels, width, height, jpeg_encoder::ColorType::Cmyk)
+        .unwrap();
+    let request = MultipartBuilder::new()
+        .file("images", "print.jpg", "image/jpeg", &cmyk)
+        .into_request("/api/edit");
+
+    // The mock provider echoes what it was given, i.e. the normalized input
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/jpeg");
+    let img = image::load_from_memory(&response.body).unwrap();
+    assert_eq!(img.color(), image::ColorType::Rgb8);
+    let [r, g, b] = img.to_rgb8().get_pixel(4, 4).0;
+    assert!(
+        r < 40 && g > 215 && b > 215,
+        "unexpected color: {:?}",
+        (r, g, b)
+    );
+}
+
+/// Mock app allowed to fetch image URLs from the local origin servers below
+fn url_input_app() -> axum::Router {
+    build_router(AppConfig {
+        url_input_allow_private: true,
+        ..mock_config()
+    })
+}
+
+#[tokio::test]
+async fn test_edit_fetches_image_url_input() {
+    let png = sample_png(6, 6);
+    let served = png.clone();
+    let origin = axum::Router::new().route(
+        "/room.png",
+        axum::routing::get(move || async move { ([(header::CONTENT_TYPE, "image/png")], served) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, origin).await.unwrap() });
+
+    let request = MultipartBuilder::new()
+        .text("image_url", &format!("http://{}/room.png", addr))
+        .into_request("/api/edit");
+
+    let response = send(url_input_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(&response.body[..], &png[..]);
+}
+
+#[tokio::test]
+async fn test_edit_does_not_forward_origin_headers() {
+    let served = sample_png(6, 6);
+    let origin = axum::Router::new().route(
+        "/room.png",
+        axum::routing::get(move || async move {
+            (
+                [
+                    (header::CONTENT_TYPE, "image/png"),
+                    (header::SET_COOKIE, "session=origin-secret; Path=/"),
+                    (header::SERVER, "origin-cdn/1.0"),
+                    (header::CACHE_CONTROL, "public, max-age=31536000"),
+                ],
+                served,
+            )
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, origin).await.unwrap() });
+
+    let request = MultipartBuilder::new()
+        .text("image_url", &format!("http://{}/room.png", addr))
+        .text("output_format", "webp")
+        .into_request("/api/edit");
+
+    let response = send(url_input_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key(header::SET_COOKIE));
+    assert!(!response.headers.contains_key(header::SERVER));
+    assert!(!response.headers.contains_key(header::CACHE_CONTROL));
+    // Describes the re-encoded result, not the downloaded image
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/webp");
+}
+
+/// Serve `result` the way Fal.ai answers a direct request to `model`, with
+/// the result on a CDN that sets a cookie and names itself
+///
+/// Returns an app whose Fal.ai requests go to that server, and the JSON body
+/// of the last request it received.
+async fn fal_app(
+    model: &str,
+    result: Vec<u8>,
+) -> (axum::Router, Arc<Mutex<Option<serde_json::Value>>>) {
+    let cdn = axum::Router::new().route(
+        "/result.png",
+        axum::routing::get(move || async move {
+            (
+                [
+                    (header::CONTENT_TYPE, "image/png"),
+                    (header::SET_COOKIE, "session=fal-secret"),
+                    (header::SERVER, "fal-cdn/1.0"),
+                ],
+                result,
+            )
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let cdn_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, cdn).await.unwrap() });
+
+    let received = Arc::new(Mutex::new(None));
+    let last_request = received.clone();
+    let images =
+        serde_json::json!({ "images": [{ "url": format!("http://{}/result.png", cdn_addr) }] });
+    let fal = axum::Router::new().route(
+        &format!("/{}", model),
+        axum::routing::post(
+            move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                *last_request.lock().unwrap() = Some(body);
+                axum::Json(images)
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let fal_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, fal).await.unwrap() });
+
+    let app = build_router(AppConfig {
+        host: "127.0.0.1".to_string(),
+        fal_key: Some("test-fal-key".to_string()),
+        fal_base_url: Some(format!("http://{}", fal_addr)),
+        ..AppConfig::default()
+    });
+    (app, received)
+}
+
+#[tokio::test]
+async fn test_edit_does_not_forward_fal_result_headers() {
+    let result = sample_png(3, 2);
+    let (app, _) = fal_app("fal-ai/flux/dev", result.clone()).await;
+    let request = MultipartBuilder::new()
+        .text("prompt", "Add a rug")
+        .text("provider", "fal:fal-ai/flux/dev")
+        .file("image", "room.png", "image/png", &sample_png(3, 2))
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key(header::SET_COOKIE));
+    assert!(!response.headers.contains_key(header::SERVER));
+    assert_eq!(&response.body[..], &result[..]);
+}
+
+#[tokio::test]
+async fn test_edit_refuses_private_image_urls_by_default() {
+    let request = MultipartBuilder::new()
+        .text("image_url", "http://169.254.169.254/latest/meta-data/")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("not a public address"));
+}
+
+/// `GET /api/edit` request with the given, already percent-encoded, query
+fn query_edit_request(query: &str) -> axum::http::Request<axum::body::Body> {
+    axum::http::Request::get(format!("/api/edit?{}", query))
+        .body(axum::body::Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_get_edit_fetches_image_url_query() {
+    let png = sample_png(6, 6);
+    let served = png.clone();
+    let origin = axum::Router::new().route(
+        "/room.png",
+        axum::routing::get(move || async move { ([(header::CONTENT_TYPE, "image/png")], served) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, origin).await.unwrap() });
+
+    let image_url = format!("http%3A%2F%2F{}%2Froom.png", addr);
+    let request = query_edit_request(&format!("image_url={}&prompt=Add%20a%20rug", image_url));
+
+    let response = send(url_input_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/png");
+    assert_eq!(&response.body[..], &png[..]);
+}
+
+#[tokio::test]
+async fn test_get_edit_without_image_url_is_bad_request() {
+    for query in ["prompt=Add%20a%20rug", "image_url=&prompt=Add%20a%20rug"] {
+        let response = send(mock_app(), query_edit_request(query)).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "query {}", query);
+        assert!(response.json()["error"]
+            .as_str()
+            .unwrap()
+            .contains("image_url"));
+    }
+}
+
+/// Configuration with no API keys and the mock provider disabled
+fn keyless_config(dev_mode: bool) -> AppConfig {
+    AppConfig {
+        host: "127.0.0.1".to_string(),
+        dev_mode,
+        ..AppConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_dev_mode_serves_unavailable_provider_with_mock() {
+    let png = sample_png(4, 4);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .text("provider", "google")
+        .into_request("/api/edit");
+
+    let response = send(build_router(keyless_config(true)), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["X-Dev-Mode"], "true");
+    assert_eq!(&response.body[..], &png[..]);
+}
+
+#[tokio::test]
+async fn test_without_dev_mode_unavailable_provider_is_not_found() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("provider", "google")
+        .into_request("/api/edit");
+
+    let response = send(build_router(keyless_config(false)), request).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(!response.headers.contains_key("X-Dev-Mode"));
+}
+
+#[tokio::test]
+async fn test_mock_provider_does_not_set_dev_mode_header() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("X-Dev-Mode"));
+}
+
+#[tokio::test]
+async fn test_edit_include_metadata_sets_provider_metadata_header() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("include_metadata", "true")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let metadata: serde_json::Value =
+        serde_json::from_str(response.headers["x-provider-metadata"].to_str().unwrap()).unwrap();
+    assert_eq!(metadata, serde_json::json!({ "model_version": "mock" }));
+}
+
+/// Perceptual hash reported for a mock edit of `png`
+async fn phash_of(png: &[u8]) -> String {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", png)
+        .text("phash", "true")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    response.headers["x-image-phash"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_edit_phash_same_for_identical_images() {
+    let png = sample_png(32, 32);
+
+    let first = phash_of(&png).await;
+    let second = phash_of(&png).await;
+
+    assert_eq!(first.len(), 16);
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn test_edit_phash_differs_for_different_images() {
+    let gradient = |vertical: bool| {
+        let img = image::GrayImage::from_fn(32, 32, |x, y| {
+            image::Luma([(if vertical { y } else { x } * 8) as u8])
+        });
+        let mut png = Vec::new();
+        image::DynamicImage::ImageLuma8(img)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    };
+
+    assert_ne!(
+        phash_of(&gradient(false)).await,
+        phash_of(&gradient(true)).await
+    );
+}
+
+/// Sharpness score reported for a mock edit of `image`, if any
+async fn quality_score_of(image: &image::DynamicImage, requested: bool) -> Option<f64> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let mut builder = MultipartBuilder::new().file("images", "room.png", "image/png", &png);
+    if requested {
+        builder = builder.text("quality_score", "true");
+    }
+
+    let response = send(mock_app(), builder.into_request("/api/edit")).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    response
+        .headers
+        .get("x-quality-score")
+        .map(|value| value.to_str().unwrap().parse().unwrap())
+}
+
+#[tokio::test]
+async fn test_edit_quality_score_lower_for_blurred_result() {
+    let sharp = image::DynamicImage::ImageLuma8(image::GrayImage::from_fn(48, 48, |x, y| {
+        image::Luma([if (x / 3 + y / 3) % 2 == 0 { 255 } else { 0 }])
+    }));
+
+    let sharp_score = quality_score_of(&sharp, true).await.unwrap();
+    let blurred_score = quality_score_of(&sharp.blur(2.0), true).await.unwrap();
+
+    assert!(
+        blurred_score < sharp_score,
+        "{} >= {}",
+        blurred_score,
+        sharp_score
+    );
+    assert_eq!(quality_score_of(&sharp, false).await, None);
+}
+
+#[tokio::test]
+async fn test_edit_embed_metadata_round_trips_through_png() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .text("prompt", "Add a rug")
+        .text("embed_metadata", "true")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/png");
+    let text = image_utils::png_text(&response.body).unwrap();
+    let value = |key: &str| text.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+    assert_eq!(value("prompt"), Some("Add a rug"));
+    assert_eq!(value("provider"), Some("google"));
+    assert_eq!(value("model"), Some("mock"));
+    // The mock provider reports no seed
+    assert_eq!(value("seed"), None);
+    assert_eq!(
+        image_utils::image_dimensions(&response.body).unwrap(),
+        (8, 8)
+    );
+}
+
+#[tokio::test]
+async fn test_edit_embed_metadata_skipped_for_jpeg() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(8, 8))
+        .text("embed_metadata", "true")
+        .text("output_format", "jpeg")
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/jpeg");
+}
+
+#[tokio::test]
+async fn test_edit_region_accepted_by_region_capable_provider() {
+    let result = sample_png(4, 4);
+    let (app, received) = fal_app("fal-ai/flux-lora/inpainting", result.clone()).await;
+    let request = MultipartBuilder::new()
+        .text("provider", "fal:fal-ai/flux-lora/inpainting")
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("region", "0.25,0.25,0.5,0.5")
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, result);
+    let request = received.lock().unwrap().take().unwrap();
+    assert!(request["mask_url"]
+        .as_str()
+        .unwrap()
+        .starts_with("data:image/png;base64,"));
+}
+
+#[tokio::test]
+async fn test_edit_region_rejected_when_invalid_or_unsupported() {
+    let invalid = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("region", "0.75,0,0.5,1")
+        .into_request("/api/edit");
+    let response = send(mock_app(), invalid).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("region"));
+
+    let unsupported = MultipartBuilder::new()
+        .text("provider", "composite")
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("region", "0,0,0.5,0.5")
+        .into_request("/api/edit");
+    let response = send(build_router(keyless_config(false)), unsupported).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("does not support region editing"));
+
+    // The dev-mode mock can't limit its passthrough to a region, so it refuses
+    // rather than ignore it
+    let dev_fallback = MultipartBuilder::new()
+        .text("provider", "google")
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("region", "0,0,0.5,0.5")
+        .into_request("/api/edit");
+    let response = send(build_router(keyless_config(true)), dev_fallback).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("does not support region editing"));
+}
+
+#[tokio::test]
+async fn test_edit_validates_sampling_ranges() {
+    for (name, value, expected) in [
+        ("temperature", "0.7", StatusCode::OK),
+        ("top_p", "0.95", StatusCode::OK),
+        ("temperature", "3", StatusCode::BAD_REQUEST),
+        ("top_p", "-0.1", StatusCode::BAD_REQUEST),
+        ("seed", "42", StatusCode::OK),
+        ("num_inference_steps", "28", StatusCode::OK),
+        ("guidance_scale", "2.5", StatusCode::OK),
+        ("seed", "-1", StatusCode::BAD_REQUEST),
+        ("num_inference_steps", "51", StatusCode::BAD_REQUEST),
+        ("guidance_scale", "0.5", StatusCode::BAD_REQUEST),
+    ] {
+        let request = MultipartBuilder::new()
+            .file("images", "room.png", "image/png", &sample_png(4, 4))
+            .text(name, value)
+            .into_request("/api/edit");
+
+        let response = send(mock_app(), request).await;
+
+        assert_eq!(response.status, expected, "{}={}", name, value);
+    }
+}
+
+#[tokio::test]
+async fn test_edit_validates_provider_options() {
+    for (value, expected) in [
+        (
+            r#"{"guidance_scale": 3.5, "acceleration": "high"}"#,
+            StatusCode::OK,
+        ),
+        (
+            r#"{"image_url": "https://example.com/room.png"}"#,
+            StatusCode::BAD_REQUEST,
+        ),
+        (r#"{"loras": [{"path": "style"}]}"#, StatusCode::BAD_REQUEST),
+        (r#"["guidance_scale"]"#, StatusCode::BAD_REQUEST),
+        (
+            r#"{"enable_safety_checker": false}"#,
+            StatusCode::BAD_REQUEST,
+        ),
+        (r#"{"num_images": 50}"#, StatusCode::BAD_REQUEST),
+        (r#"{"sync_mode": false}"#, StatusCode::BAD_REQUEST),
+        (r#"{"output_format": "jpeg"}"#, StatusCode::BAD_REQUEST),
+    ] {
+        let request = MultipartBuilder::new()
+            .file("images", "room.png", "image/png", &sample_png(4, 4))
+            .text("provider_options", value)
+            .into_request("/api/edit");
+
+        let response = send(mock_app(), request).await;
+
+        assert_eq!(response.status, expected, "{}", value);
+    }
+}
+
+#[tokio::test]
+async fn test_edit_omits_phash_by_default() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("x-image-phash"));
+}
+
+#[tokio::test]
+async fn test_edit_omits_provider_metadata_by_default() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("x-provider-metadata"));
+}
+
+#[tokio::test]
+async fn test_edit_without_transforms_returns_provider_bytes_verbatim() {
+    // A text chunk and the encoder's own compression would not survive a decode
+    // and re-encode, so an identical body shows the result was never decoded
+    let png = image_utils::embed_png_text(
+        &sample_png(64, 64),
+        &[("Comment", "from the provider".to_string())],
+    )
+    .unwrap()
+    .to_vec();
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "image/png");
+    assert_eq!(response.body, png);
+
+    // Any transform takes the processing path instead
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .text("out_width", "64")
+        .into_request("/api/edit");
+    let response = send(mock_app(), request).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_ne!(response.body, png);
+}
+
+#[tokio::test]
+async fn test_image_response_is_not_recompressed() {
+    let png = sample_png(64, 64);
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert(header::ACCEPT_ENCODING, "br, gzip".parse().unwrap());
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key(header::CONTENT_ENCODING));
+    assert_eq!(response.body, png);
+}
+
+#[tokio::test]
+async fn test_json_response_is_compressed() {
+    let request = axum::http::Request::get("/api/openapi.json")
+        .header(header::ACCEPT_ENCODING, "gzip")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_ENCODING], "gzip");
+}
+
+#[tokio::test]
+async fn test_edit_rejects_malformed_provider_keys_header() {
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert("X-Provider-Keys", "{\"google\": ".parse().unwrap());
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("X-Provider-Keys"));
+}
+
+/// Edit request whose multipart body is cut off `keep` bytes in
+fn truncated_edit_request(body: Vec<u8>, keep: usize) -> axum::http::Request<axum::body::Body> {
+    axum::http::Request::post("/api/edit")
+        .header(header::CONTENT_TYPE, MultipartBuilder::content_type())
+        .body(axum::body::Body::from(body[..keep].to_vec()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_edit_rejects_truncated_upload() {
+    let png = sample_png(32, 32);
+    let body = MultipartBuilder::new()
+        .text("prompt", "Add a sofa")
+        .file("images", "room.png", "image/png", &png)
+        .build();
+
+    // Cut inside the image part, as when a client disconnects mid-upload
+    let request = truncated_edit_request(body.clone(), body.len() - png.len() / 2);
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("upload was incomplete"));
+}
+
+#[tokio::test]
+async fn test_edit_rejects_upload_missing_closing_boundary() {
+    let body = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .build();
+
+    // The image part is complete but the closing boundary never arrives
+    let closing = "--frameforge-test-boundary--\r\n".len();
+    let response = send(
+        mock_app(),
+        truncated_edit_request(body.clone(), body.len() - closing),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("upload was incomplete"));
+}
+
+#[tokio::test]
+async fn test_unusable_provider_rejected_before_image_is_read() {
+    // The provider part arrives, then the upload stalls before any image data
+    let mut head = MultipartBuilder::new()
+        .text("provider", "fal:fal-ai/flux/dev")
+        .build();
+    head.truncate(head.len() - "--\r\n".len());
+    head.extend_from_slice(b"\r\nContent-Disposition: form-data; name=\"images\"");
+    let chunks = futures::stream::iter([Ok::<_, std::io::Error>(axum::body::Bytes::from(head))])
+        .chain(futures::stream::pending());
+    let request = axum::http::Request::post("/api/edit")
+        .header(header::CONTENT_TYPE, MultipartBuilder::content_type())
+        .body(axum::body::Body::from_stream(chunks))
+        .unwrap();
+
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        send(build_router(keyless_config(false)), request),
+    )
+    .await
+    .expect("request waited for the image upload");
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("FAL_KEY"));
+}
+
+#[tokio::test]
+async fn test_composite_provider_tiles_uploaded_images() {
+    for (provider, expected) in [("composite", (200, 100)), ("composite:cols=1", (100, 200))] {
+        let request = MultipartBuilder::new()
+            .text("provider", provider)
+            .file("images", "a.png", "image/png", &sample_png(100, 100))
+            .file("images", "b.png", "image/png", &sample_png(100, 100))
+            .into_request("/api/edit");
+
+        let response = send(build_router(keyless_config(false)), request).await;
+
+        assert_eq!(response.status, StatusCode::OK, "{}", provider);
+        assert_eq!(response.headers[header::CONTENT_TYPE], "image/png");
+        let img = image::load_from_memory(&response.body).unwrap();
+        assert_eq!((img.width(), img.height()), expected, "{}", provider);
+    }
+}
+
+/// Dimensions of two 100x100 images composited by the provider the request resolves to
+async fn composite_size(
+    header: Option<&str>,
+    field: Option<&str>,
+) -> (StatusCode, Option<(u32, u32)>) {
+    let mut builder = MultipartBuilder::new();
+    if let Some(provider) = field {
+        builder = builder.text("provider", provider);
+    }
+    let mut request = builder
+        .file("images", "a.png", "image/png", &sample_png(100, 100))
+        .file("images", "b.png", "image/png", &sample_png(100, 100))
+        .into_request("/api/edit");
+    if let Some(provider) = header {
+        request
+            .headers_mut()
+            .insert("X-Provider", provider.parse().unwrap());
+    }
+
+    let response = send(build_router(keyless_config(false)), request).await;
+
+    let size = image::load_from_memory(&response.body)
+        .ok()
+        .map(|img| (img.width(), img.height()));
+    (response.status, size)
+}
+
+#[tokio::test]
+async fn test_provider_selected_by_header() {
+    // Without a Google key the default provider is unavailable
+    assert_eq!(composite_size(None, None).await.0, StatusCode::NOT_FOUND);
+
+    assert_eq!(
+        composite_size(Some("composite:cols=1"), None).await,
+        (StatusCode::OK, Some((100, 200)))
+    );
+}
+
+#[tokio::test]
+async fn test_provider_field_overrides_header() {
+    assert_eq!(
+        composite_size(Some("composite:cols=1"), Some("composite")).await,
+        (StatusCode::OK, Some((200, 100)))
+    );
+    // An unavailable provider in the header doesn't matter when the field names one
+    assert_eq!(
+        composite_size(Some("google"), Some("composite")).await,
+        (StatusCode::OK, Some((200, 100)))
+    );
+    assert_eq!(
+        composite_size(Some("composite"), Some("google")).await.0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+fn solid_png(color: [u8; 3]) -> Vec<u8> {
+    let img = image::RgbImage::from_pixel(10, 10, image::Rgb(color));
+    image_utils::image_to_bytes(
+        &image::DynamicImage::ImageRgb8(img),
+        image::ImageFormat::Png,
+    )
+    .unwrap()
+    .to_vec()
+}
+
+#[tokio::test]
+async fn test_named_image_fields_are_sent_base_then_reference() {
+    let (red, green, blue) = ([255, 0, 0], [0, 255, 0], [0, 0, 255]);
+    // Form order differs from the order the provider receives
+    let request = MultipartBuilder::new()
+        .text("provider", "composite:cols=3")
+        .file("images", "extra.png", "image/png", &solid_png(red))
+        .file(
+            "reference_image",
+            "reference.png",
+            "image/png",
+            &solid_png(green),
+        )
+        .file("base_image", "base.png", "image/png", &solid_png(blue))
+        .into_request("/api/edit");
+
+    let response = send(build_router(keyless_config(false)), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let tiles = image::load_from_memory(&response.body).unwrap().to_rgb8();
+    assert_eq!(tiles.dimensions(), (30, 10));
+    assert_eq!(tiles.get_pixel(5, 5).0, blue);
+    assert_eq!(tiles.get_pixel(15, 5).0, green);
+    assert_eq!(tiles.get_pixel(25, 5).0, red);
+}
+
+#[tokio::test]
+async fn test_repeated_named_image_field_rejected() {
+    let request = MultipartBuilder::new()
+        .file("base_image", "a.png", "image/png", &sample_png(4, 4))
+        .file("base_image", "b.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+}
+
+/// Edit request with the given chained steps
+fn chained_request(steps: usize) -> axum::http::Request<axum::body::Body> {
+    (0..steps)
+        .fold(
+            MultipartBuilder::new().file("images", "room.png", "image/png", &sample_png(4, 4)),
+            |builder, step| builder.text("steps", &format!("Step {}", step)),
+        )
+        .into_request("/api/edit")
+}
+
+#[tokio::test]
+async fn test_chained_edit_at_step_limit_succeeds() {
+    let app = build_router(AppConfig {
+        max_edit_steps: 3,
+        ..mock_config()
+    });
+
+    let response = send(app, chained_request(3)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(&response.body[..], &sample_png(4, 4)[..]);
+}
+
+#[tokio::test]
+async fn test_chained_edit_over_step_limit_rejected() {
+    let app = build_router(AppConfig {
+        max_edit_steps: 3,
+        ..mock_config()
+    });
+
+    let response = send(app, chained_request(4)).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+    assert!(response.json()["error"]
+        .as_str()
+        .unwrap()
+        .contains("maximum 3"));
+}
+
+#[tokio::test]
+async fn test_edit_logs_only_prompt_hashes_with_hash_redaction() {
+    let capture = LogCapture::default();
+    let _guard = capture.install();
+    let prompt = "Add a reading nook by the window";
+    let app = build_router(AppConfig {
+        log_redaction: LogRedaction::Hash,
+        ..mock_config()
+    });
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("prompt", prompt)
+        .into_request("/api/edit");
+
+    assert_eq!(send(app, request).await.status, StatusCode::OK);
+
+    let events = capture.events();
+    let logged_prompts: Vec<&String> = events
+        .iter()
+        .filter_map(|event| event.fields.get("prompt"))
+        .collect();
+    // "Received prompt" and "Using prompt"
+    assert!(logged_prompts.len() >= 2, "{:?}", logged_prompts);
+    assert!(
+        logged_prompts
+            .iter()
+            .all(|logged| logged.starts_with("sha256:")),
+        "{:?}",
+        logged_prompts
+    );
+    for event in &events {
+        for value in event.fields.values() {
+            assert!(
+                !value.contains("reading nook"),
+                "prompt leaked in {:?}",
+                event
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! End-to-end tests for `POST /api/edit/stream` running against the mock provider
+
+mod common;
+
+use axum::http::{header, StatusCode};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use common::{mock_app, sample_png, send, MultipartBuilder};
+use std::io::Cursor;
+
+/// PNG of pseudo-random pixels, which doesn't compress below one chunk
+fn noisy_png(width: u32, height: u32) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    let img = image::RgbImage::from_fn(width, height, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let [r, g, b, _] = state.to_le_bytes();
+        image::Rgb([r, g, b])
+    });
+    let mut buffer = Vec::new();
+    image::DynamicImage::ImageRgb8(img)
+        .write_to(&mut Cursor::new(&mut buffer), image::ImageFormat::Png)
+        .unwrap();
+    buffer
+}
+
+/// `(event, data)` pairs of an SSE body
+fn parse_events(body: &[u8]) -> Vec<(String, String)> {
+    std::str::from_utf8(body)
+        .unwrap()
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let mut event = String::new();
+            let mut data = String::new();
+            for line in block.lines() {
+                if let Some(value) = line.strip_prefix("event: ") {
+                    event = value.to_string();
+                } else if let Some(value) = line.strip_prefix("data: ") {
+                    data.push_str(value);
+                }
+            }
+            (event, data)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_stream_chunks_reassemble_into_result() {
+    let png = noisy_png(160, 160);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .text("prompt", "Add a rug")
+        .into_request("/api/edit/stream");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers[header::CONTENT_TYPE], "text/event-stream");
+    let events = parse_events(&response.body);
+    let (first, rest) = events.split_first().unwrap();
+    let (last, chunks) = rest.split_last().unwrap();
+
+    assert_eq!(first.0, "start");
+    let start: serde_json::Value = serde_json::from_str(&first.1).unwrap();
+    assert_eq!(start["content_type"], "image/png");
+    assert_eq!(start["size"], png.len());
+
+    assert!(
+        chunks.len() > 1,
+        "expected several chunks, got {}",
+        chunks.len()
+    );
+    let mut reassembled = Vec::new();
+    for (event, data) in chunks {
+        assert_eq!(event, "chunk");
+        reassembled.extend(STANDARD.decode(data).unwrap());
+    }
+    assert_eq!(reassembled, png);
+
+    assert_eq!(last.0, "done");
+    let done: serde_json::Value = serde_json::from_str(&last.1).unwrap();
+    assert_eq!(done["chunks"], chunks.len());
+}
+
+#[tokio::test]
+async fn test_stream_errors_before_any_event() {
+    let request = MultipartBuilder::new()
+        .text("prompt", "Add a rug")
+        .into_request("/api/edit/stream");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.json()["error_type"], "invalid_input");
+}
+
+#[tokio::test]
+async fn test_stream_is_chunked_and_buffered_edit_has_content_length() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, mock_app()).await.unwrap() });
+    let post = |path: &str| {
+        reqwest::Client::new()
+            .post(format!("http://{}{}", addr, path))
+            .header(header::CONTENT_TYPE, MultipartBuilder::content_type())
+            .body(
+                MultipartBuilder::new()
+                    .file("images", "room.png", "image/png", &sample_png(8, 8))
+                    .build(),
+            )
+            .send()
+    };
+
+    let buffered = post("/api/edit").await.unwrap();
+    assert_eq!(buffered.status(), 200);
+    let length: usize = buffered.headers()[header::CONTENT_LENGTH]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(!buffered.headers().contains_key(header::TRANSFER_ENCODING));
+    assert_eq!(length, buffered.bytes().await.unwrap().len());
+
+    let streamed = post("/api/edit/stream").await.unwrap();
+    assert_eq!(streamed.status(), 200);
+    assert!(!streamed.headers().contains_key(header::CONTENT_LENGTH));
+    assert_eq!(streamed.headers()[header::TRANSFER_ENCODING], "chunked");
+}
+
+#[tokio::test]
+async fn test_stream_keeps_result_headers() {
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .text("include_metadata", "true")
+        .into_request("/api/edit/stream");
+
+    let response = send(mock_app(), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.headers.contains_key("x-provider-metadata"));
+}
@@ -0,0 +1,272 @@
+//! End-to-end tests for the async job endpoints running against the mock provider
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use bytes::Bytes;
+use common::{mock_config, sample_png, send, MultipartBuilder};
+use frameforge_server::app::{build_router, build_router_with_state};
+use frameforge_server::jobs::{JobOutput, JobState};
+use frameforge_server::state::AppState;
+use std::time::Duration;
+
+/// Mock-mode state with async jobs enabled
+fn jobs_state() -> AppState {
+    let mut config = mock_config();
+    config.features.async_jobs = true;
+    AppState::new(config)
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+fn delete(uri: &str) -> Request<Body> {
+    Request::delete(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn test_preview_before_done_is_resized_input() {
+    let state = jobs_state();
+    let id = state.jobs.create(Bytes::from(sample_png(1024, 512)));
+    let app = build_router_with_state(state);
+
+    let response = send(app, get(&format!("/api/jobs/{}/preview", id))).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["x-job-status"], "running");
+    assert_eq!(response.headers["content-type"], "image/png");
+    let preview = image::load_from_memory(&response.body).unwrap();
+    assert_eq!((preview.width(), preview.height()), (256, 128));
+}
+
+#[tokio::test]
+async fn test_preview_after_done_is_result() {
+    let state = jobs_state();
+    let id = state.jobs.create(Bytes::from(sample_png(1024, 512)));
+    let result = Bytes::from(sample_png(8, 8));
+    state.jobs.finish(
+        &id,
+        JobState::Succeeded(JobOutput {
+            content_type: "image/png".to_string(),
+            body: result.clone(),
+        }),
+    );
+    let app = build_router_with_state(state);
+
+    let response = send(app, get(&format!("/api/jobs/{}/preview", id))).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["x-job-status"], "succeeded");
+    assert_eq!(response.body, result);
+}
+
+#[tokio::test]
+async fn test_submitted_job_completes_with_edit_result() {
+    let app = build_router_with_state(jobs_state());
+    let image = sample_png(4, 4);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &image)
+        .into_request("/api/jobs");
+
+    let response = send(app.clone(), request).await;
+    assert_eq!(response.status, StatusCode::ACCEPTED);
+    let id = response.json()["job_id"].as_str().unwrap().to_string();
+    assert_eq!(response.headers["location"], format!("/api/jobs/{}", id));
+    assert_eq!(response.json()["status"], "running");
+
+    let mut status = String::new();
+    for _ in 0..100 {
+        let response = send(app.clone(), get(&format!("/api/jobs/{}", id))).await;
+        status = response.json()["status"].as_str().unwrap().to_string();
+        if status != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(status, "succeeded");
+
+    let preview = send(app, get(&format!("/api/jobs/{}/preview", id))).await;
+    assert_eq!(preview.status, StatusCode::OK);
+    assert_eq!(preview.body, image);
+}
+
+#[tokio::test]
+async fn test_invalid_submission_rejected_synchronously() {
+    let app = build_router_with_state(jobs_state());
+    let request = MultipartBuilder::new()
+        .text("prompt", "Add a sofa")
+        .into_request("/api/jobs");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_unknown_job_is_404() {
+    let app = build_router_with_state(jobs_state());
+
+    let response = send(app, get("/api/jobs/missing/preview")).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["error_type"], "not_found");
+}
+
+#[tokio::test]
+async fn test_job_routes_absent_without_feature_flag() {
+    let app = build_router(mock_config());
+
+    let response = send(app, get("/api/jobs/missing")).await;
+
+    // Unrouted, rather than an unknown job
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(response.body.is_empty());
+}
+
+#[tokio::test]
+async fn test_prefer_respond_async_on_edit_starts_job() {
+    let app = build_router_with_state(jobs_state());
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert("prefer", "respond-async".parse().unwrap());
+
+    let response = send(app.clone(), request).await;
+
+    assert_eq!(response.status, StatusCode::ACCEPTED);
+    assert_eq!(response.headers["preference-applied"], "respond-async");
+    let id = response.json()["job_id"].as_str().unwrap().to_string();
+    let status = send(app, get(&format!("/api/jobs/{}", id))).await;
+    assert_eq!(status.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_edit_without_prefer_stays_synchronous() {
+    let app = build_router_with_state(jobs_state());
+    let png = sample_png(4, 4);
+    let request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &png)
+        .into_request("/api/edit");
+
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("preference-applied"));
+    assert_eq!(response.body, png);
+}
+
+#[tokio::test]
+async fn test_prefer_respond_async_ignored_without_feature_flag() {
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/edit");
+    request
+        .headers_mut()
+        .insert("prefer", "respond-async".parse().unwrap());
+
+    let response = send(build_router(mock_config()), request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("preference-applied"));
+}
+
+/// Async job submission carrying `key` as its `Idempotency-Key`
+fn keyed_submission(key: &str) -> Request<Body> {
+    let mut request = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .into_request("/api/jobs");
+    request
+        .headers_mut()
+        .insert("idempotency-key", key.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn test_submits_with_same_idempotency_key_share_running_job() {
+    let state = jobs_state();
+    // Keys are scoped to the tenant; untagged requests belong to `anonymous`
+    let (running, _) = state
+        .jobs
+        .create_or_join(Bytes::from(sample_png(4, 4)), Some("anonymous:retry-1"));
+    let app = build_router_with_state(state);
+
+    let first = send(app.clone(), keyed_submission("retry-1")).await;
+    let second = send(app.clone(), keyed_submission("retry-1")).await;
+
+    assert_eq!(first.status, StatusCode::ACCEPTED);
+    assert_eq!(first.json()["job_id"], running.as_str());
+    assert_eq!(second.json()["job_id"], running.as_str());
+    assert_eq!(second.headers["location"], format!("/api/jobs/{}", running));
+
+    let mut other_tenant = keyed_submission("retry-1");
+    other_tenant
+        .headers_mut()
+        .insert("x-tenant-id", "acme".parse().unwrap());
+    let other = send(app, other_tenant).await;
+    assert_eq!(other.status, StatusCode::ACCEPTED);
+    assert_ne!(other.json()["job_id"], running.as_str());
+}
+
+#[tokio::test]
+async fn test_empty_idempotency_key_rejected() {
+    let app = build_router_with_state(jobs_state());
+
+    let response = send(app, keyed_submission(" ")).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_cancel_running_job() {
+    let state = jobs_state();
+    let id = state.jobs.create(Bytes::from(sample_png(4, 4)));
+    let task = tokio::spawn(std::future::pending::<()>());
+    state.jobs.attach(&id, task.abort_handle());
+    let app = build_router_with_state(state);
+
+    let response = send(app.clone(), delete(&format!("/api/jobs/{}", id))).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["status"], "cancelled");
+    assert!(task.await.unwrap_err().is_cancelled());
+
+    let status = send(app.clone(), get(&format!("/api/jobs/{}", id))).await;
+    assert_eq!(status.json()["status"], "cancelled");
+    let preview = send(app, get(&format!("/api/jobs/{}/preview", id))).await;
+    assert_eq!(preview.status, StatusCode::CONFLICT);
+    assert_eq!(preview.headers["x-job-status"], "cancelled");
+}
+
+#[tokio::test]
+async fn test_cancel_completed_job_is_conflict() {
+    let state = jobs_state();
+    let id = state.jobs.create(Bytes::from(sample_png(4, 4)));
+    state.jobs.finish(
+        &id,
+        JobState::Succeeded(JobOutput {
+            content_type: "image/png".to_string(),
+            body: Bytes::from(sample_png(8, 8)),
+        }),
+    );
+    let app = build_router_with_state(state);
+
+    let response = send(app.clone(), delete(&format!("/api/jobs/{}", id))).await;
+
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.json()["error_type"], "conflict");
+    let status = send(app, get(&format!("/api/jobs/{}", id))).await;
+    assert_eq!(status.json()["status"], "succeeded");
+}
+
+#[tokio::test]
+async fn test_cancel_unknown_job_is_404() {
+    let app = build_router_with_state(jobs_state());
+
+    let response = send(app, delete("/api/jobs/missing")).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
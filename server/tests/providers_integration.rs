@@ -0,0 +1,34 @@
+//! End-to-end tests for the provider and model metadata endpoints
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use common::{mock_app, send};
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn test_params_for_encoded_fal_model_path() {
+    let response = send(
+        mock_app(),
+        get("/api/providers/fal:fal-ai%2Fflux-kontext%2Fdev/params"),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["provider"], "fal:fal-ai/flux-kontext/dev");
+    assert_eq!(json["params"][1]["name"], "seed");
+    assert_eq!(json["params"][1]["type"], "integer");
+}
+
+#[tokio::test]
+async fn test_params_for_unknown_provider_is_404() {
+    let response = send(mock_app(), get("/api/providers/nope/params")).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["error_type"], "provider_not_found");
+}
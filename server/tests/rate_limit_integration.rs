@@ -0,0 +1,64 @@
+//! Rate limiting through the full router
+//!
+//! Requests carry the peer address the way `into_make_service_with_connect_info`
+//! provides it, so clients are told apart as in production.
+
+mod common;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode};
+use common::{mock_config, send};
+use frameforge_server::app::build_router_with_state;
+use frameforge_server::config::AppConfig;
+use frameforge_server::middleware::RateLimitAlgorithm;
+use frameforge_server::state::AppState;
+use std::net::SocketAddr;
+
+/// Hourly budget of `/api/edit` requests per client
+const EDIT_LIMIT: usize = 100;
+
+/// `GET /api/edit` without an image URL (a cheap 400) from `ip`
+fn edit_request(ip: [u8; 4]) -> Request<Body> {
+    let mut request = Request::get("/api/edit?prompt=rug")
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from((ip, 50000))));
+    request
+}
+
+#[tokio::test]
+async fn test_configured_algorithm_rejects_requests_over_the_limit() {
+    for algorithm in [
+        RateLimitAlgorithm::FixedWindow,
+        RateLimitAlgorithm::SlidingWindow,
+    ] {
+        let state = AppState::new(AppConfig {
+            rate_limit_algorithm: algorithm,
+            ..mock_config()
+        });
+        let limiter = state.rate_limiter.clone();
+        let app = build_router_with_state(state);
+
+        for _ in 0..EDIT_LIMIT {
+            let response = send(app.clone(), edit_request([10, 0, 0, 1])).await;
+            assert_eq!(response.status, StatusCode::BAD_REQUEST, "{:?}", algorithm);
+        }
+
+        let response = send(app.clone(), edit_request([10, 0, 0, 1])).await;
+        assert_eq!(
+            response.status,
+            StatusCode::TOO_MANY_REQUESTS,
+            "{:?}",
+            algorithm
+        );
+        assert!(response.headers.contains_key("Retry-After"));
+
+        // Another client still has its full budget
+        let response = send(app, edit_request([10, 0, 0, 2])).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{:?}", algorithm);
+        assert_eq!(limiter.tracked_clients(), 2);
+    }
+}
@@ -0,0 +1,96 @@
+//! End-to-end tests for the stored result endpoint running against the mock provider
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{mock_app, mock_config, sample_png, send, MultipartBuilder};
+use frameforge_server::app::build_router;
+
+/// Mock-mode app with stored results enabled
+fn results_app() -> Router {
+    let mut config = mock_config();
+    config.features.results = true;
+    build_router(config)
+}
+
+fn edit_request(png: &[u8]) -> Request<Body> {
+    MultipartBuilder::new()
+        .file("images", "room.png", "image/png", png)
+        .text("prompt", "Add a sofa")
+        .into_request("/api/edit")
+}
+
+fn head(uri: &str) -> Request<Body> {
+    Request::head(uri).body(Body::empty()).unwrap()
+}
+
+/// Run an edit and return its result id
+async fn stored_result(app: &Router, png: &[u8]) -> String {
+    let response = send(app.clone(), edit_request(png)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    response.headers["x-result-id"]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_get_returns_stored_result() {
+    let app = results_app();
+    let png = sample_png(16, 16);
+    let id = stored_result(&app, &png).await;
+
+    let request = Request::get(format!("/api/results/{}", id))
+        .body(Body::empty())
+        .unwrap();
+    let response = send(app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, png);
+}
+
+#[tokio::test]
+async fn test_head_returns_headers_without_body() {
+    let app = results_app();
+    let png = sample_png(16, 16);
+    let id = stored_result(&app, &png).await;
+
+    let response = send(app, head(&format!("/api/results/{}", id))).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.is_empty());
+    assert_eq!(response.headers["content-type"], "image/png");
+    assert_eq!(
+        response.headers["content-length"],
+        png.len().to_string().as_str()
+    );
+    assert_eq!(
+        response.headers["cache-control"],
+        "public, max-age=31536000, immutable"
+    );
+}
+
+#[tokio::test]
+async fn test_head_unknown_result_is_404() {
+    let response = send(results_app(), head("/api/results/0123abcd")).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(response.body.is_empty());
+}
+
+#[tokio::test]
+async fn test_results_absent_without_feature_flag() {
+    let app = mock_app();
+    let response = send(app.clone(), edit_request(&sample_png(16, 16))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("x-result-id"));
+
+    let request = Request::get("/api/results/0123abcd")
+        .body(Body::empty())
+        .unwrap();
+    let response = send(app, request).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(response.body.is_empty());
+}
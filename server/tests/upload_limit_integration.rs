@@ -0,0 +1,88 @@
+//! `Expect: 100-continue` uploads over a real socket
+//!
+//! The upload limit must be enforced on the request head alone: an over-limit
+//! `Content-Length` gets a 413 instead of `100 Continue`, so the client never
+//! sends the body.
+
+mod common;
+
+use common::{mock_config, sample_png, MultipartBuilder};
+use frameforge_server::app::build_router;
+use frameforge_server::config::AppConfig;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Serve the mock-mode router with the given upload limit and return its address
+async fn serve(max_upload_bytes: usize) -> std::net::SocketAddr {
+    let app = build_router(AppConfig {
+        max_upload_bytes,
+        ..mock_config()
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// Send only the request head of an edit upload expecting 100-continue
+async fn send_head(addr: std::net::SocketAddr, content_length: usize) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let head = format!(
+        "POST /api/edit HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nExpect: 100-continue\r\n\r\n",
+        addr,
+        MultipartBuilder::content_type(),
+        content_length
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream
+}
+
+/// Read whatever the server sends within a short window
+async fn read_available(stream: &mut TcpStream) -> String {
+    let mut buf = vec![0u8; 8192];
+    let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+        .await
+        .expect("server did not answer the request head")
+        .unwrap();
+    String::from_utf8_lossy(&buf[..n]).into_owned()
+}
+
+#[tokio::test]
+async fn test_over_limit_content_length_rejected_before_body() {
+    let addr = serve(1024).await;
+
+    // Only the head is sent; the server must answer without waiting for the body
+    let mut stream = send_head(addr, 10 * 1024 * 1024).await;
+    let response = read_available(&mut stream).await;
+
+    assert!(
+        response.starts_with("HTTP/1.1 413"),
+        "unexpected response: {}",
+        response
+    );
+    assert!(!response.contains("100 Continue"));
+    assert!(response.contains("payload_too_large"));
+}
+
+#[tokio::test]
+async fn test_within_limit_upload_gets_100_continue() {
+    let addr = serve(1024 * 1024).await;
+    let body = MultipartBuilder::new()
+        .file("images", "room.png", "image/png", &sample_png(4, 4))
+        .build();
+
+    let mut stream = send_head(addr, body.len()).await;
+    let interim = read_available(&mut stream).await;
+    assert!(
+        interim.starts_with("HTTP/1.1 100 Continue"),
+        "unexpected response: {}",
+        interim
+    );
+
+    stream.write_all(&body).await.unwrap();
+    let mut response = interim;
+    while !response.contains("HTTP/1.1 200") {
+        response.push_str(&read_available(&mut stream).await);
+    }
+}
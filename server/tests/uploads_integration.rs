@@ -0,0 +1,141 @@
+//! End-to-end tests for the upload endpoints running against the mock provider
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{mock_config, sample_png, send, MultipartBuilder};
+use frameforge_server::app::{build_router, build_router_with_state};
+use frameforge_server::middleware::rate_limit::MockClock;
+use frameforge_server::state::AppState;
+use frameforge_server::uploads::UploadStore;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Mock-mode state with uploads enabled
+fn uploads_state() -> AppState {
+    let mut config = mock_config();
+    config.features.uploads = true;
+    AppState::new(config)
+}
+
+fn post(uri: &str) -> Request<Body> {
+    Request::post(uri).body(Body::empty()).unwrap()
+}
+
+fn put(uri: &str, data: &[u8]) -> Request<Body> {
+    Request::put(uri)
+        .header("content-type", "image/png")
+        .body(Body::from(data.to_vec()))
+        .unwrap()
+}
+
+/// Reserve an upload, returning its id and upload URL
+async fn reserve(app: &Router) -> (String, String) {
+    let response = send(app.clone(), post("/api/uploads")).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+
+    let json = response.json();
+    let id = json["upload_id"].as_str().unwrap().to_string();
+    let url = json["upload_url"].as_str().unwrap().to_string();
+    assert_eq!(response.headers["location"], url.as_str());
+    (id, url)
+}
+
+fn edit_with_upload(id: &str) -> Request<Body> {
+    MultipartBuilder::new()
+        .text("upload_id", id)
+        .text("prompt", "Add a sofa")
+        .into_request("/api/edit")
+}
+
+#[tokio::test]
+async fn test_upload_then_reference_in_edit() {
+    let app = build_router_with_state(uploads_state());
+    let png = sample_png(16, 16);
+
+    let (id, url) = reserve(&app).await;
+    let response = send(app.clone(), put(&url, &png)).await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+
+    let response = send(app, edit_with_upload(&id)).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, png);
+}
+
+#[tokio::test]
+async fn test_reference_before_put_is_bad_request() {
+    let app = build_router_with_state(uploads_state());
+    let (id, _) = reserve(&app).await;
+
+    let response = send(app, edit_with_upload(&id)).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_expired_upload_is_404() {
+    let clock = Arc::new(MockClock::new());
+    let mut state = uploads_state();
+    state.uploads = Arc::new(UploadStore::new(Duration::from_secs(60)).with_clock(clock.clone()));
+    let app = build_router_with_state(state);
+
+    let (id, url) = reserve(&app).await;
+    let response = send(app.clone(), put(&url, &sample_png(4, 4))).await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    clock.advance(Duration::from_secs(60));
+
+    let response = send(app.clone(), edit_with_upload(&id)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["error_type"], "not_found");
+
+    let response = send(app, put(&url, &sample_png(4, 4))).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_put_that_does_not_fit_is_refused_without_evicting_others() {
+    let png = sample_png(4, 4);
+    let mut state = uploads_state();
+    state.uploads = Arc::new(UploadStore::with_limits(
+        Duration::from_secs(60),
+        png.len() * 3 / 2,
+        png.len(),
+    ));
+    let app = build_router_with_state(state);
+
+    let (first, first_url) = reserve(&app).await;
+    let (_, second_url) = reserve(&app).await;
+    assert_eq!(
+        send(app.clone(), put(&first_url, &png)).await.status,
+        StatusCode::NO_CONTENT
+    );
+
+    let response = send(app.clone(), put(&second_url, &png)).await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    // The earlier client's upload is still usable
+    let response = send(app, edit_with_upload(&first)).await;
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_put_rejects_non_image_body() {
+    let app = build_router_with_state(uploads_state());
+    let (_, url) = reserve(&app).await;
+
+    let response = send(app, put(&url, b"definitely not an image")).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_upload_routes_absent_without_feature_flag() {
+    let response = send(build_router(mock_config()), post("/api/uploads")).await;
+
+    // Unrouted, rather than an unknown upload
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(response.body.is_empty());
+}